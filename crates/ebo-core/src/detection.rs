@@ -0,0 +1,164 @@
+//! Finding brackets without an entered sequence, for when the camera settings
+//! aren't known: a bracket is taken to be a run of frames whose biases are
+//! evenly spaced around the one of its middle frame, like every auto bracket
+//! of a camera, whatever order it shoots them in.
+
+use crate::matching::within;
+use crate::sequence::EvMode;
+use num_rational::Rational32;
+use std::ops::Range;
+
+/// The most frames any camera's auto bracketing takes
+const MAX_FRAMES: usize = 9;
+
+/// A bracket found by [`detect_brackets`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedBracket {
+    /// Positions of its frames
+    pub range: Range<usize>,
+    /// Bias of the middle exposure, 0 unless shot with exposure compensation
+    pub base: Rational32,
+    /// Difference between neighbouring exposures
+    pub step: Rational32,
+}
+
+impl DetectedBracket {
+    pub fn frames(&self) -> usize {
+        self.range.len()
+    }
+
+    /// The sequence to enter for brackets like this one, in shot order, e.g.
+    /// `0, -1, 1` in delta mode. Absolute mode keeps the recorded biases.
+    pub fn sequence(&self, biases: &[Option<Rational32>], ev_mode: &EvMode) -> Vec<Rational32> {
+        biases[self.range.clone()]
+            .iter()
+            .flatten()
+            .map(|bias| match ev_mode {
+                EvMode::Absolute => *bias,
+                EvMode::Delta => bias - self.base,
+            })
+            .collect()
+    }
+}
+
+/// Finds brackets of 3 to 9 frames among `biases`: runs of an odd number of
+/// frames with distinct biases, evenly spaced within `tolerance`. The longest
+/// run is taken at each position and the search goes on after it, so the
+/// brackets never overlap. Brackets of one camera that follow each other
+/// closely are told apart by their repeating biases, e.g. `0, -1, 1, 0, -1, 1`.
+pub fn detect_brackets(
+    biases: &[Option<Rational32>],
+    tolerance: Rational32,
+) -> Vec<DetectedBracket> {
+    let mut found = Vec::new();
+    let mut start = 0;
+    while start < biases.len() {
+        let longest = (3..=MAX_FRAMES.min(biases.len() - start))
+            .rev()
+            .filter(|frames| frames % 2 == 1)
+            .find_map(|frames| {
+                let range = start..start + frames;
+                let (base, step) = evenly_spaced(&biases[range.clone()], tolerance)?;
+                Some(DetectedBracket { range, base, step })
+            });
+        match longest {
+            Some(bracket) => {
+                start = bracket.range.end;
+                found.push(bracket);
+            }
+            None => start += 1,
+        }
+    }
+    found
+}
+
+/// The middle bias and the step of `biases` if they are distinct and evenly
+/// spaced. Frames without a bias never are.
+fn evenly_spaced(
+    biases: &[Option<Rational32>],
+    tolerance: Rational32,
+) -> Option<(Rational32, Rational32)> {
+    let mut values: Vec<Rational32> = biases.iter().copied().collect::<Option<_>>()?;
+    values.sort();
+    let frames = values.len();
+    let (lowest, highest) = (values[0], values[frames - 1]);
+    let step = (highest - lowest) / Rational32::from(frames as i32 - 1);
+    if step <= tolerance {
+        return None;
+    }
+    let evenly = values.iter().enumerate().all(|(i, value)| {
+        within(
+            *value,
+            lowest + step * Rational32::from(i as i32),
+            tolerance,
+        )
+    });
+    if !evenly {
+        return None;
+    }
+    // Report what the camera most likely stepped in, e.g. 2/3 for 7/10
+    Some((values[frames / 2], nearest_third(step, tolerance)))
+}
+
+/// `step` rounded to thirds of a stop if that is within `tolerance`.
+fn nearest_third(step: Rational32, tolerance: Rational32) -> Rational32 {
+    let thirds = (step * 3).round() / 3;
+    if within(step, thirds, tolerance) {
+        thirds
+    } else {
+        step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::parse_exposure_sequence;
+
+    fn detect(biases: &str) -> Vec<DetectedBracket> {
+        let biases: Vec<_> = parse_exposure_sequence(biases)
+            .into_iter()
+            .map(Some)
+            .collect();
+        detect_brackets(&biases, Rational32::from(0))
+    }
+
+    #[test]
+    fn detects_evenly_spaced_brackets() {
+        let found = detect("0, -2/3, 2/3, 0, -2/3, 2/3, -4/3, 4/3");
+        let found: Vec<_> = found.iter().map(|b| (b.frames(), b.step)).collect();
+        assert_eq!(
+            found,
+            [(3, Rational32::new(2, 3)), (5, Rational32::new(2, 3))]
+        );
+    }
+
+    #[test]
+    fn keeps_the_base_of_compensated_brackets() {
+        let found = detect("1, 0, 2");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].base, Rational32::from(1));
+    }
+
+    #[test]
+    fn ignores_unevenly_spaced_frames() {
+        assert!(detect("0, -1, 2").is_empty());
+    }
+
+    #[test]
+    fn delta_sequence_is_relative_to_the_base() {
+        let biases: Vec<_> = parse_exposure_sequence("1, 0, 2")
+            .into_iter()
+            .map(Some)
+            .collect();
+        let found = detect_brackets(&biases, Rational32::from(0));
+        assert_eq!(
+            found[0].sequence(&biases, &EvMode::Delta),
+            parse_exposure_sequence("0, -1, 1")
+        );
+        assert_eq!(
+            found[0].sequence(&biases, &EvMode::Absolute),
+            parse_exposure_sequence("1, 0, 2")
+        );
+    }
+}
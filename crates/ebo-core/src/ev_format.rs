@@ -0,0 +1,127 @@
+//! How exposure biases are shown to people, e.g. `−0⅔` instead of the
+//! `-7/10` a camera records.
+
+use num_rational::Rational32;
+use serde::{Deserialize, Serialize};
+
+/// Fractions of a stop written as a single character, a recorded value within
+/// [`STOP_FRACTION_TOLERANCE`] of one is shown as it, so tenths like 3/10 read
+/// as the third stop they stand for.
+const STOP_FRACTIONS: [(f64, &str); 5] = [
+    (1.0 / 4.0, "¼"),
+    (1.0 / 3.0, "⅓"),
+    (1.0 / 2.0, "½"),
+    (2.0 / 3.0, "⅔"),
+    (3.0 / 4.0, "¾"),
+];
+
+const STOP_FRACTION_TOLERANCE: f64 = 0.04;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvFormat {
+    /// Whole stops and their fraction, `+1⅓`, `−0⅔`
+    #[default]
+    Stops,
+    /// `+1.33`, `−0.7`
+    Decimal,
+    /// `+1,33`, `−0,7`, as written in most of Europe
+    DecimalComma,
+    /// The rational as recorded, `4/3`, `-7/10`
+    Recorded,
+}
+
+impl EvFormat {
+    pub const ALL: [EvFormat; 4] = [
+        EvFormat::Stops,
+        EvFormat::Decimal,
+        EvFormat::DecimalComma,
+        EvFormat::Recorded,
+    ];
+
+    /// `ev` written in this format, without a unit.
+    pub fn format(self, ev: Rational32) -> String {
+        if self == EvFormat::Recorded {
+            return ev.to_string();
+        }
+        let value = *ev.numer() as f64 / *ev.denom() as f64;
+        let magnitude = match self {
+            EvFormat::Stops => stops(value.abs()),
+            EvFormat::DecimalComma => decimal(value.abs()).replace('.', ","),
+            _ => decimal(value.abs()),
+        };
+        if magnitude == "0" {
+            magnitude
+        } else if value < 0.0 {
+            format!("−{}", magnitude)
+        } else {
+            format!("+{}", magnitude)
+        }
+    }
+}
+
+/// Up to two decimals, without trailing zeros.
+fn decimal(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn stops(value: f64) -> String {
+    let whole = value.trunc();
+    let rest = value - whole;
+    if rest < STOP_FRACTION_TOLERANCE {
+        return format!("{}", whole);
+    }
+    if 1.0 - rest < STOP_FRACTION_TOLERANCE {
+        return format!("{}", whole + 1.0);
+    }
+    match STOP_FRACTIONS
+        .iter()
+        .find(|(fraction, _)| (rest - fraction).abs() < STOP_FRACTION_TOLERANCE)
+    {
+        Some((_, symbol)) => format!("{}{}", whole, symbol),
+        // Not a common fraction of a stop, e.g. a typo or an odd camera
+        None => decimal(value),
+    }
+}
+
+impl std::fmt::Display for EvFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvFormat::Stops => write!(f, "Stops (−0⅔)"),
+            EvFormat::Decimal => write!(f, "Decimal (−0.67)"),
+            EvFormat::DecimalComma => write!(f, "Decimal comma (−0,67)"),
+            EvFormat::Recorded => write!(f, "As recorded (-2/3)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_round_tenths_to_the_fraction_they_stand_for() {
+        assert_eq!(EvFormat::Stops.format(Rational32::new(-7, 10)), "−0⅔");
+        assert_eq!(EvFormat::Stops.format(Rational32::new(4, 3)), "+1⅓");
+    }
+
+    #[test]
+    fn decimals_use_the_chosen_separator() {
+        assert_eq!(EvFormat::Decimal.format(Rational32::new(4, 3)), "+1.33");
+        assert_eq!(
+            EvFormat::DecimalComma.format(Rational32::new(4, 3)),
+            "+1,33"
+        );
+    }
+
+    #[test]
+    fn recorded_keeps_the_rational() {
+        assert_eq!(EvFormat::Recorded.format(Rational32::new(-7, 10)), "-7/10");
+    }
+
+    #[test]
+    fn zero_has_no_sign() {
+        assert_eq!(EvFormat::Stops.format(Rational32::from(0)), "0");
+    }
+}
@@ -0,0 +1,49 @@
+//! Exposure computed from the settings of a frame, for brackets shot by
+//! changing the shutter speed, aperture or ISO by hand, whose recorded bias is
+//! 0 or missing.
+
+use num_rational::Rational32;
+
+/// Base ISO the computed exposure is relative to
+const BASE_ISO: f64 = 100.0;
+
+/// How much light a frame took in, in stops relative to 1 s at f/1 and ISO
+/// 100, rounded to the third of a stop cameras step in. Only the differences
+/// between frames mean something, like the deltas of [`crate::EvMode::Delta`].
+/// `None` for settings that can't be right, e.g. an exposure time of 0.
+pub fn relative_exposure(
+    exposure_time: Rational32,
+    fnumber: Rational32,
+    iso: u32,
+) -> Option<Rational32> {
+    let time = *exposure_time.numer() as f64 / *exposure_time.denom() as f64;
+    let aperture = *fnumber.numer() as f64 / *fnumber.denom() as f64;
+    if time <= 0.0 || aperture <= 0.0 || iso == 0 {
+        return None;
+    }
+    let stops = time.log2() - 2.0 * aperture.log2() + (iso as f64 / BASE_ISO).log2();
+    Some(Rational32::new((stops * 3.0).round() as i32, 3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubling_the_exposure_time_adds_a_stop() {
+        let at = |time| relative_exposure(Rational32::new(1, time), Rational32::from(8), 100);
+        assert_eq!(at(60).unwrap() - at(125).unwrap(), Rational32::from(1));
+    }
+
+    #[test]
+    fn impossible_settings_have_no_exposure() {
+        assert_eq!(
+            relative_exposure(Rational32::from(0), Rational32::from(8), 100),
+            None
+        );
+        assert_eq!(
+            relative_exposure(Rational32::new(1, 60), Rational32::from(8), 0),
+            None
+        );
+    }
+}
@@ -0,0 +1,45 @@
+//! The bracket matching of the Exposure Bracketing Organizer, without any
+//! file access or UI, so other tools can embed it. [`parse_exposure_sequence`]
+//! reads a sequence like `0/10, -10/10, 10/10` and [`find_bracket_sequences`]
+//! finds the runs of frames whose biases follow it; [`detect_brackets`] finds
+//! brackets without a sequence. [`EvFormat`] writes biases the way people read
+//! them, and [`relative_exposure`] stands in for the bias of manually
+//! bracketed frames.
+//!
+//! ```
+//! use ebo_core::{
+//!     find_bracket_sequences, parse_exposure_pattern, DeltaReference, EvMode, MatchMode,
+//! };
+//! use num_rational::Rational32;
+//!
+//! let biases = [0, -1, 1, 0, -1, 1].map(|b| Some(Rational32::from(b)));
+//! let sequence = parse_exposure_pattern("0, -1, 1");
+//! let found = find_bracket_sequences(
+//!     &biases,
+//!     &sequence,
+//!     &EvMode::Absolute,
+//!     DeltaReference::Zero,
+//!     None,
+//!     Rational32::from(0),
+//!     MatchMode::Greedy,
+//! );
+//! assert_eq!(found, vec![0..3, 3..6]);
+//! ```
+
+mod detection;
+mod ev_format;
+mod exposure;
+mod matching;
+mod sequence;
+
+pub use detection::{detect_brackets, DetectedBracket};
+pub use ev_format::EvFormat;
+pub use exposure::relative_exposure;
+pub use matching::{
+    biases_match_sequence, find_bracket_patterns, find_bracket_sequences, without_overlaps,
+    MatchMode,
+};
+pub use sequence::{
+    parse_exposure_pattern, parse_exposure_sequence, parse_exposure_sequences, DeltaReference,
+    EvMode,
+};
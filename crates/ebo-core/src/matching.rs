@@ -0,0 +1,335 @@
+//! Finding the frames that follow an exposure bias sequence.
+
+use crate::sequence::{DeltaReference, EvMode};
+use log::{debug, warn};
+use num_rational::Rational32;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::ops::Range;
+
+/// Whether a frame can be part of more than one matched window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    /// Every window that follows the sequence, even if it shares frames with
+    /// the one before
+    #[default]
+    Overlapping,
+    /// Windows are taken in order, the search goes on after the last frame
+    /// of a match, so each frame ends up in one window at most
+    Greedy,
+}
+
+impl std::fmt::Display for MatchMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchMode::Overlapping => write!(f, "Overlapping"),
+            MatchMode::Greedy => write!(f, "Non-Overlapping"),
+        }
+    }
+}
+
+/// Finds the windows of consecutive `biases` following `sequence`, as ranges
+/// of their positions. A frame without a bias never matches, a `None` in
+/// `sequence` (a `*` entered) matches a frame with any bias.
+///
+/// With an `anchor_bias`, only windows whose reference frame (the one chosen
+/// by `delta_reference` in delta mode, the anchor's own position in absolute mode) has
+/// that bias are tried. This skips most positions on huge folders and keeps
+/// windows from starting on the wrong frame, e.g. a ZeroMinusPlus window
+/// that would start on the -1 EV frame.
+///
+/// Biases within `tolerance` of the expected value count as equal, since
+/// cameras write the same bias differently, e.g. 0.7 EV as 7/10 or as 2/3.
+/// `match_mode` decides whether the windows found may share frames.
+pub fn find_bracket_sequences(
+    biases: &[Option<Rational32>],
+    sequence: &[Option<Rational32>],
+    ev_mode: &EvMode,
+    delta_reference: DeltaReference,
+    anchor_bias: Option<Rational32>,
+    tolerance: Rational32,
+    match_mode: MatchMode,
+) -> Vec<Range<usize>> {
+    let sequence_len = sequence.len();
+    if sequence_len == 0 {
+        warn!("Sequence length is zero, cannot process.");
+        return Vec::new();
+    }
+    if *ev_mode == EvMode::Delta && delta_reference.index(sequence).is_none() {
+        warn!(
+            "{}, cannot process.",
+            delta_reference.missing_reason(sequence)
+        );
+        return Vec::new();
+    }
+
+    if biases.len() < sequence_len {
+        return Vec::new();
+    }
+
+    let candidates: Vec<Range<usize>> = match anchor_bias {
+        Some(anchor_bias) => {
+            let reference_index = match ev_mode {
+                EvMode::Absolute => sequence
+                    .iter()
+                    .position(|r| r.is_some_and(|r| within(r, anchor_bias, tolerance))),
+                EvMode::Delta => delta_reference.index(sequence),
+            };
+            let Some(reference_index) = reference_index else {
+                warn!(
+                    "The anchor bias {} is not part of the sequence, nothing can match.",
+                    anchor_bias
+                );
+                return Vec::new();
+            };
+            biases
+                .iter()
+                .enumerate()
+                .filter(|(_, bias)| bias.is_some_and(|b| within(b, anchor_bias, tolerance)))
+                .filter_map(|(position, _)| {
+                    let start = position.checked_sub(reference_index)?;
+                    (start + sequence_len <= biases.len()).then_some(start..start + sequence_len)
+                })
+                .collect()
+        }
+        None => (0..=biases.len() - sequence_len)
+            .map(|start| start..start + sequence_len)
+            .collect(),
+    };
+
+    let matches = candidates.into_iter().filter(|window| {
+        biases_match_sequence(
+            &biases[window.clone()],
+            sequence,
+            ev_mode,
+            delta_reference,
+            tolerance,
+        )
+    });
+    match match_mode {
+        MatchMode::Overlapping => matches.collect(),
+        // The candidates are in order of their start
+        MatchMode::Greedy => without_overlaps(matches, |window| window),
+    }
+}
+
+/// Takes the `windows`, ordered by their start, that begin after the last
+/// one taken ended, like [`MatchMode::Greedy`] does. For callers that drop
+/// some of the overlapping matches first, e.g. by their capture times.
+pub fn without_overlaps<T>(
+    windows: impl IntoIterator<Item = T>,
+    range: impl Fn(&T) -> &Range<usize>,
+) -> Vec<T> {
+    let mut next_free = 0;
+    windows
+        .into_iter()
+        .filter(|window| {
+            let range = range(window);
+            let free = range.start >= next_free;
+            if free {
+                next_free = range.end;
+            }
+            free
+        })
+        .collect()
+}
+
+/// Finds the windows of `biases` following any of `sequences`, as the index
+/// of the sequence matched and the range of positions. Where several match
+/// at the same position the longest one is taken, so a 3 frame sequence
+/// doesn't claim the start of a 5 frame bracket; ties go to the one listed
+/// first. See [`find_bracket_sequences`] for the other arguments.
+pub fn find_bracket_patterns(
+    biases: &[Option<Rational32>],
+    sequences: &[Vec<Option<Rational32>>],
+    ev_mode: &EvMode,
+    delta_reference: DeltaReference,
+    anchor_bias: Option<Rational32>,
+    tolerance: Rational32,
+    match_mode: MatchMode,
+) -> Vec<(usize, Range<usize>)> {
+    let mut found: Vec<(usize, Range<usize>)> = sequences
+        .iter()
+        .enumerate()
+        .flat_map(|(index, sequence)| {
+            find_bracket_sequences(
+                biases,
+                sequence,
+                ev_mode,
+                delta_reference,
+                anchor_bias,
+                tolerance,
+                MatchMode::Overlapping,
+            )
+            .into_iter()
+            .map(move |window| (index, window))
+        })
+        .collect();
+    found.sort_by_key(|(index, window)| (window.start, Reverse(window.len()), *index));
+    found.dedup_by_key(|(_, window)| window.start);
+    match match_mode {
+        MatchMode::Overlapping => found,
+        MatchMode::Greedy => without_overlaps(found, |(_, window)| window),
+    }
+}
+
+/// Whether consecutive biases follow `sequence`: equal to it in absolute
+/// mode, or differing from the bias of the reference frame as much as the
+/// sequence values differ from the reference value in delta mode, each
+/// within `tolerance`. Frames at a `None` of `sequence` only need a bias.
+pub fn biases_match_sequence(
+    biases: &[Option<Rational32>],
+    sequence: &[Option<Rational32>],
+    ev_mode: &EvMode,
+    delta_reference: DeltaReference,
+    tolerance: Rational32,
+) -> bool {
+    if biases.len() != sequence.len() {
+        return false;
+    }
+    match ev_mode {
+        EvMode::Absolute => biases
+            .iter()
+            .zip(sequence)
+            .all(|(bias, expected)| match expected {
+                Some(expected) => bias.is_some_and(|b| within(b, *expected, tolerance)),
+                None => bias.is_some(),
+            }),
+        EvMode::Delta => {
+            let Some(reference_index) = delta_reference.index(sequence) else {
+                return false;
+            };
+            let Some(base_bias) = biases[reference_index] else {
+                return false;
+            };
+            let Some(reference_value) = sequence[reference_index] else {
+                return false;
+            };
+            biases.iter().zip(sequence).all(|(bias, value)| {
+                let Some(value) = value else {
+                    return bias.is_some();
+                };
+                let seq_delta = value - reference_value;
+                bias.is_some_and(|current_bias| {
+                    let delta = current_bias - base_bias;
+                    debug!(
+                        "Current bias: {}, Base bias: {}, Calculated delta: {}, Seq delta: {}",
+                        current_bias, base_bias, delta, seq_delta
+                    );
+                    within(delta, seq_delta, tolerance)
+                })
+            })
+        }
+    }
+}
+
+/// Whether `a` and `b` differ by at most `tolerance`.
+pub(crate) fn within(a: Rational32, b: Rational32, tolerance: Rational32) -> bool {
+    let difference = a - b;
+    difference <= tolerance && -difference <= tolerance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::{parse_exposure_pattern, parse_exposure_sequences};
+
+    const EXACT: Rational32 = Rational32::new_raw(0, 1);
+
+    fn biases(values: &[i32]) -> Vec<Option<Rational32>> {
+        values.iter().map(|&b| Some(Rational32::from(b))).collect()
+    }
+
+    fn find(
+        biases: &[Option<Rational32>],
+        sequence: &str,
+        tolerance: Rational32,
+        match_mode: MatchMode,
+    ) -> Vec<Range<usize>> {
+        find_bracket_sequences(
+            biases,
+            &parse_exposure_pattern(sequence),
+            &EvMode::Absolute,
+            DeltaReference::Zero,
+            None,
+            tolerance,
+            match_mode,
+        )
+    }
+
+    #[test]
+    fn finds_every_window_following_the_sequence() {
+        let biases = biases(&[0, -1, 1, 0, 0, -1, 1]);
+        assert_eq!(
+            find(&biases, "0, -1, 1", EXACT, MatchMode::Overlapping),
+            vec![0..3, 4..7]
+        );
+    }
+
+    #[test]
+    fn overlapping_windows_share_frames() {
+        let biases = biases(&[0; 5]);
+        assert_eq!(
+            find(&biases, "0, 0", EXACT, MatchMode::Overlapping),
+            vec![0..2, 1..3, 2..4, 3..5]
+        );
+    }
+
+    #[test]
+    fn greedy_matching_claims_each_frame_once() {
+        let biases = biases(&[0; 5]);
+        assert_eq!(
+            find(&biases, "0, 0", EXACT, MatchMode::Greedy),
+            vec![0..2, 2..4]
+        );
+    }
+
+    #[test]
+    fn biases_within_tolerance_match() {
+        // A camera writing 2/3 EV still matches a typed 7/10 within 0.05 EV
+        let biases: Vec<_> = [0, -1, 1]
+            .iter()
+            .map(|&b| Some(Rational32::new(2 * b, 3)))
+            .collect();
+        let tolerance = Rational32::new(1, 20);
+        assert_eq!(
+            find(&biases, "0, -7/10, 7/10", tolerance, MatchMode::Greedy),
+            vec![0..3]
+        );
+        assert!(find(&biases, "0, -7/10, 7/10", EXACT, MatchMode::Greedy).is_empty());
+    }
+
+    #[test]
+    fn wildcard_matches_any_bias() {
+        let biases = biases(&[0, -3, 5, 0, 1, -1]);
+        assert_eq!(
+            find(&biases, "0, *, *", EXACT, MatchMode::Greedy),
+            vec![0..3, 3..6]
+        );
+    }
+
+    #[test]
+    fn frames_without_bias_never_match() {
+        let mut biases = biases(&[0, -1, 1]);
+        biases[1] = None;
+        assert!(find(&biases, "0, *, 1", EXACT, MatchMode::Greedy).is_empty());
+    }
+
+    #[test]
+    fn longest_of_several_sequences_wins() {
+        // A camera alternating between 3 and 5 frame brackets
+        let sequences = parse_exposure_sequences("0, -1, 1; 0, -1, 1, -2, 2");
+        let biases = biases(&[0, -1, 1, -2, 2, 0, -1, 1]);
+        let found = find_bracket_patterns(
+            &biases,
+            &sequences,
+            &EvMode::Absolute,
+            DeltaReference::Zero,
+            None,
+            EXACT,
+            MatchMode::Greedy,
+        );
+        assert_eq!(found, vec![(1, 0..5), (0, 5..8)]);
+    }
+}
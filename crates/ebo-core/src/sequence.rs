@@ -0,0 +1,150 @@
+//! Exposure bias sequences and how frames are compared against them.
+
+use num_rational::Rational32;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvMode {
+    Absolute,
+    Delta,
+}
+
+impl std::fmt::Display for EvMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvMode::Absolute => write!(f, "Absolute EV Value"),
+            EvMode::Delta => write!(f, "Delta EV Change"),
+        }
+    }
+}
+
+/// Which frame of a sequence the deltas of delta mode are measured from.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DeltaReference {
+    /// The frame at the 0 of the sequence
+    #[default]
+    Zero,
+    First,
+    Middle,
+    /// 1-based position in the sequence
+    Position(usize),
+}
+
+impl DeltaReference {
+    /// Index of the reference frame in `sequence`, `None` if there is none or
+    /// it is a `*`, which has no value to measure from.
+    pub fn index(&self, sequence: &[Option<Rational32>]) -> Option<usize> {
+        self.position(sequence)
+            .filter(|index| sequence[*index].is_some())
+    }
+
+    fn position(&self, sequence: &[Option<Rational32>]) -> Option<usize> {
+        match self {
+            DeltaReference::Zero => sequence
+                .iter()
+                .position(|r| *r == Some(Rational32::from(0))),
+            DeltaReference::First => (!sequence.is_empty()).then_some(0),
+            DeltaReference::Middle => (!sequence.is_empty()).then_some(sequence.len() / 2),
+            DeltaReference::Position(position) => (1..=sequence.len())
+                .contains(position)
+                .then(|| position - 1),
+        }
+    }
+
+    /// Why [`DeltaReference::index`] found no reference in `sequence`.
+    pub fn missing_reason(&self, sequence: &[Option<Rational32>]) -> String {
+        if self.position(sequence).is_some() {
+            return "The reference frame of delta EV mode can't be a *, the other biases are measured from its value".to_string();
+        }
+        match self {
+            DeltaReference::Zero => {
+                "Delta EV mode needs a 0 in the sequence to act as the reference".to_string()
+            }
+            DeltaReference::Position(position) => format!(
+                "The reference frame {} is outside of the sequence",
+                position
+            ),
+            _ => "The sequence is empty".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for DeltaReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeltaReference::Zero => write!(f, "0 EV Frame"),
+            DeltaReference::First => write!(f, "First Frame"),
+            DeltaReference::Middle => write!(f, "Middle Frame"),
+            DeltaReference::Position(position) => write!(f, "Frame {}", position),
+        }
+    }
+}
+
+/// Parses `zero`, `first`, `middle` or a 1-based position like `2`, as used
+/// by `.ebo.toml` and the API.
+impl std::str::FromStr for DeltaReference {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "zero" => Ok(DeltaReference::Zero),
+            "first" => Ok(DeltaReference::First),
+            "middle" => Ok(DeltaReference::Middle),
+            other => match other.parse::<usize>() {
+                Ok(position) if position > 0 => Ok(DeltaReference::Position(position)),
+                _ => Err(format!(
+                    "Invalid delta reference \"{}\", use zero, first, middle or a frame number",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Parses a comma separated list of exposure biases, written as integers or
+/// rationals like `-10/10`.
+pub fn parse_exposure_sequence(sequence_str: &str) -> Vec<Rational32> {
+    sequence_str
+        .split(',')
+        .map(|s| s.trim())
+        .filter_map(|s| {
+            let parts: Vec<&str> = s.split('/').collect();
+            if parts.len() == 2 {
+                let n: i32 = parts[0].parse().ok()?;
+                let d: i32 = parts[1].parse().ok()?;
+                if d != 0 {
+                    Some(Rational32::new(n, d))
+                } else {
+                    None
+                }
+            } else {
+                s.parse::<i32>().ok().map(Rational32::from)
+            }
+        })
+        .collect()
+}
+
+/// Parses a sequence to match frames against, like [`parse_exposure_sequence`]
+/// but with `*` for a frame of any bias, as `None`: `0, *, *` groups a 0 EV
+/// frame with the two after it whatever the camera recorded for them.
+pub fn parse_exposure_pattern(pattern_str: &str) -> Vec<Option<Rational32>> {
+    pattern_str
+        .split(',')
+        .filter_map(|value| match value.trim() {
+            "*" => Some(None),
+            value => parse_exposure_sequence(value).first().copied().map(Some),
+        })
+        .collect()
+}
+
+/// Parses several sequences separated by semicolons, e.g. `0, -1, 1; 0, -1, 1, -2, 2`
+/// for a camera that alternates between 3 and 5 frame brackets, see
+/// [`parse_exposure_pattern`]. Empty ones are left out.
+pub fn parse_exposure_sequences(sequences_str: &str) -> Vec<Vec<Option<Rational32>>> {
+    sequences_str
+        .split(';')
+        .map(parse_exposure_pattern)
+        .filter(|sequence| !sequence.is_empty())
+        .collect()
+}
@@ -1,47 +1,158 @@
+use chrono::NaiveDateTime;
+use ebo_core::{
+    parse_exposure_sequence, parse_exposure_sequences, DeltaReference, EvFormat, EvMode, MatchMode,
+};
 use eframe::egui;
-use log::warn;
+use log::{info, warn};
 use num_rational::Rational32;
-use rfd;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::{
-    atomic::{AtomicBool, AtomicUsize, Ordering},
-    Arc,
-};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
 use std::thread;
-use crate::file_utils::{count_files_in_directory, extract_raw_metadata, process_directory};
+use std::time::Duration;
+use crate::archive_manifest::{manifest_roots, write_manifests, ArchiveVerifyWindow};
+use crate::audit::{AuditSettings, AuditWindow};
+use crate::background_mode::enter_background_mode;
+use crate::bracket_kind::CaptureSettings;
+use crate::bracket_simulation::BracketSimulationWindow;
+use crate::camera_profile::{self, DetectedCamera};
+use crate::catalog_browser::CatalogBrowser;
+use crate::detachable;
+use crate::dry_run::DryRunWindow;
+use crate::exif_metadata::NON_RAW_EXTENSIONS;
+use crate::exiftool::{self, ExiftoolSettings};
+use crate::exposure_repair::ExposureRepair;
+use crate::file_utils::{
+    collect_directories, count_files_by_extension, count_files_in_directory, ev_tolerance,
+    open_file, process_directories, process_files, process_sequence_list, seconds_to_time_delta,
+    BracketModeOverride, ClockOffset, DateFolders, JobProgress, ProcessSettings, ProgressEvent,
+    RunReport, DEFAULT_FOLDER_NAME,
+};
+use crate::filter_preview::{FilterPreviewSettings, FilterPreviewWindow};
+use crate::first_run::{FirstRunWizard, WizardOutcome, FIRST_RUN_DONE_KEY};
+use crate::folder_settings::{self, FolderSettings};
+use crate::hdr_tools::{DEFAULT_FUSION_COMMAND, DEFAULT_HDRMERGE_PATH};
+use crate::instance::{self, IpcCommand};
+use crate::journal::{self, JournalEntry, RunJournal};
+use crate::metadata_cache::read_file_metadata;
+use crate::metadata_export::{self, SequenceMatching};
+use crate::output_writer::{TextfileConflict, TextfileFormat};
+use crate::result_review::ResultReview;
+use crate::run_log::{self, RunLog};
+use crate::script_actions::{self, ScriptAction};
+use crate::sequence_check::sequence_warnings;
+use crate::sequence_list::load_sequence_list;
+use crate::sleep_inhibitor::SleepInhibitor;
+use crate::taskbar::TaskbarProgress;
+use crate::theme::{self, Theme};
+use crate::webhook::{self, RunSummary};
+
+/// Key of the chosen theme in egui's persisted memory
+const THEME_KEY: &str = "theme";
+const BRACKET_MODE_OVERRIDES_KEY: &str = "bracket_mode_overrides";
+const CLOCK_OFFSETS_KEY: &str = "clock_offsets";
+const PRESETS_KEY: &str = "presets";
+const EXIFTOOL_KEY: &str = "exiftool";
+/// Key of the settings a launch restores in eframe's storage
+const SESSION_SETTINGS_KEY: &str = "session_settings";
+
+/// Enough for an archive sorted by year, month and day
+const DEFAULT_SUBFOLDER_DEPTH: usize = 3;
+
+/// Pauses longer than this end a burst, in seconds
+const DEFAULT_BURST_GAP: f64 = 3.0;
+
+/// Longest time a sequence may be shot over, in seconds
+const DEFAULT_MAX_SEQUENCE_SPAN: f64 = 10.0;
+
+/// Covers 7/10 against 2/3, but not a third of a stop
+pub const DEFAULT_EV_TOLERANCE: f64 = 0.05;
 
-#[derive(Debug, Clone, PartialEq)]
+/// Progress of a run is shown at most this often, a repaint per file would keep a core busy
+const PROGRESS_REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+
+pub const DEFAULT_EXTENSIONS: &[&str] = &[
+    "ari", "cr3", "cr2", "crw", "erf", "raf", "3fr", "kdc", "dcs", "dcr", "iiq", "mos", "mef",
+    "mrw", "nef", "nrw", "orf", "rw2", "pef", "iiq", "srw", "arw", "srf", "sr2", "dng",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Action {
     MoveToFolder,
+    CopyToFolder,
     SaveSequencesToTextfile,
+    WriteGroupTags,
+    AddToCatalog,
+    /// A script per sequence aligning and fusing its frames with
+    /// align_image_stack and enfuse
+    WriteFusionScripts,
+    /// A Hugin project per sequence with its frames as one stack
+    WriteHuginProjects,
+    /// A script per folder merging each sequence into a DNG with HDRMerge
+    WriteHdrMergeCommands,
+    /// Tags in darktable's sidecars and a script per folder grouping each
+    /// sequence in darktable
+    WriteDarktableGroups,
+    /// A hierarchical `Bracket|…` keyword per sequence for Lightroom
+    WriteLightroomKeywords,
+    /// A `Bracket/…` tag per sequence in `digiKam:TagsList`
+    WriteDigikamTags,
+    /// The command template of the settings, run with the system's shell
+    RunCommand,
+    /// Gives the frames of each sequence a common prefix and index, e.g.
+    /// `BRK0001_1of5_IMG_2034.NEF`, without moving them
+    RenameInPlace,
+    /// A script from the actions directory, by file name
+    Script(String),
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum EvMode {
-    Absolute,
-    Delta,
-}
-
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BracketOrder {
     ZeroMinusPlus,
     MinusZeroPlus,
 }
 
-impl std::fmt::Display for BracketOrder {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum View {
+    Organize,
+    Catalog,
+}
+
+/// The order files are searched for sequences in.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileOrder {
+    /// As selected, or as listed by the filesystem for a folder
+    #[default]
+    #[serde(rename = "filesystem")]
+    Selection,
+    CaptureTime,
+    FileName,
+    /// By file name, with numbers compared by value so `IMG_9999` comes
+    /// before `IMG_10000`
+    NaturalFileName,
+}
+
+impl std::fmt::Display for FileOrder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            BracketOrder::ZeroMinusPlus => write!(f, "ZeroMinusPlus"),
-            BracketOrder::MinusZeroPlus => write!(f, "MinusZeroPlus"),
+            FileOrder::Selection => write!(f, "Order as Selected"),
+            FileOrder::CaptureTime => write!(f, "Sort by Capture Time"),
+            FileOrder::FileName => write!(f, "Sort by File Name"),
+            FileOrder::NaturalFileName => write!(f, "Sort by File Name and Number"),
         }
     }
 }
 
-impl std::fmt::Display for EvMode {
+impl std::fmt::Display for BracketOrder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            EvMode::Absolute => write!(f, "Absolute EV Value"),
-            EvMode::Delta => write!(f, "Delta EV Change"),
+            BracketOrder::ZeroMinusPlus => write!(f, "ZeroMinusPlus"),
+            BracketOrder::MinusZeroPlus => write!(f, "MinusZeroPlus"),
         }
     }
 }
@@ -50,41 +161,356 @@ impl std::fmt::Display for Action {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Action::MoveToFolder => write!(f, "Move to Folder"),
+            Action::CopyToFolder => write!(f, "Copy to Folder"),
             Action::SaveSequencesToTextfile => write!(f, "Save Sequences to Textfile"),
+            Action::WriteGroupTags => write!(f, "Write Group Tags (XMP)"),
+            Action::AddToCatalog => write!(f, "Add to Catalog Only"),
+            Action::WriteFusionScripts => write!(f, "Write Fusion Scripts"),
+            Action::WriteHuginProjects => write!(f, "Write Hugin Projects"),
+            Action::WriteHdrMergeCommands => write!(f, "Write HDRMerge Commands"),
+            Action::WriteDarktableGroups => write!(f, "Write darktable Groups"),
+            Action::WriteLightroomKeywords => write!(f, "Write Lightroom Keywords"),
+            Action::WriteDigikamTags => write!(f, "Write digiKam Tags"),
+            Action::RunCommand => write!(f, "Run Command"),
+            Action::RenameInPlace => write!(f, "Rename in Place"),
+            Action::Script(file_name) => write!(f, "Script {}", file_name),
         }
     }
 }
+
+impl Action {
+    /// Whether the action puts the sequences into folders of their own.
+    pub fn organizes(&self) -> bool {
+        matches!(self, Action::MoveToFolder | Action::CopyToFolder)
+    }
+
+    /// Whether the action moves, renames or copies the files, before any
+    /// other action runs on them.
+    pub fn relocates(&self) -> bool {
+        self.organizes() || *self == Action::RenameInPlace
+    }
+}
+
+/// The actions of a run as shown and logged, e.g. "Move to Folder + Save Sequences to Textfile".
+pub fn describe_actions(actions: &[Action]) -> String {
+    actions
+        .iter()
+        .map(Action::to_string)
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// Refuses sets of actions a run can't execute together.
+pub fn check_actions(actions: &[Action]) -> Result<(), String> {
+    if actions.is_empty() {
+        return Err("No action selected".to_string());
+    }
+    if actions.iter().filter(|action| action.relocates()).count() > 1 {
+        return Err(
+            "Move to Folder, Copy to Folder and Rename in Place can't be combined".to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Reads a single action or a list of them, so settings written before
+/// actions could be combined still load.
+pub fn deserialize_actions<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Action>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMore {
+        One(Action),
+        More(Vec<Action>),
+    }
+    Ok(match OneOrMore::deserialize(deserializer)? {
+        OneOrMore::One(action) => vec![action],
+        OneOrMore::More(actions) => actions,
+    })
+}
+
+/// [`deserialize_actions`] for settings that may leave the actions out.
+pub fn deserialize_optional_actions<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<Action>>, D::Error> {
+    deserialize_actions(deserializer).map(Some)
+}
+
 #[derive(Debug)]
 pub struct ExposureInfo {
+    pub path: PathBuf,
     pub filename: String,
     pub exposure_bias_n: Option<i32>,
     pub exposure_bias_d: Option<i32>,
     pub exposure_mode: Option<u16>,
+    pub capture_time: Option<NaiveDateTime>,
+    pub capture_settings: Option<CaptureSettings>,
     pub error_message: Option<String>,
 }
 
+/// What to do with the selected rows of the exposure bias table.
+enum ExposureTableAction {
+    Open,
+    Exclude,
+    Include,
+    Copy,
+    ExportCsv,
+    ExportJson,
+    Repair,
+    Simulate,
+}
+
+/// The selected rows of the exposure bias table, all rows if none is selected.
+fn selected_rows<'a>(
+    infos: &'a [ExposureInfo],
+    selection: &'a BTreeSet<usize>,
+) -> impl Iterator<Item = &'a ExposureInfo> + 'a {
+    infos
+        .iter()
+        .enumerate()
+        .filter(move |(i, _)| selection.is_empty() || selection.contains(i))
+        .map(|(_, info)| info)
+}
+
+/// A folder picked for processing, with a preview of what its scan will see.
+pub struct PickedFolder {
+    pub path: PathBuf,
+    /// Files in the folder and the scanned subfolders by lowercase extension,
+    /// or why the folder can't be read
+    pub files_by_extension: Result<BTreeMap<String, usize>, String>,
+    /// Folders the scan covers, the picked one and its subfolders
+    pub folders: usize,
+    /// What the folder's `.ebo.toml` sets, if it has one
+    pub settings: Option<Result<String, String>>,
+}
+
+impl PickedFolder {
+    fn new(path: PathBuf, subfolder_depth: usize) -> Self {
+        let dirs = collect_directories(&path, subfolder_depth);
+        let files_by_extension = count_files_by_extension(&path)
+            .map_err(|e| e.to_string())
+            .map(|mut counts| {
+                for counted in dirs[1..]
+                    .iter()
+                    .filter_map(|dir| count_files_by_extension(dir).ok())
+                {
+                    for (extension, count) in counted {
+                        *counts.entry(extension).or_default() += count;
+                    }
+                }
+                counts
+            });
+        Self {
+            files_by_extension,
+            folders: dirs.len(),
+            settings: FolderSettings::load(&path)
+                .map(|s| s.map(|s| s.summary()))
+                .transpose(),
+            path,
+        }
+    }
+
+    /// "120 ARW, 2 JPG (skipped)" for the files `extensions` does or doesn't take.
+    fn preview(&self, extensions: &[String]) -> String {
+        let counts = match &self.files_by_extension {
+            Ok(counts) => counts,
+            Err(error) => return format!("Can't be read: {}", error),
+        };
+        if counts.is_empty() {
+            return "No files".to_string();
+        }
+        let (scanned, skipped): (Vec<_>, Vec<_>) = counts.iter().partition(|(extension, _)| {
+            extensions.iter().any(|e| e.eq_ignore_ascii_case(extension))
+        });
+        let list = |counts: Vec<(&String, &usize)>| {
+            counts
+                .iter()
+                .map(|(extension, count)| match extension.as_str() {
+                    "" => format!("{} without extension", count),
+                    extension => format!("{} {}", count, extension.to_uppercase()),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let files = match (scanned.is_empty(), skipped.is_empty()) {
+            (true, _) => format!("No matching files, {} (skipped)", list(skipped)),
+            (false, true) => list(scanned),
+            (false, false) => format!("{}, {} (skipped)", list(scanned), list(skipped)),
+        };
+        if self.folders > 1 {
+            format!("{} in {} folders", files, self.folders)
+        } else {
+            files
+        }
+    }
+
+    fn matching_files(&self, extensions: &[String]) -> usize {
+        self.files_by_extension.as_ref().map_or(0, |counts| {
+            counts
+                .iter()
+                .filter(|(extension, _)| {
+                    extensions.iter().any(|e| e.eq_ignore_ascii_case(extension))
+                })
+                .map(|(_, count)| count)
+                .sum()
+        })
+    }
+}
+
 pub struct ExposureBracketingOrganizerApp {
-    pub picked_folder: Option<String>,
-    pub total_files: Arc<AtomicUsize>,
-    pub processed_files: Arc<AtomicUsize>,
-    pub exposure_bracketings_found: Arc<AtomicUsize>,
-    pub running: Arc<AtomicBool>,
+    pub view: View,
+    /// Root folders of the next run, each one searched on its own
+    pub picked_folders: Vec<PickedFolder>,
+    pub scan_subfolders: bool,
+    /// How many levels of subfolders below each root are scanned
+    pub subfolder_depth: usize,
+    pub picked_files: Option<Vec<PathBuf>>,
+    pub file_order: FileOrder,
+    /// Order of the files of a picked folder
+    pub folder_order: FileOrder,
+    pub loaded_list: Option<PathBuf>,
+    pub loaded_sequences: Option<Vec<Vec<PathBuf>>>,
+    /// The `.ebo.toml` of the picked folder, until it is applied or dismissed
+    folder_settings: Option<Result<FolderSettings, String>>,
+    pub progress: JobProgress,
 
     pub extensions: Vec<String>,
     pub exposure_bias_sequence: String,
-    pub selected_action: Action,
+    /// Executed on each sequence, see [`check_actions`]
+    pub selected_actions: Vec<Action>,
+    /// Scripts from the actions directory, found on startup
+    script_actions: Vec<ScriptAction>,
     pub ev_mode: EvMode,
+    pub delta_reference: DeltaReference,
+    /// How far a bias may be off the sequence value and still match, in EV
+    pub ev_tolerance: f64,
+    pub match_mode: MatchMode,
+    pub anchor_on_reference: bool,
+    /// Bias of the reference frames windows are anchored on, usually 0 EV
+    pub anchor_bias: String,
+    pub detect_other_brackets: bool,
+    pub computed_exposure: bool,
+    pub group_per_camera: bool,
+    /// Find brackets without a sequence
+    pub auto_detect: bool,
+    pub split_bursts: bool,
+    /// Longest pause within a burst, in seconds
+    pub burst_gap: f64,
+    pub limit_sequence_span: bool,
+    /// Longest time between the first and last frame of a sequence, in seconds
+    pub max_sequence_span: f64,
     pub filter_by_auto_bracket: bool,
+    /// Cameras whose brackets report another exposure mode than auto bracket
+    pub bracket_mode_overrides: Vec<BracketModeOverride>,
+    /// Time corrections of cameras whose clocks differ from the others
+    pub clock_offsets: Vec<ClockOffset>,
+    pub presets: Vec<Preset>,
+    /// Name the current settings are saved under as a preset
+    preset_name: String,
+    /// Camera the saved preset is applied for
+    preset_camera: String,
+    /// The camera most files of a picked folder were shot with, found in the background
+    detected_camera: Arc<Mutex<Option<DetectedCamera>>>,
+    detecting_camera: bool,
+    /// Which camera profile was applied for the picked folder
+    camera_profile_note: Option<String>,
+    pub use_metadata_cache: bool,
+    pub background_mode: bool,
+    pub exiftool: ExiftoolSettings,
+    /// Result of checking the configured exiftool
+    exiftool_version: Option<Result<String, String>>,
+    pub webhook_url: String,
+    pub embed_xmp: bool,
+    /// Template of the fusion scripts, see [`crate::hdr_tools`]
+    pub fusion_command: String,
+    /// The HDRMerge executable the HDRMerge commands run
+    pub hdrmerge_path: String,
+    /// Run per sequence by "Run Command", see [`crate::script_actions::fill_command`]
+    pub command_template: String,
+    pub folder_metadata: bool,
+    pub date_folders: DateFolders,
+    /// Name of the sequence folders with `{name}`, `{date}` and `{note}`
+    pub folder_name: String,
+    /// Where sequence folders are created, `None` for next to the files
+    pub destination_root: Option<PathBuf>,
+    /// List the organized files with their hashes to verify the archive later
+    pub write_manifest: bool,
+    /// Remove scanned subfolders a move left empty
+    pub remove_empty_folders: bool,
+    /// Move the XMP sidecars of a sequence's files along
+    pub move_sidecars: bool,
+    /// Move the JPEGs and sidecars named like a sequence's raws along
+    pub move_companions: bool,
+    pub textfile_conflict: TextfileConflict,
+    pub textfile_format: TextfileFormat,
+    /// Replace the sequences file instead of appending to it
+    pub overwrite_textfile: bool,
+    pub write_run_log: bool,
+    /// Where run logs go, `None` for the processed folder
+    pub log_directory: Option<PathBuf>,
 
     pub show_exposure_window: bool,
+    pub exposure_window_detached: bool,
     pub exposure_infos: Vec<ExposureInfo>,
+    /// Selected rows of the exposure bias table, by index
+    exposure_selection: BTreeSet<usize>,
+    /// Row a Shift+Click range starts at
+    exposure_selection_anchor: Option<usize>,
+    /// Files left out of detection, picked in the exposure bias table
+    pub excluded_files: HashSet<PathBuf>,
+    exposure_repair: ExposureRepair,
+    audit_window: AuditWindow,
+    archive_verify_window: ArchiveVerifyWindow,
+    bracket_simulation: BracketSimulationWindow,
+    result_review: ResultReview,
+    dry_run_window: DryRunWindow,
+    /// Notes given in the preview, for the run confirmed from it
+    sequence_notes: HashMap<PathBuf, String>,
+    /// Sequences rejected in the preview, by their first file
+    rejected_sequences: HashSet<PathBuf>,
+    /// Start shows the matched sequences for review instead of organizing them
+    pub review_sequences: bool,
+    /// The journal of the last run while asking whether to undo it
+    undo_confirmation: Option<Vec<JournalEntry>>,
+    first_run_wizard: FirstRunWizard,
+    filter_preview: FilterPreviewWindow,
     pub show_error_messagebox: bool,
     pub error_messagebox_text: String,
+    pub show_modify_originals_confirmation: bool,
+    /// Asking whether to start with a sequence that looks implausible
+    show_sequence_warnings_confirmation: bool,
+    /// Why the last run found no sequences, filled by the worker thread
+    run_diagnostics: Arc<Mutex<Vec<String>>>,
+    /// What the last run found and changed besides its sequences, filled by the worker thread
+    run_outcome: Arc<Mutex<RunOutcome>>,
+    catalog_browser: CatalogBrowser,
+    catalog_detached: bool,
 
     pub exposure_settings: ExposureSettings,
+    /// How biases are shown in tables, reports and folder comments
+    pub ev_format: EvFormat,
+    pub theme: Theme,
+    /// Whether the high-contrast style is currently set on the context
+    applied_high_contrast: Option<bool>,
+
+    window_title: String,
+    taskbar_progress: TaskbarProgress,
+    ipc_commands: Option<Receiver<IpcCommand>>,
+}
+
+/// The parts of a finished run's report shown below the progress bar.
+#[derive(Debug, Default)]
+struct RunOutcome {
+    removed_folders: Vec<PathBuf>,
+    /// Sequence folders the run moved or copied files into
+    created_folders: Vec<PathBuf>,
+    /// Number of sequences by their number of frames
+    frame_counts: BTreeMap<usize, usize>,
+    anomalies: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExposureSettings {
     pub ev_step: f32,
     pub num_images: u32,
@@ -101,6 +527,55 @@ impl Default for ExposureSettings {
     }
 }
 
+/// The settings the last session ended with, restored on the next launch.
+#[derive(Serialize, Deserialize)]
+struct SessionSettings {
+    exposure_settings: ExposureSettings,
+    exposure_bias_sequence: String,
+    extensions: Vec<String>,
+    #[serde(alias = "selected_action", deserialize_with = "deserialize_actions")]
+    selected_actions: Vec<Action>,
+    ev_mode: EvMode,
+    filter_by_auto_bracket: bool,
+    /// The first picked folder, opened again if it still exists
+    folder: Option<PathBuf>,
+    #[serde(default)]
+    ev_format: EvFormat,
+    #[serde(default)]
+    ev_tolerance: Option<f64>,
+    #[serde(default)]
+    match_mode: MatchMode,
+    #[serde(default)]
+    review_sequences: bool,
+    #[serde(default)]
+    fusion_command: Option<String>,
+    #[serde(default)]
+    hdrmerge_path: Option<String>,
+    #[serde(default)]
+    command_template: Option<String>,
+    #[serde(default)]
+    folder_name: Option<String>,
+}
+
+/// A named set of bracket settings, e.g. "Sony 5x1EV real estate", to switch
+/// between cameras and jobs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub exposure_settings: ExposureSettings,
+    pub exposure_bias_sequence: String,
+    pub ev_mode: EvMode,
+    pub filter_by_auto_bracket: bool,
+    pub detect_other_brackets: bool,
+    pub extensions: Vec<String>,
+    #[serde(alias = "selected_action", deserialize_with = "deserialize_actions")]
+    pub selected_actions: Vec<Action>,
+    /// Part of the make and model of the camera the preset is applied for
+    /// automatically, see [`camera_profile`]
+    #[serde(default)]
+    pub camera: Option<String>,
+}
+
 impl Default for ExposureBracketingOrganizerApp {
     fn default() -> Self {
         let exposure_settings = ExposureSettings::default();
@@ -111,75 +586,196 @@ impl Default for ExposureBracketingOrganizerApp {
         );
 
         Self {
-            picked_folder: None,
-            total_files: Arc::new(AtomicUsize::new(0)),
-            processed_files: Arc::new(AtomicUsize::new(0)),
-            exposure_bracketings_found: Arc::new(AtomicUsize::new(0)),
-            running: Arc::new(AtomicBool::new(false)),
+            view: View::Organize,
+            picked_folders: Vec::new(),
+            scan_subfolders: false,
+            subfolder_depth: DEFAULT_SUBFOLDER_DEPTH,
+            picked_files: None,
+            file_order: FileOrder::CaptureTime,
+            folder_order: FileOrder::Selection,
+            loaded_list: None,
+            loaded_sequences: None,
+            folder_settings: None,
+            progress: JobProgress::default(),
 
             exposure_bias_sequence,
-            selected_action: Action::MoveToFolder,
+            selected_actions: vec![Action::MoveToFolder],
+            script_actions: script_actions::discover(),
             ev_mode: EvMode::Delta,
+            delta_reference: DeltaReference::Zero,
+            ev_tolerance: DEFAULT_EV_TOLERANCE,
+            match_mode: MatchMode::default(),
+            anchor_on_reference: false,
+            anchor_bias: "0".to_string(),
+            detect_other_brackets: false,
+            computed_exposure: false,
+            group_per_camera: false,
+            auto_detect: false,
+            split_bursts: false,
+            burst_gap: DEFAULT_BURST_GAP,
+            limit_sequence_span: false,
+            max_sequence_span: DEFAULT_MAX_SEQUENCE_SPAN,
             filter_by_auto_bracket: true,
-            extensions: vec![
-                "ari".into(),
-                "cr3".into(),
-                "cr2".into(),
-                "crw".into(),
-                "erf".into(),
-                "raf".into(),
-                "3fr".into(),
-                "kdc".into(),
-                "dcs".into(),
-                "dcr".into(),
-                "iiq".into(),
-                "mos".into(),
-                "mef".into(),
-                "mrw".into(),
-                "nef".into(),
-                "nrw".into(),
-                "orf".into(),
-                "rw2".into(),
-                "pef".into(),
-                "iiq".into(),
-                "srw".into(),
-                "arw".into(),
-                "srf".into(),
-                "sr2".into(),
-                "dng".into(),
-            ],
+            bracket_mode_overrides: Vec::new(),
+            clock_offsets: Vec::new(),
+            presets: Vec::new(),
+            preset_name: String::new(),
+            preset_camera: String::new(),
+            detected_camera: Arc::new(Mutex::new(None)),
+            detecting_camera: false,
+            camera_profile_note: None,
+            use_metadata_cache: true,
+            background_mode: false,
+            exiftool: ExiftoolSettings::default(),
+            exiftool_version: None,
+            webhook_url: String::new(),
+            embed_xmp: false,
+            fusion_command: DEFAULT_FUSION_COMMAND.to_string(),
+            hdrmerge_path: DEFAULT_HDRMERGE_PATH.to_string(),
+            command_template: String::new(),
+            folder_metadata: false,
+            date_folders: DateFolders::Off,
+            folder_name: DEFAULT_FOLDER_NAME.to_string(),
+            destination_root: None,
+            write_manifest: false,
+            remove_empty_folders: false,
+            move_sidecars: true,
+            move_companions: false,
+            textfile_conflict: TextfileConflict::Append,
+            textfile_format: TextfileFormat::Paths,
+            overwrite_textfile: false,
+            write_run_log: false,
+            log_directory: None,
+            extensions: DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
 
             show_exposure_window: false,
+            exposure_window_detached: false,
             exposure_infos: Vec::new(),
+            exposure_selection: BTreeSet::new(),
+            exposure_selection_anchor: None,
+            excluded_files: HashSet::new(),
+            exposure_repair: ExposureRepair::default(),
+            audit_window: AuditWindow::default(),
+            archive_verify_window: ArchiveVerifyWindow::default(),
+            bracket_simulation: BracketSimulationWindow::default(),
+            result_review: ResultReview::default(),
+            dry_run_window: DryRunWindow::default(),
+            sequence_notes: HashMap::new(),
+            rejected_sequences: HashSet::new(),
+            review_sequences: false,
+            undo_confirmation: None,
+            first_run_wizard: FirstRunWizard::default(),
+            filter_preview: FilterPreviewWindow::default(),
             show_error_messagebox: false,
             error_messagebox_text: "".to_string(),
+            show_modify_originals_confirmation: false,
+            show_sequence_warnings_confirmation: false,
+            run_diagnostics: Arc::default(),
+            run_outcome: Arc::default(),
+            catalog_browser: CatalogBrowser::default(),
+            catalog_detached: false,
             exposure_settings,
+            ev_format: EvFormat::default(),
+            theme: Theme::default(),
+            applied_high_contrast: None,
+
+            window_title: crate::APP_NAME.to_string(),
+            taskbar_progress: TaskbarProgress::default(),
+            ipc_commands: None,
         }
     }
 }
 
-fn parse_exposure_sequence(sequence_str: &str) -> Vec<Rational32> {
-    sequence_str
-        .split(',')
-        .map(|s| s.trim())
-        .filter_map(|s| {
-            let parts: Vec<&str> = s.split('/').collect();
-            if parts.len() == 2 {
-                let n: i32 = parts[0].parse().ok()?;
-                let d: i32 = parts[1].parse().ok()?;
-                if d != 0 {
-                    Some(Rational32::new(n, d))
-                } else {
-                    None
+impl ExposureBracketingOrganizerApp {
+    pub fn new(cc: &eframe::CreationContext<'_>, startup_command: IpcCommand) -> Self {
+        let mut app = Self {
+            ipc_commands: instance::listen(cc.egui_ctx.clone()),
+            theme: cc
+                .egui_ctx
+                .data_mut(|d| d.get_persisted(egui::Id::new(THEME_KEY)))
+                .unwrap_or_default(),
+            bracket_mode_overrides: cc
+                .egui_ctx
+                .data_mut(|d| d.get_persisted(egui::Id::new(BRACKET_MODE_OVERRIDES_KEY)))
+                .unwrap_or_default(),
+            clock_offsets: cc
+                .egui_ctx
+                .data_mut(|d| d.get_persisted(egui::Id::new(CLOCK_OFFSETS_KEY)))
+                .unwrap_or_default(),
+            presets: cc
+                .egui_ctx
+                .data_mut(|d| d.get_persisted(egui::Id::new(PRESETS_KEY)))
+                .unwrap_or_default(),
+            exiftool: cc
+                .egui_ctx
+                .data_mut(|d| d.get_persisted(egui::Id::new(EXIFTOOL_KEY)))
+                .unwrap_or_default(),
+            ..Self::default()
+        };
+        exiftool::configure(app.exiftool.binary());
+        let session = cc.storage.and_then(|storage| {
+            eframe::get_value::<SessionSettings>(storage, SESSION_SETTINGS_KEY)
+        });
+        let last_folder = session.and_then(|session| app.restore_session(session));
+        repaint_on_progress(&app.progress, cc.egui_ctx.clone());
+        match startup_command {
+            IpcCommand::Open(path) => app.select_folder(path),
+            IpcCommand::Run(path) => {
+                app.select_folder(path);
+                app.apply_folder_settings();
+                app.request_processing();
+            }
+            IpcCommand::Focus => {
+                // Not in the way of a folder opened from the file manager or a scripted run
+                app.first_run_wizard.open = !cc
+                    .egui_ctx
+                    .data_mut(|d| d.get_persisted(egui::Id::new(FIRST_RUN_DONE_KEY)))
+                    .unwrap_or(false);
+                if let Some(folder) = last_folder.filter(|folder| folder.is_dir()) {
+                    app.select_folder(folder);
                 }
-            } else {
-                s.parse::<i32>().ok().map(Rational32::from)
             }
-        })
-        .collect()
+        }
+        app
+    }
+
+    /// Takes over the settings of the last session, returns its folder.
+    fn restore_session(&mut self, session: SessionSettings) -> Option<PathBuf> {
+        self.exposure_settings = session.exposure_settings;
+        self.exposure_bias_sequence = session.exposure_bias_sequence;
+        self.extensions = session.extensions;
+        // A script removed since is not offered anymore
+        let actions: Vec<Action> = session.selected_actions.into_iter()
+            .filter(|action| !matches!(action, Action::Script(file_name) if script_actions::find(file_name).is_none()))
+            .collect();
+        if !actions.is_empty() {
+            self.selected_actions = actions;
+        }
+        self.ev_mode = session.ev_mode;
+        self.filter_by_auto_bracket = session.filter_by_auto_bracket;
+        self.ev_format = session.ev_format;
+        if let Some(tolerance) = session.ev_tolerance {
+            self.ev_tolerance = tolerance;
+        }
+        self.match_mode = session.match_mode;
+        self.review_sequences = session.review_sequences;
+        if let Some(command) = session.fusion_command {
+            self.fusion_command = command;
+        }
+        if let Some(path) = session.hdrmerge_path {
+            self.hdrmerge_path = path;
+        }
+        if let Some(command) = session.command_template {
+            self.command_template = command;
+        }
+        if let Some(folder_name) = session.folder_name {
+            self.folder_name = folder_name;
+        }
+        session.folder
+    }
 }
 
-fn exposure_mode_to_string(mode: u16) -> &'static str {
+pub fn exposure_mode_to_string(mode: u16) -> &'static str {
     match mode {
         0 => "Auto exposure",
         1 => "Manual exposure",
@@ -188,6 +784,33 @@ fn exposure_mode_to_string(mode: u16) -> &'static str {
     }
 }
 
+/// E.g. "+1:00:05" or "-0:00:30"
+fn format_clock_offset(seconds: i64) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.unsigned_abs();
+    format!(
+        "{}{}:{:02}:{:02}",
+        sign,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Takes "[-]h:mm:ss", "[-]m:ss" or plain seconds.
+fn parse_clock_offset(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let mut seconds = 0.0;
+    for part in text.split(':') {
+        seconds = seconds * 60.0 + part.trim().parse::<f64>().ok()?;
+    }
+    Some(sign * seconds)
+}
+
 fn generate_exposure_sequence(ev_step: f32, num_images: u32, order: &BracketOrder) -> String {
     if num_images == 0 {
         return "".to_string();
@@ -224,8 +847,166 @@ fn generate_exposure_sequence(ev_step: f32, num_images: u32, order: &BracketOrde
 }
 
 impl eframe::App for ExposureBracketingOrganizerApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let session = SessionSettings {
+            exposure_settings: self.exposure_settings.clone(),
+            exposure_bias_sequence: self.exposure_bias_sequence.clone(),
+            extensions: self.extensions.clone(),
+            selected_actions: self.selected_actions.clone(),
+            ev_mode: self.ev_mode.clone(),
+            filter_by_auto_bracket: self.filter_by_auto_bracket,
+            folder: self.picked_folders.first().map(|f| f.path.clone()),
+            ev_format: self.ev_format,
+            ev_tolerance: Some(self.ev_tolerance),
+            match_mode: self.match_mode,
+            review_sequences: self.review_sequences,
+            fusion_command: Some(self.fusion_command.clone()),
+            hdrmerge_path: Some(self.hdrmerge_path.clone()),
+            command_template: Some(self.command_template.clone()),
+            folder_name: Some(self.folder_name.clone()),
+        };
+        eframe::set_value(storage, SESSION_SETTINGS_KEY, &session);
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // Checked every frame, so switching the OS setting takes effect right away
+        let high_contrast = self.theme.is_high_contrast();
+        if self.applied_high_contrast != Some(high_contrast) {
+            theme::apply(ctx, high_contrast);
+            self.applied_high_contrast = Some(high_contrast);
+        }
+
+        egui::TopBottomPanel::top("view_tabs").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.view, View::Organize, "Organize");
+                if self.catalog_detached {
+                    ui.add_enabled(false, egui::Button::selectable(false, "Catalog"))
+                        .on_disabled_hover_text("The catalog is open in a separate window");
+                } else if ui
+                    .selectable_value(&mut self.view, View::Catalog, "Catalog")
+                    .on_hover_text("Browse the sequences added to the catalog")
+                    .clicked()
+                {
+                    self.catalog_browser.reload();
+                }
+                if self.view == View::Catalog
+                    && ui
+                        .small_button("Detach")
+                        .on_hover_text("Open the catalog in a separate window")
+                        .clicked()
+                {
+                    self.catalog_detached = true;
+                    self.view = View::Organize;
+                }
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("Getting Started")
+                        .on_hover_text("Set up the sequence from a sample bracket and preview a folder")
+                        .clicked()
+                    {
+                        self.first_run_wizard.restart();
+                    }
+                    let previous = self.theme;
+                    // Right to left, the label goes after the combo box
+                    let response = egui::ComboBox::from_id_salt("theme_selector")
+                        .selected_text(self.theme.to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.theme, Theme::System, "System")
+                                .on_hover_text("High contrast while the high-contrast mode of Windows is on");
+                            ui.selectable_value(&mut self.theme, Theme::Standard, "Standard");
+                            ui.selectable_value(&mut self.theme, Theme::HighContrast, "High Contrast")
+                                .on_hover_text("White on black with larger controls");
+                        })
+                        .response;
+                    let label = ui.label("Theme");
+                    response.labelled_by(label.id);
+                    if self.theme != previous {
+                        ctx.data_mut(|d| d.insert_persisted(egui::Id::new(THEME_KEY), self.theme));
+                    }
+                    let response = egui::ComboBox::from_id_salt("ev_format_selector")
+                        .selected_text(self.ev_format.to_string())
+                        .show_ui(ui, |ui| {
+                            for format in EvFormat::ALL {
+                                ui.selectable_value(&mut self.ev_format, format, format.to_string());
+                            }
+                        })
+                        .response
+                        .on_hover_text("How exposure biases are written in tables, reports and folder comments");
+                    let label = ui.label("EV");
+                    response.labelled_by(label.id);
+                });
+            });
+        });
+
+        match self.view {
+            View::Organize => self.show_organizer(ctx),
+            View::Catalog => {
+                egui::CentralPanel::default()
+                    .show(ctx, |ui| self.catalog_browser.show(ui, self.ev_format));
+            }
+        }
+
+        if self.catalog_detached {
+            let mut detached = true;
+            let is_open = detachable::show(ctx, "catalog_window", "Catalog", &mut detached, |ui| {
+                self.catalog_browser.show(ui, self.ev_format)
+            });
+            if !detached {
+                self.view = View::Catalog;
+            }
+            self.catalog_detached = detached && is_open;
+        }
+
+        self.handle_ipc_commands(ctx);
+        self.update_progress_indicators(ctx, frame);
+
+        // Exposure Bias Information window
+        self.show_exposure_window(ctx);
+        self.exposure_repair.show(
+            ctx,
+            &self.exposure_bias_sequence,
+            &self.extensions,
+            self.ev_format,
+        );
+        self.audit_window.show(ctx, || AuditSettings {
+            extensions: self.extensions.clone(),
+            sequences: parse_exposure_sequences(&self.exposure_bias_sequence),
+            ev_mode: self.ev_mode.clone(),
+            delta_reference: self.delta_reference,
+            ev_tolerance: ev_tolerance(self.ev_tolerance),
+            use_metadata_cache: self.use_metadata_cache,
+            ev_format: self.ev_format,
+        });
+        self.archive_verify_window.show(ctx);
+        self.bracket_simulation.show(ctx);
+        self.filter_preview.show(ctx, || FilterPreviewSettings {
+            extensions: self.extensions.clone(),
+            bracket_mode_overrides: self.bracket_mode_overrides.clone(),
+            use_metadata_cache: self.use_metadata_cache,
+        });
+        if let Some(outcome) = self.first_run_wizard.show(ctx, &self.extensions) {
+            self.apply_wizard_outcome(outcome);
+        }
+        let can_run = !self.progress.running.load(Ordering::Relaxed);
+        self.result_review.show(ctx, can_run);
+        if self.dry_run_window.show(ctx, can_run) {
+            self.sequence_notes = self.dry_run_window.notes();
+            self.rejected_sequences = self.dry_run_window.rejected();
+            self.request_processing();
+        }
+        self.show_sequence_warnings_confirmation(ctx);
+        self.show_modify_originals_confirmation(ctx);
+        self.show_undo_confirmation(ctx);
+        self.show_run_diagnostics(ctx);
+        self.show_error_messagebox(ctx);
+    }
+}
+
+impl ExposureBracketingOrganizerApp {
+    fn show_organizer(&mut self, ctx: &egui::Context) {
+        self.apply_detected_camera_profile(ctx);
         egui::CentralPanel::default().show(ctx, |ui| {
+            self.show_folder_settings_offer(ui);
 
             // Create a grid that acts like a two-column WidgetGallery with 1/3 : 2/3 ratio
             let avail_width = ui.available_width();
@@ -250,44 +1031,123 @@ impl eframe::App for ExposureBracketingOrganizerApp {
                             });
                             ui.vertical(|ui| {
                                 ui.horizontal(|ui| {
-                                    if ui.button("Browse…").clicked() {
-                                        if let Some(path) = rfd::FileDialog::new().pick_folder() {
-                                            self.picked_folder = Some(path.display().to_string());
+                                    if accessible_name(ui.button("Browse…"), "Browse for folders to organize").clicked() {
+                                        if let Some(paths) = rfd::FileDialog::new().pick_folders() {
+                                            self.picked_folders.clear();
+                                            self.add_folders(paths);
                                         }
                                     }
-                                    if let Some(p) = &self.picked_folder {
-                                        ui.monospace(p);
-                                    } else {
-                                        ui.label("No folder selected");
+                                    if !self.picked_folders.is_empty()
+                                        && ui.button("Add Folders…")
+                                            .on_hover_text("Process more folders in the same run, each one on its own.")
+                                            .clicked()
+                                    {
+                                        if let Some(paths) = rfd::FileDialog::new().pick_folders() {
+                                            self.add_folders(paths);
+                                        }
+                                    }
+                                    if ui.button("Pick files…")
+                                        .on_hover_text("Only organize a hand-selected set of files instead of a whole folder.")
+                                        .clicked()
+                                    {
+                                        if let Some(paths) = rfd::FileDialog::new()
+                                            .add_filter("Images", &self.extensions)
+                                            .pick_files()
+                                        {
+                                            self.picked_files = Some(paths);
+                                            self.picked_folders.clear();
+                                            self.loaded_list = None;
+                                            self.loaded_sequences = None;
+                                        }
+                                    }
+                                    if ui.button("Load list…")
+                                        .on_hover_text("Apply the action to the groups of a previously exported sequences.txt, sequences.tsv or JSON list.")
+                                        .clicked()
+                                    {
+                                        if let Some(path) = rfd::FileDialog::new()
+                                            .add_filter("Sequence Lists", &["txt", "tsv", "json"])
+                                            .pick_file()
+                                        {
+                                            match load_sequence_list(&path) {
+                                                Ok(sequences) => {
+                                                    self.loaded_sequences = Some(sequences);
+                                                    self.loaded_list = Some(path);
+                                                    self.picked_folders.clear();
+                                                    self.picked_files = None;
+                                                }
+                                                Err(e) => {
+                                                    self.show_error_messagebox = true;
+                                                    self.error_messagebox_text = e;
+                                                }
+                                            }
+                                        }
                                     }
                                 });
+                                if !self.picked_folders.is_empty() {
+                                    self.show_picked_folders(ui);
+                                } else if let Some(files) = &self.picked_files {
+                                    ui.label(format!("{} files selected", files.len()));
+                                    let response = egui::ComboBox::from_id_salt("file_order_selector")
+                                        .selected_text(self.file_order.to_string())
+                                        .show_ui(ui, |ui| {
+                                            ui.selectable_value(&mut self.file_order, FileOrder::Selection, "Order as Selected");
+                                            ui.selectable_value(&mut self.file_order, FileOrder::CaptureTime, "Sort by Capture Time");
+                                            ui.selectable_value(&mut self.file_order, FileOrder::FileName, "Sort by File Name");
+                                            ui.selectable_value(&mut self.file_order, FileOrder::NaturalFileName, "Sort by File Name and Number")
+                                                .on_hover_text("Compares the numbers in file names by value, so IMG_9999 comes before IMG_10000.");
+                                        })
+                                        .response;
+                                    accessible_name(response, "File order");
+                                } else if let (Some(list), Some(sequences)) =
+                                    (&self.loaded_list, &self.loaded_sequences)
+                                {
+                                    ui.monospace(list.display().to_string());
+                                    ui.label(format!("{} sequences loaded", sequences.len()));
+                                } else {
+                                    ui.label("No folder selected");
+                                }
+                                if !self.excluded_files.is_empty() {
+                                    ui.horizontal(|ui| {
+                                        ui.label(format!("{} files excluded from processing", self.excluded_files.len()));
+                                        if ui.small_button("Include All").clicked() {
+                                            self.excluded_files.clear();
+                                        }
+                                    });
+                                }
                             });
                             ui.end_row();
 
+                            // Row: Presets
+                            let label = ui.label(egui::RichText::new("Preset").strong());
+                            ui.vertical(|ui| self.show_presets(ui, label.id));
+                            ui.end_row();
+
                             // Row: Generate Exposure Sequence
                             ui.label(egui::RichText::new("Generate Sequence").strong());
                             ui.vertical(|ui| {
                                 let mut changed = false;
                                 ui.horizontal(|ui| {
-                                    ui.label("EV Step:").on_hover_text("Step between each exposure in EV (Exposure Value).");
-                                    if ui.add(egui::Slider::new(&mut self.exposure_settings.ev_step, 0.1..=5.0).step_by(0.1).fixed_decimals(1)).changed() {
+                                    let label = ui.label("EV Step:").on_hover_text("Step between each exposure in EV (Exposure Value).");
+                                    if ui.add(egui::Slider::new(&mut self.exposure_settings.ev_step, 0.1..=5.0).step_by(0.1).fixed_decimals(1)).labelled_by(label.id).changed() {
                                         changed = true;
                                     }
                                 });
                                 ui.horizontal(|ui| {
-                                    ui.label("Images: ").on_hover_text("Total number of images in the bracket (must be an odd number).");
-                                    if ui.add(egui::Slider::new(&mut self.exposure_settings.num_images, 3..=9).step_by(2.0)).changed() {
+                                    let label = ui.label("Images: ").on_hover_text("Total number of images in the bracket (must be an odd number).");
+                                    if ui.add(egui::Slider::new(&mut self.exposure_settings.num_images, 3..=9).step_by(2.0)).labelled_by(label.id).changed() {
                                         changed = true;
                                     }
                                 });
                                 ui.horizontal(|ui| {
-                                    ui.label("Bracket Order:");
+                                    let label = ui.label("Bracket Order:");
                                     egui::ComboBox::from_id_salt("bracket_order_selector")
                                         .selected_text(self.exposure_settings.bracket_order.to_string())
                                         .show_ui(ui, |ui| {
                                             changed |= ui.selectable_value(&mut self.exposure_settings.bracket_order, BracketOrder::ZeroMinusPlus, "ZeroMinusPlus").changed();
                                             changed |= ui.selectable_value(&mut self.exposure_settings.bracket_order, BracketOrder::MinusZeroPlus, "MinusZeroPlus").changed();
-                                        });
+                                        })
+                                        .response
+                                        .labelled_by(label.id);
                                 });
 
                                 if changed {
@@ -301,18 +1161,118 @@ impl eframe::App for ExposureBracketingOrganizerApp {
                             ui.end_row();
 
                             // Row: Exposure Bias Sequence
-                            ui.vertical(|ui| {
+                            let label = ui.vertical(|ui| {
                                 ui.label(egui::RichText::new("Exposure Bias Sequence").strong())
-                                    .on_hover_text("The Exposure Bias in EXIF is specified as signed rational");
-                            });
+                                    .on_hover_text("The Exposure Bias in EXIF is specified as signed rational. Separate several sequences with a semicolon, e.g. \"0, -1, 1; 0, -1, 1, -2, 2\" for a camera alternating between 3 and 5 frame brackets. The longest matching one is taken. A * matches a frame of any exposure bias, e.g. \"0, *, *\".")
+                            }).inner;
                             ui.vertical(|ui| {
-                                ui.text_edit_singleline(&mut self.exposure_bias_sequence);
-                                egui::ComboBox::from_id_salt("ev_mode_selector")
-                                    .selected_text(self.ev_mode.to_string())
-                                    .show_ui(ui, |ui| {
-                                        ui.selectable_value(&mut self.ev_mode, EvMode::Absolute, "Absolute EV Value");
-                                        ui.selectable_value(&mut self.ev_mode, EvMode::Delta, "Delta EV Change");
-                                    });
+                                ui.add_enabled(!self.auto_detect, egui::TextEdit::singleline(&mut self.exposure_bias_sequence))
+                                    .labelled_by(label.id);
+                                ui.checkbox(&mut self.auto_detect, "Auto-detect brackets")
+                                    .on_hover_text("No sequence needed: files shot without a longer pause are searched for runs of 3 to 9 frames whose biases are evenly spaced around the middle one. The preview lists the frame count, step and sequence of each bracket found.");
+                                if self.loaded_sequences.is_none() && !self.auto_detect {
+                                    for warning in self.sequence_warnings() {
+                                        ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", warning));
+                                    }
+                                }
+                                ui.horizontal(|ui| {
+                                    let response = egui::ComboBox::from_id_salt("ev_mode_selector")
+                                        .selected_text(self.ev_mode.to_string())
+                                        .show_ui(ui, |ui| {
+                                            ui.selectable_value(&mut self.ev_mode, EvMode::Absolute, "Absolute EV Value");
+                                            ui.selectable_value(&mut self.ev_mode, EvMode::Delta, "Delta EV Change");
+                                        })
+                                        .response;
+                                    accessible_name(response, "Exposure bias mode");
+                                    if self.ev_mode == EvMode::Delta {
+                                        let label = ui.label("from");
+                                        let position = match self.delta_reference {
+                                            DeltaReference::Position(position) => position,
+                                            _ => 1,
+                                        };
+                                        egui::ComboBox::from_id_salt("delta_reference_selector")
+                                            .selected_text(self.delta_reference.to_string())
+                                            .show_ui(ui, |ui| {
+                                                ui.selectable_value(&mut self.delta_reference, DeltaReference::Zero, "0 EV Frame")
+                                                    .on_hover_text("The frame at the 0 of the sequence");
+                                                ui.selectable_value(&mut self.delta_reference, DeltaReference::First, "First Frame");
+                                                ui.selectable_value(&mut self.delta_reference, DeltaReference::Middle, "Middle Frame");
+                                                ui.selectable_value(&mut self.delta_reference, DeltaReference::Position(position), "Frame Number");
+                                            })
+                                            .response
+                                            .on_hover_text("The frame the other exposure biases are measured from. Sequences shot with exposure compensation, e.g. -1, -2, 0 around a -1 EV baseline, still match from the first frame.")
+                                            .labelled_by(label.id);
+                                        if let DeltaReference::Position(position) = &mut self.delta_reference {
+                                            let response = ui.add(egui::DragValue::new(position).range(1..=99));
+                                            accessible_name(response, "Reference frame number");
+                                        }
+                                    }
+                                    let label = ui.label("±");
+                                    ui.add(egui::DragValue::new(&mut self.ev_tolerance).range(0.0..=0.3).speed(0.01).max_decimals(2).suffix(" EV"))
+                                        .on_hover_text("Biases this close to the sequence value still match, as cameras write the same bias differently, e.g. 0.7 EV as 7/10 or as 2/3.")
+                                        .labelled_by(label.id);
+                                });
+                                ui.horizontal(|ui| {
+                                    let label = ui.label("Matches");
+                                    egui::ComboBox::from_id_salt("match_mode_selector")
+                                        .selected_text(self.match_mode.to_string())
+                                        .show_ui(ui, |ui| {
+                                            ui.selectable_value(&mut self.match_mode, MatchMode::Overlapping, "Overlapping")
+                                                .on_hover_text("Every run of frames following the sequence, even if it shares frames with the previous one. Such a file can only be moved once, the second sequence then fails.");
+                                            ui.selectable_value(&mut self.match_mode, MatchMode::Greedy, "Non-Overlapping")
+                                                .on_hover_text("The search goes on after the last frame of a match, so each file ends up in one sequence at most.");
+                                        })
+                                        .response
+                                        .labelled_by(label.id);
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.checkbox(&mut self.anchor_on_reference, "Anchor on frames with")
+                                        .on_hover_text("Only tries brackets whose reference frame (the 0 of the sequence) has this bias. Faster on huge folders and avoids brackets starting on the wrong frame.");
+                                    let response = ui.add_enabled(
+                                        self.anchor_on_reference,
+                                        egui::TextEdit::singleline(&mut self.anchor_bias).desired_width(40.0),
+                                    );
+                                    accessible_name(response, "Anchor bias in EV");
+                                    ui.label("EV");
+                                });
+                                ui.checkbox(&mut self.computed_exposure, "Compute EV from shutter speed, aperture and ISO")
+                                    .on_hover_text("For brackets shot by hand, whose exposure bias is 0 or missing. Frames are matched on the differences of their computed exposure, like in delta mode. Turn off the auto bracket filter for them.");
+                                ui.checkbox(&mut self.detect_other_brackets, "Also detect aperture, white balance and flash brackets")
+                                    .on_hover_text("Groups of as many frames as the sequence, where only the aperture, the white balance or the flash changes. They are moved to folders starting with aperture-, wb- or flash-.");
+                                let is_non_raw = |ext: &String| NON_RAW_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e));
+                                let mut include_non_raw = self.extensions.iter().any(is_non_raw);
+                                if ui.checkbox(&mut include_non_raw, "Include JPEG, HEIF and TIFF files")
+                                    .on_hover_text("Also groups the files cameras write next to their raws, or instead of them, by their EXIF.")
+                                    .changed()
+                                {
+                                    self.extensions.retain(|ext| !is_non_raw(ext));
+                                    if include_non_raw {
+                                        self.extensions.extend(NON_RAW_EXTENSIONS.iter().map(|e| e.to_string()));
+                                    }
+                                }
+                                ui.checkbox(&mut self.group_per_camera, "Group per camera body")
+                                    .on_hover_text("Searches the files of each camera on their own, so frames of two bodies shot at the same time, e.g. one writing raws and one only JPEGs, never end up in one sequence. The frames of one camera are grouped whatever their file type.");
+                                ui.horizontal(|ui| {
+                                    ui.checkbox(&mut self.split_bursts, "Split into bursts at pauses over")
+                                        .on_hover_text("Only matches sequences within a burst of frames shot without a longer pause or camera change, so no bracket spans two scenes.");
+                                    let response = ui.add_enabled(
+                                        self.split_bursts,
+                                        egui::DragValue::new(&mut self.burst_gap).range(0.0..=3600.0).speed(0.5),
+                                    );
+                                    accessible_name(response, "Longest pause within a burst in seconds");
+                                    ui.label("s");
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.checkbox(&mut self.limit_sequence_span, "Reject sequences shot over more than")
+                                        .on_hover_text("Skips matches whose first and last frame are further apart, as separate shots whose biases happen to follow the sequence.");
+                                    let response = ui.add_enabled(
+                                        self.limit_sequence_span,
+                                        egui::DragValue::new(&mut self.max_sequence_span).range(0.0..=3600.0).speed(0.5),
+                                    );
+                                    accessible_name(response, "Longest time a sequence is shot over in seconds");
+                                    ui.label("s");
+                                });
+                                self.show_clock_offsets(ui);
                             });
                             ui.end_row();
 
@@ -322,20 +1282,270 @@ impl eframe::App for ExposureBracketingOrganizerApp {
                             });
                             ui.vertical(|ui| {
                                 ui.checkbox(&mut self.filter_by_auto_bracket, "Only 'Auto bracket' exposure mode");
+                                ui.add_enabled_ui(self.filter_by_auto_bracket, |ui| {
+                                    self.show_bracket_mode_overrides(ui);
+                                    let sources = self.filter_sources();
+                                    let mut open = self.filter_preview.open;
+                                    let response = ui.add_enabled(!sources.is_empty(), egui::Button::selectable(open, "Show Skipped Files"))
+                                        .on_hover_text("List the files of the selection the filter skips, with their exposure mode.");
+                                    if response.clicked() {
+                                        open = !open;
+                                        if open {
+                                            self.filter_preview.open(sources, self.filter_preview_settings());
+                                        } else {
+                                            self.filter_preview.open = false;
+                                        }
+                                    }
+                                });
                             });
                             ui.end_row();
 
-                            // Row: Action
+                            // Row: Performance
+                            ui.vertical(|ui| {
+                                ui.label(egui::RichText::new("Performance").strong());
+                            });
+                            ui.vertical(|ui| {
+                                ui.checkbox(&mut self.use_metadata_cache, "Only read new or modified files")
+                                    .on_hover_text("Reuse the metadata of files that are unchanged since a previous run.");
+                                ui.checkbox(&mut self.background_mode, "Background mode")
+                                    .on_hover_text("Run with low CPU and IO priority so other applications stay responsive.");
+                            });
+                            ui.end_row();
+
+                            // Row: Exiftool
+                            let label = ui.vertical(|ui| {
+                                ui.label(egui::RichText::new("Exiftool").strong())
+                                    .on_hover_text("Raws of cameras rawler doesn't support yet are read by a separately installed exiftool, which is much slower.")
+                            }).inner;
+                            ui.vertical(|ui| {
+                                self.show_exiftool_settings(ui, label.id);
+                            });
+                            ui.end_row();
+
+                            // Row: Webhook notification
+                            let label = ui.vertical(|ui| {
+                                ui.label(egui::RichText::new("Webhook").strong())
+                                    .on_hover_text("A JSON summary is POSTed to this URL when a run is done.")
+                            }).inner;
+                            ui.vertical(|ui| {
+                                ui.add(egui::TextEdit::singleline(&mut self.webhook_url).hint_text("https://…"))
+                                    .labelled_by(label.id);
+                            });
+                            ui.end_row();
+
+                            // Row: Run log
+                            ui.vertical(|ui| {
+                                ui.label(egui::RichText::new("Run Log").strong())
+                                    .on_hover_text("Info, warnings and errors of a run are written to a timestamped file, e.g. ebo-run-20240501-183000.log.");
+                            });
                             ui.vertical(|ui| {
-                                ui.label(egui::RichText::new("Actions").strong());
+                                ui.checkbox(&mut self.write_run_log, "Write a log file");
+                                if self.write_run_log {
+                                    ui.horizontal(|ui| {
+                                        let folder = match &self.log_directory {
+                                            Some(dir) => dir.display().to_string(),
+                                            None => "Processed folder".to_string(),
+                                        };
+                                        let label = ui.label(folder)
+                                            .on_hover_text("Without a log folder, runs that only add to the catalog write their log to the app's storage");
+                                        if ui.small_button("Choose…").labelled_by(label.id).clicked() {
+                                            if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                                                self.log_directory = Some(path);
+                                            }
+                                        }
+                                        if self.log_directory.is_some() && ui.small_button("Reset").on_hover_text("Write logs to the processed folder").clicked() {
+                                            self.log_directory = None;
+                                        }
+                                    });
+                                }
                             });
+                            ui.end_row();
+
+                            // Row: Action
+                            let label = ui.vertical(|ui| {
+                                ui.label(egui::RichText::new("Actions").strong())
+                            }).inner;
                             ui.vertical(|ui| {
+                                let selected_text = if self.selected_actions.is_empty() {
+                                    "None".to_string()
+                                } else {
+                                    self.selected_actions.iter()
+                                        .map(|action| match action {
+                                            Action::Script(file_name) => self.script_actions.iter()
+                                                .find(|script| script.file_name == *file_name)
+                                                .map_or_else(|| action.to_string(), |script| script.name.clone()),
+                                            action => action.to_string(),
+                                        })
+                                        .collect::<Vec<_>>()
+                                        .join(" + ")
+                                };
                                 egui::ComboBox::from_id_salt("action_selector")
-                                    .selected_text(self.selected_action.to_string())
+                                    .selected_text(selected_text)
+                                    // Stays open, so several actions can be ticked
+                                    .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
                                     .show_ui(ui, |ui| {
-                                        ui.selectable_value(&mut self.selected_action, Action::MoveToFolder, "Move to Folder");
-                                        ui.selectable_value(&mut self.selected_action, Action::SaveSequencesToTextfile, "Save Sequences to Textfile");
+                                        action_toggle(ui, &mut self.selected_actions, Action::MoveToFolder, "Move to Folder");
+                                        action_toggle(ui, &mut self.selected_actions, Action::CopyToFolder, "Copy to Folder")
+                                            .on_hover_text("Copies each sequence into a folder of its own, the originals stay where they are");
+                                        action_toggle(ui, &mut self.selected_actions, Action::RenameInPlace, "Rename in Place")
+                                            .on_hover_text("Leaves the files where they are and renames them with a prefix per sequence, e.g. BRK0001_1of5_IMG_2034.NEF, XMP sidecars along with them");
+                                        action_toggle(ui, &mut self.selected_actions, Action::SaveSequencesToTextfile, "Save Sequences to Textfile");
+                                        action_toggle(ui, &mut self.selected_actions, Action::WriteGroupTags, "Write Group Tags (XMP)")
+                                            .on_hover_text("Tags every file of a sequence with a shared keyword in its XMP sidecar, so galleries like Immich or PhotoPrism can find the brackets together");
+                                        action_toggle(ui, &mut self.selected_actions, Action::AddToCatalog, "Add to Catalog Only")
+                                            .on_hover_text("Records the sequences in the app's own catalog without changing, moving or adding any file");
+                                        action_toggle(ui, &mut self.selected_actions, Action::WriteFusionScripts, "Write Fusion Scripts")
+                                            .on_hover_text("Writes a script next to each sequence that aligns and fuses its frames with align_image_stack and enfuse from Hugin");
+                                        action_toggle(ui, &mut self.selected_actions, Action::WriteHuginProjects, "Write Hugin Projects")
+                                            .on_hover_text("Writes a Hugin project next to each sequence with its frames as one stack, ready for exposure fusion");
+                                        action_toggle(ui, &mut self.selected_actions, Action::WriteHdrMergeCommands, "Write HDRMerge Commands")
+                                            .on_hover_text("Writes a script into each folder with one HDRMerge command per sequence, merging its raws into a DNG");
+                                        action_toggle(ui, &mut self.selected_actions, Action::WriteDarktableGroups, "Write darktable Groups")
+                                            .on_hover_text("Tags the frames in darktable's sidecars and writes a Lua script into each folder that stacks each sequence as a group in darktable, led by its first frame");
+                                        action_toggle(ui, &mut self.selected_actions, Action::WriteLightroomKeywords, "Write Lightroom Keywords")
+                                            .on_hover_text("Adds a hierarchical keyword like Bracket|2024-05-01_IMG_1234 to the XMP of every file of a sequence, so Lightroom can filter and stack the brackets by keyword");
+                                        action_toggle(ui, &mut self.selected_actions, Action::WriteDigikamTags, "Write digiKam Tags")
+                                            .on_hover_text("Adds a tag like Bracket/2024-05-01_IMG_1234 to the XMP sidecar of every file of a sequence, so digiKam shows the brackets grouped under the Bracket tag");
+                                        action_toggle(ui, &mut self.selected_actions, Action::RunCommand, "Run Command")
+                                            .on_hover_text("Runs a command of your own once per sequence, e.g. your HDR pipeline");
+                                        if !self.script_actions.is_empty() {
+                                            ui.separator();
+                                        }
+                                        for script in &self.script_actions {
+                                            let response = action_toggle(ui, &mut self.selected_actions, Action::Script(script.file_name.clone()), &script.name);
+                                            if let Some(description) = &script.description {
+                                                response.on_hover_text(description);
+                                            }
+                                        }
+                                    })
+                                    .response
+                                    .labelled_by(label.id);
+                                if self.selected_actions.iter().any(Action::organizes) {
+                                    ui.horizontal(|ui| {
+                                        let label = ui.label("Gather in:");
+                                        egui::ComboBox::from_id_salt("date_folders_selector")
+                                            .selected_text(self.date_folders.to_string())
+                                            .show_ui(ui, |ui| {
+                                                ui.selectable_value(&mut self.date_folders, DateFolders::Off, "No Date Folders")
+                                                    .on_hover_text("Each sequence folder directly next to the files");
+                                                ui.selectable_value(&mut self.date_folders, DateFolders::Day, "Per Day")
+                                                    .on_hover_text("Sequence folders inside a folder like 2024-05-01, existing ones are reused");
+                                                ui.selectable_value(&mut self.date_folders, DateFolders::Hour, "Per Day and Hour")
+                                                    .on_hover_text("Sequence folders inside a folder like 2024-05-01/14, existing ones are reused");
+                                                ui.selectable_value(&mut self.date_folders, DateFolders::YearMonthDay, "Per Year, Month and Day")
+                                                    .on_hover_text("Sequence folders inside folders like 2024/05/01, a chronological archive of a whole card dump");
+                                            })
+                                            .response
+                                            .labelled_by(label.id);
+                                    });
+                                    ui.horizontal(|ui| {
+                                        let label = ui.label("Folder name:");
+                                        ui.add(egui::TextEdit::singleline(&mut self.folder_name).hint_text(DEFAULT_FOLDER_NAME).desired_width(180.0))
+                                            .labelled_by(label.id)
+                                            .on_hover_text("{name} is the first file's name, {date} the capture date and {note} the note given in the preview, e.g. {date} {name} - {note}");
+                                    });
+                                    ui.horizontal(|ui| {
+                                        let label = ui.label("Put folders in:");
+                                        let folder = match &self.destination_root {
+                                            Some(root) => root.display().to_string(),
+                                            None => "Next to the files".to_string(),
+                                        };
+                                        ui.label(folder);
+                                        if ui.small_button("Choose…").labelled_by(label.id)
+                                            .on_hover_text("Create the sequence folders below another folder, e.g. D:\\HDR, instead of in the scanned folders")
+                                            .clicked()
+                                        {
+                                            if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                                                self.destination_root = Some(path);
+                                            }
+                                        }
+                                        if self.destination_root.is_some() && ui.small_button("Reset").on_hover_text("Create the sequence folders next to the files").clicked() {
+                                            self.destination_root = None;
+                                        }
+                                    });
+                                    ui.add_enabled(!self.move_companions, egui::Checkbox::new(&mut self.move_sidecars, "Take along XMP sidecars"))
+                                        .on_hover_text("Also moves or copies the .xmp sidecars Lightroom, darktable and others keep next to the files, so edits stay with their raws.");
+                                    ui.checkbox(&mut self.move_companions, "Take along files of the same name")
+                                        .on_hover_text("Also moves or copies the files named like those of a sequence, e.g. the JPEGs a camera writes next to its raws and the sidecars of an editor. Files of the types being searched are grouped on their own.");
+                                    ui.checkbox(&mut self.folder_metadata, "Date and describe folders")
+                                        .on_hover_text("Sets the modification time of each folder to the capture time of its sequence and adds a comment file managers show as tooltip.");
+                                    if self.selected_actions.contains(&Action::MoveToFolder) && self.scan_subfolders {
+                                        ui.checkbox(&mut self.remove_empty_folders, "Remove emptied subfolders")
+                                            .on_hover_text("Deletes scanned subfolders that are empty once their sequences were moved out, e.g. the DCIM folders of a card dump. Folders that were empty before stay.");
+                                    }
+                                    ui.checkbox(&mut self.write_manifest, "Write archive manifest")
+                                        .on_hover_text("Lists every file below the organized folder with its size and hash in ebo-manifest.json, so \"Verify Archive\" can find files that got corrupted or lost later.");
+                                }
+                                if self.selected_actions.contains(&Action::SaveSequencesToTextfile) {
+                                    ui.horizontal(|ui| {
+                                        let label = ui.label("Format:");
+                                        egui::ComboBox::from_id_salt("textfile_format_selector")
+                                            .selected_text(self.textfile_format.to_string())
+                                            .show_ui(ui, |ui| {
+                                                ui.selectable_value(&mut self.textfile_format, TextfileFormat::Paths, "Paths")
+                                                    .on_hover_text("sequences.txt, one path per line and a blank line between sequences");
+                                                ui.selectable_value(&mut self.textfile_format, TextfileFormat::TabSeparated, "Paths and EV, Tab Separated")
+                                                    .on_hover_text("sequences.tsv, each path followed by its exposure bias");
+                                                ui.selectable_value(&mut self.textfile_format, TextfileFormat::Csv, "CSV")
+                                                    .on_hover_text("sequences.csv, one row per file with its sequence, frame number and exposure bias");
+                                                ui.selectable_value(&mut self.textfile_format, TextfileFormat::Json, "JSON")
+                                                    .on_hover_text("sequences.json, a sequence list \"Load list…\" reads");
+                                            })
+                                            .response
+                                            .labelled_by(label.id);
+                                    });
+                                    ui.checkbox(&mut self.overwrite_textfile, "Overwrite instead of append")
+                                        .on_hover_text("Replaces the sequences file of each folder with the sequences of this run, so running a folder again doesn't list its sequences twice");
+                                    ui.horizontal(|ui| {
+                                        let label = ui.label("If another run writes it too:");
+                                        egui::ComboBox::from_id_salt("textfile_conflict_selector")
+                                            .selected_text(self.textfile_conflict.to_string())
+                                            .show_ui(ui, |ui| {
+                                                ui.selectable_value(&mut self.textfile_conflict, TextfileConflict::Append, "Append")
+                                                    .on_hover_text("Both runs add their sequences, each sequence stays in one piece");
+                                                ui.selectable_value(&mut self.textfile_conflict, TextfileConflict::Wait, "Wait for the Other Run")
+                                                    .on_hover_text("Keeps the sequences of each run together");
+                                                ui.selectable_value(&mut self.textfile_conflict, TextfileConflict::SeparateFile, "Write a Separate File")
+                                                    .on_hover_text("Writes sequences-2.txt etc. instead");
+                                            })
+                                            .response
+                                            .labelled_by(label.id);
+                                    });
+                                }
+                                if self.selected_actions.contains(&Action::WriteFusionScripts) {
+                                    ui.horizontal(|ui| {
+                                        let label = ui.label("Commands:");
+                                        if ui.small_button("Reset").clicked() {
+                                            self.fusion_command = DEFAULT_FUSION_COMMAND.to_string();
+                                        }
+                                        label
+                                    });
+                                    ui.add(egui::TextEdit::multiline(&mut self.fusion_command).code_editor().desired_rows(2).desired_width(f32::INFINITY))
+                                        .on_hover_text("One command per line, run from the folder of the script. {files} stands for the frames, {name} for the name of the sequence and {aligned} for the files align_image_stack -a \"{name}_aligned_\" writes.");
+                                }
+                                if self.selected_actions.contains(&Action::WriteHdrMergeCommands) {
+                                    ui.horizontal(|ui| {
+                                        let label = ui.label("HDRMerge:");
+                                        ui.add(egui::TextEdit::singleline(&mut self.hdrmerge_path).hint_text(DEFAULT_HDRMERGE_PATH))
+                                            .on_hover_text("The HDRMerge executable the commands run, just its name if it is on the PATH")
+                                            .labelled_by(label.id);
+                                        if accessible_name(ui.button("Browse…"), "Browse for the HDRMerge executable").clicked() {
+                                            if let Some(path) = rfd::FileDialog::new().pick_file() {
+                                                self.hdrmerge_path = path.display().to_string();
+                                            }
+                                        }
                                     });
+                                }
+                                if self.selected_actions.contains(&Action::RunCommand) {
+                                    let label = ui.label("Command:");
+                                    ui.add(egui::TextEdit::multiline(&mut self.command_template).code_editor().desired_rows(2).desired_width(f32::INFINITY).hint_text("enfuse -o HDR_{count}_{ev_range}EV.tif {files}"))
+                                        .on_hover_text("Run once per sequence with the system's shell, in the folder of its first file. {files} stands for the quoted frames, {folder} for their folder, {count} for the number of frames and {ev_range} for the EV between the darkest and the brightest frame.")
+                                        .labelled_by(label.id);
+                                }
+                                if self.selected_actions.iter().any(|action| matches!(action, Action::WriteGroupTags | Action::WriteLightroomKeywords)) {
+                                    ui.checkbox(&mut self.embed_xmp, "Write into DNG and JPEG files")
+                                        .on_hover_text("Stores the tags in the files themselves instead of sidecars. Other formats still get a sidecar.");
+                                }
                             });
                             ui.end_row();
 
@@ -346,11 +1556,11 @@ impl eframe::App for ExposureBracketingOrganizerApp {
                             ui.vertical(|ui| {
                                 ui.label(format!(
                                     "Exposure bracketings: {}",
-                                    self.exposure_bracketings_found.load(Ordering::Relaxed)
+                                    self.progress.exposure_bracketings_found.load(Ordering::Relaxed)
                                 ));
                                 ui.label(format!(
                                     "Files processed: {}",
-                                    self.processed_files.load(Ordering::Relaxed)
+                                    self.progress.processed_files.load(Ordering::Relaxed)
                                 ));
                             });
                             ui.end_row();
@@ -361,17 +1571,72 @@ impl eframe::App for ExposureBracketingOrganizerApp {
             ui.add_space(12.0);
 
             // If scanning/processing show a compact status in the central area (progress bar still handled in bottom panel)
-            let total = self.total_files.load(Ordering::Relaxed);
-            let processed = self.processed_files.load(Ordering::Relaxed);
-            let is_running = self.running.load(Ordering::Relaxed);
+            let total = self.progress.total_files.load(Ordering::Relaxed);
+            let processed = self.progress.processed_files.load(Ordering::Relaxed);
+            let is_running = self.progress.running.load(Ordering::Relaxed);
 
             if total > 0 {
                 let fraction = (processed as f32 / total as f32).clamp(0.0, 1.0);
                 ui.horizontal(|ui| {
-                    ui.add(egui::ProgressBar::new(fraction).show_percentage());
+                    let response = ui.add(egui::ProgressBar::new(fraction).show_percentage());
+                    // Screen readers read a live region again whenever its text changes,
+                    // so only report every 10 % instead of every file
+                    let announcement = if is_running {
+                        format!("Progress {} percent", (fraction * 10.0).floor() as u32 * 10)
+                    } else if self.progress.is_cancelled() {
+                        format!(
+                            "Cancelled, {} exposure bracketings processed",
+                            self.progress.exposure_bracketings_found.load(Ordering::Relaxed)
+                        )
+                    } else {
+                        format!(
+                            "Finished, {} exposure bracketings found",
+                            self.progress.exposure_bracketings_found.load(Ordering::Relaxed)
+                        )
+                    };
+                    announce(&response, announcement);
+                    if is_running && !self.progress.is_cancelled() {
+                        self.show_pause_button(ui);
+                    }
+                    if !is_running && self.progress.is_cancelled() {
+                        ui.label("Cancelled");
+                    }
                 });
+                let outcome = self.run_outcome.lock().unwrap();
+                let mut review = None;
+                if !is_running {
+                    if outcome.frame_counts.len() > 1 {
+                        let counts: Vec<String> = outcome.frame_counts.iter().map(|(frames, count)| format!("{} × {} frames", count, frames)).collect();
+                        ui.label(format!("Sequences: {}", counts.join(", ")));
+                    }
+                    for anomaly in &outcome.anomalies {
+                        ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", anomaly));
+                    }
+                    if !outcome.removed_folders.is_empty() {
+                        let list: Vec<String> = outcome.removed_folders.iter().map(|f| f.display().to_string()).collect();
+                        ui.label(format!("Removed {} empty folders", outcome.removed_folders.len()))
+                            .on_hover_text(list.join("\n"));
+                    }
+                    if !outcome.created_folders.is_empty()
+                        && ui.button("Review Results")
+                            .on_hover_text("Step through the new folders with their frames and a fused preview, and undo single ones")
+                            .clicked()
+                    {
+                        review = Some(outcome.created_folders.clone());
+                    }
+                }
+                drop(outcome);
+                if let Some(folders) = review {
+                    self.result_review.open(ui.ctx(), folders);
+                }
             } else if is_running {
-                ui.label("Scanning files...");
+                ui.horizontal(|ui| {
+                    let response = ui.label("Scanning files...");
+                    announce(&response, "Scanning files");
+                    if !self.progress.is_cancelled() {
+                        self.show_pause_button(ui);
+                    }
+                });
             }
 
             ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
@@ -386,70 +1651,49 @@ impl eframe::App for ExposureBracketingOrganizerApp {
                 let button_size = egui::vec2(140.0, 44.0);
 
                 // Big Start button (only enabled when not already running and folder selected)
-                let start_enabled =
-                    !self.running.load(Ordering::Relaxed) && self.picked_folder.is_some();
+                let start_enabled = !self.progress.running.load(Ordering::Relaxed)
+                    && !self.selected_actions.is_empty()
+                    && (!self.picked_folders.is_empty()
+                        || self.picked_files.is_some()
+                        || self.loaded_sequences.is_some());
                 let btn = egui::Button::new("Start").min_size(button_size).frame(true);
                 let response = if start_enabled {
                     ui.add_enabled(true, btn)
                 } else {
                     ui.add_enabled(false, btn)
                 };
+                let response = accessible_name(response, &format!("Start: {}", describe_actions(&self.selected_actions)));
 
                 if response.clicked() && start_enabled {
-                    if let Some(picked_folder) = &self.picked_folder {
-                        // spawn background processing if not already running
-                        if !self.running.load(Ordering::Relaxed) {
-                            // clone needed state into the thread
-                            let folder = picked_folder.clone();
-                            let total_files = Arc::clone(&self.total_files);
-                            let processed_files = Arc::clone(&self.processed_files);
-                            let exposure_bracketings_found =
-                                Arc::clone(&self.exposure_bracketings_found);
-                            let running = Arc::clone(&self.running);
-                            let extensions_vec: Vec<String> = self.extensions.clone();
-                            let exposure_bias_sequence = self.exposure_bias_sequence.clone();
-                            let selected_action = self.selected_action.clone();
-                            let ev_mode = self.ev_mode.clone();
-                            let filter_by_auto_bracket = self.filter_by_auto_bracket;
-
-                            let sequence = parse_exposure_sequence(&exposure_bias_sequence);
-                            if sequence.is_empty() || sequence.len() == 1 {
-                                self.show_error_messagebox = true;
-                                self.error_messagebox_text =
-                                    "Invalid or single-value exposure bias sequence.".to_string();
-                                return;
-                            }
+                    if self.review_sequences {
+                        self.start_processing(true);
+                    } else {
+                        self.request_processing();
+                    }
+                }
+                ui.checkbox(&mut self.review_sequences, "Review first")
+                    .on_hover_text("Start lists the matched sequences with their files and exposure biases first, so false matches can be unchecked before anything is changed.");
 
-                            // start background work
-                            running.store(true, Ordering::Relaxed);
-                            total_files.store(0, Ordering::Relaxed);
-                            processed_files.store(0, Ordering::Relaxed);
-                            exposure_bracketings_found.store(0, Ordering::Relaxed);
-
-                            // Spawn a thread that calls the top-level helpers
-                            thread::spawn(move || {
-                                let root = PathBuf::from(folder);
-                                if root.exists() {
-                                    let total = count_files_in_directory(&root, &extensions_vec);
-                                    total_files.store(total, Ordering::Relaxed);
-
-                                    process_directory(
-                                        &root,
-                                        &processed_files,
-                                        &exposure_bracketings_found,
-                                        extensions_vec,
-                                        sequence,
-                                        selected_action,
-                                        ev_mode,
-                                        filter_by_auto_bracket,
-                                    );
-                                } else {
-                                    warn!("Picked folder does not exist: {}", root.display());
-                                }
+                ui.add_space(8.0);
+                let preview_button = egui::Button::new("Preview (Dry Run)").min_size(button_size).frame(true);
+                if ui.add_enabled(start_enabled, preview_button)
+                    .on_hover_text("Match the sequences and list what would be done with them, without changing any files")
+                    .clicked()
+                {
+                    self.start_processing(true);
+                }
 
-                                running.store(false, Ordering::Relaxed);
-                            });
-                        }
+                if self.progress.running.load(Ordering::Relaxed) {
+                    ui.add_space(8.0);
+                    let cancelling = self.progress.is_cancelled();
+                    let cancel_button = egui::Button::new(if cancelling { "Cancelling…" } else { "Cancel" })
+                        .min_size(button_size)
+                        .frame(true);
+                    if ui.add_enabled(!cancelling, cancel_button)
+                        .on_hover_text("Stop after the current sequence. Sequences already organized stay as they are, no sequence is left half moved.")
+                        .clicked()
+                    {
+                        self.progress.cancel();
                     }
                 }
 
@@ -461,10 +1705,12 @@ impl eframe::App for ExposureBracketingOrganizerApp {
                     .frame(true);
                 if ui.add(get_bias_button).clicked() {
                     if let Some(paths) = rfd::FileDialog::new()
-                        .add_filter("Raw Images", &self.extensions)
+                        .add_filter("Images", &self.extensions)
                         .pick_files()
                     {
                         self.exposure_infos.clear();
+                        self.exposure_selection.clear();
+                        self.exposure_selection_anchor = None;
                         for path in paths {
                             let filename = path
                                 .file_name()
@@ -472,19 +1718,17 @@ impl eframe::App for ExposureBracketingOrganizerApp {
                                 .to_string_lossy()
                                 .to_string();
 
-                            let info = if let Some(raw_metadata) =
-                                extract_raw_metadata(&path)
-                            {
-                                let exposure_bias = raw_metadata
-                                    .exif
-                                    .exposure_bias
-                                    .map(|eb| Rational32::new(eb.n, eb.d));
-                                let exposure_mode = raw_metadata.exif.exposure_mode;
+                            let info = if let Some(metadata) = read_file_metadata(&path) {
+                                let exposure_bias = metadata.exposure_bias;
+                                let exposure_mode = metadata.exposure_mode;
                                 ExposureInfo {
+                                    path: path.clone(),
                                     filename,
                                     exposure_bias_n: exposure_bias.map(|eb| *eb.numer()),
                                     exposure_bias_d: exposure_bias.map(|eb| *eb.denom()),
                                     exposure_mode,
+                                    capture_time: metadata.capture_time,
+                                    capture_settings: metadata.capture_settings,
                                     error_message: if exposure_bias.is_none() {
                                         Some("No exposure bias found".to_string())
                                     } else {
@@ -493,10 +1737,13 @@ impl eframe::App for ExposureBracketingOrganizerApp {
                                 }
                             } else {
                                 ExposureInfo {
+                                    path: path.clone(),
                                     filename,
                                     exposure_bias_n: None,
                                     exposure_bias_d: None,
                                     exposure_mode: None,
+                                    capture_time: None,
+                                    capture_settings: None,
                                     error_message: Some("Could not read metadata".to_string()),
                                 }
                             };
@@ -505,28 +1752,1012 @@ impl eframe::App for ExposureBracketingOrganizerApp {
                         self.show_exposure_window = true;
                     }
                 }
-            });
-        });
 
-        // Exposure Bias Information window
-        self.show_exposure_window(ctx);
-        self.show_error_messagebox(ctx);
-        ctx.request_repaint();
-    }
-}
+                ui.add_space(8.0);
 
-impl ExposureBracketingOrganizerApp {
-    fn show_exposure_window(&mut self, ctx: &egui::Context) {
+                let audit_button = egui::Button::new("Audit Folders")
+                    .min_size(button_size)
+                    .frame(true);
+                if ui.add(audit_button)
+                    .on_hover_text("Check that an organized folder tree holds complete brackets")
+                    .clicked()
+                {
+                    self.audit_window.open(self.picked_folders.first().map(|f| f.path.clone()));
+                }
+
+                ui.add_space(8.0);
+
+                let verify_button = egui::Button::new("Verify Archive")
+                    .min_size(button_size)
+                    .frame(true);
+                if ui.add(verify_button)
+                    .on_hover_text("Check the files of an organized folder against its archive manifest to find corrupted or lost ones")
+                    .clicked()
+                {
+                    let root = self.destination_root.clone().or_else(|| self.picked_folders.first().map(|f| f.path.clone()));
+                    self.archive_verify_window.open(root);
+                }
+
+                ui.add_space(8.0);
+
+                let undo_button = egui::Button::new("Undo Last Run")
+                    .min_size(button_size)
+                    .frame(true);
+                let can_undo = !self.progress.running.load(Ordering::Relaxed) && journal::has_last_run();
+                if ui.add_enabled(can_undo, undo_button)
+                    .on_hover_text("Move the files of the last run that moved or copied sequences back where they were, or delete the copies")
+                    .clicked()
+                {
+                    match journal::load_last_run() {
+                        Ok(entries) => self.undo_confirmation = entries,
+                        Err(e) => {
+                            self.show_error_messagebox = true;
+                            self.error_messagebox_text = e;
+                        }
+                    }
+                }
+            });
+        });
+    }
+}
+
+impl ExposureBracketingOrganizerApp {
+    fn select_folder(&mut self, path: PathBuf) {
+        self.picked_folders.clear();
+        self.add_folders(vec![path]);
+    }
+
+    /// Adds root folders to the run, the `.ebo.toml` of the first one is offered.
+    fn add_folders(&mut self, paths: Vec<PathBuf>) {
+        self.view = View::Organize;
+        self.picked_files = None;
+        self.loaded_list = None;
+        self.loaded_sequences = None;
+        if self.picked_folders.is_empty() {
+            self.folder_settings = paths
+                .first()
+                .and_then(|path| FolderSettings::load(path).transpose());
+            self.camera_profile_note = None;
+            if let Some(path) = paths.first() {
+                self.detect_camera(path.clone());
+            }
+        }
+        for path in paths {
+            if !self.picked_folders.iter().any(|f| f.path == path) {
+                self.picked_folders
+                    .push(PickedFolder::new(path, self.subfolder_depth()));
+            }
+        }
+    }
+
+    /// The picked folders or files, as the filter preview scans them.
+    fn filter_sources(&self) -> Vec<PathBuf> {
+        match &self.picked_files {
+            Some(files) => files.clone(),
+            None => self
+                .picked_folders
+                .iter()
+                .flat_map(|f| collect_directories(&f.path, self.subfolder_depth()))
+                .collect(),
+        }
+    }
+
+    fn filter_preview_settings(&self) -> FilterPreviewSettings {
+        FilterPreviewSettings {
+            extensions: self.extensions.clone(),
+            bracket_mode_overrides: self.bracket_mode_overrides.clone(),
+            use_metadata_cache: self.use_metadata_cache,
+        }
+    }
+
+    /// Levels of subfolders a run scans below each picked folder.
+    fn subfolder_depth(&self) -> usize {
+        if self.scan_subfolders {
+            self.subfolder_depth
+        } else {
+            0
+        }
+    }
+
+    /// Counts the files of the picked folders again, e.g. after the scanned
+    /// subfolders changed.
+    fn refresh_picked_folders(&mut self) {
+        let depth = self.subfolder_depth();
+        for folder in &mut self.picked_folders {
+            *folder = PickedFolder::new(folder.path.clone(), depth);
+        }
+    }
+
+    /// Lists the picked folders with the files each one contributes, so the
+    /// scope of a run can be checked before starting it.
+    fn show_picked_folders(&mut self, ui: &mut egui::Ui) {
+        let mut rescan = false;
+        ui.horizontal(|ui| {
+            rescan |= ui.checkbox(&mut self.scan_subfolders, "Include subfolders")
+                .on_hover_text("Also process the subfolders, each one on its own. Hidden folders and folders of already moved sequences are skipped.")
+                .changed();
+            ui.add_enabled_ui(self.scan_subfolders, |ui| {
+                let response = ui.add(egui::DragValue::new(&mut self.subfolder_depth).range(1..=20));
+                rescan |= response.changed();
+                accessible_name(response, "Subfolder levels");
+                ui.label("levels deep");
+            });
+        });
+        if rescan {
+            self.refresh_picked_folders();
+        }
+        let response = egui::ComboBox::from_id_salt("folder_order_selector")
+            .selected_text(match self.folder_order {
+                FileOrder::Selection => "Filesystem Order".to_string(),
+                ref order => order.to_string(),
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.folder_order, FileOrder::Selection, "Filesystem Order")
+                    .on_hover_text("As the filesystem lists them, which copying between drives can scramble.");
+                ui.selectable_value(&mut self.folder_order, FileOrder::CaptureTime, "Sort by Capture Time");
+                ui.selectable_value(&mut self.folder_order, FileOrder::FileName, "Sort by File Name");
+                ui.selectable_value(&mut self.folder_order, FileOrder::NaturalFileName, "Sort by File Name and Number")
+                    .on_hover_text("Compares the numbers in file names by value, so IMG_9999 comes before IMG_10000.");
+            })
+            .response;
+        accessible_name(response, "Folder file order");
+        let mut remove = None;
+        for (i, folder) in self.picked_folders.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.monospace(folder.path.display().to_string());
+                if self.picked_folders.len() > 1 && ui.small_button("Remove").clicked() {
+                    remove = Some(i);
+                }
+            });
+            ui.indent(("picked_folder", i), |ui| {
+                ui.label(folder.preview(&self.extensions));
+                match &folder.settings {
+                    Some(Ok(summary)) => {
+                        ui.label(format!("{}: {}", folder_settings::FILE_NAME, summary));
+                    }
+                    Some(Err(error)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+                    None => {}
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.picked_folders.remove(i);
+        }
+        if let Some(note) = &self.camera_profile_note {
+            ui.label(note);
+        }
+        if self.picked_folders.len() > 1 {
+            let files: usize = self
+                .picked_folders
+                .iter()
+                .map(|f| f.matching_files(&self.extensions))
+                .sum();
+            ui.label(format!(
+                "{} files to scan in {} folders",
+                files,
+                self.picked_folders.len()
+            ));
+        }
+    }
+
+    /// Offers the `.ebo.toml` of the picked folder, or shows why it can't be used.
+    fn show_folder_settings_offer(&mut self, ui: &mut egui::Ui) {
+        let Some(folder_settings) = &self.folder_settings else {
+            return;
+        };
+        let mut apply = false;
+        let mut dismiss = false;
+        ui.group(|ui| match folder_settings {
+            Ok(settings) => {
+                ui.label(format!(
+                    "This folder has a {} with {}.",
+                    folder_settings::FILE_NAME,
+                    settings.summary()
+                ));
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").clicked();
+                    dismiss = ui.button("Ignore").clicked();
+                });
+            }
+            Err(error) => {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+                dismiss = ui.button("Dismiss").clicked();
+            }
+        });
+        if apply {
+            self.apply_folder_settings();
+        } else if dismiss {
+            self.folder_settings = None;
+        }
+    }
+
+    /// Exceptions of the auto-bracket filter for cameras that report e.g.
+    /// manual exposure during AEB.
+    fn show_bracket_mode_overrides(&mut self, ui: &mut egui::Ui) {
+        let title = if self.bracket_mode_overrides.is_empty() {
+            "Camera exceptions".to_string()
+        } else {
+            format!("Camera exceptions ({})", self.bracket_mode_overrides.len())
+        };
+        let mut changed = false;
+        egui::CollapsingHeader::new(title)
+            .id_salt("bracket_mode_overrides")
+            .show(ui, |ui| {
+                ui.label("Also accept these exposure modes from cameras that don't report 'Auto bracket' during AEB.");
+                let mut remove = None;
+                for (i, entry) in self.bracket_mode_overrides.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        let label = ui.label("Camera:");
+                        changed |= ui.add(egui::TextEdit::singleline(&mut entry.camera).hint_text("e.g. ILCE-7RM5").desired_width(140.0))
+                            .labelled_by(label.id)
+                            .changed();
+                        let response = egui::ComboBox::from_id_salt(("bracket_mode_override", i))
+                            .selected_text(exposure_mode_to_string(entry.exposure_mode))
+                            .show_ui(ui, |ui| {
+                                for mode in [0, 1] {
+                                    changed |= ui.selectable_value(&mut entry.exposure_mode, mode, exposure_mode_to_string(mode)).changed();
+                                }
+                            })
+                            .response;
+                        accessible_name(response, "Accepted exposure mode");
+                        if ui.small_button("Remove").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    self.bracket_mode_overrides.remove(i);
+                    changed = true;
+                }
+                if ui.small_button("Add Exception").clicked() {
+                    self.bracket_mode_overrides.push(BracketModeOverride {
+                        camera: String::new(),
+                        exposure_mode: 1,
+                    });
+                    changed = true;
+                }
+            });
+        if changed {
+            let overrides = self.bracket_mode_overrides.clone();
+            ui.ctx().data_mut(|d| {
+                d.insert_persisted(egui::Id::new(BRACKET_MODE_OVERRIDES_KEY), overrides)
+            });
+        }
+    }
+
+    /// Clock corrections for the bodies of a multi-camera shoot.
+    fn show_clock_offsets(&mut self, ui: &mut egui::Ui) {
+        let title = if self.clock_offsets.is_empty() {
+            "Camera clock offsets".to_string()
+        } else {
+            format!("Camera clock offsets ({})", self.clock_offsets.len())
+        };
+        let mut changed = false;
+        egui::CollapsingHeader::new(title)
+            .id_salt("clock_offsets")
+            .show(ui, |ui| {
+                ui.label("Shifts the capture times of a camera whose clock differs from the others, before files are sorted and split into bursts.");
+                let mut remove = None;
+                for (i, entry) in self.clock_offsets.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        let label = ui.label("Camera:");
+                        changed |= ui.add(egui::TextEdit::singleline(&mut entry.camera).hint_text("e.g. ILCE-7RM5").desired_width(140.0))
+                            .labelled_by(label.id)
+                            .changed();
+                        let response = ui.add(
+                            egui::DragValue::new(&mut entry.seconds)
+                                .custom_formatter(|seconds, _| format_clock_offset(seconds as i64))
+                                .custom_parser(parse_clock_offset),
+                        )
+                        .on_hover_text("Added to the capture times as hours:minutes:seconds, negative for a clock running ahead");
+                        changed |= response.changed();
+                        accessible_name(response, "Clock offset");
+                        if ui.small_button("Remove").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    self.clock_offsets.remove(i);
+                    changed = true;
+                }
+                if ui.small_button("Add Offset").clicked() {
+                    self.clock_offsets.push(ClockOffset {
+                        camera: String::new(),
+                        seconds: 0,
+                    });
+                    changed = true;
+                }
+            });
+        if changed {
+            let offsets = self.clock_offsets.clone();
+            ui.ctx()
+                .data_mut(|d| d.insert_persisted(egui::Id::new(CLOCK_OFFSETS_KEY), offsets));
+        }
+    }
+
+    fn show_presets(&mut self, ui: &mut egui::Ui, label: egui::Id) {
+        let current = self.preset(String::new(), None);
+        let active = self.presets.iter().position(|p| {
+            Preset {
+                name: String::new(),
+                camera: None,
+                ..p.clone()
+            } == current
+        });
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            let mut selected = None;
+            egui::ComboBox::from_id_salt("preset_selector")
+                .selected_text(active.map_or("Custom", |i| self.presets[i].name.as_str()))
+                .show_ui(ui, |ui| {
+                    for (i, preset) in self.presets.iter().enumerate() {
+                        let text = match &preset.camera {
+                            Some(camera) => format!("{} ({})", preset.name, camera),
+                            None => preset.name.clone(),
+                        };
+                        if ui.selectable_label(active == Some(i), text).clicked() {
+                            selected = Some(i);
+                        }
+                    }
+                })
+                .response
+                .labelled_by(label);
+            if let Some(i) = selected {
+                self.apply_preset(self.presets[i].clone());
+            }
+            if let Some(i) = active {
+                if ui
+                    .small_button("Delete")
+                    .on_hover_text("Remove this preset, the settings stay as they are")
+                    .clicked()
+                {
+                    self.presets.remove(i);
+                    changed = true;
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            let response = ui.add(egui::TextEdit::singleline(&mut self.preset_name).hint_text("e.g. Sony 5x1EV real estate").desired_width(200.0));
+            accessible_name(response, "Preset name");
+            let response = ui.add(egui::TextEdit::singleline(&mut self.preset_camera).hint_text("Camera, e.g. ILCE-7RM5").desired_width(140.0))
+                .on_hover_text("Optional. Applies the sequence of the preset automatically to folders mostly shot with this camera, matched by part of its make and model.");
+            accessible_name(response, "Preset camera");
+            let name = self.preset_name.trim().to_string();
+            if ui.add_enabled(!name.is_empty(), egui::Button::new("Save Preset"))
+                .on_hover_text("Saves the sequence, bracket order, filters, file types and action under this name, replacing a preset of the same name")
+                .clicked()
+            {
+                let camera = Some(self.preset_camera.trim().to_string()).filter(|c| !c.is_empty());
+                let preset = self.preset(name, camera);
+                match self.presets.iter_mut().find(|p| p.name == preset.name) {
+                    Some(existing) => *existing = preset,
+                    None => self.presets.push(preset),
+                }
+                self.preset_name.clear();
+                self.preset_camera.clear();
+                changed = true;
+            }
+        });
+        if changed {
+            let presets = self.presets.clone();
+            ui.ctx()
+                .data_mut(|d| d.insert_persisted(egui::Id::new(PRESETS_KEY), presets));
+        }
+    }
+
+    fn show_exiftool_settings(&mut self, ui: &mut egui::Ui, label_id: egui::Id) {
+        let mut changed = ui
+            .checkbox(
+                &mut self.exiftool.enabled,
+                "Read files rawler can't decode with exiftool",
+            )
+            .changed();
+        if self.exiftool.enabled {
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.exiftool.path)
+                            .hint_text("exiftool in the PATH"),
+                    )
+                    .labelled_by(label_id)
+                    .changed();
+                if ui.small_button("Choose…").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        self.exiftool.path = path.display().to_string();
+                        changed = true;
+                    }
+                }
+                if let Some(binary) = self.exiftool.binary() {
+                    if ui
+                        .small_button("Check")
+                        .on_hover_text("Runs exiftool -ver")
+                        .clicked()
+                    {
+                        self.exiftool_version = Some(exiftool::version(&binary));
+                    }
+                }
+            });
+            match &self.exiftool_version {
+                Some(Ok(version)) => {
+                    ui.label(format!("✔ exiftool {}", version));
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", e));
+                }
+                None => {}
+            }
+        }
+        if changed {
+            self.exiftool_version = None;
+            exiftool::configure(self.exiftool.binary());
+            let settings = self.exiftool.clone();
+            ui.ctx()
+                .data_mut(|d| d.insert_persisted(egui::Id::new(EXIFTOOL_KEY), settings));
+        }
+    }
+
+    /// The current settings as a preset named `name`.
+    fn preset(&self, name: String, camera: Option<String>) -> Preset {
+        Preset {
+            name,
+            camera,
+            exposure_settings: self.exposure_settings.clone(),
+            exposure_bias_sequence: self.exposure_bias_sequence.clone(),
+            ev_mode: self.ev_mode.clone(),
+            filter_by_auto_bracket: self.filter_by_auto_bracket,
+            detect_other_brackets: self.detect_other_brackets,
+            extensions: self.extensions.clone(),
+            selected_actions: self.selected_actions.clone(),
+        }
+    }
+
+    fn apply_preset(&mut self, preset: Preset) {
+        info!("Applying preset {}", preset.name);
+        self.exposure_settings = preset.exposure_settings;
+        self.exposure_bias_sequence = preset.exposure_bias_sequence;
+        self.ev_mode = preset.ev_mode;
+        self.filter_by_auto_bracket = preset.filter_by_auto_bracket;
+        self.detect_other_brackets = preset.detect_other_brackets;
+        self.extensions = preset.extensions;
+        self.selected_actions = preset.selected_actions;
+    }
+
+    /// Looks for the camera `folder` was mostly shot with, its profile is
+    /// applied once it is known.
+    fn detect_camera(&mut self, folder: PathBuf) {
+        if !self.presets.iter().any(|p| p.camera.is_some()) {
+            return;
+        }
+        self.detecting_camera = true;
+        let extensions = self.extensions.clone();
+        let detected = Arc::clone(&self.detected_camera);
+        thread::spawn(move || {
+            let camera = camera_profile::dominant_camera(&folder, &extensions);
+            *detected.lock().unwrap() = Some(DetectedCamera { folder, camera });
+        });
+    }
+
+    /// Applies the sequence of the camera profile matching the picked folder,
+    /// the file types and action stay as they are.
+    fn apply_detected_camera_profile(&mut self, ctx: &egui::Context) {
+        if !self.detecting_camera {
+            return;
+        }
+        let Some(DetectedCamera { folder, camera }) = self.detected_camera.lock().unwrap().take()
+        else {
+            ctx.request_repaint_after(PROGRESS_REPAINT_INTERVAL);
+            return;
+        };
+        match self.picked_folders.first() {
+            // A folder picked meanwhile has its own detection running
+            Some(first) if first.path != folder => return,
+            Some(_) => self.detecting_camera = false,
+            None => {
+                self.detecting_camera = false;
+                return;
+            }
+        }
+        let Some(camera) = camera else {
+            return;
+        };
+        let Some(preset) = camera_profile::profile_for(&self.presets, &camera).cloned() else {
+            return;
+        };
+        info!(
+            "{} shot with {}, applying profile {}",
+            folder.display(),
+            camera,
+            preset.name
+        );
+        self.camera_profile_note = Some(format!(
+            "Shot with {}, applied the sequence of \"{}\"",
+            camera, preset.name
+        ));
+        self.exposure_settings = preset.exposure_settings;
+        self.exposure_bias_sequence = preset.exposure_bias_sequence;
+        self.ev_mode = preset.ev_mode;
+        self.filter_by_auto_bracket = preset.filter_by_auto_bracket;
+        self.detect_other_brackets = preset.detect_other_brackets;
+    }
+
+    /// Takes over the settings of the picked folder's `.ebo.toml`, if it has a valid one.
+    fn apply_folder_settings(&mut self) {
+        let Some(Ok(settings)) = self.folder_settings.take() else {
+            return;
+        };
+        info!(
+            "Applying {}: {}",
+            folder_settings::FILE_NAME,
+            settings.summary()
+        );
+        if let Some(sequence) = settings.sequence {
+            self.exposure_bias_sequence = sequence;
+        }
+        if let Some(actions) = settings.action {
+            self.selected_actions = actions;
+        }
+        if let Some(ev_mode) = settings.ev_mode {
+            self.ev_mode = ev_mode;
+        }
+        if let Some(delta_reference) = settings.delta_reference {
+            self.delta_reference = delta_reference;
+        }
+        if let Some(tolerance) = settings.ev_tolerance {
+            self.ev_tolerance = tolerance;
+        }
+        if let Some(match_mode) = settings.match_mode {
+            self.match_mode = match_mode;
+        }
+        if let Some(filter) = settings.filter_by_auto_bracket {
+            self.filter_by_auto_bracket = filter;
+        }
+        if let Some(overrides) = settings.bracket_mode_overrides {
+            self.bracket_mode_overrides = overrides;
+        }
+        if let Some(offsets) = settings.clock_offsets {
+            self.clock_offsets = offsets;
+        }
+        if let Some(use_cache) = settings.use_metadata_cache {
+            self.use_metadata_cache = use_cache;
+        }
+        if let Some(extensions) = settings.extensions {
+            self.extensions = extensions;
+        }
+        if let Some(anchor_bias) = settings.anchor_bias {
+            self.anchor_on_reference = true;
+            self.anchor_bias = anchor_bias;
+        }
+        if let Some(detect) = settings.detect_other_brackets {
+            self.detect_other_brackets = detect;
+        }
+        if let Some(computed) = settings.computed_exposure {
+            self.computed_exposure = computed;
+        }
+        if let Some(group) = settings.group_per_camera {
+            self.group_per_camera = group;
+        }
+        if let Some(auto_detect) = settings.auto_detect {
+            self.auto_detect = auto_detect;
+        }
+        if let Some(burst_gap) = settings.burst_gap {
+            self.split_bursts = true;
+            self.burst_gap = burst_gap;
+        }
+        if let Some(max_sequence_span) = settings.max_sequence_span {
+            self.limit_sequence_span = true;
+            self.max_sequence_span = max_sequence_span;
+        }
+        if let Some(date_folders) = settings.date_folders {
+            self.date_folders = date_folders;
+        }
+        if let Some(folder_name) = settings.folder_name {
+            self.folder_name = folder_name;
+        }
+        if let Some(file_order) = settings.file_order {
+            self.folder_order = file_order;
+        }
+        if let Some(root) = settings.destination_root {
+            self.destination_root = Some(match self.picked_folders.first() {
+                Some(folder) => folder.path.join(root),
+                None => root,
+            });
+        }
+        if let Some(remove) = settings.remove_empty_folders {
+            self.remove_empty_folders = remove;
+        }
+        if let Some(move_sidecars) = settings.move_sidecars {
+            self.move_sidecars = move_sidecars;
+        }
+        if let Some(move_companions) = settings.move_companions {
+            self.move_companions = move_companions;
+        }
+        if let Some(write_manifest) = settings.write_manifest {
+            self.write_manifest = write_manifest;
+        }
+        if let Some(depth) = settings.subfolder_depth {
+            self.scan_subfolders = depth > 0;
+            if depth > 0 {
+                self.subfolder_depth = depth;
+            }
+            self.refresh_picked_folders();
+        }
+    }
+
+    /// Handles commands forwarded by other instances or scripts.
+    fn handle_ipc_commands(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.ipc_commands else {
+            return;
+        };
+        let commands: Vec<IpcCommand> = receiver.try_iter().collect();
+        for command in commands {
+            match command {
+                IpcCommand::Focus => {}
+                IpcCommand::Open(path) => self.select_folder(path),
+                IpcCommand::Run(path) => {
+                    if self.progress.running.load(Ordering::Relaxed) {
+                        warn!(
+                            "Ignoring forwarded job for {}, a job is already running",
+                            path.display()
+                        );
+                        continue;
+                    }
+                    self.select_folder(path);
+                    self.apply_folder_settings();
+                    self.request_processing();
+                }
+            }
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        }
+    }
+
+    /// Takes the settings chosen in the first-run wizard and previews them on its folder.
+    fn apply_wizard_outcome(&mut self, outcome: WizardOutcome) {
+        self.exposure_bias_sequence = outcome.sequence;
+        self.ev_mode = outcome.ev_mode;
+        self.delta_reference = outcome.delta_reference;
+        self.filter_by_auto_bracket = outcome.filter_by_auto_bracket;
+        self.selected_actions = vec![outcome.action];
+        self.select_folder(outcome.folder);
+        self.start_processing(true);
+    }
+
+    /// The warnings of each entered sequence, see [`sequence_warnings`].
+    fn sequence_warnings(&self) -> Vec<String> {
+        parse_exposure_sequences(&self.exposure_bias_sequence)
+            .iter()
+            .flat_map(|sequence| sequence_warnings(sequence))
+            .collect()
+    }
+
+    /// Starts processing, after asking for confirmation if the sequence looks
+    /// implausible or the run would modify original files.
+    fn request_processing(&mut self) {
+        let implausible = self.loaded_sequences.is_none()
+            && !self.auto_detect
+            && !self.sequence_warnings().is_empty();
+        if implausible {
+            self.show_sequence_warnings_confirmation = true;
+        } else {
+            self.confirm_originals_and_start();
+        }
+    }
+
+    fn confirm_originals_and_start(&mut self) {
+        if self.selected_actions.iter().any(|action| {
+            matches!(
+                action,
+                Action::WriteGroupTags | Action::WriteLightroomKeywords
+            )
+        }) && self.embed_xmp
+        {
+            self.show_modify_originals_confirmation = true;
+        } else {
+            self.start_processing(false);
+        }
+    }
+
+    /// Starts processing the current selection on a background thread. A dry run
+    /// only matches the sequences and shows what would be done in the preview window.
+    fn start_processing(&mut self, dry_run: bool) {
+        if self.progress.running.load(Ordering::Relaxed) {
+            return;
+        }
+
+        // clone needed state into the thread
+        let picked_files = self.picked_files.clone();
+        let loaded_sequences = self.loaded_sequences.clone();
+        let file_order = self.file_order.clone();
+        let progress = self.progress.clone();
+        let background_mode = self.background_mode;
+        let webhook_url = self.webhook_url.trim().to_string();
+        // The folder that is reported as processed, for file selections the folder they are in
+        let picked_folders: Vec<PathBuf> =
+            self.picked_folders.iter().map(|f| f.path.clone()).collect();
+        let subfolder_depth = self.subfolder_depth();
+        let source_folder = match (
+            picked_folders.first(),
+            &self.picked_files,
+            &self.loaded_list,
+        ) {
+            (Some(folder), _, _) => Some(folder.clone()),
+            (None, Some(files), _) => files.first().and_then(|f| f.parent()).map(PathBuf::from),
+            (None, None, Some(list)) => list.parent().map(PathBuf::from),
+            (None, None, None) => None,
+        };
+
+        let sequences = parse_exposure_sequences(&self.exposure_bias_sequence);
+        if loaded_sequences.is_none()
+            && !self.auto_detect
+            && (sequences.is_empty()
+                || sequences
+                    .iter()
+                    .any(|s| s.len() == 1 || s.iter().all(Option::is_none)))
+        {
+            self.show_error_messagebox = true;
+            self.error_messagebox_text =
+                "Invalid or single-value exposure bias sequence.".to_string();
+            return;
+        }
+
+        if let Err(e) = check_actions(&self.selected_actions) {
+            self.show_error_messagebox = true;
+            self.error_messagebox_text = e;
+            return;
+        }
+
+        let anchor_bias = if self.anchor_on_reference {
+            match parse_exposure_sequence(&self.anchor_bias).as_slice() {
+                [bias] => Some(*bias),
+                _ => {
+                    self.show_error_messagebox = true;
+                    self.error_messagebox_text =
+                        "Invalid anchor bias, enter a single value like 0 or -1/3.".to_string();
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        let settings = ProcessSettings {
+            extensions: self.extensions.clone(),
+            sequences,
+            selected_actions: self.selected_actions.clone(),
+            ev_mode: self.ev_mode.clone(),
+            delta_reference: self.delta_reference,
+            filter_by_auto_bracket: self.filter_by_auto_bracket,
+            bracket_mode_overrides: self.bracket_mode_overrides.clone(),
+            clock_offsets: self.clock_offsets.clone(),
+            use_metadata_cache: self.use_metadata_cache,
+            embed_xmp: self.embed_xmp,
+            fusion_command: self.fusion_command.clone(),
+            hdrmerge_path: self.hdrmerge_path.clone(),
+            command_template: self.command_template.clone(),
+            folder_metadata: self.folder_metadata,
+            date_folders: self.date_folders,
+            folder_name: self.folder_name.clone(),
+            folder_order: self.folder_order.clone(),
+            textfile_conflict: self.textfile_conflict,
+            textfile_format: self.textfile_format,
+            overwrite_textfile: self.overwrite_textfile,
+            anchor_bias,
+            ev_tolerance: ev_tolerance(self.ev_tolerance),
+            match_mode: self.match_mode,
+            detect_other_brackets: self.detect_other_brackets,
+            computed_exposure: self.computed_exposure,
+            group_per_camera: self.group_per_camera,
+            auto_detect: self.auto_detect,
+            burst_gap: self
+                .split_bursts
+                .then(|| seconds_to_time_delta(self.burst_gap)),
+            max_sequence_span: self
+                .limit_sequence_span
+                .then(|| seconds_to_time_delta(self.max_sequence_span)),
+            excluded_files: self.excluded_files.clone(),
+            dry_run,
+            destination_root: self.destination_root.clone(),
+            write_manifest: self.write_manifest,
+            remove_empty_folders: self.remove_empty_folders && self.scan_subfolders,
+            move_sidecars: self.move_sidecars,
+            move_companions: self.move_companions,
+            ev_format: self.ev_format,
+            sequence_notes: if dry_run {
+                HashMap::new()
+            } else {
+                std::mem::take(&mut self.sequence_notes)
+            },
+            rejected_sequences: if dry_run {
+                HashSet::new()
+            } else {
+                std::mem::take(&mut self.rejected_sequences)
+            },
+        };
+        let dry_run_result = dry_run.then(|| {
+            self.dry_run_window
+                .start(settings.selected_actions.iter().any(Action::organizes))
+        });
+        let manifest_roots = match &source_folder {
+            Some(folder) if picked_folders.is_empty() => {
+                manifest_roots(std::slice::from_ref(folder), &settings)
+            }
+            _ => manifest_roots(&picked_folders, &settings),
+        };
+        let log_dir = (self.write_run_log && !dry_run)
+            .then(|| {
+                run_log::log_dir(
+                    self.log_directory.as_deref(),
+                    source_folder.as_deref(),
+                    &settings.selected_actions,
+                )
+            })
+            .flatten();
+
+        let run_diagnostics = Arc::clone(&self.run_diagnostics);
+        run_diagnostics.lock().unwrap().clear();
+        let run_outcome = Arc::clone(&self.run_outcome);
+        *run_outcome.lock().unwrap() = RunOutcome::default();
+
+        // start background work
+        progress.start();
+
+        // Spawn a thread that calls the top-level helpers
+        thread::spawn(move || {
+            // Keep an overnight run from being interrupted by suspend
+            let _sleep_inhibitor = SleepInhibitor::acquire();
+            if background_mode {
+                enter_background_mode();
+            }
+            // Previews change nothing, so they keep the journal of the last real run
+            let _journal = dry_run_result.is_none().then(RunJournal::start);
+            let _run_log = log_dir.and_then(|dir| {
+                RunLog::start(&dir)
+                    .map_err(|e| warn!("Failed to create a run log in {}: {}", dir.display(), e))
+                    .ok()
+            });
+            if let Some(folder) = &source_folder {
+                info!(
+                    "Processing {} with action \"{}\"",
+                    folder.display(),
+                    describe_actions(&settings.selected_actions)
+                );
+            }
+
+            let mut report = if let Some(sequences) = loaded_sequences {
+                let total = sequences.iter().map(|s| s.len()).sum();
+                progress.set_total_files(total);
+
+                process_sequence_list(&sequences, &progress, &settings)
+            } else if let Some(paths) = picked_files {
+                progress.set_total_files(paths.len());
+
+                process_files(&paths, file_order, &progress, settings)
+            } else if !picked_folders.is_empty() {
+                let roots: Vec<PathBuf> = picked_folders
+                    .into_iter()
+                    .filter(|root| {
+                        let exists = root.exists();
+                        if !exists {
+                            warn!("Picked folder does not exist: {}", root.display());
+                        }
+                        exists
+                    })
+                    .collect();
+                let dirs: Vec<PathBuf> = roots
+                    .iter()
+                    .flat_map(|root| collect_directories(root, subfolder_depth))
+                    .collect();
+                let total = dirs
+                    .iter()
+                    .map(|dir| count_files_in_directory(dir, &settings.extensions))
+                    .sum();
+                progress.set_total_files(total);
+
+                process_directories(&dirs, &progress, settings)
+            } else {
+                RunReport::default()
+            };
+            report.summarize();
+            write_manifests(&manifest_roots, &mut report);
+
+            if !webhook_url.is_empty() && dry_run_result.is_none() {
+                webhook::notify(
+                    &webhook_url,
+                    &RunSummary::new(source_folder.as_deref(), &progress, &report),
+                );
+            }
+
+            // Before finishing, the repaint for the end of the run has to show them
+            *run_diagnostics.lock().unwrap() = report.diagnostics.clone();
+            *run_outcome.lock().unwrap() = RunOutcome {
+                removed_folders: report.removed_folders.clone(),
+                created_folders: if dry_run_result.is_none() {
+                    report
+                        .sequences
+                        .iter()
+                        .filter_map(|s| s.destination.clone())
+                        .filter(|d| d.is_dir())
+                        .collect()
+                } else {
+                    Vec::new()
+                },
+                frame_counts: report.frame_counts.clone(),
+                anomalies: report.anomalies.clone(),
+            };
+            progress.finish(&report);
+            if let Some(dry_run_result) = dry_run_result {
+                *dry_run_result.lock().unwrap() = Some(report);
+            }
+        });
+    }
+
+    fn show_pause_button(&mut self, ui: &mut egui::Ui) {
+        if self.progress.is_paused() {
+            ui.label("Paused");
+            if ui.button("Resume").clicked() {
+                self.progress.set_paused(false);
+            }
+        } else if ui.button("Pause")
+            .on_hover_text("Hold the run at the next file or sequence, e.g. to give a slow network drive a break")
+            .clicked()
+        {
+            self.progress.set_paused(true);
+        }
+    }
+
+    /// Mirrors the run progress in the window title and the Windows taskbar button,
+    /// so a long run can be followed while the window is minimized.
+    fn update_progress_indicators(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+        let total = self.progress.total_files.load(Ordering::Relaxed);
+        let processed = self.progress.processed_files.load(Ordering::Relaxed);
+        let progress = (self.progress.running.load(Ordering::Relaxed) && total > 0)
+            .then(|| (processed as f32 / total as f32).clamp(0.0, 1.0));
+
+        let title = match progress {
+            Some(fraction) if self.progress.is_paused() => {
+                format!("Paused at {:.0}% — {}", fraction * 100.0, crate::APP_NAME)
+            }
+            Some(fraction) => format!("{:.0}% — {}", fraction * 100.0, crate::APP_NAME),
+            None => crate::APP_NAME.to_string(),
+        };
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+            self.taskbar_progress.set(frame, progress);
+        }
+    }
+
+    fn show_exposure_window(&mut self, ctx: &egui::Context) {
         let mut action_to_take: Option<String> = None;
+        let mut table_action: Option<ExposureTableAction> = None;
 
         if self.show_exposure_window {
-            let mut is_open = true;
+            let mut detached = self.exposure_window_detached;
+            let infos = &self.exposure_infos;
+            let selection = &mut self.exposure_selection;
+            let selection_anchor = &mut self.exposure_selection_anchor;
+            let excluded_files = &self.excluded_files;
+            let is_open = detachable::show(
+                ctx,
+                "exposure_bias_window",
+                "Exposure Bias Information",
+                &mut detached,
+                |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            "Click to select, Ctrl+Click to add a row, Shift+Click to add a range.",
+                        );
+                        if ui.small_button("Select All").clicked() {
+                            *selection = (0..infos.len()).collect();
+                        }
+                        if ui.small_button("Select None").clicked() {
+                            selection.clear();
+                        }
+                    });
 
-            egui::Window::new("Exposure Bias Information")
-                .min_width(200.0)
-                .title_bar(true)
-                .open(&mut is_open)
-                .show(ctx, |ui| {
                     egui::ScrollArea::vertical().show(ui, |ui| {
                         egui::Grid::new("exposure_bias_grid")
                             .striped(true)
@@ -540,15 +2771,41 @@ impl ExposureBracketingOrganizerApp {
                                 ui.end_row();
 
                                 // Data rows
-                                for info in &self.exposure_infos {
-                                    ui.label(&info.filename);
+                                for (i, info) in infos.iter().enumerate() {
+                                    let mut filename = egui::RichText::new(&info.filename);
+                                    if excluded_files.contains(&info.path) {
+                                        filename = filename.strikethrough();
+                                    }
+                                    let response = ui.add(egui::Button::selectable(
+                                        selection.contains(&i),
+                                        filename,
+                                    ));
+                                    if response.clicked() {
+                                        let modifiers = ui.input(|input| input.modifiers);
+                                        match *selection_anchor {
+                                            Some(anchor) if modifiers.shift => {
+                                                selection.extend(anchor.min(i)..=anchor.max(i))
+                                            }
+                                            _ if modifiers.command => {
+                                                if !selection.remove(&i) {
+                                                    selection.insert(i);
+                                                }
+                                                *selection_anchor = Some(i);
+                                            }
+                                            _ => {
+                                                selection.clear();
+                                                selection.insert(i);
+                                                *selection_anchor = Some(i);
+                                            }
+                                        }
+                                    }
 
                                     if let Some(error) = &info.error_message {
                                         ui.label(error);
-                                    } else if let (Some(n), Some(d)) =
+                                    } else if let (Some(n), Some(d @ 1..)) =
                                         (info.exposure_bias_n, info.exposure_bias_d)
                                     {
-                                        ui.label(format!("{}/{}", n, d));
+                                        ui.label(self.ev_format.format(Rational32::new(n, d)));
                                     } else {
                                         ui.label("-");
                                     }
@@ -565,10 +2822,67 @@ impl ExposureBracketingOrganizerApp {
 
                     ui.add_space(12.0);
 
+                    let has_selection = !selection.is_empty();
+                    let target = if has_selection {
+                        "the selected files"
+                    } else {
+                        "all files"
+                    };
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(if has_selection {
+                            format!("{} of {} selected", selection.len(), infos.len())
+                        } else {
+                            format!("{} files", infos.len())
+                        });
+                        if ui.add_enabled(has_selection, egui::Button::new("Open")).clicked() {
+                            table_action = Some(ExposureTableAction::Open);
+                        }
+                        if ui.add_enabled(has_selection, egui::Button::new("Exclude from Processing"))
+                            .on_hover_text("Leave these files out when sequences are detected, e.g. test shots")
+                            .clicked()
+                        {
+                            table_action = Some(ExposureTableAction::Exclude);
+                        }
+                        if ui.add_enabled(has_selection, egui::Button::new("Include Again")).clicked() {
+                            table_action = Some(ExposureTableAction::Include);
+                        }
+                        if ui.button("Copy Values")
+                            .on_hover_text(format!("Copy filename, exposure bias and mode of {} as tab separated text", target))
+                            .clicked()
+                        {
+                            table_action = Some(ExposureTableAction::Copy);
+                        }
+                        if ui.button("Export CSV…")
+                            .on_hover_text(format!("Save filename, exposure bias and mode, ISO, shutter, aperture and capture time of {} for a spreadsheet", target))
+                            .clicked()
+                        {
+                            table_action = Some(ExposureTableAction::ExportCsv);
+                        }
+                        if ui.button("Export JSON…")
+                            .on_hover_text(format!("Save the metadata of {} and the sequences among them for scripts", target))
+                            .clicked()
+                        {
+                            table_action = Some(ExposureTableAction::ExportJson);
+                        }
+                        if ui.add_enabled(selection.len() == 1, egui::Button::new("Simulate Bracket…"))
+                            .on_hover_text("Preview the selected file pushed and pulled by 2 EV, to see whether the scene needed a bracket")
+                            .clicked()
+                        {
+                            table_action = Some(ExposureTableAction::Simulate);
+                        }
+                    });
+
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Min), |ui| {
-                        if ui.button("Apply Sequence").clicked() {
+                        if ui
+                            .button("Apply Sequence")
+                            .on_hover_text(format!(
+                                "Use the exposure biases of {} as the sequence",
+                                target
+                            ))
+                            .clicked()
+                        {
                             let mut sequence = String::new();
-                            for info in &self.exposure_infos {
+                            for info in selected_rows(infos, selection) {
                                 if let (Some(n), Some(d)) =
                                     (info.exposure_bias_n, info.exposure_bias_d)
                                 {
@@ -580,8 +2894,20 @@ impl ExposureBracketingOrganizerApp {
                             }
                             action_to_take = Some(sequence);
                         }
+                        if ui
+                            .button("Repair…")
+                            .on_hover_text(format!(
+                                "Write exposure biases for {} into their XMP sidecars",
+                                target
+                            ))
+                            .clicked()
+                        {
+                            table_action = Some(ExposureTableAction::Repair);
+                        }
                     });
-                });
+                },
+            );
+            self.exposure_window_detached = detached;
 
             if !is_open {
                 self.show_exposure_window = false;
@@ -591,6 +2917,234 @@ impl ExposureBracketingOrganizerApp {
                 self.exposure_bias_sequence = sequence;
                 self.show_exposure_window = false;
             }
+            if let Some(action) = table_action {
+                self.apply_exposure_table_action(ctx, action);
+            }
+        }
+    }
+
+    fn apply_exposure_table_action(&mut self, ctx: &egui::Context, action: ExposureTableAction) {
+        let mut rows = selected_rows(&self.exposure_infos, &self.exposure_selection);
+        match action {
+            ExposureTableAction::Open => {
+                for info in rows {
+                    open_file(&info.path);
+                }
+            }
+            ExposureTableAction::Exclude => {
+                for info in rows {
+                    self.excluded_files.insert(info.path.clone());
+                }
+            }
+            ExposureTableAction::Include => {
+                for info in rows {
+                    self.excluded_files.remove(&info.path);
+                }
+            }
+            ExposureTableAction::Copy => {
+                let text: Vec<String> = rows
+                    .map(|info| {
+                        let bias = match (info.exposure_bias_n, info.exposure_bias_d) {
+                            (Some(n), Some(d)) => format!("{}/{}", n, d),
+                            _ => String::new(),
+                        };
+                        let mode = info.exposure_mode.map_or("", exposure_mode_to_string);
+                        format!("{}\t{}\t{}", info.filename, bias, mode)
+                    })
+                    .collect();
+                ctx.copy_text(text.join("\n"));
+            }
+            ExposureTableAction::ExportCsv => {
+                let infos: Vec<&ExposureInfo> = rows.collect();
+                let Some(path) = rfd::FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .set_file_name("exposure_metadata.csv")
+                    .save_file()
+                else {
+                    return;
+                };
+                match metadata_export::write_csv(&infos, &path) {
+                    Ok(()) => info!(
+                        "Exported the metadata of {} files to {}",
+                        infos.len(),
+                        path.display()
+                    ),
+                    Err(e) => {
+                        self.show_error_messagebox = true;
+                        self.error_messagebox_text = e;
+                    }
+                }
+            }
+            ExposureTableAction::ExportJson => {
+                let infos: Vec<&ExposureInfo> = rows.collect();
+                let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .set_file_name("exposure_metadata.json")
+                    .save_file()
+                else {
+                    return;
+                };
+                let matching = SequenceMatching {
+                    sequences: parse_exposure_sequences(&self.exposure_bias_sequence),
+                    ev_mode: self.ev_mode.clone(),
+                    delta_reference: self.delta_reference,
+                    ev_tolerance: ev_tolerance(self.ev_tolerance),
+                };
+                match metadata_export::write_json(&infos, &matching, &path) {
+                    Ok(()) => info!(
+                        "Exported the metadata of {} files to {}",
+                        infos.len(),
+                        path.display()
+                    ),
+                    Err(e) => {
+                        self.show_error_messagebox = true;
+                        self.error_messagebox_text = e;
+                    }
+                }
+            }
+            ExposureTableAction::Repair => {
+                let paths: Vec<PathBuf> = rows.map(|info| info.path.clone()).collect();
+                self.exposure_repair.open_files(paths);
+            }
+            ExposureTableAction::Simulate => {
+                if let Some(info) = rows.next() {
+                    self.bracket_simulation.open(info.path.clone());
+                }
+            }
+        }
+    }
+
+    fn show_sequence_warnings_confirmation(&mut self, ctx: &egui::Context) {
+        if !self.show_sequence_warnings_confirmation {
+            return;
+        }
+        let warnings = self.sequence_warnings();
+        let mut confirmed = false;
+        let mut cancelled = false;
+        egui::Window::new("Unusual Sequence")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The sequence {} is unlikely to match brackets of a camera:",
+                    self.exposure_bias_sequence
+                ));
+                for warning in &warnings {
+                    ui.label(format!("• {}", warning));
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Start Anyway").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+        if confirmed || cancelled {
+            self.show_sequence_warnings_confirmation = false;
+        }
+        if confirmed {
+            self.confirm_originals_and_start();
+        }
+    }
+
+    fn show_modify_originals_confirmation(&mut self, ctx: &egui::Context) {
+        if self.show_modify_originals_confirmation {
+            let mut confirmed = false;
+            let mut cancelled = false;
+            egui::Window::new("Modify Originals?")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label("The group tags will be written into your DNG and JPEG files. This changes the original files, e.g. their checksums no longer match existing backups.");
+                    ui.horizontal(|ui| {
+                        if ui.button("Modify Originals").clicked() {
+                            confirmed = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancelled = true;
+                        }
+                    });
+                });
+            if confirmed || cancelled {
+                self.show_modify_originals_confirmation = false;
+            }
+            if confirmed {
+                self.start_processing(false);
+            }
+        }
+    }
+
+    fn show_undo_confirmation(&mut self, ctx: &egui::Context) {
+        let Some(entries) = &self.undo_confirmation else {
+            return;
+        };
+        let moves = entries
+            .iter()
+            .filter(|e| matches!(e, JournalEntry::Move { .. }))
+            .count();
+        let folders = entries
+            .iter()
+            .filter(|e| matches!(e, JournalEntry::CreateFolder { .. }))
+            .count();
+        let files = entries
+            .iter()
+            .filter(|e| matches!(e, JournalEntry::CreateFile { .. }))
+            .count();
+        let mut confirmed = false;
+        let mut cancelled = false;
+        egui::Window::new("Undo Last Run?")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("{} files will be moved back where they were, {} copies and other files added by the run deleted and the {} folders created removed, if they are empty.", moves, files, folders));
+                ui.horizontal(|ui| {
+                    if ui.button("Undo").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+        if !(confirmed || cancelled) {
+            return;
+        }
+        let entries = self.undo_confirmation.take().unwrap_or_default();
+        if cancelled {
+            return;
+        }
+        let errors = journal::undo_last_run(entries);
+        self.refresh_picked_folders();
+        if !errors.is_empty() {
+            self.show_error_messagebox = true;
+            self.error_messagebox_text = format!(
+                "Not everything could be undone, \"Undo Last Run\" tries the rest again:\n{}",
+                errors.join("\n")
+            );
+        }
+    }
+
+    fn show_run_diagnostics(&mut self, ctx: &egui::Context) {
+        if self.progress.running.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut diagnostics = self.run_diagnostics.lock().unwrap();
+        if diagnostics.is_empty() {
+            return;
+        }
+        let mut is_open = true;
+        egui::Window::new("No Sequences Found")
+            .open(&mut is_open)
+            .show(ctx, |ui| {
+                ui.label("The run found no sequences. Likely causes:");
+                for cause in diagnostics.iter() {
+                    ui.label(format!("• {}", cause));
+                }
+            });
+        if !is_open {
+            diagnostics.clear();
         }
     }
 
@@ -611,3 +3165,62 @@ impl ExposureBracketingOrganizerApp {
         }
     }
 }
+
+/// Repaints when a run reports progress instead of every frame, so the app
+/// idles while nothing happens. Files read in quick succession are batched
+/// into one repaint per [`PROGRESS_REPAINT_INTERVAL`].
+fn repaint_on_progress(progress: &JobProgress, ctx: egui::Context) {
+    let events = progress.subscribe();
+    thread::spawn(move || {
+        for event in events {
+            match event {
+                ProgressEvent::FileProcessed { .. } | ProgressEvent::SequenceFound { .. } => {
+                    ctx.request_repaint_after(PROGRESS_REPAINT_INTERVAL);
+                }
+                _ => ctx.request_repaint(),
+            }
+        }
+    });
+}
+
+/// An entry of the action selector that ticks or unticks `action`. Move and
+/// Copy to Folder and Rename in Place replace each other, a file can only
+/// end up in one place.
+fn action_toggle(
+    ui: &mut egui::Ui,
+    actions: &mut Vec<Action>,
+    action: Action,
+    text: &str,
+) -> egui::Response {
+    let selected = actions.contains(&action);
+    let response = ui.selectable_label(selected, text);
+    if response.clicked() {
+        if selected {
+            actions.retain(|a| *a != action);
+        } else {
+            if action.relocates() {
+                actions.retain(|a| !a.relocates());
+            }
+            actions.push(action);
+        }
+    }
+    response
+}
+
+/// Replaces the name screen readers use for a widget whose visible text is
+/// ambiguous on its own, e.g. a "Browse…" button or a combo box showing its value.
+pub fn accessible_name(response: egui::Response, name: &str) -> egui::Response {
+    response
+        .ctx
+        .accesskit_node_builder(response.id, |node| node.set_label(name));
+    response
+}
+
+/// Makes a widget a polite live region, so screen readers read `text` out
+/// whenever it changes without the widget having focus.
+fn announce(response: &egui::Response, text: impl Into<String>) {
+    response.ctx.accesskit_node_builder(response.id, |node| {
+        node.set_live(egui::accesskit::Live::Polite);
+        node.set_label(text.into());
+    });
+}
@@ -0,0 +1,444 @@
+//! Manifest of an organized archive, to notice silent corruption years later.
+//!
+//! `ebo-manifest.json` at the top of a root lists every file below it with
+//! its size, modification time, MD5 hash and the sequence folder it belongs
+//! to. It is rewritten after each run that organized sequences into the root;
+//! files whose size and modification time are unchanged keep their recorded
+//! hash, so only new files are read and a file that rotted since still
+//! differs from the hash it had when it was organized. Verifying reads every
+//! file again and compares it with the manifest.
+//!
+//! Hidden files and folders, e.g. staging folders or `.directory` comments,
+//! are left out. The notes given to sequences during review are kept by
+//! their sequence folder, as long as the folder is there.
+
+use crate::app::Action;
+use crate::file_utils::{is_sequence_folder, ProcessSettings, RunReport};
+use chrono::{Local, NaiveDateTime};
+use eframe::egui;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+pub const MANIFEST_FILE_NAME: &str = "ebo-manifest.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub created: NaiveDateTime,
+    pub files: Vec<ManifestFile>,
+    /// Review notes by the sequence folder they were given to, relative to the root
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub notes: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Relative to the root, separated by `/`
+    pub path: String,
+    pub size: u64,
+    /// Seconds since the Unix epoch
+    pub modified: Option<u64>,
+    pub md5: String,
+    /// The sequence folder the file is in, relative to the root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<String>,
+}
+
+impl Manifest {
+    fn load(root: &Path) -> Result<Option<Self>, String> {
+        let path = root.join(MANIFEST_FILE_NAME);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))
+    }
+}
+
+/// A file found below the root while scanning.
+struct ScannedFile {
+    path: PathBuf,
+    relative: String,
+    size: u64,
+    modified: Option<u64>,
+    sequence: Option<String>,
+}
+
+fn relative_name(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+fn scan(root: &Path, dir: &Path, files: &mut Vec<ScannedFile>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read directory {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    let sequence = (dir != root && is_sequence_folder(dir)).then(|| relative_name(root, dir));
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|e| e.path());
+    for entry in entries {
+        let path = entry.path();
+        if is_hidden(&path) || (dir == root && entry.file_name() == MANIFEST_FILE_NAME) {
+            continue;
+        }
+        // No symlinks, they could lead out of the archive or in a circle
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            scan(root, &path, files);
+        } else if file_type.is_file() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            files.push(ScannedFile {
+                relative: relative_name(root, &path),
+                path,
+                size: metadata.len(),
+                modified: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs()),
+                sequence: sequence.clone(),
+            });
+        }
+    }
+}
+
+fn md5_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut context = md5::Context::new();
+    io::copy(&mut file, &mut context)?;
+    Ok(format!("{:x}", context.finalize()))
+}
+
+/// Writes the manifest of `root`, reusing the hashes of unchanged files.
+/// `notes` are added to those of the previous manifest by the absolute path
+/// of their sequence folder. Returns the number of files listed.
+pub fn write_manifest(root: &Path, notes: &[(&Path, &str)]) -> Result<usize, String> {
+    let (previous, mut known_notes): (HashMap<String, ManifestFile>, _) = Manifest::load(root)
+        .unwrap_or_else(|e| {
+            warn!("{}, hashing all files again", e);
+            None
+        })
+        .map(|m| {
+            let files = m.files.into_iter().map(|f| (f.path.clone(), f)).collect();
+            (files, m.notes)
+        })
+        .unwrap_or_default();
+
+    let mut scanned = Vec::new();
+    scan(root, root, &mut scanned);
+    for (folder, note) in notes {
+        if folder.starts_with(root) {
+            known_notes.insert(relative_name(root, folder), note.to_string());
+        }
+    }
+    let sequences: HashSet<&str> = scanned
+        .iter()
+        .filter_map(|file| file.sequence.as_deref())
+        .collect();
+    known_notes.retain(|sequence, _| sequences.contains(sequence.as_str()));
+    let mut files = Vec::new();
+    for file in scanned {
+        let md5 = match previous.get(&file.relative) {
+            Some(known) if known.size == file.size && known.modified == file.modified => {
+                known.md5.clone()
+            }
+            _ => md5_file(&file.path)
+                .map_err(|e| format!("Failed to read {}: {}", file.path.display(), e))?,
+        };
+        files.push(ManifestFile {
+            path: file.relative,
+            size: file.size,
+            modified: file.modified,
+            md5,
+            sequence: file.sequence,
+        });
+    }
+
+    let manifest = Manifest {
+        created: Local::now().naive_local(),
+        files,
+        notes: known_notes,
+    };
+    let path = root.join(MANIFEST_FILE_NAME);
+    let content = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    info!(
+        "Wrote manifest of {} files to {}",
+        manifest.files.len(),
+        path.display()
+    );
+    Ok(manifest.files.len())
+}
+
+/// The roots whose manifests a run with `settings` updates: the destination
+/// root if one is set, else the processed folders themselves. Empty unless the
+/// run organizes sequences into folders and asks for a manifest.
+pub fn manifest_roots(folders: &[PathBuf], settings: &ProcessSettings) -> Vec<PathBuf> {
+    let organizes = settings.selected_actions.iter().any(Action::organizes);
+    if !settings.write_manifest || settings.dry_run || !organizes {
+        return Vec::new();
+    }
+    match &settings.destination_root {
+        Some(root) => vec![root.clone()],
+        None => folders.to_vec(),
+    }
+}
+
+/// Writes the manifests of `roots` after a run with the notes of its
+/// sequences, failures end up in its report.
+pub fn write_manifests(roots: &[PathBuf], report: &mut RunReport) {
+    let notes: Vec<(&Path, &str)> = report
+        .sequences
+        .iter()
+        .filter_map(|sequence| Some((sequence.destination.as_deref()?, sequence.note.as_deref()?)))
+        .collect();
+    let mut errors = Vec::new();
+    for root in roots {
+        if let Err(e) = write_manifest(root, &notes) {
+            warn!("{}", e);
+            errors.push(e);
+        }
+    }
+    report.errors.extend(errors);
+}
+
+#[derive(Debug, Default)]
+pub struct ArchiveCheck {
+    pub verified: usize,
+    /// Listed but gone
+    pub missing: Vec<String>,
+    /// Different size or content than listed
+    pub changed: Vec<String>,
+    /// Added since the manifest was written
+    pub unlisted: Vec<String>,
+}
+
+impl ArchiveCheck {
+    pub fn is_intact(&self) -> bool {
+        self.missing.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Reads every file listed in the manifest of `root` and compares it.
+pub fn verify_archive(root: &Path) -> Result<ArchiveCheck, String> {
+    let Some(manifest) = Manifest::load(root)? else {
+        return Err(format!(
+            "{} has no {}, write one with \"Write archive manifest\" first",
+            root.display(),
+            MANIFEST_FILE_NAME
+        ));
+    };
+    let mut check = ArchiveCheck::default();
+    let mut listed = HashSet::new();
+    for file in &manifest.files {
+        listed.insert(file.path.as_str());
+        let path = root.join(&file.path);
+        let intact = match fs::metadata(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                check.missing.push(file.path.clone());
+                continue;
+            }
+            Err(_) => false,
+            Ok(metadata) if metadata.len() != file.size => false,
+            Ok(_) => md5_file(&path).is_ok_and(|md5| md5 == file.md5),
+        };
+        if intact {
+            check.verified += 1;
+        } else {
+            check.changed.push(file.path.clone());
+        }
+    }
+
+    let mut scanned = Vec::new();
+    scan(root, root, &mut scanned);
+    check.unlisted = scanned
+        .into_iter()
+        .map(|f| f.relative)
+        .filter(|relative| !listed.contains(relative.as_str()))
+        .collect();
+    info!(
+        "Verified {}: {} intact, {} missing, {} changed, {} not listed",
+        root.display(),
+        check.verified,
+        check.missing.len(),
+        check.changed.len(),
+        check.unlisted.len()
+    );
+    Ok(check)
+}
+
+/// Window to pick an archive root and verify it against its manifest in the background.
+#[derive(Default)]
+pub struct ArchiveVerifyWindow {
+    pub open: bool,
+    root: Option<PathBuf>,
+    result: Arc<Mutex<Option<Result<ArchiveCheck, String>>>>,
+    running: bool,
+}
+
+impl ArchiveVerifyWindow {
+    pub fn open(&mut self, root: Option<PathBuf>) {
+        if root.is_some() {
+            self.root = root;
+        }
+        self.open = true;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if !self.open {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Verify Archive")
+            .open(&mut open)
+            .default_width(520.0)
+            .show(ctx, |ui| self.show_contents(ui));
+        self.open = open;
+    }
+
+    fn show_contents(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!(
+            "Reads every file listed in the {} of a folder and reports files that are missing or changed.",
+            MANIFEST_FILE_NAME
+        ));
+        ui.horizontal(|ui| {
+            let label = ui.label(match &self.root {
+                Some(root) => root.display().to_string(),
+                None => "No folder selected".to_string(),
+            });
+            if ui.button("Browse…").labelled_by(label.id).clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    self.root = Some(path);
+                }
+            }
+        });
+
+        let result = self.result.lock().unwrap().is_some();
+        if self.running && result {
+            self.running = false;
+        }
+        ui.horizontal(|ui| {
+            let can_run = self.root.is_some() && !self.running;
+            if ui
+                .add_enabled(can_run, egui::Button::new("Verify"))
+                .clicked()
+            {
+                self.start();
+            }
+            if self.running {
+                ui.spinner();
+                ui.label("Reading files…");
+            }
+        });
+
+        let result = self.result.lock().unwrap();
+        let Some(result) = result.as_ref() else {
+            return;
+        };
+        ui.separator();
+        let check = match result {
+            Ok(check) => check,
+            Err(e) => {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", e));
+                return;
+            }
+        };
+        if check.is_intact() {
+            ui.label(format!("✔ All {} files are intact.", check.verified));
+        } else {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!(
+                    "⚠ {} files intact, {} missing, {} changed",
+                    check.verified,
+                    check.missing.len(),
+                    check.changed.len()
+                ),
+            );
+        }
+        if !check.unlisted.is_empty() {
+            ui.label(format!(
+                "{} files were added since the manifest was written.",
+                check.unlisted.len()
+            ));
+        }
+        egui::ScrollArea::vertical()
+            .max_height(360.0)
+            .show(ui, |ui| {
+                for path in &check.missing {
+                    ui.label(format!("Missing: {}", path));
+                }
+                for path in &check.changed {
+                    ui.label(format!("Changed: {}", path));
+                }
+                for path in &check.unlisted {
+                    ui.label(format!("Not listed: {}", path));
+                }
+            });
+    }
+
+    fn start(&mut self) {
+        let Some(root) = self.root.clone() else {
+            return;
+        };
+        *self.result.lock().unwrap() = None;
+        self.running = true;
+        let result = Arc::clone(&self.result);
+        thread::spawn(move || {
+            let check = verify_archive(&root);
+            *result.lock().unwrap() = Some(check);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_stay_while_their_sequence_folder_is_there() {
+        let root = std::env::temp_dir().join(format!("ebo-manifest-{}", std::process::id()));
+        let folder = root.join("IMG_0001");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("IMG_0001.ARW"), "raw").unwrap();
+
+        write_manifest(&root, &[(folder.as_path(), "ghosting")]).unwrap();
+        // Kept by later runs that have no note for the folder
+        write_manifest(&root, &[]).unwrap();
+        let manifest = Manifest::load(&root).unwrap().unwrap();
+        assert_eq!(manifest.notes["IMG_0001"], "ghosting");
+
+        fs::remove_dir_all(&folder).unwrap();
+        write_manifest(&root, &[]).unwrap();
+        let manifest = Manifest::load(&root).unwrap().unwrap();
+        assert!(manifest.notes.is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,320 @@
+//! Audit of an already organized folder tree, made by this app or by hand:
+//! every folder holding images has to contain complete brackets of the
+//! entered sequence, shot with a single camera.
+
+use crate::file_utils::{has_matching_extension, open_in_file_manager};
+use crate::metadata_cache::{CachedMetadata, MetadataCache};
+use ebo_core::{biases_match_sequence, DeltaReference, EvFormat, EvMode};
+use eframe::egui;
+use log::{info, warn};
+use num_rational::Rational32;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub struct AuditSettings {
+    pub extensions: Vec<String>,
+    /// A folder is fine if its brackets all follow one of them
+    pub sequences: Vec<Vec<Option<Rational32>>>,
+    pub ev_mode: EvMode,
+    pub delta_reference: DeltaReference,
+    pub ev_tolerance: Rational32,
+    pub use_metadata_cache: bool,
+    /// How the biases of a broken bracket are listed
+    pub ev_format: EvFormat,
+}
+
+#[derive(Debug)]
+pub struct FolderAudit {
+    pub folder: PathBuf,
+    pub files: usize,
+    /// Empty for a complete, consistent folder
+    pub problems: Vec<String>,
+}
+
+/// Audits every folder below `root` that holds images. The root itself is
+/// only audited if it has no subfolders, i.e. it is a single bracket folder.
+pub fn audit_tree(root: &Path, settings: &AuditSettings) -> Vec<FolderAudit> {
+    let mut cache = if settings.use_metadata_cache {
+        MetadataCache::load()
+    } else {
+        MetadataCache::default()
+    };
+
+    let mut folders = Vec::new();
+    collect_folders(root, &mut folders);
+    if folders.len() > 1 {
+        folders.retain(|f| f != root);
+    }
+
+    let mut audits: Vec<FolderAudit> = folders
+        .iter()
+        .filter_map(|folder| audit_folder(folder, settings, &mut cache))
+        .collect();
+    audits.sort_by(|a, b| a.folder.cmp(&b.folder));
+
+    if settings.use_metadata_cache {
+        cache.save();
+    }
+    let incomplete = audits.iter().filter(|a| !a.problems.is_empty()).count();
+    info!(
+        "Audited {} folders below {}, {} with problems",
+        audits.len(),
+        root.display(),
+        incomplete
+    );
+    audits
+}
+
+fn collect_folders(dir: &Path, folders: &mut Vec<PathBuf>) {
+    folders.push(dir.to_path_buf());
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read directory {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        // No symlinks, they could lead in a circle
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            collect_folders(&entry.path(), folders);
+        }
+    }
+}
+
+/// `None` for folders without images.
+fn audit_folder(
+    folder: &Path,
+    settings: &AuditSettings,
+    cache: &mut MetadataCache,
+) -> Option<FolderAudit> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(folder)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && has_matching_extension(p, &settings.extensions))
+        .collect();
+    if paths.is_empty() {
+        return None;
+    }
+    paths.sort();
+
+    let mut problems = Vec::new();
+    let mut files: Vec<(PathBuf, CachedMetadata)> = Vec::new();
+    for path in &paths {
+        match cache.read(path) {
+            Some(metadata) => files.push((path.clone(), metadata)),
+            None => problems.push(format!(
+                "{} has no readable metadata",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            )),
+        }
+    }
+    // Capture order, files without a capture time last in name order
+    files.sort_by_key(|(_, m)| (m.capture_time.is_none(), m.capture_time));
+
+    // Sequences the folder has a whole number of brackets of
+    let fitting: Vec<&Vec<Option<Rational32>>> = settings
+        .sequences
+        .iter()
+        .filter(|s| paths.len().is_multiple_of(s.len()))
+        .collect();
+    if fitting.is_empty() {
+        let lengths: Vec<String> = settings
+            .sequences
+            .iter()
+            .map(|s| s.len().to_string())
+            .collect();
+        problems.push(format!(
+            "{} files, a complete bracket has {}",
+            paths.len(),
+            lengths.join(" or ")
+        ));
+    } else if problems.is_empty() {
+        let biases: Vec<Option<Rational32>> = files.iter().map(|(_, m)| m.exposure_bias).collect();
+        let mismatches: Vec<Vec<String>> = fitting
+            .iter()
+            .map(|sequence| sequence_mismatches(&biases, sequence, settings))
+            .collect();
+        if mismatches.iter().all(|m| !m.is_empty()) {
+            problems.extend(mismatches.into_iter().next().unwrap_or_default());
+        }
+    }
+
+    let cameras: BTreeSet<&str> = files
+        .iter()
+        .filter_map(|(_, m)| m.camera.as_deref())
+        .collect();
+    if cameras.len() > 1 {
+        let cameras: Vec<&str> = cameras.into_iter().collect();
+        problems.push(format!("Mixed cameras: {}", cameras.join(", ")));
+    }
+
+    Some(FolderAudit {
+        folder: folder.to_path_buf(),
+        files: paths.len(),
+        problems,
+    })
+}
+
+/// Why the brackets of `biases` don't follow `sequence`, empty if they all do.
+fn sequence_mismatches(
+    biases: &[Option<Rational32>],
+    sequence: &[Option<Rational32>],
+    settings: &AuditSettings,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (i, bracket) in biases.chunks(sequence.len()).enumerate() {
+        if !biases_match_sequence(
+            bracket,
+            sequence,
+            &settings.ev_mode,
+            settings.delta_reference,
+            settings.ev_tolerance,
+        ) {
+            problems.push(format!(
+                "Exposure biases {} don't follow the sequence{}",
+                format_biases(bracket, settings.ev_format),
+                if biases.len() > sequence.len() {
+                    format!(" in bracket {}", i + 1)
+                } else {
+                    String::new()
+                }
+            ));
+        }
+    }
+    problems
+}
+
+fn format_biases(biases: &[Option<Rational32>], ev_format: EvFormat) -> String {
+    biases
+        .iter()
+        .map(|b| b.map_or("?".to_string(), |b| ev_format.format(b)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Window to pick a folder tree, run the audit in the background and list
+/// the folders that failed it.
+#[derive(Default)]
+pub struct AuditWindow {
+    pub open: bool,
+    root: Option<PathBuf>,
+    result: Arc<Mutex<Option<Vec<FolderAudit>>>>,
+    running: bool,
+    show_all: bool,
+}
+
+impl AuditWindow {
+    pub fn open(&mut self, root: Option<PathBuf>) {
+        if root.is_some() {
+            self.root = root;
+        }
+        self.open = true;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, settings: impl FnOnce() -> AuditSettings) {
+        if !self.open {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Audit Folders")
+            .open(&mut open)
+            .default_width(520.0)
+            .show(ctx, |ui| self.show_contents(ui, settings));
+        self.open = open;
+    }
+
+    fn show_contents(&mut self, ui: &mut egui::Ui, settings: impl FnOnce() -> AuditSettings) {
+        ui.label("Checks that every folder holds complete brackets of the exposure bias sequence, shot with one camera.");
+        ui.horizontal(|ui| {
+            let label = ui.label(match &self.root {
+                Some(root) => root.display().to_string(),
+                None => "No folder selected".to_string(),
+            });
+            if ui.button("Browse…").labelled_by(label.id).clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    self.root = Some(path);
+                }
+            }
+        });
+
+        let result = self.result.lock().unwrap().is_some();
+        if self.running && result {
+            self.running = false;
+        }
+        ui.horizontal(|ui| {
+            let can_run = self.root.is_some() && !self.running;
+            if ui
+                .add_enabled(can_run, egui::Button::new("Run Audit"))
+                .clicked()
+            {
+                self.start(settings());
+            }
+            if self.running {
+                ui.spinner();
+                ui.label("Auditing…");
+            }
+        });
+
+        let result = self.result.lock().unwrap();
+        let Some(audits) = result.as_ref() else {
+            return;
+        };
+        let failed = audits.iter().filter(|a| !a.problems.is_empty()).count();
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} folders audited, {} with problems",
+                audits.len(),
+                failed
+            ));
+            ui.checkbox(&mut self.show_all, "Show complete folders");
+        });
+
+        egui::ScrollArea::vertical()
+            .max_height(360.0)
+            .show(ui, |ui| {
+                for audit in audits {
+                    if audit.problems.is_empty() && !self.show_all {
+                        continue;
+                    }
+                    ui.horizontal(|ui| {
+                        if audit.problems.is_empty() {
+                            ui.label("✔");
+                        } else {
+                            ui.colored_label(ui.visuals().warn_fg_color, "⚠");
+                        }
+                        ui.strong(audit.folder.display().to_string());
+                        ui.label(format!("{} files", audit.files));
+                        if ui.small_button("Open Folder").clicked() {
+                            open_in_file_manager(&audit.folder);
+                        }
+                    });
+                    for problem in &audit.problems {
+                        ui.label(format!("    {}", problem));
+                    }
+                }
+            });
+    }
+
+    fn start(&mut self, settings: AuditSettings) {
+        let Some(root) = self.root.clone() else {
+            return;
+        };
+        if settings.sequences.is_empty() {
+            *self.result.lock().unwrap() = Some(Vec::new());
+            return;
+        }
+        *self.result.lock().unwrap() = None;
+        self.running = true;
+        let result = Arc::clone(&self.result);
+        thread::spawn(move || {
+            let audits = audit_tree(&root, &settings);
+            *result.lock().unwrap() = Some(audits);
+        });
+    }
+}
@@ -0,0 +1,52 @@
+//! Lowers the CPU and IO priority of the calling thread, so a long scan does not
+//! slow down other applications working on the same disks.
+
+use log::debug;
+
+#[cfg(target_os = "linux")]
+pub fn enter_background_mode() {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+    // SAFETY: on Linux both calls accept a thread id and only affect that thread
+    unsafe {
+        let tid = libc::gettid();
+        if libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, 19) != 0 {
+            debug!("Failed to lower CPU priority of the worker thread");
+        }
+        if libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            tid,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        ) != 0
+        {
+            debug!("Failed to lower IO priority of the worker thread");
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn enter_background_mode() {
+    // SAFETY: PRIO_DARWIN_THREAD with id 0 targets the calling thread
+    if unsafe { libc::setpriority(libc::PRIO_DARWIN_THREAD, 0, libc::PRIO_DARWIN_BG) } != 0 {
+        debug!("Failed to move the worker thread to the background band");
+    }
+}
+
+#[cfg(windows)]
+pub fn enter_background_mode() {
+    use windows::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_MODE_BACKGROUND_BEGIN,
+    };
+    // SAFETY: background processing mode only affects the calling thread
+    if unsafe { SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_BEGIN) }.is_err() {
+        debug!("Failed to enter background processing mode");
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn enter_background_mode() {
+    debug!("Background mode is not supported on this platform");
+}
@@ -0,0 +1,153 @@
+//! Bracketing modes besides exposure bracketing: aperture, white balance and
+//! flash brackets, recognized by which capture setting varies between the
+//! frames while the others stay the same.
+//!
+//! Only EXIF is looked at. Cameras that record a white balance or flash
+//! compensation shift in their maker notes alone are not recognized.
+
+use num_rational::Rational32;
+use rawler::exif::Exif;
+use rawler::formats::tiff::Rational;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BracketKind {
+    Exposure,
+    Aperture,
+    WhiteBalance,
+    Flash,
+}
+
+impl BracketKind {
+    pub const ALL: [BracketKind; 4] = [
+        BracketKind::Exposure,
+        BracketKind::Aperture,
+        BracketKind::WhiteBalance,
+        BracketKind::Flash,
+    ];
+
+    /// Put before the name of a moved sequence's folder, exposure brackets keep
+    /// the plain name of their first file.
+    pub fn folder_prefix(&self) -> &'static str {
+        match self {
+            BracketKind::Exposure => "",
+            BracketKind::Aperture => "aperture-",
+            BracketKind::WhiteBalance => "wb-",
+            BracketKind::Flash => "flash-",
+        }
+    }
+}
+
+impl std::fmt::Display for BracketKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BracketKind::Exposure => write!(f, "Exposure bracket"),
+            BracketKind::Aperture => write!(f, "Aperture bracket"),
+            BracketKind::WhiteBalance => write!(f, "White balance bracket"),
+            BracketKind::Flash => write!(f, "Flash bracket"),
+        }
+    }
+}
+
+/// The EXIF capture settings the other bracketing modes vary.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureSettings {
+    pub fnumber: Option<Rational32>,
+    pub exposure_time: Option<Rational32>,
+    pub iso: Option<u32>,
+    pub white_balance: Option<u16>,
+    pub light_source: Option<u16>,
+    pub flash: Option<u16>,
+    pub flash_energy: Option<Rational32>,
+}
+
+impl From<&Exif> for CaptureSettings {
+    fn from(exif: &Exif) -> Self {
+        Self {
+            fnumber: exif.fnumber.and_then(to_rational),
+            exposure_time: exif.exposure_time.and_then(to_rational),
+            iso: exif.iso_speed.or(exif.iso_speed_ratings.map(u32::from)),
+            white_balance: exif.white_balance,
+            light_source: exif.light_source,
+            flash: exif.flash,
+            flash_energy: exif.flash_energy.and_then(to_rational),
+        }
+    }
+}
+
+fn to_rational(value: Rational) -> Option<Rational32> {
+    let n = i32::try_from(value.n).ok()?;
+    let d = i32::try_from(value.d).ok()?;
+    (d != 0).then(|| Rational32::new(n, d))
+}
+
+impl CaptureSettings {
+    fn flash_fired(&self) -> Option<bool> {
+        // Bit 0 of the EXIF Flash tag
+        self.flash.map(|flash| flash & 1 == 1)
+    }
+}
+
+/// Recognizes an aperture, white balance or flash bracket in consecutive
+/// frames. All frames must have the same exposure bias, otherwise they are
+/// left to exposure bracket detection.
+pub fn classify(
+    frames: &[Option<&CaptureSettings>],
+    biases: &[Option<Rational32>],
+) -> Option<BracketKind> {
+    if frames.len() < 2 || !all_equal(biases.iter()) {
+        return None;
+    }
+    let frames: Vec<&CaptureSettings> = frames.iter().copied().collect::<Option<_>>()?;
+    let same_exposure_time = all_equal(frames.iter().map(|f| f.exposure_time));
+    let same_iso = all_equal(frames.iter().map(|f| f.iso));
+    let same_fnumber = all_equal(frames.iter().map(|f| f.fnumber));
+
+    if same_exposure_time && same_iso && all_distinct(frames.iter().map(|f| f.fnumber)) {
+        return Some(BracketKind::Aperture);
+    }
+    if !(same_exposure_time && same_iso && same_fnumber) {
+        return None;
+    }
+    let white_balance = frames
+        .iter()
+        .map(|f| match (f.white_balance, f.light_source) {
+            (None, None) => None,
+            settings => Some(settings),
+        });
+    if all_distinct(white_balance) {
+        return Some(BracketKind::WhiteBalance);
+    }
+    let fired: Vec<Option<bool>> = frames.iter().map(|f| f.flash_fired()).collect();
+    let any_fired = fired.contains(&Some(true));
+    if any_fired
+        && (all_distinct(frames.iter().map(|f| f.flash_energy)) || !all_equal(fired.iter()))
+    {
+        return Some(BracketKind::Flash);
+    }
+    None
+}
+
+fn all_equal<T: PartialEq>(mut values: impl Iterator<Item = T>) -> bool {
+    match values.next() {
+        Some(first) => values.all(|v| v == first),
+        None => true,
+    }
+}
+
+/// All values known and different from each other.
+fn all_distinct<T: Eq + Hash>(values: impl Iterator<Item = Option<T>>) -> bool {
+    let mut seen = HashSet::new();
+    for value in values {
+        let Some(value) = value else {
+            return false;
+        };
+        if !seen.insert(value) {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,232 @@
+//! Simulated brackets from a single raw file, to judge whether a scene would
+//! have needed bracketing. The raw data is developed once without a tone
+//! curve, then pushed and pulled digitally by the simulated exposure bias,
+//! clipped like the sensor would have clipped and shown with the sRGB curve.
+//!
+//! Pushing can't recover shadows a real longer exposure would have captured
+//! without noise, and pulling can't bring back clipped highlights; that is the
+//! point: what clips at 0 EV and stays clipped at −2 EV needed a real bracket.
+//! Since the whole raw is decoded, an odd looking 0 EV frame also hints that
+//! rawler doesn't decode the camera correctly.
+
+use crate::app::accessible_name;
+use eframe::egui;
+use rawler::decoders::RawDecodeParams;
+use rawler::imgop::develop::{Intermediate, ProcessingStep, RawDevelop};
+use rawler::{get_decoder, rawsource::RawSource};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The exposure biases simulated, in stops
+const SIMULATED_EV: [i32; 3] = [-2, 0, 2];
+
+/// Width of the rendered previews, the raw is sampled down to it
+const PREVIEW_WIDTH: usize = 480;
+
+/// Linear values below this count as crushed shadows, about 10 stops below white
+const SHADOW_FLOOR: f32 = 1.0 / 1024.0;
+
+pub struct SimulatedFrame {
+    pub ev: i32,
+    pub image: egui::ColorImage,
+    /// Share of pixels with a channel at white
+    pub clipped: f32,
+    /// Share of pixels with all channels near black
+    pub crushed: f32,
+}
+
+type Simulation = Result<Vec<SimulatedFrame>, String>;
+
+/// Develops `path` and renders it at each of [`SIMULATED_EV`].
+pub fn simulate(path: &Path) -> Simulation {
+    let raw_file = RawSource::new(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let decoder = get_decoder(&raw_file).map_err(|e| format!("Unsupported file: {}", e))?;
+    let raw_image = decoder
+        .raw_image(&raw_file, &RawDecodeParams::default(), false)
+        .map_err(|e| format!("Failed to decode: {}", e))?;
+    // Linear output, the curve is applied after pushing or pulling
+    let develop = RawDevelop {
+        steps: RawDevelop::default()
+            .steps
+            .into_iter()
+            .filter(|step| *step != ProcessingStep::SRgb)
+            .collect(),
+    };
+    let intermediate = develop
+        .develop_intermediate(&raw_image)
+        .map_err(|e| format!("Failed to develop: {}", e))?;
+    let (width, height, pixels) = sample(&intermediate);
+    Ok(SIMULATED_EV
+        .iter()
+        .map(|&ev| render(&pixels, width, height, ev))
+        .collect())
+}
+
+/// The developed image sampled down to [`PREVIEW_WIDTH`], as linear RGB.
+fn sample(intermediate: &Intermediate) -> (usize, usize, Vec<[f32; 3]>) {
+    let dim = intermediate.dim();
+    let step = dim.w.div_ceil(PREVIEW_WIDTH).max(1);
+    let (width, height) = (dim.w / step, dim.h / step);
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * step * dim.w + x * step;
+            pixels.push(match intermediate {
+                Intermediate::Monochrome(p) => [p.data[index]; 3],
+                Intermediate::ThreeColor(p) => p.data[index],
+                Intermediate::FourColor(p) => {
+                    let [r, g, b, _] = p.data[index];
+                    [r, g, b]
+                }
+            });
+        }
+    }
+    (width, height, pixels)
+}
+
+fn render(pixels: &[[f32; 3]], width: usize, height: usize, ev: i32) -> SimulatedFrame {
+    let gain = 2f32.powi(ev);
+    let mut rgb = Vec::with_capacity(pixels.len() * 3);
+    let (mut clipped, mut crushed) = (0, 0);
+    for pixel in pixels {
+        let pushed = pixel.map(|v| (v * gain).clamp(0.0, 1.0));
+        if pushed.iter().any(|v| *v >= 1.0) {
+            clipped += 1;
+        } else if pushed.iter().all(|v| *v < SHADOW_FLOOR) {
+            crushed += 1;
+        }
+        rgb.extend(pushed.map(|v| (srgb_gamma(v) * 255.0).round() as u8));
+    }
+    let total = pixels.len().max(1) as f32;
+    SimulatedFrame {
+        ev,
+        image: egui::ColorImage::from_rgb([width, height], &rgb),
+        clipped: clipped as f32 / total,
+        crushed: crushed as f32 / total,
+    }
+}
+
+fn srgb_gamma(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Window showing the simulated brackets of one file, rendered in the background.
+#[derive(Default)]
+pub struct BracketSimulationWindow {
+    pub open: bool,
+    path: Option<PathBuf>,
+    result: Arc<Mutex<Option<Simulation>>>,
+    frames: Vec<(SimulatedFrame, egui::TextureHandle)>,
+    error: Option<String>,
+    running: bool,
+}
+
+impl BracketSimulationWindow {
+    /// Opens the window and starts simulating `path`.
+    pub fn open(&mut self, path: PathBuf) {
+        self.path = Some(path);
+        self.open = true;
+        self.start();
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if !self.open {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Simulate Bracket")
+            .open(&mut open)
+            .default_width(3.0 * PREVIEW_WIDTH as f32 / 2.0)
+            .show(ctx, |ui| self.show_contents(ui));
+        self.open = open;
+    }
+
+    fn show_contents(&mut self, ui: &mut egui::Ui) {
+        ui.label("Pushes and pulls a single raw file by −2 and +2 EV, to judge whether the scene needed a bracket. Highlights clipped at −2 EV and shadows crushed at +2 EV are lost without one.");
+        ui.horizontal(|ui| {
+            let label = ui.label(match &self.path {
+                Some(path) => path.display().to_string(),
+                None => "No file selected".to_string(),
+            });
+            if ui
+                .add_enabled(!self.running, egui::Button::new("Browse…"))
+                .labelled_by(label.id)
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    self.open(path);
+                }
+            }
+        });
+
+        if let Some(result) = self.result.lock().unwrap().take() {
+            self.running = false;
+            match result {
+                Ok(frames) => {
+                    self.frames = frames
+                        .into_iter()
+                        .map(|mut frame| {
+                            let texture = ui.ctx().load_texture(
+                                format!("simulated_{}", frame.ev),
+                                std::mem::take(&mut frame.image),
+                                Default::default(),
+                            );
+                            (frame, texture)
+                        })
+                        .collect();
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+        if self.running {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Decoding…");
+            });
+            return;
+        }
+        if let Some(e) = &self.error {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", e));
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            for (frame, texture) in &self.frames {
+                ui.vertical(|ui| {
+                    ui.strong(format!("{:+} EV", frame.ev));
+                    let image =
+                        ui.add(egui::Image::new(texture).max_width(PREVIEW_WIDTH as f32 / 2.0));
+                    accessible_name(image, &format!("Simulated frame at {:+} EV", frame.ev));
+                    ui.label(format!(
+                        "{:.1}% clipped, {:.1}% crushed",
+                        frame.clipped * 100.0,
+                        frame.crushed * 100.0
+                    ));
+                });
+            }
+        });
+    }
+
+    fn start(&mut self) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        *self.result.lock().unwrap() = None;
+        self.frames.clear();
+        self.error = None;
+        self.running = true;
+        let result = Arc::clone(&self.result);
+        thread::spawn(move || {
+            // rawler panics on some layouts it doesn't support yet
+            let frames = panic::catch_unwind(|| simulate(&path))
+                .unwrap_or_else(|_| Err("rawler can't develop this file".to_string()));
+            *result.lock().unwrap() = Some(frames);
+        });
+    }
+}
@@ -0,0 +1,57 @@
+//! Presets tied to a camera, applied when a folder shot with it is picked.
+//!
+//! The camera of a folder is the most common one among a sample of its
+//! files, so a few frames from a second body don't switch the profile. A
+//! preset's camera is matched like camera exceptions and clock offsets, as
+//! part of the make and model; the most specific match wins.
+
+use crate::app::Preset;
+use crate::file_utils::{camera_matches, has_matching_extension};
+use crate::metadata_cache::read_metadata;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files read to find the camera of a folder, enough to outvote a second body
+const SAMPLED_FILES: usize = 25;
+
+/// The camera a picked folder was mostly shot with.
+pub struct DetectedCamera {
+    pub folder: PathBuf,
+    pub camera: Option<String>,
+}
+
+/// The camera most files of `dir` were shot with, `None` if none is recorded.
+pub fn dominant_camera(dir: &Path, extensions: &[String]) -> Option<String> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && has_matching_extension(path, extensions))
+        .collect();
+    files.sort();
+    // Spread over the folder, the first files may be test shots of another body
+    let step = files.len().div_ceil(SAMPLED_FILES).max(1);
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for path in files.iter().step_by(step) {
+        if let Some(camera) = read_metadata(path).and_then(|m| m.camera) {
+            *counts.entry(camera).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(camera, _)| camera)
+}
+
+/// The preset whose camera matches `camera` most specifically.
+pub fn profile_for<'a>(presets: &'a [Preset], camera: &str) -> Option<&'a Preset> {
+    presets
+        .iter()
+        .filter_map(|preset| {
+            let pattern = preset.camera.as_deref()?;
+            camera_matches(pattern, Some(camera)).then_some((pattern.trim().len(), preset))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, preset)| preset)
+}
@@ -0,0 +1,152 @@
+//! Detected sequences kept in the app's own storage, for archives whose files
+//! must stay bit-identical.
+
+use chrono::{Local, NaiveDateTime};
+use log::warn;
+use num_rational::Rational32;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CATALOG_FILE_NAME: &str = "catalog.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogFile {
+    pub path: PathBuf,
+    pub exposure_bias: Option<Rational32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogSequence {
+    pub files: Vec<CatalogFile>,
+    pub capture_time: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub camera: Option<String>,
+    /// When the sequence was added to the catalog
+    pub cataloged: NaiveDateTime,
+    /// Note or tag given during review, e.g. "best of sunset"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl CatalogSequence {
+    pub fn new(
+        files: Vec<CatalogFile>,
+        capture_time: Option<NaiveDateTime>,
+        camera: Option<String>,
+    ) -> Self {
+        Self {
+            files,
+            capture_time,
+            camera,
+            cataloged: Local::now().naive_local(),
+            note: None,
+        }
+    }
+
+    pub fn folder(&self) -> Option<&Path> {
+        self.files.first().and_then(|f| f.path.parent())
+    }
+
+    /// The smallest difference between two exposure biases of the sequence.
+    pub fn ev_step(&self) -> Option<Rational32> {
+        let mut biases: Vec<Rational32> =
+            self.files.iter().filter_map(|f| f.exposure_bias).collect();
+        biases.sort();
+        biases.dedup();
+        biases.windows(2).map(|w| w[1] - w[0]).min()
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Catalog {
+    pub sequences: Vec<CatalogSequence>,
+}
+
+impl Catalog {
+    fn catalog_path() -> Option<PathBuf> {
+        eframe::storage_dir(crate::APP_NAME).map(|dir| dir.join(CATALOG_FILE_NAME))
+    }
+
+    pub fn load() -> Self {
+        let Some(path) = Self::catalog_path() else {
+            return Self::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring invalid catalog {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::catalog_path() else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            if let Err(e) = fs::create_dir_all(dir) {
+                warn!("Failed to create {}: {}", dir.display(), e);
+                return;
+            }
+        }
+        match serde_json::to_string(self) {
+            Ok(content) => {
+                if let Err(e) = fs::write(&path, content) {
+                    warn!("Failed to write catalog {}: {}", path.display(), e);
+                }
+            }
+            Err(e) => warn!("Failed to serialize catalog: {}", e),
+        }
+    }
+
+    /// Adds a sequence. Earlier sequences sharing a file with it are replaced,
+    /// so scanning a folder again doesn't list its brackets twice.
+    pub fn add(&mut self, sequence: CatalogSequence) {
+        let paths: HashSet<&PathBuf> = sequence.files.iter().map(|f| &f.path).collect();
+        self.sequences
+            .retain(|s| !s.files.iter().any(|f| paths.contains(&f.path)));
+        self.sequences.push(sequence);
+    }
+
+    /// Replaces the sequences at `indices` with a single one holding all their
+    /// files, e.g. for a bracket that was split by an interruption.
+    pub fn merge(&mut self, indices: &[usize]) {
+        let mut merged: Vec<CatalogSequence> = self.take(indices);
+        if merged.is_empty() {
+            return;
+        }
+        merged.sort_by_key(|s| (s.capture_time.is_none(), s.capture_time));
+        let capture_time = merged.iter().find_map(|s| s.capture_time);
+        let camera = merged.iter().find_map(|s| s.camera.clone());
+        let files = merged.into_iter().flat_map(|s| s.files).collect();
+        self.sequences
+            .push(CatalogSequence::new(files, capture_time, camera));
+    }
+
+    pub fn remove(&mut self, indices: &[usize]) {
+        self.take(indices);
+    }
+
+    fn take(&mut self, indices: &[usize]) -> Vec<CatalogSequence> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        indices.retain(|&i| i < self.sequences.len());
+        indices
+            .into_iter()
+            .rev()
+            .map(|i| self.sequences.remove(i))
+            .collect()
+    }
+
+    pub fn folder_count(&self) -> usize {
+        self.sequences
+            .iter()
+            .filter_map(CatalogSequence::folder)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+}
@@ -0,0 +1,467 @@
+//! Tab listing the sequences stored in the catalog, across all scanned folders.
+
+use crate::app::accessible_name;
+use crate::catalog::{Catalog, CatalogSequence};
+use crate::catalog_query::Query;
+use crate::catalog_sqlite;
+use crate::file_utils::{load_thumbnail, open_in_file_manager};
+use crate::sequence_list::save_sequence_list;
+use chrono::NaiveDate;
+use ebo_core::EvFormat;
+use eframe::egui;
+use num_rational::Rational32;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+const THUMBNAIL_SIZE: u32 = 64;
+const ROW_HEIGHT: f32 = 72.0;
+
+#[derive(Default)]
+pub struct CatalogBrowser {
+    /// Loaded when the tab is first shown
+    catalog: Option<Catalog>,
+    selected: HashSet<usize>,
+    query: String,
+    date_from: String,
+    date_to: String,
+    camera: Option<String>,
+    ev_step: Option<Rational32>,
+    status: String,
+    thumbnails: Thumbnails,
+}
+
+impl CatalogBrowser {
+    /// Reads the catalog again, e.g. after a run added sequences to it.
+    pub fn reload(&mut self) {
+        self.catalog = Some(Catalog::load());
+        self.selected.clear();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, ev_format: EvFormat) {
+        if self.catalog.is_none() {
+            self.reload();
+        }
+        self.show_filters(ui, ev_format);
+        ui.separator();
+        self.show_actions(ui);
+        ui.separator();
+        self.show_sequences(ui, ev_format);
+    }
+
+    fn show_filters(&mut self, ui: &mut egui::Ui, ev_format: EvFormat) {
+        let Some(catalog) = &self.catalog else {
+            return;
+        };
+        let cameras: BTreeSet<&String> = catalog
+            .sequences
+            .iter()
+            .filter_map(|s| s.camera.as_ref())
+            .collect();
+        let ev_steps: BTreeSet<Rational32> = catalog
+            .sequences
+            .iter()
+            .filter_map(|s| s.ev_step())
+            .collect();
+
+        let search = ui.add(
+            egui::TextEdit::singleline(&mut self.query)
+                .hint_text("Search, e.g. camera:A7R5 step:>=1 frames:5 date:2024-06")
+                .desired_width(f32::INFINITY),
+        );
+        accessible_name(search, "Search catalog").on_hover_text("Terms: camera:, folder:, file:, note:, step:, frames: and date:, comparisons like step:>=1 or date:<2024-06 and plain words. All terms have to match.");
+        let (_, errors) = Query::parse(&self.query);
+        for error in errors {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            let label = ui.label("From");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.date_from)
+                    .hint_text("YYYY-MM-DD")
+                    .desired_width(80.0),
+            )
+            .labelled_by(label.id);
+            let label = ui.label("To");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.date_to)
+                    .hint_text("YYYY-MM-DD")
+                    .desired_width(80.0),
+            )
+            .labelled_by(label.id);
+
+            let response = egui::ComboBox::from_id_salt("catalog_camera")
+                .selected_text(self.camera.as_deref().unwrap_or("All cameras"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.camera, None, "All cameras");
+                    for camera in &cameras {
+                        ui.selectable_value(&mut self.camera, Some(camera.to_string()), *camera);
+                    }
+                })
+                .response;
+            accessible_name(response, "Camera filter");
+
+            let response = egui::ComboBox::from_id_salt("catalog_ev_step")
+                .selected_text(match self.ev_step {
+                    Some(step) => format!("{} EV steps", format_step(step, ev_format)),
+                    None => "All EV steps".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.ev_step, None, "All EV steps");
+                    for step in &ev_steps {
+                        ui.selectable_value(
+                            &mut self.ev_step,
+                            Some(*step),
+                            format!("{} EV", format_step(*step, ev_format)),
+                        );
+                    }
+                })
+                .response;
+            accessible_name(response, "EV step filter");
+        });
+    }
+
+    fn show_actions(&mut self, ui: &mut egui::Ui) {
+        let visible = self.visible_sequences();
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Reload").clicked() {
+                self.reload();
+            }
+            if ui.button("Select All").clicked() {
+                self.selected.extend(visible.iter().copied());
+            }
+            if ui.button("Select None").clicked() {
+                self.selected.clear();
+            }
+            ui.separator();
+
+            if ui
+                .button("Export List…")
+                .on_hover_text("Saves the selected sequences, or all listed ones if none are selected, as a sequence list that can be loaded to run an action on them")
+                .clicked()
+            {
+                self.export_list(&visible);
+            }
+            if ui
+                .button("Export SQLite…")
+                .on_hover_text("Saves the whole catalog as an SQLite database, e.g. to query it with other tools or to move it to another machine")
+                .clicked()
+            {
+                self.export_sqlite();
+            }
+            if ui
+                .button("Import SQLite…")
+                .on_hover_text("Adds the sequences of an exported SQLite catalog")
+                .clicked()
+            {
+                self.import_sqlite();
+            }
+            if ui
+                .add_enabled(self.selected.len() >= 2, egui::Button::new("Merge"))
+                .on_hover_text("Combines the selected sequences into one")
+                .clicked()
+            {
+                self.change_catalog(|catalog, selected| catalog.merge(selected));
+            }
+            if ui
+                .add_enabled(!self.selected.is_empty(), egui::Button::new("Remove"))
+                .on_hover_text("Removes the selected sequences from the catalog, the files are not touched")
+                .clicked()
+            {
+                self.change_catalog(|catalog, selected| catalog.remove(selected));
+            }
+        });
+
+        let total = self.catalog.as_ref().map_or(0, |c| c.sequences.len());
+        let folders = self.catalog.as_ref().map_or(0, Catalog::folder_count);
+        ui.label(format!(
+            "Showing {} of {} sequences from {} folders, {} selected. {}",
+            visible.len(),
+            total,
+            folders,
+            self.selected.len(),
+            self.status
+        ));
+    }
+
+    fn show_sequences(&mut self, ui: &mut egui::Ui, ev_format: EvFormat) {
+        let visible = self.visible_sequences();
+        let Some(catalog) = &self.catalog else {
+            return;
+        };
+        let ctx = ui.ctx().clone();
+
+        egui::ScrollArea::vertical().auto_shrink(false).show_rows(
+            ui,
+            ROW_HEIGHT,
+            visible.len(),
+            |ui, rows| {
+                for &index in &visible[rows] {
+                    let sequence = &catalog.sequences[index];
+                    ui.horizontal(|ui| {
+                        ui.set_height(ROW_HEIGHT);
+
+                        let title = sequence_title(sequence);
+                        let mut selected = self.selected.contains(&index);
+                        let checkbox = ui.checkbox(&mut selected, "");
+                        if accessible_name(checkbox, &format!("Select {}", title)).changed() {
+                            if selected {
+                                self.selected.insert(index);
+                            } else {
+                                self.selected.remove(&index);
+                            }
+                        }
+
+                        let size = egui::vec2(THUMBNAIL_SIZE as f32, THUMBNAIL_SIZE as f32);
+                        let thumbnail = sequence
+                            .files
+                            .first()
+                            .and_then(|f| self.thumbnails.get(&ctx, &f.path));
+                        match thumbnail {
+                            Some(texture) => {
+                                let image = ui.add(egui::Image::new(texture).max_size(size));
+                                accessible_name(image, &format!("Thumbnail of {}", title));
+                            }
+                            None => {
+                                ui.allocate_space(size);
+                            }
+                        }
+
+                        ui.vertical(|ui| {
+                            ui.strong(&title);
+                            ui.label(sequence_details(sequence, ev_format));
+                            if let Some(note) = &sequence.note {
+                                ui.label(format!("Note: {}", note));
+                            }
+                            if let Some(folder) = sequence.folder() {
+                                ui.horizontal(|ui| {
+                                    let button = ui.small_button("Open Folder");
+                                    let name = format!("Open folder {}", folder.display());
+                                    if accessible_name(button, &name).clicked() {
+                                        open_in_file_manager(folder);
+                                    }
+                                    ui.label(folder.display().to_string());
+                                });
+                            }
+                        });
+                    });
+                }
+            },
+        );
+    }
+
+    /// Indices of the sequences passing the filters, newest first.
+    fn visible_sequences(&self) -> Vec<usize> {
+        let Some(catalog) = &self.catalog else {
+            return Vec::new();
+        };
+        let from = NaiveDate::parse_from_str(self.date_from.trim(), "%Y-%m-%d").ok();
+        let to = NaiveDate::parse_from_str(self.date_to.trim(), "%Y-%m-%d").ok();
+        let (query, _) = Query::parse(&self.query);
+
+        let mut visible: Vec<usize> = catalog
+            .sequences
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| {
+                let date = s.capture_time.map(|t| t.date());
+                from.is_none_or(|from| date.is_some_and(|d| d >= from))
+                    && to.is_none_or(|to| date.is_some_and(|d| d <= to))
+                    && self
+                        .camera
+                        .as_ref()
+                        .is_none_or(|c| s.camera.as_ref() == Some(c))
+                    && self.ev_step.is_none_or(|step| s.ev_step() == Some(step))
+                    && query.matches(s)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        visible.sort_by_key(|&i| std::cmp::Reverse(catalog.sequences[i].capture_time));
+        visible
+    }
+
+    fn export_list(&mut self, visible: &[usize]) {
+        let Some(catalog) = &self.catalog else {
+            return;
+        };
+        let indices: Vec<usize> = if self.selected.is_empty() {
+            visible.to_vec()
+        } else {
+            visible
+                .iter()
+                .copied()
+                .filter(|i| self.selected.contains(i))
+                .collect()
+        };
+        let groups: Vec<Vec<PathBuf>> = indices
+            .iter()
+            .map(|&i| {
+                catalog.sequences[i]
+                    .files
+                    .iter()
+                    .map(|f| f.path.clone())
+                    .collect()
+            })
+            .collect();
+
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Sequence List", &["txt", "json"])
+            .set_file_name("sequences.txt")
+            .save_file()
+        else {
+            return;
+        };
+        self.status = match save_sequence_list(&path, &groups) {
+            Ok(()) => format!("Exported {} sequences to {}.", groups.len(), path.display()),
+            Err(e) => e,
+        };
+    }
+
+    fn export_sqlite(&mut self) {
+        let Some(catalog) = &self.catalog else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("SQLite Database", &["sqlite", "db"])
+            .set_file_name("catalog.sqlite")
+            .save_file()
+        else {
+            return;
+        };
+        self.status = match catalog_sqlite::export(catalog, &path) {
+            Ok(()) => format!("Exported the catalog to {}.", path.display()),
+            Err(e) => e,
+        };
+    }
+
+    fn import_sqlite(&mut self) {
+        let Some(catalog) = &mut self.catalog else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("SQLite Database", &["sqlite", "db"])
+            .pick_file()
+        else {
+            return;
+        };
+        self.status = match catalog_sqlite::import(&path) {
+            Ok(sequences) => {
+                let count = sequences.len();
+                for sequence in sequences {
+                    catalog.add(sequence);
+                }
+                catalog.save();
+                self.selected.clear();
+                format!("Imported {} sequences from {}.", count, path.display())
+            }
+            Err(e) => e,
+        };
+    }
+
+    fn change_catalog(&mut self, change: impl FnOnce(&mut Catalog, &[usize])) {
+        let Some(catalog) = &mut self.catalog else {
+            return;
+        };
+        let selected: Vec<usize> = self.selected.drain().collect();
+        change(catalog, &selected);
+        catalog.save();
+        self.status.clear();
+    }
+}
+
+fn sequence_title(sequence: &CatalogSequence) -> String {
+    let time = sequence
+        .capture_time
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "Unknown date".to_string());
+    match &sequence.camera {
+        Some(camera) => format!("{} — {}", time, camera),
+        None => time,
+    }
+}
+
+/// A step is a distance, it goes without the sign of a bias.
+fn format_step(step: Rational32, ev_format: EvFormat) -> String {
+    ev_format.format(step).trim_start_matches('+').to_string()
+}
+
+fn sequence_details(sequence: &CatalogSequence, ev_format: EvFormat) -> String {
+    let first_file = sequence
+        .files
+        .first()
+        .and_then(|f| f.path.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let biases: Vec<String> = sequence
+        .files
+        .iter()
+        .map(|f| {
+            f.exposure_bias
+                .map_or("?".to_string(), |b| ev_format.format(b))
+        })
+        .collect();
+    format!(
+        "{} ({} files), EV {}",
+        first_file,
+        sequence.files.len(),
+        biases.join(", ")
+    )
+}
+
+/// Thumbnails decoded on a background thread and uploaded as textures.
+#[derive(Default)]
+struct Thumbnails {
+    /// `None` while a thumbnail is loading or if the file has none
+    textures: HashMap<PathBuf, Option<egui::TextureHandle>>,
+    loader: Option<ThumbnailLoader>,
+}
+
+struct ThumbnailLoader {
+    requests: Sender<PathBuf>,
+    results: Receiver<(PathBuf, Option<egui::ColorImage>)>,
+}
+
+impl Thumbnails {
+    fn get(&mut self, ctx: &egui::Context, path: &PathBuf) -> Option<&egui::TextureHandle> {
+        let loader = self
+            .loader
+            .get_or_insert_with(|| ThumbnailLoader::spawn(ctx.clone()));
+        for (loaded, image) in loader.results.try_iter() {
+            let texture = image.map(|image| {
+                ctx.load_texture(loaded.display().to_string(), image, Default::default())
+            });
+            self.textures.insert(loaded, texture);
+        }
+
+        if !self.textures.contains_key(path) {
+            self.textures.insert(path.clone(), None);
+            let _ = loader.requests.send(path.clone());
+        }
+        self.textures.get(path)?.as_ref()
+    }
+}
+
+impl ThumbnailLoader {
+    fn spawn(ctx: egui::Context) -> Self {
+        let (requests, request_receiver) = mpsc::channel::<PathBuf>();
+        let (result_sender, results) = mpsc::channel();
+        thread::spawn(move || {
+            for path in request_receiver {
+                let image = load_thumbnail(&path, THUMBNAIL_SIZE).map(|image| {
+                    let image = image.to_rgba8();
+                    egui::ColorImage::from_rgba_unmultiplied(
+                        [image.width() as usize, image.height() as usize],
+                        image.as_raw(),
+                    )
+                });
+                if result_sender.send((path, image)).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self { requests, results }
+    }
+}
@@ -0,0 +1,323 @@
+//! Search queries over the catalog, e.g. `camera:A7R5 step:>=1 frames:5 date:2024-06`.
+//!
+//! A query is a list of terms that all have to match:
+//! - `camera:`, `folder:`, `file:` and `note:` match a part of the camera
+//!   name, folder, any file name or the review note, ignoring case
+//! - `step:` and `frames:` compare the EV step and the number of files, with an
+//!   optional `>`, `>=`, `<`, `<=` or `=` in front of the value
+//! - `date:` matches capture dates starting with the value (`2024`, `2024-06`,
+//!   `2024-06-01`) or compares them at that precision, e.g. `date:>=2024-06`
+//! - Any other word matches a part of the camera name, folder, file names or note
+//!
+//! Values containing spaces can be quoted: `camera:"Sony ILCE-7RM5"`.
+
+use crate::catalog::CatalogSequence;
+use ebo_core::parse_exposure_sequence;
+use num_traits::ToPrimitive;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// Splits a leading operator off `value`, no operator means equal.
+    fn split(value: &str) -> (Self, &str) {
+        for (prefix, comparison) in [
+            (">=", Comparison::GreaterOrEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+            ("=", Comparison::Equal),
+        ] {
+            if let Some(rest) = value.strip_prefix(prefix) {
+                return (comparison, rest);
+            }
+        }
+        (Comparison::Equal, value)
+    }
+
+    fn accepts(self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Less => ordering.is_lt(),
+            Comparison::LessOrEqual => ordering.is_le(),
+            Comparison::Equal => ordering.is_eq(),
+            Comparison::GreaterOrEqual => ordering.is_ge(),
+            Comparison::Greater => ordering.is_gt(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Camera(String),
+    Folder(String),
+    File(String),
+    Note(String),
+    Text(String),
+    Step(Comparison, f64),
+    Frames(Comparison, usize),
+    Date(Comparison, String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+impl Query {
+    /// Parses a query. Terms that can't be understood are returned as errors
+    /// and otherwise ignored, so a typo doesn't hide the whole catalog.
+    pub fn parse(query: &str) -> (Self, Vec<String>) {
+        let mut terms = Vec::new();
+        let mut errors = Vec::new();
+
+        for token in tokenize(query) {
+            let Some((key, value)) = token.split_once(':') else {
+                terms.push(Term::Text(token.to_lowercase()));
+                continue;
+            };
+            let term = match key.to_lowercase().as_str() {
+                "camera" => Some(Term::Camera(value.to_lowercase())),
+                "folder" => Some(Term::Folder(value.to_lowercase())),
+                "file" => Some(Term::File(value.to_lowercase())),
+                "note" => Some(Term::Note(value.to_lowercase())),
+                "step" => {
+                    let (comparison, value) = Comparison::split(value);
+                    parse_ev(value).map(|step| Term::Step(comparison, step))
+                }
+                "frames" => {
+                    let (comparison, value) = Comparison::split(value);
+                    value
+                        .parse()
+                        .ok()
+                        .map(|frames| Term::Frames(comparison, frames))
+                }
+                "date" => {
+                    let (comparison, value) = Comparison::split(value);
+                    is_date_prefix(value).then(|| Term::Date(comparison, value.to_string()))
+                }
+                _ => None,
+            };
+            match term {
+                Some(term) => terms.push(term),
+                None => errors.push(format!("Can't understand \"{}\"", token)),
+            }
+        }
+        (Self { terms }, errors)
+    }
+
+    pub fn matches(&self, sequence: &CatalogSequence) -> bool {
+        self.terms.iter().all(|term| term_matches(term, sequence))
+    }
+}
+
+fn term_matches(term: &Term, sequence: &CatalogSequence) -> bool {
+    let camera = || {
+        sequence
+            .camera
+            .as_deref()
+            .unwrap_or_default()
+            .to_lowercase()
+    };
+    let folder = || {
+        sequence
+            .folder()
+            .map(|f| f.display().to_string().to_lowercase())
+            .unwrap_or_default()
+    };
+    let note = || sequence.note.as_deref().unwrap_or_default().to_lowercase();
+    let any_file = |text: &str| {
+        sequence.files.iter().any(|f| {
+            f.path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().to_lowercase().contains(text))
+        })
+    };
+
+    match term {
+        Term::Camera(text) => camera().contains(text.as_str()),
+        Term::Folder(text) => folder().contains(text.as_str()),
+        Term::File(text) => any_file(text),
+        Term::Note(text) => note().contains(text.as_str()),
+        Term::Text(text) => {
+            camera().contains(text.as_str())
+                || folder().contains(text.as_str())
+                || any_file(text)
+                || note().contains(text.as_str())
+        }
+        Term::Step(comparison, value) => {
+            let Some(step) = sequence.ev_step().and_then(|s| s.to_f64()) else {
+                return false;
+            };
+            // Steps like 1/3 are stored exactly but typed as 0.3 or 0.33
+            let ordering = if (step - value).abs() < 0.05 {
+                Ordering::Equal
+            } else {
+                step.total_cmp(value)
+            };
+            comparison.accepts(ordering)
+        }
+        Term::Frames(comparison, value) => comparison.accepts(sequence.files.len().cmp(value)),
+        Term::Date(comparison, value) => {
+            let Some(time) = sequence.capture_time else {
+                return false;
+            };
+            // Compare at the precision of the query, so 2024-06-15 equals 2024-06
+            let date = time.format("%Y-%m-%d").to_string();
+            let date = &date[..value.len().min(date.len())];
+            comparison.accepts(date.cmp(value.as_str()))
+        }
+    }
+}
+
+/// Splits at whitespace, keeping quoted parts together and dropping the quotes.
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// Accepts EV values as decimals (`0.5`) or fractions (`1/3`).
+fn parse_ev(value: &str) -> Option<f64> {
+    if let Ok(value) = value.parse::<f64>() {
+        return Some(value);
+    }
+    match parse_exposure_sequence(value).as_slice() {
+        [ev] => ev.to_f64(),
+        _ => None,
+    }
+}
+
+/// `YYYY`, `YYYY-MM` or `YYYY-MM-DD`.
+fn is_date_prefix(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    let lengths_ok = matches!(
+        parts.iter().map(|p| p.len()).collect::<Vec<_>>().as_slice(),
+        [4] | [4, 2] | [4, 2, 2]
+    );
+    lengths_ok && parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::CatalogFile;
+    use chrono::NaiveDate;
+    use num_rational::Rational32;
+    use std::path::PathBuf;
+
+    fn sequence() -> CatalogSequence {
+        let files = [-1, 0, 1]
+            .into_iter()
+            .enumerate()
+            .map(|(i, bias)| CatalogFile {
+                path: PathBuf::from(format!("/photos/2024/IMG_000{}.ARW", i + 1)),
+                exposure_bias: Some(Rational32::from(bias)),
+            })
+            .collect();
+        let capture_time =
+            NaiveDate::from_ymd_opt(2024, 6, 15).and_then(|date| date.and_hms_opt(20, 30, 0));
+        let mut sequence =
+            CatalogSequence::new(files, capture_time, Some("Sony ILCE-7RM5".to_string()));
+        sequence.note = Some("Best of sunset".to_string());
+        sequence
+    }
+
+    fn matches(query: &str) -> bool {
+        let (query, errors) = Query::parse(query);
+        assert!(errors.is_empty(), "{:?}", errors);
+        query.matches(&sequence())
+    }
+
+    #[test]
+    fn parses_keys_and_words() {
+        let (query, errors) = Query::parse("Camera:A7R5 sunset");
+        assert!(errors.is_empty());
+        assert_eq!(
+            query.terms,
+            [
+                Term::Camera("a7r5".to_string()),
+                Term::Text("sunset".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_quoted_values_together() {
+        let (query, _) = Query::parse("camera:\"Sony ILCE-7RM5\" note:\"best of\"");
+        assert_eq!(
+            query.terms,
+            [
+                Term::Camera("sony ilce-7rm5".to_string()),
+                Term::Note("best of".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_comparisons() {
+        let (query, errors) = Query::parse("step:>=1/3 frames:<5 date:2024-06");
+        assert!(errors.is_empty());
+        assert_eq!(
+            query.terms,
+            [
+                Term::Step(Comparison::GreaterOrEqual, 1.0 / 3.0),
+                Term::Frames(Comparison::Less, 5),
+                Term::Date(Comparison::Equal, "2024-06".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_terms_it_cannot_understand() {
+        let (query, errors) = Query::parse("lens:24mm frames:many date:06-2024 step:big sunset");
+        assert_eq!(errors.len(), 4);
+        assert_eq!(errors[0], "Can't understand \"lens:24mm\"");
+        assert_eq!(query.terms, [Term::Text("sunset".to_string())]);
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert!(matches(""));
+        assert!(matches("   "));
+    }
+
+    #[test]
+    fn text_matches_any_field_ignoring_case() {
+        assert!(matches("7rm5"));
+        assert!(matches("SUNSET"));
+        assert!(matches("img_0002"));
+        assert!(matches("folder:photos/2024"));
+        assert!(!matches("camera:sunset"));
+    }
+
+    #[test]
+    fn compares_step_frames_and_date() {
+        assert!(matches("step:1 frames:3"));
+        assert!(matches("step:>0.5 frames:>=3"));
+        assert!(!matches("frames:>3"));
+        assert!(matches("date:2024-06 date:>=2024-06-01 date:<2025"));
+        assert!(!matches("date:2024-07"));
+    }
+}
@@ -0,0 +1,197 @@
+//! Exports the catalog to an SQLite database and imports it back, so other
+//! tools can query the bracket inventory and the catalog can be moved between
+//! machines.
+//!
+//! Schema (version 1, stored in `PRAGMA user_version`):
+//!
+//! ```sql
+//! CREATE TABLE sequences (
+//!     id           INTEGER PRIMARY KEY,
+//!     capture_time TEXT,              -- first frame, ISO 8601 local time, NULL if unknown
+//!     camera       TEXT,              -- make and model, NULL if unknown
+//!     folder       TEXT,              -- folder of the first file
+//!     frame_count  INTEGER NOT NULL,
+//!     ev_step      REAL,              -- smallest difference between two exposure biases
+//!     cataloged    TEXT NOT NULL      -- when the sequence was added, ISO 8601 local time
+//! );
+//! CREATE TABLE files (
+//!     sequence_id  INTEGER NOT NULL REFERENCES sequences(id) ON DELETE CASCADE,
+//!     position     INTEGER NOT NULL,  -- order within the sequence, starting at 0
+//!     path         TEXT NOT NULL,
+//!     exposure_bias_numerator   INTEGER,
+//!     exposure_bias_denominator INTEGER,
+//!     exposure_bias REAL,             -- the same bias in EV, for convenient queries
+//!     PRIMARY KEY (sequence_id, position)
+//! );
+//! ```
+//!
+//! Only the `sequences.capture_time`, `camera` and `cataloged` columns and the
+//! `files` table are read on import, the other columns are derived.
+
+use crate::catalog::{Catalog, CatalogFile, CatalogSequence};
+use chrono::NaiveDateTime;
+use num_rational::Rational32;
+use num_traits::ToPrimitive;
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SCHEMA_VERSION: i32 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE sequences (
+    id           INTEGER PRIMARY KEY,
+    capture_time TEXT,
+    camera       TEXT,
+    folder       TEXT,
+    frame_count  INTEGER NOT NULL,
+    ev_step      REAL,
+    cataloged    TEXT NOT NULL
+);
+CREATE TABLE files (
+    sequence_id  INTEGER NOT NULL REFERENCES sequences(id) ON DELETE CASCADE,
+    position     INTEGER NOT NULL,
+    path         TEXT NOT NULL,
+    exposure_bias_numerator   INTEGER,
+    exposure_bias_denominator INTEGER,
+    exposure_bias REAL,
+    PRIMARY KEY (sequence_id, position)
+);
+CREATE INDEX files_path ON files(path);
+CREATE INDEX sequences_capture_time ON sequences(capture_time);
+";
+
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Writes the whole catalog to a new database at `path`, replacing an existing file.
+pub fn export(catalog: &Catalog, path: &Path) -> Result<(), String> {
+    let error =
+        |e: rusqlite::Error| format!("Failed to export catalog to {}: {}", path.display(), e);
+
+    if path.exists() {
+        fs::remove_file(path)
+            .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+    }
+    let mut connection = Connection::open(path).map_err(error)?;
+    connection.execute_batch(SCHEMA).map_err(error)?;
+    connection
+        .pragma_update(None, "user_version", SCHEMA_VERSION)
+        .map_err(error)?;
+
+    let transaction = connection.transaction().map_err(error)?;
+    {
+        let mut insert_sequence = transaction
+            .prepare(
+                "INSERT INTO sequences (id, capture_time, camera, folder, frame_count, ev_step, cataloged)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )
+            .map_err(error)?;
+        let mut insert_file = transaction
+            .prepare(
+                "INSERT INTO files (sequence_id, position, path, exposure_bias_numerator, exposure_bias_denominator, exposure_bias)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .map_err(error)?;
+
+        for (id, sequence) in catalog.sequences.iter().enumerate() {
+            let id = id as i64 + 1;
+            insert_sequence
+                .execute(params![
+                    id,
+                    sequence
+                        .capture_time
+                        .map(|t| t.format(TIME_FORMAT).to_string()),
+                    sequence.camera,
+                    sequence.folder().map(|f| f.to_string_lossy().to_string()),
+                    sequence.files.len() as i64,
+                    sequence.ev_step().and_then(|s| s.to_f64()),
+                    sequence.cataloged.format(TIME_FORMAT).to_string(),
+                ])
+                .map_err(error)?;
+
+            for (position, file) in sequence.files.iter().enumerate() {
+                insert_file
+                    .execute(params![
+                        id,
+                        position as i64,
+                        file.path.to_string_lossy(),
+                        file.exposure_bias.map(|b| *b.numer()),
+                        file.exposure_bias.map(|b| *b.denom()),
+                        file.exposure_bias.and_then(|b| b.to_f64()),
+                    ])
+                    .map_err(error)?;
+            }
+        }
+    }
+    transaction.commit().map_err(error)
+}
+
+/// Reads the sequences of a database written by [`export`].
+pub fn import(path: &Path) -> Result<Vec<CatalogSequence>, String> {
+    let error =
+        |e: rusqlite::Error| format!("Failed to import catalog from {}: {}", path.display(), e);
+
+    let connection = Connection::open(path).map_err(error)?;
+    let version: i32 = connection
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(error)?;
+    if version != SCHEMA_VERSION {
+        return Err(format!(
+            "{} is not a catalog database of a supported version",
+            path.display()
+        ));
+    }
+
+    let mut select_sequences = connection
+        .prepare("SELECT id, capture_time, camera, cataloged FROM sequences ORDER BY id")
+        .map_err(error)?;
+    let mut select_files = connection
+        .prepare(
+            "SELECT path, exposure_bias_numerator, exposure_bias_denominator FROM files
+             WHERE sequence_id = ?1 ORDER BY position",
+        )
+        .map_err(error)?;
+
+    let rows = select_sequences
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })
+        .map_err(error)?;
+
+    let mut sequences = Vec::new();
+    for row in rows {
+        let (id, capture_time, camera, cataloged) = row.map_err(error)?;
+        let files = select_files
+            .query_map([id], |row| {
+                let numerator: Option<i32> = row.get(1)?;
+                let denominator: Option<i32> = row.get(2)?;
+                Ok(CatalogFile {
+                    path: PathBuf::from(row.get::<_, String>(0)?),
+                    exposure_bias: match (numerator, denominator) {
+                        (Some(n), Some(d)) if d != 0 => Some(Rational32::new(n, d)),
+                        _ => None,
+                    },
+                })
+            })
+            .map_err(error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(error)?;
+
+        let mut sequence =
+            CatalogSequence::new(files, capture_time.as_deref().and_then(parse_time), camera);
+        if let Some(cataloged) = parse_time(&cataloged) {
+            sequence.cataloged = cataloged;
+        }
+        sequences.push(sequence);
+    }
+    Ok(sequences)
+}
+
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, TIME_FORMAT).ok()
+}
@@ -0,0 +1,386 @@
+//! The command line, and headless runs from it. `--run <folder> --progress=plain`
+//! or `--progress=json-lines` processes the folder without opening a window,
+//! with the settings of its `.ebo.toml`, and prints a progress line per step
+//! to stdout for wrapper scripts. `--folder <folder>` does the same, with
+//! settings given as options overriding the file's, e.g. on a server without
+//! a display:
+//!
+//! ```text
+//! ExposureBracketingOrganizer --folder /photos/import --sequence "0/10,-10/10,10/10" --action move
+//! ```
+//!
+//! The progress lines look like this:
+//!
+//! ```text
+//! reading 12/300 /photos/IMG_0012.ARW
+//! {"stage":"reading","processed":12,"total":300,"sequences":3,"file":"/photos/IMG_0012.ARW"}
+//! ```
+//!
+//! `--verify-archive <folder>` checks the folder against its archive manifest
+//! and prints every missing or changed file, e.g. for a scheduled task.
+//!
+//! `completions <shell>` prints the shell completions and `man` the man page,
+//! both generated from [`Args`].
+
+use crate::app::Action;
+use crate::archive_manifest;
+use crate::file_utils::{JobProgress, ProgressEvent};
+use crate::server::{self, JobRequest};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use ebo_core::EvMode;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+
+/// Release builds on Windows are programs without a console of their own, so
+/// the output of the command line modes, and clap's help and errors, would go
+/// nowhere. Attaches to the console of the shell they were started from, if
+/// there is one.
+#[cfg(windows)]
+pub fn attach_parent_console() {
+    use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // SAFETY: takes no pointers, fails harmlessly when started without a console
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+pub fn attach_parent_console() {}
+
+/// Without options the window opens, with a folder given it is opened in it.
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Finds exposure brackets among raw files and organizes them."
+)]
+pub struct Args {
+    /// Folder to open in the window, or to process with --run
+    #[arg(value_name = "FOLDER")]
+    pub path: Option<PathBuf>,
+    /// Process the folder right away, in the running window unless --progress is given
+    #[arg(long)]
+    pub run: bool,
+    /// Process without a window and print the progress as `plain` lines or `json-lines`
+    #[arg(long, value_name = "FORMAT")]
+    pub progress: Option<ProgressFormat>,
+    /// Serve the JSON API instead of opening a window
+    #[arg(long, value_name = "ADDRESS", num_args = 0..=1, default_missing_value = server::DEFAULT_ADDRESS)]
+    pub serve: Option<String>,
+    /// Check a folder against its archive manifest and exit
+    #[arg(long, value_name = "FOLDER")]
+    pub verify_archive: Option<PathBuf>,
+    /// Read files rawler can't decode with this exiftool, for --serve and headless jobs
+    #[arg(long, value_name = "BINARY")]
+    pub exiftool: Option<PathBuf>,
+    #[command(flatten)]
+    pub job: JobArgs,
+    #[command(subcommand)]
+    pub subcommand: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print the completions for a shell
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the man page
+    Man,
+}
+
+impl Command {
+    /// Writes the completions or the man page to stdout.
+    pub fn run(&self) -> io::Result<()> {
+        let mut command = Args::command();
+        let mut output = Vec::new();
+        match self {
+            Command::Completions { shell } => {
+                let name = command.get_name().to_string();
+                clap_complete::generate(*shell, &mut command, name, &mut output);
+            }
+            Command::Man => clap_mangen::Man::new(command).render(&mut output)?,
+        }
+        io::stdout().write_all(&output)
+    }
+}
+
+/// Settings of a headless job, those left out are taken from the folder's
+/// `.ebo.toml`, then from the defaults.
+#[derive(Debug, Default, clap::Args)]
+#[command(next_help_heading = "Headless job")]
+pub struct JobArgs {
+    /// Process this folder without a window
+    #[arg(long, value_name = "FOLDER")]
+    pub folder: Option<PathBuf>,
+    /// Exposure biases of one bracket, e.g. "0/10,-10/10,10/10"
+    #[arg(long)]
+    pub sequence: Option<String>,
+    /// Find brackets by their evenly spaced biases, without a sequence
+    #[arg(long)]
+    pub auto_detect: bool,
+    /// Actions executed on every sequence, e.g. move,textfile
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub action: Vec<ActionArg>,
+    /// Also run this script from the actions directory on every sequence
+    #[arg(long, value_name = "FILE_NAME")]
+    pub script: Option<String>,
+    /// Also run this command on every sequence, with {files}, {folder},
+    /// {count} and {ev_range} filled in
+    #[arg(long, value_name = "TEMPLATE")]
+    pub command: Option<String>,
+    #[arg(long, value_enum)]
+    pub ev_mode: Option<EvModeArg>,
+    /// `zero`, `first`, `middle` or a frame number
+    #[arg(long, value_name = "FRAME")]
+    pub delta_reference: Option<String>,
+    /// File types to process, e.g. arw,dng
+    #[arg(long, value_delimiter = ',')]
+    pub extensions: Option<Vec<String>>,
+    /// Levels of subfolders processed below the folder
+    #[arg(long, value_name = "LEVELS")]
+    pub subfolder_depth: Option<usize>,
+    /// Where sequence folders are created instead of next to the files
+    #[arg(long, value_name = "FOLDER")]
+    pub destination_root: Option<PathBuf>,
+    /// Only report the sequences and where their files would go
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ActionArg {
+    /// Move to Folder
+    Move,
+    /// Copy to Folder
+    Copy,
+    /// Rename in Place
+    Rename,
+    /// Save Sequences to Textfile
+    Textfile,
+    /// Write Group Tags (XMP)
+    Tags,
+    /// Add to Catalog Only
+    Catalog,
+    /// Write Fusion Scripts
+    FusionScripts,
+    /// Write Hugin Projects
+    Hugin,
+    /// Write HDRMerge Commands
+    Hdrmerge,
+    /// Write darktable Groups
+    Darktable,
+    /// Write Lightroom Keywords
+    Lightroom,
+    /// Write digiKam Tags
+    Digikam,
+}
+
+impl From<ActionArg> for Action {
+    fn from(action: ActionArg) -> Self {
+        match action {
+            ActionArg::Move => Action::MoveToFolder,
+            ActionArg::Copy => Action::CopyToFolder,
+            ActionArg::Rename => Action::RenameInPlace,
+            ActionArg::Textfile => Action::SaveSequencesToTextfile,
+            ActionArg::Tags => Action::WriteGroupTags,
+            ActionArg::Catalog => Action::AddToCatalog,
+            ActionArg::FusionScripts => Action::WriteFusionScripts,
+            ActionArg::Hugin => Action::WriteHuginProjects,
+            ActionArg::Hdrmerge => Action::WriteHdrMergeCommands,
+            ActionArg::Darktable => Action::WriteDarktableGroups,
+            ActionArg::Lightroom => Action::WriteLightroomKeywords,
+            ActionArg::Digikam => Action::WriteDigikamTags,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum EvModeArg {
+    Delta,
+    Absolute,
+}
+
+impl From<EvModeArg> for EvMode {
+    fn from(ev_mode: EvModeArg) -> Self {
+        match ev_mode {
+            EvModeArg::Delta => EvMode::Delta,
+            EvModeArg::Absolute => EvMode::Absolute,
+        }
+    }
+}
+
+impl JobArgs {
+    /// The job for `folder`, with the settings given on the command line.
+    pub fn into_request(self, folder: PathBuf) -> JobRequest {
+        JobRequest {
+            folder,
+            sequence: self.sequence,
+            auto_detect: self.auto_detect.then_some(true),
+            action: {
+                let actions: Vec<Action> = self
+                    .action
+                    .into_iter()
+                    .map(Action::from)
+                    .chain(self.script.map(Action::Script))
+                    .chain(self.command.as_ref().map(|_| Action::RunCommand))
+                    .collect();
+                (!actions.is_empty()).then_some(actions)
+            },
+            command: self.command,
+            ev_mode: self.ev_mode.map(EvMode::from),
+            delta_reference: self.delta_reference,
+            extensions: self.extensions,
+            subfolder_depth: self.subfolder_depth,
+            destination_root: self.destination_root,
+            dry_run: self.dry_run,
+            ..JobRequest::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProgressFormat {
+    /// `<stage> <processed>/<total> [file or message]`
+    Plain,
+    /// One JSON object per line
+    JsonLines,
+}
+
+impl FromStr for ProgressFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(ProgressFormat::Plain),
+            "json-lines" => Ok(ProgressFormat::JsonLines),
+            _ => Err(format!(
+                "Unknown progress format \"{}\", use plain or json-lines",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ProgressLine<'a> {
+    /// `scanning`, `reading`, `organizing`, `error`, `paused`, `resumed` or `finished`
+    stage: &'static str,
+    processed: usize,
+    total: usize,
+    sequences: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a Path>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+}
+
+impl ProgressLine<'_> {
+    fn write(&self, out: &mut impl Write, format: ProgressFormat) -> io::Result<()> {
+        match format {
+            ProgressFormat::JsonLines => {
+                serde_json::to_writer(&mut *out, self)?;
+                writeln!(out)
+            }
+            ProgressFormat::Plain => {
+                write!(out, "{} {}/{}", self.stage, self.processed, self.total)?;
+                if let Some(file) = self.file {
+                    write!(out, " {}", file.display())?;
+                }
+                if let Some(message) = self.message {
+                    write!(out, " {}", message)?;
+                }
+                writeln!(out)
+            }
+        }
+    }
+}
+
+/// Processes the requested folder and reports the progress on stdout. `false`
+/// if the job can't be started or any sequence couldn't be organized.
+pub fn run(request: JobRequest, format: ProgressFormat) -> bool {
+    let job = match server::plan_job(request) {
+        Ok(job) => job,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+
+    let progress = JobProgress::default();
+    progress.start();
+    let events = progress.subscribe();
+    // The worker owns the only handle, so the events end if it panics
+    let worker = thread::spawn(move || {
+        let report = job.run(&progress);
+        progress.finish(&report);
+        report.errors.is_empty()
+    });
+
+    let mut out = io::stdout().lock();
+    let (mut processed, mut total, mut sequences) = (0, 0, 0);
+    for event in events {
+        let (stage, file, message) = match &event {
+            ProgressEvent::FilesCounted { total: counted } => {
+                total = *counted;
+                ("scanning", None, None)
+            }
+            ProgressEvent::FileProcessed { path } => {
+                processed += 1;
+                ("reading", Some(path.as_path()), None)
+            }
+            ProgressEvent::SequenceFound { files, .. } => {
+                sequences += 1;
+                ("organizing", files.first().map(PathBuf::as_path), None)
+            }
+            ProgressEvent::Error { message } => ("error", None, Some(message.as_str())),
+            ProgressEvent::Paused => ("paused", None, None),
+            ProgressEvent::Resumed => ("resumed", None, None),
+            ProgressEvent::Finished { .. } => ("finished", None, None),
+        };
+        let line = ProgressLine {
+            stage,
+            processed,
+            total,
+            sequences,
+            file,
+            message,
+        };
+        // A closed pipe only ends the reporting, not the run
+        let _ = line.write(&mut out, format);
+    }
+
+    worker.join().unwrap_or(false)
+}
+
+/// Verifies the archive manifest of `root` and prints the result. `false` if
+/// a file is missing or changed, or the manifest can't be read.
+pub fn verify_archive(root: &Path) -> bool {
+    let check = match archive_manifest::verify_archive(root) {
+        Ok(check) => check,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+    for path in &check.missing {
+        println!("missing {}", path);
+    }
+    for path in &check.changed {
+        println!("changed {}", path);
+    }
+    for path in &check.unlisted {
+        println!("unlisted {}", path);
+    }
+    println!(
+        "{} intact, {} missing, {} changed, {} not listed",
+        check.verified,
+        check.missing.len(),
+        check.changed.len(),
+        check.unlisted.len()
+    );
+    check.is_intact()
+}
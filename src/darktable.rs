@@ -0,0 +1,49 @@
+//! Grouping the sequences in darktable. darktable keeps its groups in its
+//! library rather than in the sidecars, so next to tagging the frames in
+//! their sidecars a Lua script per folder groups each sequence, with its
+//! first frame as the group leader.
+
+use std::path::{Path, PathBuf};
+
+/// Starts the Lua script, defining the function each sequence calls
+pub const SCRIPT_HEADER: &str =
+    "-- Groups the exposure brackets in darktable, the first frame leading each group.
+-- Run it with: darktable --luacmd \"dofile('/path/to/darktable_groups.lua')\"
+local dt = require \"darktable\"
+
+local function group(files)
+  local leader = dt.database.import(files[1])
+  leader:make_group_leader()
+  for i = 2, #files do
+    dt.database.import(files[i]):group_with(leader)
+  end
+end
+
+";
+
+/// Where the Lua script grouping the sequences in `dir` goes.
+pub fn script_path(dir: &Path) -> PathBuf {
+    dir.join("darktable_groups.lua")
+}
+
+/// The line of the Lua script grouping `files`. darktable imports the files
+/// it does not know yet, reading their sidecars.
+pub fn group_command(files: &[&Path]) -> String {
+    let files: Vec<String> = files
+        .iter()
+        .map(|file| lua_string(&file.to_string_lossy()))
+        .collect();
+    format!("group({{{}}})\n", files.join(", "))
+}
+
+/// The path of the sidecar darktable reads and writes for `file`, always
+/// `IMG_0001.ARW.xmp`.
+pub fn sidecar_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".xmp");
+    PathBuf::from(name)
+}
+
+fn lua_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,94 @@
+//! Windows that can be moved out of the main window into their own OS window,
+//! e.g. to keep a table on a second monitor while the main window shows progress.
+//!
+//! The position and size of a detached window are kept in egui's persisted
+//! memory, so it reopens where it was in the last session.
+
+use eframe::egui;
+
+const DETACHED_SIZE: [f32; 2] = [520.0, 420.0];
+
+/// Shows `add_contents` in an egui window, or in its own OS window while
+/// `detached` is set. The contents get a button to switch between the two.
+/// Returns `false` once the user closed the window.
+pub fn show(
+    ctx: &egui::Context,
+    id: &str,
+    title: &str,
+    detached: &mut bool,
+    mut add_contents: impl FnMut(&mut egui::Ui),
+) -> bool {
+    let mut open = true;
+    let geometry_id = egui::Id::new(id).with("geometry");
+    let initial_id = geometry_id.with("initial");
+
+    if !*detached {
+        egui::Window::new(title)
+            .id(egui::Id::new(id))
+            .min_width(200.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if ui
+                    .small_button("Detach")
+                    .on_hover_text("Open in a separate window")
+                    .clicked()
+                {
+                    *detached = true;
+                }
+                add_contents(ui);
+            });
+        return open;
+    }
+
+    // Keep the builder as it was when the window opened, a changing builder
+    // would move the window on every frame
+    let geometry = ctx.data_mut(|d| {
+        if let Some(geometry) = d.get_temp(initial_id) {
+            return geometry;
+        }
+        let geometry: Option<(egui::Pos2, egui::Vec2)> = d.get_persisted(geometry_id);
+        d.insert_temp(initial_id, geometry);
+        geometry
+    });
+    let mut builder = egui::ViewportBuilder::default()
+        .with_title(title)
+        .with_inner_size(geometry.map_or(DETACHED_SIZE.into(), |(_, size)| size));
+    if let Some((position, _)) = geometry {
+        builder = builder.with_position(position);
+    }
+
+    ctx.show_viewport_immediate(egui::ViewportId::from_hash_of(id), builder, |ctx, class| {
+        if class == egui::ViewportClass::Embedded {
+            // The backend can't open another OS window, stay inside the main one
+            egui::Window::new(title)
+                .id(egui::Id::new(id))
+                .open(&mut open)
+                .show(ctx, |ui| add_contents(ui));
+            return;
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if ui
+                .small_button("Dock")
+                .on_hover_text("Move back into the main window")
+                .clicked()
+            {
+                *detached = false;
+            }
+            add_contents(ui);
+        });
+        let (outer, inner) = ctx.input(|i| (i.viewport().outer_rect, i.viewport().inner_rect));
+        if let (Some(outer), Some(inner)) = (outer, inner) {
+            ctx.data_mut(|d| d.insert_persisted(geometry_id, (outer.min, inner.size())));
+        }
+        if ctx.input(|i| i.viewport().close_requested()) {
+            open = false;
+        }
+    });
+
+    if !open || !*detached {
+        // Detaching again restores the latest geometry, not the one of the first time
+        ctx.data_mut(|d| d.remove_temp::<Option<(egui::Pos2, egui::Vec2)>>(initial_id));
+    }
+    open
+}
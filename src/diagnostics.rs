@@ -0,0 +1,269 @@
+//! Explains why a run found no sequences, so the usual causes can be fixed
+//! without digging through logs: a filter skipping everything, biases that
+//! don't fit the entered sequence or files in the wrong order. Runs that did
+//! find sequences get their rare frame counts pointed out, as a 4-frame match
+//! among hundreds of 5-frame ones usually means a detection went wrong.
+
+use crate::app::exposure_mode_to_string;
+use crate::file_utils::SequenceReport;
+use ebo_core::{DeltaReference, EvFormat, EvMode};
+use num_rational::Rational32;
+use num_traits::ToPrimitive;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How many distinct biases are listed before the rest is summarized
+const MAX_LISTED_BIASES: usize = 12;
+
+/// Values closer than this are treated as typed approximations of each other,
+/// e.g. 7/10 for 2/3
+const NEAR_MISS_EV: f64 = 0.05;
+
+/// A frame count is an outlier if the usual one is this many times as common
+const OUTLIER_RATIO: usize = 20;
+
+/// How many first files of outlier sequences are named before the rest is summarized
+const MAX_LISTED_OUTLIERS: usize = 3;
+
+/// Why files of a scan didn't take part in the detection.
+#[derive(Debug, Default)]
+pub struct ScanStats {
+    pub other_extension: usize,
+    pub unreadable: usize,
+    pub still_being_written: usize,
+    /// Files excluded by the user in the exposure bias window
+    pub excluded: usize,
+    /// Files skipped by the auto-bracket filter, by their exposure mode
+    pub filtered_by_exposure_mode: BTreeMap<Option<u16>, usize>,
+}
+
+/// What the detection saw of a run that found nothing.
+pub struct NoMatchInput<'a> {
+    pub stats: &'a ScanStats,
+    /// Exposure biases of the files that were searched, in search order
+    pub biases: &'a [Option<Rational32>],
+    /// The sequences searched for, usually one
+    pub sequences: &'a [Vec<Option<Rational32>>],
+    pub ev_mode: EvMode,
+    pub delta_reference: DeltaReference,
+    /// Biases closer than this to a sequence value count as that value
+    pub tolerance: Rational32,
+    /// How the biases found are listed
+    pub ev_format: EvFormat,
+    /// Sequences found when the same files are sorted by capture time, if
+    /// they weren't already
+    pub found_in_capture_order: Option<usize>,
+    /// Files whose capture time is earlier than the one before them
+    pub out_of_order: usize,
+}
+
+/// Lists the likely causes, most common first.
+pub fn explain_no_matches(input: &NoMatchInput) -> Vec<String> {
+    let mut causes = Vec::new();
+    let stats = input.stats;
+
+    let filtered: usize = stats.filtered_by_exposure_mode.values().sum();
+    if filtered > 0 {
+        let modes: Vec<String> = stats
+            .filtered_by_exposure_mode
+            .iter()
+            .map(|(mode, count)| {
+                let mode = mode.map_or("no exposure mode", exposure_mode_to_string);
+                format!("{} × {}", count, mode)
+            })
+            .collect();
+        causes.push(format!(
+            "{} files skipped by the auto-bracket filter ({}). Turn off \"Filter by Auto Bracket\" for manually bracketed shots, or add a camera exception for bodies that report another mode during AEB.",
+            filtered,
+            modes.join(", ")
+        ));
+    }
+    if stats.unreadable > 0 {
+        causes.push(format!(
+            "{} files without readable metadata, they may be damaged or in an unsupported format",
+            stats.unreadable
+        ));
+    }
+    if stats.still_being_written > 0 {
+        causes.push(format!(
+            "{} files skipped because they were still being written",
+            stats.still_being_written
+        ));
+    }
+    if stats.excluded > 0 {
+        causes.push(format!("{} files excluded from processing", stats.excluded));
+    }
+    if stats.other_extension > 0 && input.biases.is_empty() {
+        causes.push(format!(
+            "{} files skipped because of their extension, check the file types",
+            stats.other_extension
+        ));
+    }
+
+    if input.biases.is_empty() {
+        causes.push("No files were left to search for sequences".to_string());
+        return causes;
+    }
+    let shortest = input.sequences.iter().map(Vec::len).min().unwrap_or(0);
+    if input.biases.len() < shortest {
+        causes.push(format!(
+            "Only {} files were searched, but the {} has {} values",
+            input.biases.len(),
+            if input.sequences.len() > 1 {
+                "shortest sequence"
+            } else {
+                "sequence"
+            },
+            shortest
+        ));
+    }
+
+    let mut found: BTreeMap<Rational32, usize> = BTreeMap::new();
+    for bias in input.biases.iter().flatten() {
+        *found.entry(*bias).or_default() += 1;
+    }
+    let without_bias = input.biases.iter().filter(|b| b.is_none()).count();
+    if found.is_empty() {
+        causes.push("None of the files has an exposure bias".to_string());
+        return causes;
+    }
+    if without_bias > 0 {
+        causes.push(format!("{} files have no exposure bias", without_bias));
+    }
+
+    let mut listed: Vec<String> = found
+        .iter()
+        .take(MAX_LISTED_BIASES)
+        .map(|(bias, count)| format!("{} ({}×)", input.ev_format.format(*bias), count))
+        .collect();
+    if found.len() > MAX_LISTED_BIASES {
+        listed.push(format!("and {} more", found.len() - MAX_LISTED_BIASES));
+    }
+    causes.push(format!("Biases found: {}", listed.join(", ")));
+
+    for sequence in input.sequences {
+        // A * fits any bias, only the values entered can be missing
+        let values: Vec<Rational32> = sequence.iter().flatten().copied().collect();
+        let sequence_causes = match input.ev_mode {
+            EvMode::Absolute => {
+                let available: BTreeSet<Rational32> = found.keys().copied().collect();
+                missing_values(&values, &available, input.tolerance, "bias")
+            }
+            EvMode::Delta => match input.delta_reference.index(sequence) {
+                None => vec![input.delta_reference.missing_reason(sequence)],
+                Some(reference_index) => {
+                    let reference_value = sequence[reference_index].unwrap_or_default();
+                    let differences: Vec<Rational32> =
+                        values.iter().map(|v| v - reference_value).collect();
+                    let available: BTreeSet<Rational32> = found
+                        .keys()
+                        .flat_map(|a| found.keys().map(move |b| b - a))
+                        .collect();
+                    missing_values(&differences, &available, input.tolerance, "difference")
+                }
+            },
+        };
+        // Sequences often share values, each cause is told once
+        for cause in sequence_causes {
+            if !causes.contains(&cause) {
+                causes.push(cause);
+            }
+        }
+    }
+
+    if input.out_of_order > 0 {
+        let hint = match input.found_in_capture_order {
+            Some(count) if count > 0 => format!(
+                "Sorting them by capture time finds {} sequences, try \"Sort by Capture Time\".",
+                count
+            ),
+            _ => "Try \"Sort by Capture Time\".".to_string(),
+        };
+        causes.push(format!(
+            "{} files are not in capture order. {}",
+            input.out_of_order, hint
+        ));
+    }
+
+    causes
+}
+
+/// Explains sequence values that no file has, pointing out near misses like
+/// a typed -7/10 for the -2/3 the camera recorded.
+fn missing_values(
+    sequence: &[Rational32],
+    available: &BTreeSet<Rational32>,
+    tolerance: Rational32,
+    kind: &str,
+) -> Vec<String> {
+    let mut causes = Vec::new();
+    let mut missing: Vec<Rational32> = sequence
+        .iter()
+        .copied()
+        .filter(|v| {
+            available
+                .range(v - tolerance..=v + tolerance)
+                .next()
+                .is_none()
+        })
+        .collect();
+    missing.dedup();
+
+    for value in missing {
+        let near = available.iter().find(|a| {
+            let (Some(a), Some(v)) = (a.to_f64(), value.to_f64()) else {
+                return false;
+            };
+            (a - v).abs() < NEAR_MISS_EV
+        });
+        causes.push(match near {
+            Some(near) => format!(
+                "No file has the {} {} of your sequence, but {} is close. The camera records exact fractions, enter {} instead or raise the EV tolerance.",
+                kind, value, near, near
+            ),
+            None => format!("No file has the {} {} of your sequence", kind, value),
+        });
+    }
+    causes
+}
+
+/// How many sequences were found with each number of frames.
+pub fn frame_count_histogram(sequences: &[SequenceReport]) -> BTreeMap<usize, usize> {
+    let mut histogram = BTreeMap::new();
+    for sequence in sequences {
+        *histogram.entry(sequence.files.len()).or_default() += 1;
+    }
+    histogram
+}
+
+/// Points out frame counts that are rare next to the usual one, naming the
+/// first files of those sequences to review.
+pub fn frame_count_anomalies(sequences: &[SequenceReport]) -> Vec<String> {
+    let histogram = frame_count_histogram(sequences);
+    let Some((&usual, &usual_count)) = histogram.iter().max_by_key(|(_, count)| **count) else {
+        return Vec::new();
+    };
+    histogram
+        .iter()
+        .filter(|(_, count)| **count * OUTLIER_RATIO <= usual_count)
+        .map(|(&frames, &count)| {
+            let mut first_files: Vec<String> = sequences
+                .iter()
+                .filter(|s| s.files.len() == frames)
+                .filter_map(|s| s.files.first())
+                .take(MAX_LISTED_OUTLIERS)
+                .map(|f| f.display().to_string())
+                .collect();
+            if count > MAX_LISTED_OUTLIERS {
+                first_files.push(format!("and {} more", count - MAX_LISTED_OUTLIERS));
+            }
+            format!(
+                "{} sequences have {} frames while {} have {}, check them for a missed or extra frame: {}",
+                count,
+                frames,
+                usual_count,
+                usual,
+                first_files.join(", ")
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,334 @@
+//! Preview of a run: the whole matching runs, but instead of organizing the
+//! sequences the window lists what the action would do with each of them.
+//! Sequences can be given a short note or tag here, e.g. "ghosting", which
+//! the confirmed run keeps in its report and the catalog, and false matches
+//! can be rejected, the confirmed run leaves them as they are. Opening a
+//! sequence shows the embedded previews of its frames and a quick fusion of
+//! them, to judge whether the bracket is worth keeping.
+
+use crate::app::accessible_name;
+use crate::file_utils::{load_thumbnail, RunReport, SequenceReport};
+use crate::result_review::{color_image, fuse};
+use eframe::egui;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Size the thumbnails of the frames are loaded and fused at
+const THUMBNAIL_SIZE: u32 = 320;
+const THUMBNAIL_HEIGHT: f32 = 64.0;
+const FUSED_HEIGHT: f32 = 180.0;
+
+/// Thumbnails loaded in the background, `None` for files without a preview
+type LoadedThumbnails = Vec<(PathBuf, Option<egui::ColorImage>)>;
+
+/// Thumbnails of the frames and their fusions, loaded in the background
+/// when their sequence is first opened.
+#[derive(Default)]
+struct Thumbnails {
+    loaded: Arc<Mutex<LoadedThumbnails>>,
+    /// Fusions by the first file of their sequence
+    fused_loaded: Arc<Mutex<LoadedThumbnails>>,
+    /// First files of the sequences requested
+    requested: HashSet<PathBuf>,
+    /// `None` for files without a preview
+    textures: HashMap<PathBuf, Option<egui::TextureHandle>>,
+    /// `None` for sequences without previews of the same size to fuse
+    fused: HashMap<PathBuf, Option<egui::TextureHandle>>,
+}
+
+impl Thumbnails {
+    /// Starts loading the thumbnails of a sequence's `files`, unless requested before.
+    fn request(&mut self, ctx: &egui::Context, files: &[PathBuf]) {
+        let Some(first) = files.first() else {
+            return;
+        };
+        if !self.requested.insert(first.clone()) {
+            return;
+        }
+        let files = files.to_vec();
+        let loaded = Arc::clone(&self.loaded);
+        let fused_loaded = Arc::clone(&self.fused_loaded);
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let mut images = Vec::new();
+            for path in &files {
+                let image = load_thumbnail(path, THUMBNAIL_SIZE).map(|image| image.to_rgb8());
+                loaded
+                    .lock()
+                    .unwrap()
+                    .push((path.clone(), image.as_ref().map(color_image)));
+                images.extend(image);
+                ctx.request_repaint();
+            }
+            let frames: Vec<_> = images.iter().collect();
+            let fused = fuse(&frames).map(|image| color_image(&image));
+            fused_loaded.lock().unwrap().push((files[0].clone(), fused));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Turns the thumbnails loaded meanwhile into textures.
+    fn take_loaded(&mut self, ctx: &egui::Context) {
+        let texture = |name: String, image: egui::ColorImage| {
+            ctx.load_texture(name, image, Default::default())
+        };
+        for (path, image) in self.loaded.lock().unwrap().drain(..) {
+            let handle = image.map(|image| texture(path.display().to_string(), image));
+            self.textures.insert(path, handle);
+        }
+        for (first, image) in self.fused_loaded.lock().unwrap().drain(..) {
+            let handle = image.map(|image| texture(format!("fused {}", first.display()), image));
+            self.fused.insert(first, handle);
+        }
+    }
+}
+
+/// Window showing the report of a dry run, filled by the run in the background.
+#[derive(Default)]
+pub struct DryRunWindow {
+    pub open: bool,
+    result: Arc<Mutex<Option<RunReport>>>,
+    /// Whether the files are moved into the destination, not just listed in it
+    moves_files: bool,
+    /// Notes by the first file of their sequence
+    notes: HashMap<PathBuf, String>,
+    /// First files of the sequences rejected in the review
+    rejected: HashSet<PathBuf>,
+    thumbnails: Thumbnails,
+}
+
+impl DryRunWindow {
+    /// Opens the window for a new dry run, the run reports to the returned slot.
+    pub fn start(&mut self, moves_files: bool) -> Arc<Mutex<Option<RunReport>>> {
+        *self.result.lock().unwrap() = None;
+        // The files may have changed since
+        self.thumbnails = Thumbnails::default();
+        self.open = true;
+        self.moves_files = moves_files;
+        Arc::clone(&self.result)
+    }
+
+    /// The notes given so far, for the run confirmed with "Run for Real".
+    pub fn notes(&self) -> HashMap<PathBuf, String> {
+        self.notes
+            .iter()
+            .filter(|(_, note)| !note.trim().is_empty())
+            .map(|(path, note)| (path.clone(), note.trim().to_string()))
+            .collect()
+    }
+
+    /// The sequences rejected so far, by their first file.
+    pub fn rejected(&self) -> HashSet<PathBuf> {
+        self.rejected.clone()
+    }
+
+    /// `true` when the run was confirmed with "Run for Real".
+    pub fn show(&mut self, ctx: &egui::Context, can_run: bool) -> bool {
+        if !self.open {
+            return false;
+        }
+        let mut open = true;
+        let mut run = false;
+        egui::Window::new("Preview (Dry Run)")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| run = self.show_contents(ui, can_run));
+        self.open = open && !run;
+        run
+    }
+
+    fn show_contents(&mut self, ui: &mut egui::Ui, can_run: bool) -> bool {
+        self.thumbnails.take_loaded(ui.ctx());
+        let result = self.result.lock().unwrap();
+        let Some(report) = result.as_ref() else {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Matching sequences…");
+            });
+            return false;
+        };
+
+        ui.label(format!(
+            "{} sequences would be created, nothing has been changed yet.",
+            report.sequences.len()
+        ));
+        for error in &report.errors {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", error));
+        }
+        for diagnostic in &report.diagnostics {
+            ui.label(diagnostic);
+        }
+        for anomaly in &report.anomalies {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", anomaly));
+        }
+
+        let first_files: Vec<&PathBuf> = report
+            .sequences
+            .iter()
+            .filter_map(|s| s.files.first())
+            .collect();
+        if !report.sequences.is_empty() {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Uncheck the sequences to leave as they are.");
+                if ui.small_button("Accept All").clicked() {
+                    self.rejected.clear();
+                }
+                if ui.small_button("Reject All").clicked() {
+                    self.rejected
+                        .extend(first_files.iter().map(|f| (*f).clone()));
+                }
+            });
+            egui::ScrollArea::vertical()
+                .max_height(360.0)
+                .show(ui, |ui| {
+                    for sequence in &report.sequences {
+                        show_sequence(
+                            ui,
+                            sequence,
+                            self.moves_files,
+                            &mut self.notes,
+                            &mut self.rejected,
+                            &mut self.thumbnails,
+                        );
+                    }
+                });
+        }
+
+        let accepted = first_files
+            .iter()
+            .filter(|f| !self.rejected.contains(**f))
+            .count();
+        ui.separator();
+        ui.add_enabled(can_run && accepted > 0, egui::Button::new("Run for Real"))
+            .on_hover_text(format!(
+                "Run the action on the {} of {} sequences accepted",
+                accepted,
+                first_files.len()
+            ))
+            .clicked()
+    }
+}
+
+fn show_sequence(
+    ui: &mut egui::Ui,
+    sequence: &SequenceReport,
+    moves_files: bool,
+    notes: &mut HashMap<PathBuf, String>,
+    rejected: &mut HashSet<PathBuf>,
+    thumbnails: &mut Thumbnails,
+) {
+    let first = sequence
+        .files
+        .first()
+        .map_or_else(String::new, |f| file_name(f));
+    let header = match &sequence.destination {
+        Some(destination) => format!("{}: {} → {}", sequence.kind, first, destination.display()),
+        None => format!("{}: {}", sequence.kind, first),
+    };
+    let id = ui.make_persistent_id(&sequence.files);
+    egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, false)
+        .show_header(ui, |ui| {
+            let Some(first_file) = sequence.files.first() else {
+                ui.label(header);
+                return;
+            };
+            let mut accepted = !rejected.contains(first_file);
+            let response = ui.checkbox(&mut accepted, header);
+            if response.changed() {
+                if accepted {
+                    rejected.remove(first_file);
+                } else {
+                    rejected.insert(first_file.clone());
+                }
+            }
+        })
+        .body(|ui| {
+            thumbnails.request(ui.ctx(), &sequence.files);
+            show_thumbnails(ui, &sequence.files, thumbnails);
+            if let Some(first) = sequence.files.first() {
+                ui.horizontal(|ui| {
+                    let label = ui.label("Note:");
+                    let note = notes.entry(first.clone()).or_default();
+                    ui.add(
+                        egui::TextEdit::singleline(note).hint_text("e.g. best of sunset, ghosting"),
+                    )
+                    .labelled_by(label.id);
+                });
+            }
+            if let Some(pattern) = &sequence.pattern {
+                ui.weak(format!("Follows {}", pattern));
+            }
+            if let Some(detected) = &sequence.detected {
+                ui.weak(format!("Detected {}", detected));
+            }
+            ui.weak(format!("Frames in {}", sequence.ordered_by))
+                .on_hover_text("Frames shot within the same second are put in order by their shutter count or file number, if the camera recorded no fractions of a second");
+            for (i, file) in sequence.files.iter().enumerate() {
+                let bias = sequence.biases.get(i).map_or("", String::as_str);
+                let target = sequence
+                    .destination
+                    .as_deref()
+                    .filter(|_| moves_files)
+                    .and_then(|d| file.file_name().map(|name| d.join(name)));
+                let text = match target {
+                    Some(target) => format!("{} → {}", file_name(file), target.display()),
+                    None => file.display().to_string(),
+                };
+                let text = if bias.is_empty() {
+                    text
+                } else {
+                    format!("{} EV  {}", bias, text)
+                };
+                ui.label(text).on_hover_text(file.display().to_string());
+            }
+        });
+}
+
+fn show_thumbnails(ui: &mut egui::Ui, files: &[PathBuf], thumbnails: &Thumbnails) {
+    if let Some(first) = files.first() {
+        match thumbnails.fused.get(first) {
+            Some(Some(fused)) => {
+                let image = ui.add(egui::Image::new(fused).max_height(FUSED_HEIGHT));
+                accessible_name(image, "Fused preview of the sequence")
+                    .on_hover_text("A quick blend of the frames' embedded previews, weighted by how well exposed each pixel is");
+            }
+            Some(None) => {
+                ui.weak("No fused preview, the frames have no previews of the same size.");
+            }
+            None => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.weak("Fusing the previews…");
+                });
+            }
+        }
+    }
+    egui::ScrollArea::horizontal()
+        .id_salt(files)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                for file in files {
+                    let name = file_name(file);
+                    let response = match thumbnails.textures.get(file) {
+                        Some(Some(texture)) => {
+                            let image =
+                                ui.add(egui::Image::new(texture).max_height(THUMBNAIL_HEIGHT));
+                            accessible_name(image, &name)
+                        }
+                        Some(None) => ui.weak(format!("{}\n(no preview)", name)),
+                        None => ui.spinner(),
+                    };
+                    response.on_hover_text(name);
+                }
+            });
+        });
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
@@ -0,0 +1,551 @@
+//! Reads and writes the XMP packet embedded in DNG and JPEG files.
+//!
+//! Only the XMP packet is touched. JPEGs are rewritten through a temporary
+//! file, DNGs are updated in place: a packet that fits is overwritten within
+//! its padding, otherwise it is appended and only the pointer to it changes.
+//! Both keep the modification time of the original.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// Largest packet a single APP1 segment can hold, extended XMP is not supported
+const JPEG_MAX_PACKET: usize = 65533 - JPEG_XMP_HEADER.len();
+const TIFF_TAG_XMP: u16 = 700;
+const TIFF_TYPE_BYTE: u16 = 1;
+/// Room left for later edits when a packet is newly written into a DNG
+const DNG_PADDING: usize = 2048;
+
+#[derive(Clone, Copy)]
+enum Format {
+    Jpeg,
+    Dng,
+}
+
+fn format(path: &Path) -> Option<Format> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "jpg" | "jpeg" => Some(Format::Jpeg),
+        "dng" => Some(Format::Dng),
+        _ => None,
+    }
+}
+
+/// Whether the XMP packet of this file can be updated in the file itself.
+pub fn supports(path: &Path) -> bool {
+    format(path).is_some()
+}
+
+/// Returns the embedded XMP packet, `None` if the file has none.
+pub fn read(path: &Path) -> io::Result<Option<String>> {
+    let packet = match format(path) {
+        Some(Format::Jpeg) => {
+            let data = fs::read(path)?;
+            let segments = jpeg_segments(&data)?;
+            segments
+                .iter()
+                .find_map(|s| jpeg_xmp_payload(&data, s))
+                .map(<[u8]>::to_vec)
+        }
+        Some(Format::Dng) => {
+            let mut file = File::open(path)?;
+            let tiff = Tiff::read_header(&mut file)?;
+            let ifd = tiff.read_ifd(&mut file, tiff.first_ifd)?;
+            match ifd.entry(TIFF_TAG_XMP) {
+                // Up to four bytes are stored in the entry itself
+                Some(entry) if entry.count <= 4 => {
+                    Some(entry.raw_value[..entry.count as usize].to_vec())
+                }
+                Some(entry) => {
+                    if entry.value as u64 + entry.count as u64 > file.metadata()?.len() {
+                        return Err(invalid("XMP packet runs past the end of the file"));
+                    }
+                    let mut packet = vec![0; entry.count as usize];
+                    file.seek(SeekFrom::Start(entry.value as u64))?;
+                    file.read_exact(&mut packet)?;
+                    Some(packet)
+                }
+                None => None,
+            }
+        }
+        None => return Err(unsupported(path)),
+    };
+    Ok(packet.map(|p| {
+        String::from_utf8_lossy(&p)
+            .trim_end_matches('\0')
+            .to_string()
+    }))
+}
+
+/// Replaces the embedded XMP packet, or adds one.
+pub fn write(path: &Path, packet: &str) -> io::Result<()> {
+    match format(path) {
+        Some(Format::Jpeg) => write_jpeg(path, packet),
+        Some(Format::Dng) => write_dng(path, packet),
+        None => Err(unsupported(path)),
+    }
+}
+
+fn unsupported(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{} can't hold an embedded XMP packet", path.display()),
+    )
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Rebuilds the packet with `padding` spaces in front of the `<?xpacket end`
+/// trailer, replacing any padding it had before.
+fn with_padding(packet: &str, padding: usize) -> String {
+    let (body, trailer) = match packet.rfind("<?xpacket end") {
+        Some(i) => (packet[..i].trim_end(), &packet[i..]),
+        None => (packet.trim_end(), ""),
+    };
+    format!("{}\n{}\n{}", body, " ".repeat(padding), trailer)
+}
+
+/// Pads the packet to exactly `length` bytes, `None` if it is too long.
+fn fit_packet(packet: &str, length: usize) -> Option<String> {
+    let unpadded = with_padding(packet, 0).len();
+    let padding = length.checked_sub(unpadded)?;
+    Some(with_padding(packet, padding))
+}
+
+struct Segment {
+    marker: u8,
+    /// Start of the marker, the segment runs up to `end`
+    start: usize,
+    end: usize,
+}
+
+/// Lists the segments in front of the image data (start of scan).
+fn jpeg_segments(data: &[u8]) -> io::Result<Vec<Segment>> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err(invalid("Not a JPEG file"));
+    }
+    let mut segments = Vec::new();
+    let mut position = 2;
+    loop {
+        if position + 4 > data.len() || data[position] != 0xFF {
+            return Err(invalid("Malformed JPEG segment"));
+        }
+        let marker = data[position + 1];
+        if marker == 0xFF {
+            // Fill byte
+            position += 1;
+            continue;
+        }
+        if marker == 0xDA {
+            return Ok(segments);
+        }
+        let length = u16::from_be_bytes([data[position + 2], data[position + 3]]) as usize;
+        let end = position + 2 + length;
+        if length < 2 || end > data.len() {
+            return Err(invalid("Malformed JPEG segment"));
+        }
+        segments.push(Segment {
+            marker,
+            start: position,
+            end,
+        });
+        position = end;
+    }
+}
+
+fn jpeg_xmp_payload<'a>(data: &'a [u8], segment: &Segment) -> Option<&'a [u8]> {
+    let payload = &data[segment.start + 4..segment.end];
+    (segment.marker == 0xE1 && payload.starts_with(JPEG_XMP_HEADER))
+        .then(|| &payload[JPEG_XMP_HEADER.len()..])
+}
+
+fn write_jpeg(path: &Path, packet: &str) -> io::Result<()> {
+    let metadata = fs::metadata(path)?;
+    let data = fs::read(path)?;
+    let segments = jpeg_segments(&data)?;
+
+    let packet = with_padding(packet, 0);
+    if packet.len() > JPEG_MAX_PACKET {
+        return Err(invalid("XMP packet is too large for a JPEG segment"));
+    }
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend_from_slice(&((2 + JPEG_XMP_HEADER.len() + packet.len()) as u16).to_be_bytes());
+    segment.extend_from_slice(JPEG_XMP_HEADER);
+    segment.extend_from_slice(packet.as_bytes());
+
+    let existing = segments
+        .iter()
+        .find(|s| jpeg_xmp_payload(&data, s).is_some());
+    let (replace_start, replace_end) = match existing {
+        Some(s) => (s.start, s.end),
+        None => {
+            // Behind JFIF and EXIF, where readers expect the XMP segment
+            let at = segments
+                .iter()
+                .take_while(|s| s.marker == 0xE0 || s.marker == 0xE1)
+                .last()
+                .map_or(2, |s| s.end);
+            (at, at)
+        }
+    };
+
+    let mut output = Vec::with_capacity(data.len() + segment.len());
+    output.extend_from_slice(&data[..replace_start]);
+    output.extend_from_slice(&segment);
+    output.extend_from_slice(&data[replace_end..]);
+
+    // Never leave a half written original behind
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = Path::new(&temporary);
+    fs::write(temporary, &output)?;
+    // Keep the capture order by modification time intact
+    OpenOptions::new()
+        .write(true)
+        .open(temporary)?
+        .set_modified(metadata.modified()?)?;
+    fs::set_permissions(temporary, metadata.permissions())?;
+    fs::rename(temporary, path)
+}
+
+fn write_dng(path: &Path, packet: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let modified = file.metadata()?.modified()?;
+    update_dng(&mut file, packet)?;
+    file.set_modified(modified)
+}
+
+fn update_dng(file: &mut File, packet: &str) -> io::Result<()> {
+    let tiff = Tiff::read_header(file)?;
+    let ifd = tiff.read_ifd(file, tiff.first_ifd)?;
+
+    if let Some((index, entry)) = ifd.entry_index(TIFF_TAG_XMP) {
+        // A packet of up to four bytes is stored in the entry, not at an offset
+        let fitted = (entry.count > 4)
+            .then(|| fit_packet(packet, entry.count as usize))
+            .flatten();
+        if let Some(fitted) = fitted {
+            file.seek(SeekFrom::Start(entry.value as u64))?;
+            file.write_all(fitted.as_bytes())?;
+            return file.sync_all();
+        }
+
+        // Too large for the old location, append it and point the tag there
+        let packet = with_padding(packet, DNG_PADDING);
+        let offset = append(file, packet.as_bytes())?;
+        let mut entry_bytes = Vec::with_capacity(8);
+        entry_bytes.extend_from_slice(&tiff.u32_bytes(packet.len() as u32));
+        entry_bytes.extend_from_slice(&tiff.u32_bytes(offset));
+        file.seek(SeekFrom::Start(
+            tiff.first_ifd as u64 + 2 + index as u64 * 12 + 4,
+        ))?;
+        file.write_all(&entry_bytes)?;
+        return file.sync_all();
+    }
+
+    // No packet yet: append it together with a copy of the first IFD that
+    // includes the new tag, then switch the header over to that copy
+    let packet = with_padding(packet, DNG_PADDING);
+    let packet_offset = append(file, packet.as_bytes())?;
+    let mut entries = ifd.entries;
+    entries.push(IfdEntry {
+        tag: TIFF_TAG_XMP,
+        field_type: TIFF_TYPE_BYTE,
+        count: packet.len() as u32,
+        value: packet_offset,
+        raw_value: tiff.u32_bytes(packet_offset),
+    });
+    entries.sort_by_key(|e| e.tag);
+
+    let mut ifd_bytes = Vec::with_capacity(2 + entries.len() * 12 + 4);
+    ifd_bytes.extend_from_slice(&tiff.u16_bytes(entries.len() as u16));
+    for entry in &entries {
+        ifd_bytes.extend_from_slice(&tiff.u16_bytes(entry.tag));
+        ifd_bytes.extend_from_slice(&tiff.u16_bytes(entry.field_type));
+        ifd_bytes.extend_from_slice(&tiff.u32_bytes(entry.count));
+        ifd_bytes.extend_from_slice(&entry.raw_value);
+    }
+    ifd_bytes.extend_from_slice(&tiff.u32_bytes(ifd.next_ifd));
+    let ifd_offset = append(file, &ifd_bytes)?;
+    file.sync_all()?;
+
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&tiff.u32_bytes(ifd_offset))?;
+    file.sync_all()
+}
+
+/// Appends `data` at a word boundary and returns its offset.
+fn append(file: &mut File, data: &[u8]) -> io::Result<u32> {
+    let mut end = file.seek(SeekFrom::End(0))?;
+    if end % 2 == 1 {
+        file.write_all(&[0])?;
+        end += 1;
+    }
+    let offset = u32::try_from(end).map_err(|_| invalid("DNG file is too large"))?;
+    file.write_all(data)?;
+    Ok(offset)
+}
+
+struct Tiff {
+    little_endian: bool,
+    first_ifd: u32,
+}
+
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    /// The value field read as offset, only meaningful for values stored elsewhere
+    value: u32,
+    raw_value: [u8; 4],
+}
+
+struct Ifd {
+    entries: Vec<IfdEntry>,
+    next_ifd: u32,
+}
+
+impl Ifd {
+    fn entry(&self, tag: u16) -> Option<&IfdEntry> {
+        self.entry_index(tag).map(|(_, entry)| entry)
+    }
+
+    fn entry_index(&self, tag: u16) -> Option<(usize, &IfdEntry)> {
+        self.entries.iter().enumerate().find(|(_, e)| e.tag == tag)
+    }
+}
+
+impl Tiff {
+    fn read_header(file: &mut File) -> io::Result<Self> {
+        let mut header = [0; 8];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+        let little_endian = match &header[..4] {
+            b"II*\0" => true,
+            b"MM\0*" => false,
+            _ => return Err(invalid("Not a classic TIFF based DNG")),
+        };
+        let tiff = Self {
+            little_endian,
+            first_ifd: 0,
+        };
+        let first_ifd = tiff.u32([header[4], header[5], header[6], header[7]]);
+        Ok(Self { first_ifd, ..tiff })
+    }
+
+    fn read_ifd(&self, file: &mut File, offset: u32) -> io::Result<Ifd> {
+        file.seek(SeekFrom::Start(offset as u64))?;
+        let mut count = [0; 2];
+        file.read_exact(&mut count)?;
+        let count = self.u16(count) as usize;
+        if offset as u64 + 2 + (count * 12 + 4) as u64 > file.metadata()?.len() {
+            return Err(invalid("IFD runs past the end of the file"));
+        }
+
+        let mut data = vec![0; count * 12 + 4];
+        file.read_exact(&mut data)?;
+        let entries = data[..count * 12]
+            .chunks_exact(12)
+            .map(|e| {
+                let raw_value = [e[8], e[9], e[10], e[11]];
+                IfdEntry {
+                    tag: self.u16([e[0], e[1]]),
+                    field_type: self.u16([e[2], e[3]]),
+                    count: self.u32([e[4], e[5], e[6], e[7]]),
+                    value: self.u32(raw_value),
+                    raw_value,
+                }
+            })
+            .collect();
+        let next = &data[count * 12..];
+        Ok(Ifd {
+            entries,
+            next_ifd: self.u32([next[0], next[1], next[2], next[3]]),
+        })
+    }
+
+    fn u16(&self, bytes: [u8; 2]) -> u16 {
+        if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        }
+    }
+
+    fn u32(&self, bytes: [u8; 4]) -> u32 {
+        if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    }
+
+    fn u16_bytes(&self, value: u16) -> [u8; 2] {
+        if self.little_endian {
+            value.to_le_bytes()
+        } else {
+            value.to_be_bytes()
+        }
+    }
+
+    fn u32_bytes(&self, value: u32) -> [u8; 4] {
+        if self.little_endian {
+            value.to_le_bytes()
+        } else {
+            value.to_be_bytes()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ebo-embedded-xmp-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn packet(body: &str) -> String {
+        format!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">{}</x:xmpmeta>\n<?xpacket end=\"w\"?>",
+            body
+        )
+    }
+
+    /// A TIFF with a NewSubfileType entry and `extra` entries of type BYTE
+    /// stored inline.
+    fn tiff(little_endian: bool, extra: &[(u16, &[u8])]) -> Vec<u8> {
+        let tiff = Tiff {
+            little_endian,
+            first_ifd: 8,
+        };
+        let mut data = if little_endian {
+            b"II*\0".to_vec()
+        } else {
+            b"MM\0*".to_vec()
+        };
+        data.extend_from_slice(&tiff.u32_bytes(8));
+        data.extend_from_slice(&tiff.u16_bytes(1 + extra.len() as u16));
+        data.extend_from_slice(&tiff.u16_bytes(254));
+        data.extend_from_slice(&tiff.u16_bytes(4));
+        data.extend_from_slice(&tiff.u32_bytes(1));
+        data.extend_from_slice(&[0; 4]);
+        for (tag, value) in extra {
+            let mut raw_value = [0; 4];
+            raw_value[..value.len()].copy_from_slice(value);
+            data.extend_from_slice(&tiff.u16_bytes(*tag));
+            data.extend_from_slice(&tiff.u16_bytes(TIFF_TYPE_BYTE));
+            data.extend_from_slice(&tiff.u32_bytes(value.len() as u32));
+            data.extend_from_slice(&raw_value);
+        }
+        data.extend_from_slice(&tiff.u32_bytes(0));
+        data
+    }
+
+    /// A JPEG with a JFIF segment and a few bytes of scan data.
+    fn jpeg() -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        data.extend_from_slice(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        data.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]);
+        data
+    }
+
+    fn first_ifd_tags(path: &Path) -> Vec<u16> {
+        let mut file = File::open(path).unwrap();
+        let tiff = Tiff::read_header(&mut file).unwrap();
+        let ifd = tiff.read_ifd(&mut file, tiff.first_ifd).unwrap();
+        ifd.entries.iter().map(|e| e.tag).collect()
+    }
+
+    #[test]
+    fn dng_insert_fit_and_grow() {
+        let dir = temp_dir("dng");
+        for little_endian in [true, false] {
+            let path = dir.join(format!("IMG_{}.dng", little_endian));
+            fs::write(&path, tiff(little_endian, &[])).unwrap();
+            assert_eq!(read(&path).unwrap(), None);
+
+            // No packet yet, appended with a new copy of the IFD
+            write(&path, &packet("first")).unwrap();
+            assert!(read(&path).unwrap().unwrap().contains("first"));
+            assert_eq!(first_ifd_tags(&path), [254, TIFF_TAG_XMP]);
+            let length = fs::metadata(&path).unwrap().len();
+
+            // Fits within the padding, overwritten in place
+            write(&path, &packet("second")).unwrap();
+            let read_back = read(&path).unwrap().unwrap();
+            assert!(read_back.contains("second") && !read_back.contains("first"));
+            assert!(read_back.ends_with("<?xpacket end=\"w\"?>"));
+            assert_eq!(fs::metadata(&path).unwrap().len(), length);
+
+            // Too large for the padding, appended and pointed to
+            let large = "third".repeat(1000);
+            write(&path, &packet(&large)).unwrap();
+            assert!(read(&path).unwrap().unwrap().contains(&large));
+            assert!(fs::metadata(&path).unwrap().len() > length);
+            assert_eq!(first_ifd_tags(&path), [254, TIFF_TAG_XMP]);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dng_packet_stored_inline() {
+        let dir = temp_dir("inline");
+        let path = dir.join("IMG.dng");
+        fs::write(&path, tiff(false, &[(TIFF_TAG_XMP, b"abc")])).unwrap();
+        assert_eq!(read(&path).unwrap().as_deref(), Some("abc"));
+
+        write(&path, &packet("moved")).unwrap();
+        assert!(read(&path).unwrap().unwrap().contains("moved"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn jpeg_insert_and_replace() {
+        let dir = temp_dir("jpeg");
+        let path = dir.join("IMG.jpg");
+        let original = jpeg();
+        fs::write(&path, &original).unwrap();
+        assert_eq!(read(&path).unwrap(), None);
+
+        write(&path, &packet("first")).unwrap();
+        write(&path, &packet("second")).unwrap();
+        let read_back = read(&path).unwrap().unwrap();
+        assert!(read_back.contains("second") && !read_back.contains("first"));
+
+        let data = fs::read(&path).unwrap();
+        let segments = jpeg_segments(&data).unwrap();
+        let markers: Vec<u8> = segments.iter().map(|s| s.marker).collect();
+        assert_eq!(markers, [0xE0, 0xE1]);
+        assert!(data.ends_with(&original[20..]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn writing_keeps_the_modification_time() {
+        let dir = temp_dir("mtime");
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        for (name, data) in [("IMG.jpg", jpeg()), ("IMG.dng", tiff(true, &[]))] {
+            let path = dir.join(name);
+            fs::write(&path, data).unwrap();
+            OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+
+            write(&path, &packet("tagged")).unwrap();
+            assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
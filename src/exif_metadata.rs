@@ -0,0 +1,96 @@
+//! Capture metadata of the files rawler can't decode: the JPEG, HEIF and TIFF
+//! files cameras write next to their raws, or instead of them. Their EXIF
+//! holds the same tags, so they are grouped into brackets just like raws.
+
+use crate::bracket_kind::CaptureSettings;
+use crate::file_utils::parse_exif_time;
+use crate::metadata_cache::CachedMetadata;
+use exif::{Context, Exif, In, Reader, Tag, Value};
+use log::debug;
+use num_rational::Rational32;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Extensions of the files read through this module, offered in addition to
+/// the raw formats.
+pub const NON_RAW_EXTENSIONS: &[&str] = &["jpg", "jpeg", "heic", "heif", "tif", "tiff"];
+
+/// The shutter count, which the crate has no name for
+const IMAGE_NUMBER: Tag = Tag(Context::Exif, 0x9211);
+
+/// Reads the EXIF of a JPEG, HEIF or TIFF file, `None` if it has none.
+pub fn read_exif_metadata(path: &Path) -> Option<CachedMetadata> {
+    let file = File::open(path).ok()?;
+    let exif = match Reader::new().read_from_container(&mut BufReader::new(file)) {
+        Ok(exif) => exif,
+        Err(e) => {
+            debug!("No EXIF in {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    let camera = format!(
+        "{} {}",
+        text(&exif, Tag::Make).unwrap_or_default(),
+        text(&exif, Tag::Model).unwrap_or_default()
+    );
+    let camera = camera.trim();
+    let capture_settings = CaptureSettings {
+        fnumber: rational(&exif, Tag::FNumber),
+        exposure_time: rational(&exif, Tag::ExposureTime),
+        iso: number(&exif, Tag::PhotographicSensitivity),
+        white_balance: short(&exif, Tag::WhiteBalance),
+        light_source: short(&exif, Tag::LightSource),
+        flash: short(&exif, Tag::Flash),
+        flash_energy: rational(&exif, Tag::FlashEnergy),
+    };
+
+    Some(CachedMetadata {
+        capture_time: text(&exif, Tag::DateTimeOriginal).and_then(|date_time| {
+            parse_exif_time(&date_time, text(&exif, Tag::SubSecTimeOriginal).as_deref())
+        }),
+        exposure_bias: rational(&exif, Tag::ExposureBiasValue),
+        exposure_mode: short(&exif, Tag::ExposureMode),
+        camera: (!camera.is_empty()).then(|| camera.to_string()),
+        capture_settings: Some(capture_settings),
+        image_number: number(&exif, IMAGE_NUMBER),
+        maker_note_bracket: None,
+    })
+}
+
+fn text(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => {
+            let text = String::from_utf8_lossy(values.first()?);
+            Some(text.trim().to_string())
+        }
+        _ => None,
+    }
+}
+
+fn number(exif: &Exif, tag: Tag) -> Option<u32> {
+    exif.get_field(tag, In::PRIMARY)?.value.get_uint(0)
+}
+
+fn short(exif: &Exif, tag: Tag) -> Option<u16> {
+    number(exif, tag).and_then(|n| u16::try_from(n).ok())
+}
+
+fn rational(exif: &Exif, tag: Tag) -> Option<Rational32> {
+    let (n, d) = match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::SRational(values) => {
+            let value = values.first()?;
+            (value.num, value.denom)
+        }
+        Value::Rational(values) => {
+            let value = values.first()?;
+            (
+                i32::try_from(value.num).ok()?,
+                i32::try_from(value.denom).ok()?,
+            )
+        }
+        _ => return None,
+    };
+    (d != 0).then(|| Rational32::new(n, d))
+}
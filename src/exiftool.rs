@@ -0,0 +1,196 @@
+//! Metadata of files neither rawler nor the plain EXIF reader understand,
+//! e.g. raws of a camera released after this version, read by a separately
+//! installed [exiftool](https://exiftool.org). exiftool is started once per
+//! file, so this is much slower than the built-in readers and only tried
+//! after both failed.
+
+use crate::bracket_kind::CaptureSettings;
+use crate::file_utils::parse_exif_time;
+use crate::maker_notes::{nikon_bracket_from, MakerNoteBracket, SONY_EXPOSURE_BRACKETING};
+use crate::metadata_cache::CachedMetadata;
+use log::{debug, info, warn};
+use num_rational::Rational32;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::RwLock;
+
+/// The exiftool used by every reader of the process, `None` if it is off
+static EXIFTOOL: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// The tags asked for, read with `-n` as plain numbers
+const TAGS: &[&str] = &[
+    "-DateTimeOriginal",
+    "-SubSecTimeOriginal",
+    "-ExposureCompensation",
+    "-ExposureMode",
+    "-Make",
+    "-Model",
+    "-FNumber",
+    "-ExposureTime",
+    "-ISO",
+    "-WhiteBalance",
+    "-LightSource",
+    "-Flash",
+    "-FlashEnergy",
+    "-ImageNumber",
+    "-ShutterCount",
+    "-BracketMode",
+    "-BracketShotNumber",
+    "-ShootingMode",
+    "-ExposureBracketValue",
+    "-ReleaseMode",
+];
+
+/// Canon's `BracketMode` of an auto exposure bracket
+const CANON_AEB: u16 = 1;
+
+/// Whether and which exiftool is used, as set in the window.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExiftoolSettings {
+    pub enabled: bool,
+    /// The binary, e.g. `C:\Tools\exiftool.exe`, empty to look it up in the `PATH`
+    pub path: String,
+}
+
+impl ExiftoolSettings {
+    pub fn binary(&self) -> Option<PathBuf> {
+        self.enabled.then(|| match self.path.trim() {
+            "" => PathBuf::from("exiftool"),
+            path => PathBuf::from(path),
+        })
+    }
+}
+
+/// Uses `binary` for files the built-in readers can't read, `None` to stop.
+pub fn configure(binary: Option<PathBuf>) {
+    if let Some(binary) = &binary {
+        info!("Reading unsupported files with {}", binary.display());
+    }
+    *EXIFTOOL.write().unwrap() = binary;
+}
+
+/// The version of `binary`, to check a configured path.
+pub fn version(binary: &Path) -> Result<String, String> {
+    let output = Command::new(binary)
+        .arg("-ver")
+        .output()
+        .map_err(|e| format!("Failed to start {}: {}", binary.display(), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} exited with {}",
+            binary.display(),
+            output.status
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Reads the metadata of `path` with the configured exiftool, `None` if none
+/// is configured or it found no capture time or exposure bias.
+pub fn read_exiftool_metadata(path: &Path) -> Option<CachedMetadata> {
+    let binary = EXIFTOOL.read().unwrap().clone()?;
+    let output = match Command::new(&binary)
+        .args(["-json", "-n"])
+        .args(TAGS)
+        .arg(path)
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => {
+            warn!("Failed to start {}: {}", binary.display(), e);
+            return None;
+        }
+    };
+    if !output.status.success() {
+        debug!(
+            "exiftool can't read {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+    let json: Value = serde_json::from_slice(&output.stdout).ok()?;
+    let tags = json.as_array()?.first()?.as_object()?;
+
+    let capture_time = text(tags, "DateTimeOriginal").and_then(|date_time| {
+        parse_exif_time(&date_time, text(tags, "SubSecTimeOriginal").as_deref())
+    });
+    let exposure_bias = rational(tags, "ExposureCompensation");
+    if capture_time.is_none() && exposure_bias.is_none() {
+        return None;
+    }
+    let camera = format!(
+        "{} {}",
+        text(tags, "Make").unwrap_or_default(),
+        text(tags, "Model").unwrap_or_default()
+    );
+    let camera = camera.trim();
+    // The maker note tags, see [`crate::maker_notes`]
+    let maker_note_bracket = if camera.starts_with("Canon") {
+        (number::<u16>(tags, "BracketMode") == Some(CANON_AEB)).then(|| MakerNoteBracket {
+            shot: number(tags, "BracketShotNumber").filter(|shot| *shot > 0),
+            bracket_value: None,
+        })
+    } else if camera.to_ascii_uppercase().starts_with("NIKON") {
+        nikon_bracket_from(
+            number(tags, "ShootingMode"),
+            rational(tags, "ExposureBracketValue"),
+        )
+    } else if camera.to_ascii_uppercase().starts_with("SONY") {
+        (number(tags, "ReleaseMode") == Some(SONY_EXPOSURE_BRACKETING))
+            .then(MakerNoteBracket::default)
+    } else {
+        None
+    };
+    let capture_settings = CaptureSettings {
+        fnumber: rational(tags, "FNumber"),
+        exposure_time: rational(tags, "ExposureTime"),
+        iso: number(tags, "ISO"),
+        white_balance: number(tags, "WhiteBalance"),
+        light_source: number(tags, "LightSource"),
+        flash: number(tags, "Flash"),
+        flash_energy: rational(tags, "FlashEnergy"),
+    };
+
+    debug!("Read {} with exiftool", path.display());
+    let metadata = CachedMetadata {
+        capture_time,
+        exposure_bias,
+        exposure_mode: number(tags, "ExposureMode"),
+        camera: (!camera.is_empty()).then(|| camera.to_string()),
+        capture_settings: Some(capture_settings),
+        // The maker note's counter, where the EXIF has none
+        image_number: number(tags, "ImageNumber").or_else(|| number(tags, "ShutterCount")),
+        maker_note_bracket: None,
+    };
+    Some(metadata.with_maker_note_bracket(maker_note_bracket))
+}
+
+/// exiftool writes values that look like numbers as JSON numbers, e.g. a
+/// SubSecTimeOriginal of `123`.
+fn text(tags: &Map<String, Value>, tag: &str) -> Option<String> {
+    match tags.get(tag)? {
+        Value::String(text) => Some(text.trim().to_string()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn number<T: TryFrom<u64>>(tags: &Map<String, Value>, tag: &str) -> Option<T> {
+    let value = tags.get(tag)?;
+    let number = value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|v| v.trim().parse().ok()))?;
+    T::try_from(number).ok()
+}
+
+/// `-n` writes rationals as decimals, e.g. -0.666666666666667 for -2/3
+fn rational(tags: &Map<String, Value>, tag: &str) -> Option<Rational32> {
+    let value = tags.get(tag)?;
+    let decimal = value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|v| v.trim().parse().ok()))?;
+    Rational32::approximate_float(decimal)
+}
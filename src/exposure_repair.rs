@@ -0,0 +1,289 @@
+//! Exposure bias and mode for files whose camera recorded none or wrong ones,
+//! e.g. brackets shot manually or with vintage lenses.
+//!
+//! The values are written as `exif:ExposureBiasValue` and `exif:ExposureMode`
+//! into the XMP sidecar, the originals stay untouched. Detection prefers them
+//! over the values recorded by the camera, so repaired files flow through the
+//! normal actions.
+
+use crate::metadata_cache::read_file_metadata;
+use crate::xmp::{Xmp, EXIF};
+use ebo_core::{parse_exposure_sequence, parse_exposure_sequences, EvFormat};
+use eframe::egui;
+use log::warn;
+use num_rational::Rational32;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Exposure mode value of auto bracketing, which the auto-bracket filter accepts
+pub const AUTO_BRACKET: u16 = 2;
+
+/// Values found in the sidecar of a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExposureOverride {
+    pub exposure_bias: Option<Rational32>,
+    pub exposure_mode: Option<u16>,
+}
+
+/// Reads the values written to the sidecar of `path`, if it has any.
+pub fn read_override(path: &Path) -> Option<ExposureOverride> {
+    let xmp = match Xmp::read_sidecar(path) {
+        Ok(xmp) => xmp?,
+        Err(e) => {
+            warn!("Failed to read the sidecar of {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let values = ExposureOverride {
+        exposure_bias: xmp
+            .get_text(&EXIF, "ExposureBiasValue")
+            .and_then(|v| parse_bias(&v)),
+        exposure_mode: xmp
+            .get_text(&EXIF, "ExposureMode")
+            .and_then(|v| v.trim().parse().ok()),
+    };
+    (values != ExposureOverride::default()).then_some(values)
+}
+
+/// Writes the given values to the sidecar of `path`, keeping everything else in it.
+pub fn write_override(path: &Path, values: ExposureOverride) -> io::Result<()> {
+    let mut xmp = Xmp::open_sidecar(path)?;
+    if let Some(bias) = values.exposure_bias {
+        // XMP rationals are always written with a denominator
+        let bias = format!("{}/{}", bias.numer(), bias.denom());
+        xmp.set_text(&EXIF, "ExposureBiasValue", &bias);
+    }
+    if let Some(mode) = values.exposure_mode {
+        xmp.set_text(&EXIF, "ExposureMode", &mode.to_string());
+    }
+    xmp.save()
+}
+
+/// Parses a single bias like `-2/3` or `1`.
+fn parse_bias(value: &str) -> Option<Rational32> {
+    match parse_exposure_sequence(value).as_slice() {
+        [bias] => Some(*bias),
+        _ => None,
+    }
+}
+
+struct RepairRow {
+    path: PathBuf,
+    recorded: ExposureOverride,
+    sidecar: Option<ExposureOverride>,
+    /// The bias to write, empty to leave the file alone
+    new_bias: String,
+}
+
+impl RepairRow {
+    fn new(path: PathBuf) -> Self {
+        let recorded = read_file_metadata(&path).map_or_else(ExposureOverride::default, |m| {
+            ExposureOverride {
+                exposure_bias: m.exposure_bias,
+                exposure_mode: m.exposure_mode,
+            }
+        });
+        let sidecar = read_override(&path);
+        Self {
+            path,
+            recorded,
+            sidecar,
+            new_bias: String::new(),
+        }
+    }
+}
+
+/// Window to write exposure biases into the sidecars of a set of files.
+pub struct ExposureRepair {
+    pub open: bool,
+    rows: Vec<RepairRow>,
+    set_auto_bracket: bool,
+    status: String,
+}
+
+impl Default for ExposureRepair {
+    fn default() -> Self {
+        Self {
+            open: false,
+            rows: Vec::new(),
+            set_auto_bracket: true,
+            status: String::new(),
+        }
+    }
+}
+
+impl ExposureRepair {
+    /// Opens the window for `paths`, reading their current values.
+    pub fn open_files(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        self.rows = paths.into_iter().map(RepairRow::new).collect();
+        self.status.clear();
+        self.open = true;
+    }
+
+    /// `sequence` is the exposure bias sequence of the main window, offered to
+    /// fill the new biases in shooting order.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        sequence: &str,
+        extensions: &[String],
+        ev_format: EvFormat,
+    ) {
+        if !self.open {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Repair Exposure Bias")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                self.show_contents(ui, sequence, extensions, ev_format)
+            });
+        self.open = open;
+    }
+
+    fn show_contents(
+        &mut self,
+        ui: &mut egui::Ui,
+        sequence: &str,
+        extensions: &[String],
+        ev_format: EvFormat,
+    ) {
+        ui.label(
+            "New values are written to the XMP sidecars, the files themselves are not changed.",
+        );
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Add Files…").clicked() {
+                if let Some(paths) = rfd::FileDialog::new()
+                    .add_filter("Raw Images", extensions)
+                    .pick_files()
+                {
+                    self.rows.extend(paths.into_iter().map(RepairRow::new));
+                }
+            }
+            if ui.button("Clear").clicked() {
+                self.rows.clear();
+            }
+            if ui
+                .button("Fill from Sequence")
+                .on_hover_text(format!(
+                    "Repeats {} over the files, in the order listed",
+                    sequence
+                ))
+                .clicked()
+            {
+                self.fill_from_sequence(sequence);
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .max_height(320.0)
+            .show(ui, |ui| {
+                egui::Grid::new("exposure_repair_grid")
+                    .striped(true)
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        ui.strong("Filename");
+                        ui.strong("Recorded");
+                        ui.strong("Sidecar");
+                        let header = ui.strong("New Bias");
+                        ui.end_row();
+
+                        for row in &mut self.rows {
+                            let name = row.path.file_name().unwrap_or_default().to_string_lossy();
+                            ui.label(name.as_ref());
+                            ui.label(describe(&row.recorded, ev_format));
+                            ui.label(
+                                row.sidecar
+                                    .as_ref()
+                                    .map_or("-".to_string(), |v| describe(v, ev_format)),
+                            );
+                            ui.add(
+                                egui::TextEdit::singleline(&mut row.new_bias)
+                                    .hint_text("e.g. -2/3")
+                                    .desired_width(80.0),
+                            )
+                            .labelled_by(header.id);
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        ui.checkbox(&mut self.set_auto_bracket, "Mark as auto bracket")
+            .on_hover_text(
+                "Also sets the exposure mode, so the auto-bracket filter accepts the files",
+            );
+        ui.horizontal(|ui| {
+            let pending = self.rows.iter().any(|r| !r.new_bias.trim().is_empty());
+            if ui
+                .add_enabled(pending, egui::Button::new("Write Sidecars"))
+                .clicked()
+            {
+                self.write();
+            }
+            ui.label(&self.status);
+        });
+    }
+
+    /// Fills in the first of the entered sequences, repeated over the files.
+    fn fill_from_sequence(&mut self, sequence: &str) {
+        let sequence = parse_exposure_sequences(sequence)
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        if sequence.is_empty() {
+            self.status = "The exposure bias sequence is empty or invalid".to_string();
+            return;
+        }
+        for (row, bias) in self.rows.iter_mut().zip(sequence.iter().cycle()) {
+            // A * has no value to repair to, the row keeps what was entered
+            if let Some(bias) = bias {
+                row.new_bias = format!("{}/{}", bias.numer(), bias.denom());
+            }
+        }
+    }
+
+    fn write(&mut self) {
+        let mut written = 0;
+        let mut errors = Vec::new();
+        for row in &mut self.rows {
+            let value = row.new_bias.trim();
+            if value.is_empty() {
+                continue;
+            }
+            let Some(bias) = parse_bias(value) else {
+                errors.push(format!("\"{}\" is not a bias", value));
+                continue;
+            };
+            let values = ExposureOverride {
+                exposure_bias: Some(bias),
+                exposure_mode: self.set_auto_bracket.then_some(AUTO_BRACKET),
+            };
+            match write_override(&row.path, values) {
+                Ok(()) => {
+                    written += 1;
+                    row.sidecar = read_override(&row.path);
+                    row.new_bias.clear();
+                }
+                Err(e) => errors.push(format!("{}: {}", row.path.display(), e)),
+            }
+        }
+        for error in &errors {
+            warn!("Failed to repair exposure bias, {}", error);
+        }
+        self.status = match errors.first() {
+            Some(error) => format!("{} written, {} failed: {}", written, errors.len(), error),
+            None => format!("{} sidecars written", written),
+        };
+    }
+}
+
+fn describe(values: &ExposureOverride, ev_format: EvFormat) -> String {
+    let bias = values.exposure_bias.map_or("no bias".to_string(), |b| {
+        format!("{} EV", ev_format.format(b))
+    });
+    match values.exposure_mode {
+        Some(mode) => format!("{}, {}", bias, crate::app::exposure_mode_to_string(mode)),
+        None => bias,
+    }
+}
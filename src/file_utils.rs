@@ -1,17 +1,57 @@
-use crate::app::{Action, EvMode};
-use chrono::{DateTime, Local};
+use crate::app::{Action, FileOrder};
+use crate::bracket_kind::{classify, BracketKind, CaptureSettings};
+use crate::catalog::{Catalog, CatalogFile, CatalogSequence};
+use crate::darktable;
+use crate::diagnostics::{
+    explain_no_matches, frame_count_anomalies, frame_count_histogram, NoMatchInput, ScanStats,
+};
+use crate::embedded_xmp;
+use crate::exposure_repair::AUTO_BRACKET;
+use crate::folder_metadata::{describe_folder, COMMENT_FILE_NAME};
+use crate::hdr_tools;
+use crate::journal::{self, JournalEntry};
+use crate::metadata_cache::{read_metadata, CachedMetadata, MetadataCache};
+use crate::output_writer::{RunOutput, SequenceEntry, TextfileConflict, TextfileFormat};
+use crate::script_actions;
+use crate::xmp::{self, Xmp};
+use chrono::{NaiveDateTime, TimeDelta};
+use ebo_core::{
+    biases_match_sequence, detect_brackets, find_bracket_patterns, relative_exposure,
+    without_overlaps, DeltaReference, EvFormat, EvMode, MatchMode,
+};
+use image::DynamicImage;
 use log::{debug, info, warn};
 use num_rational::Rational32;
-use num_traits::Zero;
 use rawler::decoders::{RawDecodeParams, RawMetadata};
 use rawler::{get_decoder, rawsource::RawSource};
+use serde::{Deserialize, Serialize};
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::io::Write;
+use std::io;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
 
-pub fn count_files_in_directory(dir: &Path, extensions: &Vec<String>) -> usize {
+/// Files modified within this window may still be in the middle of a copy
+const STABILITY_WINDOW: Duration = Duration::from_secs(2);
+const DEFERRED_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_DEFERRED_RETRIES: u32 = 30;
+
+/// Keyword added to every file of a tagged sequence, next to its group identifier
+const BRACKET_KEYWORD: &str = "exposure-bracket";
+
+/// Parent of the hierarchical Lightroom keyword and digiKam tag of each sequence
+const PARENT_KEYWORD: &str = "Bracket";
+
+/// Scenes are told apart at pauses longer than this when detecting brackets
+/// without a sequence, unless bursts are split at another pause
+const AUTO_DETECT_GAP: TimeDelta = TimeDelta::seconds(3);
+
+pub fn count_files_in_directory(dir: &Path, extensions: &[String]) -> usize {
     let entries = match fs::read_dir(dir) {
         Ok(e) => e,
         Err(_) => return 0,
@@ -20,253 +60,2810 @@ pub fn count_files_in_directory(dir: &Path, extensions: &Vec<String>) -> usize {
         .flatten()
         .filter(|e| {
             let path = e.path();
-            if !path.is_file() {
-                return false;
-            }
-            path.extension()
-                .and_then(|s| s.to_str())
-                .map(|s| extensions.iter().any(|ext| ext.eq_ignore_ascii_case(s)))
-                .unwrap_or(false)
+            path.is_file() && has_matching_extension(&path, extensions)
         })
         .count()
 }
 
+/// `root` and its subfolders down to `max_depth` levels, 0 for just `root`.
+/// Hidden folders, e.g. staging folders, and sequence folders made by
+/// moving are left out, so an organized archive can be scanned again.
+pub fn collect_directories(root: &Path, max_depth: usize) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    collect_subdirectories(root, max_depth, &mut dirs);
+    dirs
+}
+
+fn collect_subdirectories(dir: &Path, depth: usize, dirs: &mut Vec<PathBuf>) {
+    if depth == 0 {
+        return;
+    }
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read directory {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    let mut subdirs: Vec<PathBuf> = entries
+        .flatten()
+        // No symlinks, they could lead in a circle
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|e| e.path())
+        .filter(|p| {
+            !p.file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    subdirs.sort();
+    for subdir in subdirs {
+        if is_sequence_folder(&subdir) {
+            debug!("Skipping {}, it holds a sequence", subdir.display());
+            continue;
+        }
+        dirs.push(subdir.clone());
+        collect_subdirectories(&subdir, depth - 1, dirs);
+    }
+}
+
+/// Whether `dir` is named after one of its files like the folders
+/// [`move_sequence_to_folder`] creates. With a [`ProcessSettings::folder_name`]
+/// like `{date} {name}`, the file name is a word of the folder name.
+pub fn is_sequence_folder(dir: &Path) -> bool {
+    let Some(name) = dir.file_name().map(|n| n.to_string_lossy()) else {
+        return false;
+    };
+    let names: Vec<&str> = BracketKind::ALL
+        .iter()
+        .filter_map(|kind| name.strip_prefix(kind.folder_prefix()))
+        .collect();
+    fs::read_dir(dir).is_ok_and(|entries| {
+        entries.flatten().any(|e| {
+            let path = e.path();
+            path.file_stem().is_some_and(|stem| {
+                let stem = stem.to_string_lossy();
+                names.iter().any(|name| contains_word(name, &stem))
+            })
+        })
+    })
+}
+
+/// Whether `word` is in `text` with nothing but separators like spaces, `-`
+/// and `_` around it.
+fn contains_word(text: &str, word: &str) -> bool {
+    !word.is_empty()
+        && text.match_indices(word).any(|(start, _)| {
+            let before = text[..start].chars().next_back();
+            let after = text[start + word.len()..].chars().next();
+            [before, after]
+                .iter()
+                .all(|c| c.is_none_or(|c| c.is_whitespace() || matches!(c, '-' | '_' | '.')))
+        })
+}
+
+/// Counts the files of `dir` by their lowercase extension, for previewing
+/// what a scan would see. Files without extension are counted under "".
+pub fn count_files_by_extension(dir: &Path) -> io::Result<BTreeMap<String, usize>> {
+    let mut counts = BTreeMap::new();
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        *counts.entry(extension).or_default() += 1;
+    }
+    Ok(counts)
+}
+
+pub fn has_matching_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map(|s| extensions.iter().any(|ext| ext.eq_ignore_ascii_case(s)))
+        .unwrap_or(false)
+}
+
 pub fn extract_raw_metadata(path: &Path) -> Option<RawMetadata> {
     let path_str = path.display().to_string();
     log::debug!("Processing file: {}", path_str);
 
-    let raw_file = RawSource::new(path.as_ref()).ok()?;
+    let raw_file = RawSource::new(path).ok()?;
     let decoder = get_decoder(&raw_file).ok()?;
     decoder
         .raw_metadata(&raw_file, &RawDecodeParams::default())
         .ok()
 }
 
+/// Returns the embedded thumbnail or preview of a raw file, scaled to fit
+/// into `size` x `size` pixels.
+pub fn load_thumbnail(path: &Path, size: u32) -> Option<DynamicImage> {
+    let raw_file = RawSource::new(path).ok()?;
+    let decoder = get_decoder(&raw_file).ok()?;
+    let params = RawDecodeParams::default();
+    let image = match decoder.thumbnail_image(&raw_file, &params) {
+        Ok(Some(image)) => image,
+        _ => decoder.preview_image(&raw_file, &params).ok()??,
+    };
+    Some(image.thumbnail(size, size))
+}
+
+/// Shows a folder in the system file manager.
+pub fn open_in_file_manager(path: &Path) {
+    open_with_system(path);
+}
+
+/// Opens a file in the application the system associates with it.
+pub fn open_file(path: &Path) {
+    open_with_system(path);
+}
+
+fn open_with_system(path: &Path) {
+    #[cfg(windows)]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let program = "xdg-open";
+
+    if let Err(e) = std::process::Command::new(program).arg(path).spawn() {
+        warn!("Failed to open {}: {}", path.display(), e);
+    }
+}
+
+/// Parses the EXIF DateTimeOriginal (plus optional SubSecTimeOriginal) of a file.
+pub fn capture_time(raw_metadata: &RawMetadata) -> Option<NaiveDateTime> {
+    parse_exif_time(
+        raw_metadata.exif.date_time_original.as_deref()?,
+        raw_metadata.exif.sub_sec_time_original.as_deref(),
+    )
+}
+
+/// Parses an EXIF date like `2024:05:01 12:30:00` with its SubSecTime digits.
+pub fn parse_exif_time(date_time: &str, sub_sec: Option<&str>) -> Option<NaiveDateTime> {
+    let parsed = NaiveDateTime::parse_from_str(date_time.trim(), "%Y:%m:%d %H:%M:%S").ok()?;
+    let sub_sec = sub_sec
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()));
+    match sub_sec {
+        Some(digits) => {
+            // SubSecTime is a fraction written as digits, e.g. "05" = 0.05 s
+            let digits = &digits[..digits.len().min(9)];
+            let nanos: u32 = format!("{:0<9}", digits).parse().ok()?;
+            Some(parsed + TimeDelta::nanoseconds(nanos as i64))
+        }
+        None => Some(parsed),
+    }
+}
+
+#[derive(Clone)]
 struct FileMetadata {
     path: PathBuf,
-    //creation_time: DateTime<Local>,
+    capture_time: Option<NaiveDateTime>,
     exposure_bias: Option<Rational32>,
-    exposure_mode: Option<u16>,
+    camera: Option<String>,
+    capture_settings: Option<CaptureSettings>,
+    image_number: Option<u32>,
+    /// Position within an auto exposure bracket, as recorded in the maker notes
+    bracket_shot: Option<u16>,
+    /// What put the file at its position, set when sorting by capture time
+    ordered_by: FrameOrdering,
 }
 
-pub fn process_directory(
-    dir: &Path,
-    processed_files: &Arc<AtomicUsize>,
-    exposure_bracketings_found: &Arc<AtomicUsize>,
-    extensions: Vec<String>,
-    sequence: Vec<Rational32>,
-    selected_action: Action,
-    ev_mode: EvMode,
-    filter_by_auto_bracket: bool,
-) {
-    let files_with_metadata =
-        collect_files_with_metadata(dir, processed_files, &extensions, filter_by_auto_bracket);
+impl FileMetadata {
+    fn new(path: &Path, metadata: Option<&CachedMetadata>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            capture_time: metadata.and_then(|m| m.capture_time),
+            exposure_bias: metadata.and_then(|m| m.exposure_bias),
+            camera: metadata.and_then(|m| m.camera.clone()),
+            capture_settings: metadata.and_then(|m| m.capture_settings.clone()),
+            image_number: metadata.and_then(|m| m.image_number),
+            bracket_shot: metadata
+                .and_then(|m| m.maker_note_bracket.as_ref())
+                .and_then(|b| b.shot),
+            ordered_by: FrameOrdering::Listed,
+        }
+    }
+}
+
+/// What the frames of a sequence were put in order by, from the most to the
+/// least reliable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameOrdering {
+    /// Their capture times, down to the sub-second where it is recorded
+    CaptureTime,
+    /// The shutter count, for frames shot within the same second
+    ShutterCount,
+    /// The number in the file names, for frames shot within the same second
+    FileNumber,
+    /// The order they were selected or listed in
+    Listed,
+}
+
+impl std::fmt::Display for FrameOrdering {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameOrdering::CaptureTime => write!(f, "capture time"),
+            FrameOrdering::ShutterCount => write!(f, "shutter count"),
+            FrameOrdering::FileNumber => write!(f, "file number"),
+            FrameOrdering::Listed => write!(f, "listed order"),
+        }
+    }
+}
 
-    // Just relying on the order in the filesystem is good enough
-    // A timestamp can be ambiguous as well
-    //files_with_metadata.sort_by_key(|f| f.creation_time);
+/// Sorts `files` by capture time, files without one keep their relative order
+/// at the end. Frames of one camera sharing a capture time, e.g. a fast burst
+/// from a camera that records no sub-seconds, are ordered by their shutter
+/// count, else by the number in their file names, else kept as listed.
+fn sort_by_capture_time(files: &mut [FileMetadata]) {
+    // Stable sort, ties keep their relative order
+    files.sort_by_key(|f| (f.capture_time.is_none(), f.capture_time));
+    let mut start = 0;
+    while start < files.len() {
+        let time = files[start].capture_time;
+        let end = files[start..]
+            .iter()
+            .position(|f| f.capture_time != time)
+            .map_or(files.len(), |n| start + n);
+        let ties = &mut files[start..end];
+        let ordering = match (time, ties.len()) {
+            (None, _) => FrameOrdering::Listed,
+            (Some(_), 1) => FrameOrdering::CaptureTime,
+            (Some(time), _) => order_ties(ties, time),
+        };
+        for file in ties {
+            file.ordered_by = ordering;
+        }
+        start = end;
+    }
+}
+
+/// Sorts `files` by their file names, whatever folder they are in.
+fn sort_by_file_name(files: &mut [FileMetadata]) {
+    files.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
+}
+
+/// Sorts `files` by their file names, numbers by their value, see [`natural_cmp`].
+fn sort_by_natural_file_name(files: &mut [FileMetadata]) {
+    let name = |f: &FileMetadata| {
+        f.path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+    files.sort_by(|a, b| natural_cmp(&name(a), &name(b)));
+}
+
+fn order_ties(ties: &mut [FileMetadata], time: NaiveDateTime) -> FrameOrdering {
+    // Counters of different cameras say nothing about each other
+    let one_camera = ties.windows(2).all(|w| w[0].camera == w[1].camera);
+    let ordering = if !one_camera {
+        FrameOrdering::Listed
+    } else if distinct(ties.iter().map(|f| f.image_number)) {
+        ties.sort_by_key(|f| f.image_number);
+        FrameOrdering::ShutterCount
+    } else if distinct(ties.iter().map(|f| file_number(&f.path))) {
+        ties.sort_by_key(|f| file_number(&f.path));
+        FrameOrdering::FileNumber
+    } else {
+        FrameOrdering::Listed
+    };
+    debug!(
+        "{} frames shot at {}, ordered by {}",
+        ties.len(),
+        time,
+        ordering
+    );
+    ordering
+}
 
-    let matching_sequences = find_matching_sequences(&files_with_metadata, &sequence, ev_mode);
+/// Whether every key is known and none repeats.
+fn distinct(keys: impl Iterator<Item = Option<u32>>) -> bool {
+    let mut seen = HashSet::new();
+    keys.into_iter()
+        .all(|key| key.is_some_and(|key| seen.insert(key)))
+}
+
+/// The last number in the file name, e.g. 1234 for `DSC01234.ARW`.
+fn file_number(path: &Path) -> Option<u32> {
+    let stem = path.file_stem()?.to_str()?;
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = stem[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    stem[start..end].parse().ok()
+}
 
-    for seq in matching_sequences {
-        exposure_bracketings_found.fetch_add(1, Ordering::Relaxed);
-        execute_action_on_sequence(dir, seq, selected_action.clone());
+/// Compares file names with runs of digits compared by their value, e.g.
+/// `IMG_9999` before `IMG_10000`, and letters regardless of their case. Names
+/// equal by value, like `1` and `01`, fall back to comparing them as they are.
+fn natural_cmp(a: &str, b: &str) -> cmp::Ordering {
+    let (mut left, mut right) = (a, b);
+    while let (Some(l), Some(r)) = (left.chars().next(), right.chars().next()) {
+        let ordering = if l.is_ascii_digit() && r.is_ascii_digit() {
+            let (l_digits, l_rest) = split_digits(left);
+            let (r_digits, r_rest) = split_digits(right);
+            let (l_value, r_value) = (
+                l_digits.trim_start_matches('0'),
+                r_digits.trim_start_matches('0'),
+            );
+            left = l_rest;
+            right = r_rest;
+            l_value.len().cmp(&r_value.len()).then(l_value.cmp(r_value))
+        } else {
+            left = &left[l.len_utf8()..];
+            right = &right[r.len_utf8()..];
+            l.to_lowercase().cmp(r.to_lowercase())
+        };
+        if ordering != cmp::Ordering::Equal {
+            return ordering;
+        }
     }
+    left.len().cmp(&right.len()).then_with(|| a.cmp(b))
 }
 
-fn collect_files_with_metadata(
+/// The leading digits of `s` and the rest.
+fn split_digits(s: &str) -> (&str, &str) {
+    s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+}
+
+/// What a job reports to its subscribers, see [`JobProgress::subscribe`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    FilesCounted {
+        total: usize,
+    },
+    FileProcessed {
+        path: PathBuf,
+    },
+    SequenceFound {
+        kind: BracketKind,
+        files: Vec<PathBuf>,
+    },
+    Error {
+        message: String,
+    },
+    Paused,
+    Resumed,
+    Finished {
+        sequences_found: usize,
+        errors: usize,
+        cancelled: bool,
+    },
+}
+
+/// Counters and flags shared between a running job and whoever started it.
+///
+/// The counters can be polled, or followed as typed events through
+/// [`JobProgress::subscribe`].
+#[derive(Clone, Default)]
+pub struct JobProgress {
+    pub total_files: Arc<AtomicUsize>,
+    pub processed_files: Arc<AtomicUsize>,
+    pub exposure_bracketings_found: Arc<AtomicUsize>,
+    pub running: Arc<AtomicBool>,
+    pub cancel_requested: Arc<AtomicBool>,
+    /// Whether the job is paused, and the signal to wake it up
+    pause: Arc<(Mutex<bool>, Condvar)>,
+    subscribers: Arc<Mutex<Vec<Sender<ProgressEvent>>>>,
+}
+
+impl JobProgress {
+    /// Marks the start of a new job.
+    pub fn start(&self) {
+        self.running.store(true, Ordering::Relaxed);
+        self.cancel_requested.store(false, Ordering::Relaxed);
+        *self.pause.0.lock().unwrap() = false;
+        self.total_files.store(0, Ordering::Relaxed);
+        self.processed_files.store(0, Ordering::Relaxed);
+        self.exposure_bracketings_found.store(0, Ordering::Relaxed);
+    }
+
+    /// Asks the job to stop. Reading stops at the next file, actions only
+    /// between two sequences, so no sequence is left half processed.
+    pub fn cancel(&self) {
+        info!("Cancelling the run");
+        self.cancel_requested.store(true, Ordering::Relaxed);
+        // Wakes up a paused job, so it can stop
+        let (state, resumed) = &*self.pause;
+        let _paused = state.lock().unwrap();
+        resumed.notify_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_requested.load(Ordering::Relaxed)
+    }
+
+    /// Pauses or resumes the job. A paused job holds at the next file or
+    /// between two sequences, e.g. to give a slow NAS a break.
+    pub fn set_paused(&self, paused: bool) {
+        let (state, resumed) = &*self.pause;
+        let mut current = state.lock().unwrap();
+        if *current == paused {
+            return;
+        }
+        *current = paused;
+        resumed.notify_all();
+        drop(current);
+        if paused {
+            info!("Pausing the run");
+            self.emit(ProgressEvent::Paused);
+        } else {
+            info!("Resuming the run");
+            self.emit(ProgressEvent::Resumed);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.pause.0.lock().unwrap()
+    }
+
+    /// Blocks while the job is paused. Cancelling ends the pause too.
+    fn wait_while_paused(&self) {
+        let (state, resumed) = &*self.pause;
+        let mut paused = state.lock().unwrap();
+        while *paused && !self.is_cancelled() {
+            paused = resumed.wait(paused).unwrap();
+        }
+    }
+
+    /// Receives the events of this job from now on, until the receiver is dropped.
+    pub fn subscribe(&self) -> Receiver<ProgressEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    fn emit(&self, event: ProgressEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if subscribers.is_empty() {
+            return;
+        }
+        // Forget subscribers that stopped listening
+        subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    pub fn set_total_files(&self, total: usize) {
+        self.total_files.store(total, Ordering::Relaxed);
+        self.emit(ProgressEvent::FilesCounted { total });
+    }
+
+    fn file_processed(&self, path: &Path) {
+        self.processed_files.fetch_add(1, Ordering::Relaxed);
+        self.emit(ProgressEvent::FileProcessed {
+            path: path.to_path_buf(),
+        });
+    }
+
+    fn sequence_found(&self, kind: BracketKind, sequence: &[FileMetadata]) {
+        self.exposure_bracketings_found
+            .fetch_add(1, Ordering::Relaxed);
+        self.emit(ProgressEvent::SequenceFound {
+            kind,
+            files: sequence.iter().map(|f| f.path.clone()).collect(),
+        });
+    }
+
+    fn errors(&self, messages: &[String]) {
+        for message in messages {
+            self.emit(ProgressEvent::Error {
+                message: message.clone(),
+            });
+        }
+    }
+
+    /// Marks the end of the job.
+    pub fn finish(&self, report: &RunReport) {
+        self.running.store(false, Ordering::Relaxed);
+        self.emit(ProgressEvent::Finished {
+            sequences_found: report.sequences.len(),
+            errors: report.errors.len(),
+            cancelled: self.is_cancelled(),
+        });
+    }
+}
+
+/// The sequences found by a run. Serializes to the same JSON layout that
+/// `sequence_list::load_sequence_list` reads.
+#[derive(Debug, Default, Serialize)]
+pub struct RunReport {
+    pub sequences: Vec<SequenceReport>,
+    pub errors: Vec<String>,
+    /// Likely causes when a run found no sequences
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<String>,
+    /// Bursts the files were split into, if burst splitting was on
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bursts: Vec<BurstReport>,
+    /// Scanned subfolders removed because the run moved everything out of them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed_folders: Vec<PathBuf>,
+    /// Number of sequences by their number of frames, see [`RunReport::summarize`]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub frame_counts: BTreeMap<usize, usize>,
+    /// Sequences with an unusual number of frames that are worth reviewing
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub anomalies: Vec<String>,
+}
+
+/// A run of frames shot without a longer pause, and the sequences found in it.
+#[derive(Debug, Serialize)]
+pub struct BurstReport {
+    pub start: Option<NaiveDateTime>,
+    pub camera: Option<String>,
+    pub files: usize,
+    pub sequences: usize,
+    /// Files of the burst that are part of no sequence
+    pub unmatched: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SequenceReport {
+    pub files: Vec<PathBuf>,
+    pub kind: BracketKind,
+    /// The folder the sequence is moved to, or the text file it is listed in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination: Option<PathBuf>,
+    /// Note or tag given to the sequence during review
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Exposure bias of each file as written in the chosen format, empty
+    /// where unknown
+    pub biases: Vec<String>,
+    /// What the frames were put in order by, the least reliable of them
+    pub ordered_by: FrameOrdering,
+    /// The sequence an exposure bracket follows, when several were entered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Frame count and step of a bracket found without a sequence, e.g.
+    /// "3 frames in 1 EV steps: 0, -1, 1"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detected: Option<String>,
+}
+
+impl RunReport {
+    fn add_sequence(
+        &mut self,
+        sequence: &[FileMetadata],
+        kind: BracketKind,
+        settings: &ProcessSettings,
+    ) {
+        self.sequences.push(SequenceReport {
+            files: sequence.iter().map(|f| f.path.clone()).collect(),
+            kind,
+            destination: destination(sequence, kind, settings),
+            note: sequence_note(sequence, settings).map(str::to_string),
+            biases: sequence
+                .iter()
+                .map(|f| {
+                    f.exposure_bias
+                        .map_or_else(String::new, |bias| settings.ev_format.format(bias))
+                })
+                .collect(),
+            ordered_by: sequence
+                .iter()
+                .map(|f| f.ordered_by)
+                .max()
+                .unwrap_or(FrameOrdering::Listed),
+            pattern: (kind == BracketKind::Exposure && settings.sequences.len() > 1)
+                .then(|| matched_sequence(sequence, settings))
+                .flatten()
+                .map(format_pattern),
+            detected: (kind == BracketKind::Exposure && settings.auto_detect)
+                .then(|| describe_detected(sequence, settings))
+                .flatten(),
+        });
+    }
+
+    /// Adds the results of another folder of the same run, its diagnostics
+    /// labeled with the folder they are about.
+    fn extend(&mut self, other: RunReport, folder: Option<&Path>) {
+        self.sequences.extend(other.sequences);
+        self.errors.extend(other.errors);
+        self.diagnostics
+            .extend(other.diagnostics.into_iter().map(|cause| match folder {
+                Some(folder) => format!("{}: {}", folder.display(), cause),
+                None => cause,
+            }));
+        self.bursts.extend(other.bursts);
+        self.removed_folders.extend(other.removed_folders);
+    }
+
+    /// Fills in the frame count histogram and its anomalies once the run is done.
+    pub fn summarize(&mut self) {
+        self.frame_counts = frame_count_histogram(&self.sequences);
+        self.anomalies = frame_count_anomalies(&self.sequences);
+        for anomaly in &self.anomalies {
+            warn!("{}", anomaly);
+        }
+    }
+}
+
+/// Folders moved sequences are gathered in by their capture time, next to
+/// the scanned files. Existing date folders are reused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateFolders {
+    /// Each sequence folder directly next to the files
+    #[default]
+    Off,
+    /// `2024-05-01/<sequence>`
+    Day,
+    /// `2024-05-01/14/<sequence>`
+    Hour,
+    /// `2024/05/01/<sequence>`, a chronological archive of a whole card dump
+    YearMonthDay,
+}
+
+impl DateFolders {
+    /// The folder a sequence shot at `time` goes into, relative to the scanned folder.
+    fn path(&self, time: NaiveDateTime) -> Option<PathBuf> {
+        match self {
+            DateFolders::Off => None,
+            DateFolders::Day => Some(PathBuf::from(time.format("%Y-%m-%d").to_string())),
+            DateFolders::Hour => Some(
+                Path::new(&time.format("%Y-%m-%d").to_string()).join(time.format("%H").to_string()),
+            ),
+            DateFolders::YearMonthDay => Some(
+                Path::new(&time.format("%Y").to_string())
+                    .join(time.format("%m").to_string())
+                    .join(time.format("%d").to_string()),
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for DateFolders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DateFolders::Off => write!(f, "No Date Folders"),
+            DateFolders::Day => write!(f, "Per Day"),
+            DateFolders::Hour => write!(f, "Per Day and Hour"),
+            DateFolders::YearMonthDay => write!(f, "Per Year, Month and Day"),
+        }
+    }
+}
+
+/// Lets the auto-bracket filter accept another exposure mode from a camera,
+/// for bodies that report e.g. manual exposure during AEB.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BracketModeOverride {
+    /// Part of the make and model, matched case-insensitively, e.g. "ILCE-7RM5"
+    pub camera: String,
+    pub exposure_mode: u16,
+}
+
+impl BracketModeOverride {
+    fn applies_to(&self, camera: Option<&str>, exposure_mode: Option<u16>) -> bool {
+        exposure_mode == Some(self.exposure_mode) && camera_matches(&self.camera, camera)
+    }
+}
+
+/// Corrects the clock of one camera of a multi-body shoot, so its files
+/// order and group correctly with those of the other bodies.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClockOffset {
+    /// Part of the make and model, matched case-insensitively, e.g. "ILCE-7RM5"
+    pub camera: String,
+    /// Added to the capture times of the camera, negative for a clock running ahead
+    pub seconds: i64,
+}
+
+/// Whether a camera matches `pattern`, a part of its make and model.
+pub fn camera_matches(pattern: &str, camera: Option<&str>) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+    !pattern.is_empty() && camera.is_some_and(|c| c.to_lowercase().contains(&pattern))
+}
+
+/// Shifts the capture times of cameras with a clock offset, the first
+/// matching offset wins.
+fn apply_clock_offsets(files: &mut [FileMetadata], offsets: &[ClockOffset]) {
+    if offsets.is_empty() {
+        return;
+    }
+    let mut corrected = 0;
+    for file in files {
+        let offset = offsets
+            .iter()
+            .find(|o| camera_matches(&o.camera, file.camera.as_deref()));
+        if let (Some(offset), Some(time)) = (offset, file.capture_time) {
+            file.capture_time = Some(time + TimeDelta::seconds(offset.seconds));
+            corrected += 1;
+        }
+    }
+    info!(
+        "Corrected the capture time of {} files by a clock offset",
+        corrected
+    );
+}
+
+/// Whether the auto-bracket filter lets a file through.
+pub fn is_auto_bracket(metadata: &CachedMetadata, overrides: &[BracketModeOverride]) -> bool {
+    metadata.exposure_mode == Some(AUTO_BRACKET)
+        || metadata.maker_note_bracket.is_some()
+        || overrides
+            .iter()
+            .any(|o| o.applies_to(metadata.camera.as_deref(), metadata.exposure_mode))
+}
+
+#[derive(Clone)]
+pub struct ProcessSettings {
+    pub extensions: Vec<String>,
+    /// The exposure bias sequences searched for, usually one; where several
+    /// match at the same file the longest is taken. `None` stands for a `*`
+    pub sequences: Vec<Vec<Option<Rational32>>>,
+    /// Executed on each sequence, the ones organizing it into a folder first
+    pub selected_actions: Vec<Action>,
+    pub ev_mode: EvMode,
+    /// The frame deltas are measured from in delta mode
+    pub delta_reference: DeltaReference,
+    pub filter_by_auto_bracket: bool,
+    /// Exposure modes the auto-bracket filter also accepts from some cameras
+    pub bracket_mode_overrides: Vec<BracketModeOverride>,
+    /// Applied to capture times before sorting and burst splitting
+    pub clock_offsets: Vec<ClockOffset>,
+    pub use_metadata_cache: bool,
+    /// Write group tags and Lightroom keywords into DNG and JPEG files
+    /// themselves instead of sidecars
+    pub embed_xmp: bool,
+    /// Commands of the fusion scripts, see [`hdr_tools::write_fusion_script`]
+    pub fusion_command: String,
+    /// The HDRMerge executable the HDRMerge commands run
+    pub hdrmerge_path: String,
+    /// Run per sequence by "Run Command", see [`script_actions::fill_command`]
+    pub command_template: String,
+    /// What to do when another run writes the same sequences.txt
+    pub textfile_conflict: TextfileConflict,
+    /// The layout, and with it the name, of the sequences file
+    pub textfile_format: TextfileFormat,
+    /// Empty the sequences file before writing the run's first sequence, so
+    /// runs over the same folder don't list its sequences again
+    pub overwrite_textfile: bool,
+    /// Date created sequence folders by their capture time and add a comment
+    /// for file managers, see [`describe_folder`]
+    pub folder_metadata: bool,
+    /// Gather moved sequences in per-day, per-hour or year/month/day folders
+    pub date_folders: DateFolders,
+    /// Name of the sequence folders, see [`fill_folder_name`]
+    pub folder_name: String,
+    /// Order the files of a folder are searched in, as the filesystem lists
+    /// them unless sorted
+    pub folder_order: FileOrder,
+    /// Only try windows whose reference frame has this bias, see [`find_matching_sequences`]
+    pub anchor_bias: Option<Rational32>,
+    /// Biases closer than this to the expected value still match, e.g. 2/3
+    /// for a 7/10 in the sequence
+    pub ev_tolerance: Rational32,
+    /// Whether a file can be part of two matched sequences
+    pub match_mode: MatchMode,
+    /// Also look for aperture, white balance and flash brackets among the
+    /// files left over by exposure bracket detection
+    pub detect_other_brackets: bool,
+    /// Match on the exposure computed from shutter speed, aperture and ISO
+    /// instead of the recorded bias, for manual brackets
+    pub computed_exposure: bool,
+    /// Search the files of each camera on their own, so no sequence mixes the
+    /// frames of two bodies shot at the same time, see [`group_by_camera`]
+    pub group_per_camera: bool,
+    /// Find brackets by their evenly spaced biases instead of the entered
+    /// sequences, within bursts, see [`detect_brackets`]
+    pub auto_detect: bool,
+    /// Split the files into bursts at pauses longer than this and only match
+    /// sequences within a burst, see [`split_into_bursts`]
+    pub burst_gap: Option<TimeDelta>,
+    /// Reject matches whose first and last frame were shot further apart, as
+    /// unrelated shots whose biases happen to follow the sequence
+    pub max_sequence_span: Option<TimeDelta>,
+    /// Files left out of detection, e.g. test shots excluded by the user
+    pub excluded_files: HashSet<PathBuf>,
+    /// Only report what the action would do, nothing is changed
+    pub dry_run: bool,
+    /// Notes given to sequences during review, by the first file of the sequence
+    pub sequence_notes: HashMap<PathBuf, String>,
+    /// First files of the sequences rejected during review, left as they are
+    pub rejected_sequences: HashSet<PathBuf>,
+    /// Where sequence folders are created, `None` for next to their files
+    pub destination_root: Option<PathBuf>,
+    /// List the files of the organized folders with their hashes afterwards,
+    /// see [`crate::archive_manifest`]
+    pub write_manifest: bool,
+    /// Remove scanned subfolders left empty by moving their sequences out
+    pub remove_empty_folders: bool,
+    /// Move or copy the XMP sidecars of a sequence's files along with them,
+    /// so edits made in Lightroom or darktable stay with their raws
+    pub move_sidecars: bool,
+    /// Move or copy the files named like a sequence's files along with them,
+    /// e.g. the JPEGs of raws and their sidecars, see [`companion_files`]
+    pub move_companions: bool,
+    /// How biases are written in folder comments and diagnostics
+    pub ev_format: EvFormat,
+}
+
+pub fn process_directory(
     dir: &Path,
-    processed_files: &Arc<AtomicUsize>,
-    extensions: &Vec<String>,
-    filter_by_auto_bracket: bool,
-) -> Vec<FileMetadata> {
+    progress: &JobProgress,
+    settings: ProcessSettings,
+) -> RunReport {
     let entries = match fs::read_dir(dir) {
         Ok(e) => e,
         Err(e) => {
-            warn!("Failed to read directory {}: {}", dir.display(), e);
-            return Vec::new();
+            let mut report = RunReport::default();
+            record_error(
+                &mut report.errors,
+                format!("Failed to read directory {}: {}", dir.display(), e),
+            );
+            return report;
         }
     };
 
-    let mut files_with_metadata: Vec<FileMetadata> = Vec::new();
+    let paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
 
-    for entry in entries.flatten() {
-        processed_files.fetch_add(1, Ordering::Relaxed);
-        let path = entry.path();
-        if path.is_file() {
-            let ext_match = path
-                .extension()
-                .and_then(|e| e.to_str())
-                .map(|s| s.to_lowercase())
-                .map(|s| extensions.iter().any(|pat| pat == &s))
-                .unwrap_or(false);
-
-            if ext_match {
-                if let Ok(metadata) = fs::metadata(&path) {
-                    if let Ok(created) = metadata.created() {
-                        let datetime: DateTime<Local> = created.into();
-                        if let Some(raw_metadata) = extract_raw_metadata(&path) {
-                            let exposure_bias = raw_metadata
-                                .exif
-                                .exposure_bias
-                                .map(|eb| Rational32::new(eb.n, eb.d));
-                            let exposure_mode = raw_metadata.exif.exposure_mode;
-
-                            if filter_by_auto_bracket {
-                                if let Some(mode) = exposure_mode {
-                                    if mode != 2 {
-                                        continue;
-                                    }
-                                } else {
-                                    continue;
-                                }
-                            }
-
-                            files_with_metadata.push(FileMetadata {
-                                path: path.clone(),
-                                //creation_time: datetime,
-                                exposure_bias,
-                                exposure_mode,
-                            });
-                        }
-                    }
-                }
-            }
+    let order = settings.folder_order.clone();
+    process_files(&paths, order, progress, settings)
+}
+
+/// Processes several folders in one run. Each folder is searched on its own,
+/// so a sequence never spans two of them.
+pub fn process_directories(
+    dirs: &[PathBuf],
+    progress: &JobProgress,
+    settings: ProcessSettings,
+) -> RunReport {
+    let mut report = RunReport::default();
+    for dir in dirs {
+        progress.wait_while_paused();
+        if progress.is_cancelled() {
+            break;
+        }
+        // Parent folders of a scanned tree often hold no images themselves
+        if dirs.len() > 1 && count_files_in_directory(dir, &settings.extensions) == 0 {
+            continue;
         }
+        let folder = (dirs.len() > 1).then_some(dir.as_path());
+        report.extend(process_directory(dir, progress, settings.clone()), folder);
     }
-    files_with_metadata
+    // The causes of folders without sequences are moot if others had some
+    if !report.sequences.is_empty() {
+        report.diagnostics.clear();
+    }
+    if settings.remove_empty_folders
+        && settings.selected_actions.contains(&Action::MoveToFolder)
+        && !settings.dry_run
+    {
+        remove_emptied_folders(dirs, &mut report);
+    }
+    report
 }
 
-fn find_matching_sequences<'a>(
-    files: &'a [FileMetadata],
-    sequence: &[Rational32],
-    ev_mode: EvMode,
-) -> Vec<&'a [FileMetadata]> {
-    let sequence_len = sequence.len();
-    if sequence_len == 0 {
-        warn!("Sequence length is zero, cannot process.");
-        return Vec::new();
+/// Removes the scanned subfolders the run moved sequences out of once they
+/// are empty, and then their parents if those are empty too, e.g. the `DCIM`
+/// tree of a card dump. The picked folders themselves and folders that were
+/// empty before are kept.
+fn remove_emptied_folders(dirs: &[PathBuf], report: &mut RunReport) {
+    let subfolders: HashSet<&Path> = dirs
+        .iter()
+        .filter(|dir| {
+            dirs.iter()
+                .any(|other| other != *dir && dir.starts_with(other))
+        })
+        .map(PathBuf::as_path)
+        .collect();
+    // Deepest first, so a parent is only checked after its subfolders
+    let mut candidates: BTreeSet<(usize, PathBuf)> = report
+        .sequences
+        .iter()
+        .flat_map(|s| s.files.iter())
+        .filter_map(|file| file.parent())
+        .filter(|dir| subfolders.contains(dir))
+        .map(|dir| (dir.components().count(), dir.to_path_buf()))
+        .collect();
+    while let Some((_, dir)) = candidates.pop_last() {
+        let is_empty = fs::read_dir(&dir).is_ok_and(|mut entries| entries.next().is_none());
+        if !is_empty {
+            continue;
+        }
+        if let Err(e) = fs::remove_dir(&dir) {
+            warn!("Failed to remove empty folder {}: {}", dir.display(), e);
+            continue;
+        }
+        info!("Removed empty folder {}", dir.display());
+        journal::record(JournalEntry::RemoveFolder { path: dir.clone() });
+        if let Some(parent) = dir.parent().filter(|parent| subfolders.contains(parent)) {
+            candidates.insert((parent.components().count(), parent.to_path_buf()));
+        }
+        report.removed_folders.push(dir);
     }
+}
+
+pub fn process_files(
+    paths: &[PathBuf],
+    order: FileOrder,
+    progress: &JobProgress,
+    mut settings: ProcessSettings,
+) -> RunReport {
+    let mut report = RunReport::default();
+    let mut cache = if settings.use_metadata_cache {
+        MetadataCache::load()
+    } else {
+        MetadataCache::default()
+    };
+
+    let mut stats = ScanStats::default();
+    let mut files_with_metadata = collect_files_with_metadata(
+        paths,
+        progress,
+        &mut cache,
+        &settings.extensions,
+        settings
+            .filter_by_auto_bracket
+            .then_some(settings.bracket_mode_overrides.as_slice()),
+        &settings.excluded_files,
+        &mut stats,
+    );
 
-    if files.len() < sequence_len {
-        return Vec::new();
+    if settings.use_metadata_cache {
+        cache.save();
     }
 
-    let mut matching_sequences = Vec::new();
+    if progress.is_cancelled() {
+        info!("Run cancelled before any files were changed");
+        return report;
+    }
 
-    for file_group in files.windows(sequence_len) {
-        let sequence_match = match ev_mode {
-            EvMode::Absolute => {
-                file_group
-                    .iter()
-                    .zip(sequence.iter())
-                    .all(|(file_meta, seq_abs)| {
-                        if let Some(current_bias) = file_meta.exposure_bias {
-                            current_bias == *seq_abs
-                        } else {
-                            false
-                        }
-                    })
-            }
-            EvMode::Delta => {
-                let zero_bias_index = match sequence.iter().position(|r| r.is_zero()) {
-                    Some(i) => i,
-                    None => {
-                        warn!(
-                            "Delta EV mode requires a 0.0 value in the sequence to act as a reference."
-                        );
-                        continue;
-                    }
-                };
+    apply_clock_offsets(&mut files_with_metadata, &settings.clock_offsets);
+    if settings.computed_exposure {
+        apply_computed_exposure(&mut files_with_metadata);
+        // Computed exposures only compare to each other
+        settings.ev_mode = EvMode::Delta;
+        settings.anchor_bias = None;
+    }
+    match order {
+        FileOrder::Selection => {}
+        FileOrder::CaptureTime => sort_by_capture_time(&mut files_with_metadata),
+        FileOrder::FileName => sort_by_file_name(&mut files_with_metadata),
+        FileOrder::NaturalFileName => sort_by_natural_file_name(&mut files_with_metadata),
+    }
+    if settings.group_per_camera {
+        group_by_camera(&mut files_with_metadata);
+    }
 
-                let base_bias = match file_group
-                    .get(zero_bias_index)
-                    .and_then(|f| f.exposure_bias)
-                {
-                    Some(b) => b,
-                    None => continue,
-                };
+    let (matching_sequences, bursts) = detect_sequences(&files_with_metadata, &settings);
+    report.bursts = bursts;
+    if matching_sequences.is_empty() && !progress.is_cancelled() {
+        report.diagnostics = diagnose_no_matches(&files_with_metadata, order, &stats, &settings);
+        for cause in &report.diagnostics {
+            info!("No sequences found: {}", cause);
+        }
+    }
 
-                file_group
-                    .iter()
-                    .zip(sequence.iter())
-                    .all(|(file_meta, seq_delta)| {
-                        if let Some(current_bias) = file_meta.exposure_bias {
-                            debug!(
-                                "Current bias: {}, Base bias: {}, Seq delta: {}",
-                                current_bias, base_bias, seq_delta
-                            );
-                            let delta = current_bias - base_bias;
-                            debug!("Calculated delta: {}", delta);
-                            delta == *seq_delta
-                        } else {
-                            false
-                        }
-                    })
-            }
-        };
+    let mut catalog = (settings.selected_actions.contains(&Action::AddToCatalog)
+        && !settings.dry_run)
+        .then(Catalog::load);
+    let mut output = RunOutput::new(
+        settings.textfile_conflict,
+        settings.textfile_format,
+        settings.overwrite_textfile,
+    );
+    for (kind, seq) in matching_sequences {
+        // Only stop between sequences, so no sequence is left half processed
+        progress.wait_while_paused();
+        if progress.is_cancelled() {
+            info!("Run cancelled");
+            break;
+        }
+        if settings.rejected_sequences.contains(&seq[0].path) {
+            info!(
+                "Leaving the sequence of {} as it is, it was rejected in review",
+                seq[0].path.display()
+            );
+            continue;
+        }
+        progress.sequence_found(kind, seq);
+        report.add_sequence(seq, kind, &settings);
+        let known_errors = report.errors.len();
+        execute_actions_on_sequence(
+            seq,
+            kind,
+            &settings,
+            catalog.as_mut(),
+            &mut output,
+            &mut report.errors,
+        );
+        progress.errors(&report.errors[known_errors..]);
+    }
+    if let Some(catalog) = catalog {
+        catalog.save();
+    }
+    report
+}
+
+/// Runs an action on already known groups, e.g. loaded from an exported
+/// sequence list, without detecting sequences again.
+pub fn process_sequence_list(
+    sequences: &[Vec<PathBuf>],
+    progress: &JobProgress,
+    settings: &ProcessSettings,
+) -> RunReport {
+    let mut report = RunReport::default();
+    let mut catalog = (settings.selected_actions.contains(&Action::AddToCatalog)
+        && !settings.dry_run)
+        .then(Catalog::load);
+    let mut output = RunOutput::new(
+        settings.textfile_conflict,
+        settings.textfile_format,
+        settings.overwrite_textfile,
+    );
+    for group in sequences {
+        progress.wait_while_paused();
+        if progress.is_cancelled() {
+            info!("Run cancelled");
+            break;
+        }
+        for path in group {
+            progress.file_processed(path);
+        }
 
-        if sequence_match {
-            matching_sequences.push(file_group);
+        let known_errors = report.errors.len();
+        if let Some(missing) = group.iter().find(|path| !path.is_file()) {
+            record_error(
+                &mut report.errors,
+                format!(
+                    "Skipping sequence, file {} no longer exists",
+                    missing.display()
+                ),
+            );
+            progress.errors(&report.errors[known_errors..]);
+            continue;
         }
+
+        let files: Vec<FileMetadata> = group
+            .iter()
+            .map(|path| {
+                let metadata = read_metadata(path).map(|m| m.with_sidecar_overrides(path));
+                FileMetadata::new(path, metadata.as_ref())
+            })
+            .collect();
+
+        // Lists don't record the kind, their groups keep the plain folder names
+        progress.sequence_found(BracketKind::Exposure, &files);
+        report.add_sequence(&files, BracketKind::Exposure, settings);
+        execute_actions_on_sequence(
+            &files,
+            BracketKind::Exposure,
+            settings,
+            catalog.as_mut(),
+            &mut output,
+            &mut report.errors,
+        );
+        progress.errors(&report.errors[known_errors..]);
     }
-    matching_sequences
+    if let Some(catalog) = catalog {
+        catalog.save();
+    }
+    report
 }
 
-fn execute_action_on_sequence(dir: &Path, sequence: &[FileMetadata], action: Action) {
-    match action {
-        Action::MoveToFolder => {
-            if let Some(first_file) = sequence.first() {
-                let folder_name = first_file
-                    .path
-                    .file_stem()
-                    .unwrap()
-                    .to_string_lossy()
-                    .to_string();
-                let new_folder_path = dir.join(&folder_name);
-                if fs::create_dir(&new_folder_path).is_ok() {
-                    for file_meta in sequence {
-                        let new_file_path =
-                            new_folder_path.join(file_meta.path.file_name().unwrap());
-                        if let Err(e) = fs::rename(&file_meta.path, new_file_path) {
-                            warn!(
-                                "Failed to move file {} to {}: {}",
-                                file_meta.path.display(),
-                                folder_name,
-                                e
-                            );
-                        }
-                    }
-                    info!("Moved sequence to folder {}", folder_name);
-                } else {
-                    warn!("Failed to create folder {}", folder_name);
-                }
+/// `auto_bracket_filter` holds the exceptions of the auto-bracket filter,
+/// `None` if the filter is off.
+fn collect_files_with_metadata(
+    paths: &[PathBuf],
+    progress: &JobProgress,
+    cache: &mut MetadataCache,
+    extensions: &[String],
+    auto_bracket_filter: Option<&[BracketModeOverride]>,
+    excluded_files: &HashSet<PathBuf>,
+    stats: &mut ScanStats,
+) -> Vec<FileMetadata> {
+    let mut files_with_metadata: Vec<(usize, FileMetadata)> = Vec::new();
+    let mut deferred: Vec<(usize, &PathBuf, Option<u64>)> = Vec::new();
+
+    for (index, path) in paths.iter().enumerate() {
+        progress.wait_while_paused();
+        if progress.is_cancelled() {
+            return Vec::new();
+        }
+        // Not counted as processed, the total only counts matching files
+        if !(path.is_file() && has_matching_extension(path, extensions)) {
+            if path.is_file() {
+                stats.other_extension += 1;
             }
+            continue;
         }
-        Action::SaveSequencesToTextfile => {
-            let file_path = dir.join("sequences.txt");
-            let file = fs::OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(file_path);
-
-            match file {
-                Ok(mut f) => {
-                    for file_meta in sequence {
-                        if let Err(e) = writeln!(f, "{}", file_meta.path.display()) {
-                            warn!("Failed to write to sequences.txt: {}", e);
-                        }
-                    }
-                    if let Err(e) = writeln!(f) {
-                        // Add a blank line between sequences
-                        warn!("Failed to write to sequences.txt: {}", e);
-                    }
-                    info!("Appended sequence to {}", "sequences.txt");
-                }
-                Err(e) => {
-                    warn!("Failed to open sequences.txt: {}", e);
+        if excluded_files.contains(path) {
+            debug!("Skipping {}, it is excluded", path.display());
+            stats.excluded += 1;
+            progress.file_processed(path);
+            continue;
+        }
+
+        if stable_file_size(path).is_none() {
+            debug!("Deferring {}, it is still being written", path.display());
+            deferred.push((index, path, file_size(path)));
+            continue;
+        }
+
+        progress.file_processed(path);
+        if let Some(file) = read_file_metadata(path, cache, auto_bracket_filter, stats) {
+            files_with_metadata.push((index, file));
+        }
+    }
+
+    // Give files that were still being copied a chance to complete, reading
+    // them only once their size stopped changing
+    for _ in 0..MAX_DEFERRED_RETRIES {
+        progress.wait_while_paused();
+        if deferred.is_empty() || progress.is_cancelled() {
+            break;
+        }
+        thread::sleep(DEFERRED_RETRY_INTERVAL);
+        deferred.retain_mut(|(index, path, last_size)| {
+            let size = stable_file_size(path);
+            if size.is_some() && size == *last_size {
+                progress.file_processed(path);
+                if let Some(file) = read_file_metadata(path, cache, auto_bracket_filter, stats) {
+                    files_with_metadata.push((*index, file));
                 }
+                false
+            } else {
+                *last_size = file_size(path);
+                true
             }
-        }
+        });
+    }
+
+    for (_, path, _) in deferred {
+        progress.file_processed(path);
+        stats.still_being_written += 1;
+        warn!("Skipping {}, it is still being written", path.display());
+    }
+
+    // Deferred files are put back to their original position
+    files_with_metadata.sort_by_key(|(index, _)| *index);
+    files_with_metadata
+        .into_iter()
+        .map(|(_, file)| file)
+        .collect()
+}
+
+fn read_file_metadata(
+    path: &Path,
+    cache: &mut MetadataCache,
+    auto_bracket_filter: Option<&[BracketModeOverride]>,
+    stats: &mut ScanStats,
+) -> Option<FileMetadata> {
+    let Some(metadata) = cache.read(path) else {
+        stats.unreadable += 1;
+        return None;
+    };
+
+    if auto_bracket_filter.is_some_and(|overrides| !is_auto_bracket(&metadata, overrides)) {
+        *stats
+            .filtered_by_exposure_mode
+            .entry(metadata.exposure_mode)
+            .or_default() += 1;
+        return None;
+    }
+
+    Some(FileMetadata::new(path, Some(&metadata)))
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    fs::metadata(path).ok().map(|m| m.len())
+}
+
+/// Returns the size of a file that looks complete: it can be opened and was
+/// not modified within the stability window.
+fn stable_file_size(path: &Path) -> Option<u64> {
+    let metadata = fs::metadata(path).ok()?;
+    let recently_modified = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.elapsed().ok())
+        .is_some_and(|age| age < STABILITY_WINDOW);
+    if recently_modified {
+        return None;
+    }
+    // Fails for files that are exclusively locked by the copying process
+    fs::File::open(path).ok()?;
+    Some(metadata.len())
+}
+
+/// Finds the sequences of `files`, within each burst if burst splitting is on.
+fn detect_sequences<'a>(
+    files: &'a [FileMetadata],
+    settings: &ProcessSettings,
+) -> (Vec<(BracketKind, &'a [FileMetadata])>, Vec<BurstReport>) {
+    let find = |files: &'a [FileMetadata]| {
+        let exposure_brackets = if settings.auto_detect {
+            find_detected_brackets(files, settings)
+        } else {
+            find_matching_sequences(files, settings)
+        };
+        let mut found: Vec<(BracketKind, &[FileMetadata])> = exposure_brackets
+            .into_iter()
+            .map(|sequence| (BracketKind::Exposure, sequence))
+            .collect();
+        if settings.detect_other_brackets {
+            let mut lengths: Vec<usize> = if settings.auto_detect {
+                // No sequence to take the length from, so the brackets found
+                // and the usual odd lengths stand in for it
+                found
+                    .iter()
+                    .map(|(_, sequence)| sequence.len())
+                    .chain((3..=9).step_by(2))
+                    .collect()
+            } else {
+                settings.sequences.iter().map(Vec::len).collect()
+            };
+            lengths.sort_by(|a, b| b.cmp(a));
+            lengths.dedup();
+            for len in lengths {
+                let other = find_other_brackets(files, len, &found);
+                found.extend(other);
+            }
+        }
+        found
+    };
+    // Without a sequence, a pause is all that tells two scenes apart
+    let burst_gap = settings
+        .burst_gap
+        .or(settings.auto_detect.then_some(AUTO_DETECT_GAP));
+    let Some(gap) = burst_gap else {
+        if settings.group_per_camera {
+            let cameras = files.chunk_by(|a, b| a.camera == b.camera);
+            return (cameras.flat_map(find).collect(), Vec::new());
+        }
+        return (find(files), Vec::new());
+    };
+
+    let mut sequences = Vec::new();
+    let mut reports = Vec::new();
+    for burst in split_into_bursts(files, gap) {
+        let found = find(burst);
+        let matched: HashSet<&Path> = found
+            .iter()
+            .flat_map(|(_, s)| s.iter().map(|f| f.path.as_path()))
+            .collect();
+        let report = BurstReport {
+            start: burst.iter().find_map(|f| f.capture_time),
+            camera: burst[0].camera.clone(),
+            files: burst.len(),
+            sequences: found.len(),
+            unmatched: burst.len() - matched.len(),
+        };
+        debug!(
+            "Burst of {} files starting {}: {} sequences, {} files unmatched",
+            report.files,
+            report
+                .start
+                .map_or("at an unknown time".to_string(), |t| t.to_string()),
+            report.sequences,
+            report.unmatched
+        );
+        reports.push(report);
+        sequences.extend(found);
+    }
+    info!("{} files split into {} bursts", files.len(), reports.len());
+    (sequences, reports)
+}
+
+/// Finds aperture, white balance and flash brackets of `len` frames among
+/// the files that are part of none of the `exposure_brackets`. Matched frames
+/// are skipped, so these brackets never overlap.
+fn find_other_brackets<'a>(
+    files: &'a [FileMetadata],
+    len: usize,
+    exposure_brackets: &[(BracketKind, &[FileMetadata])],
+) -> Vec<(BracketKind, &'a [FileMetadata])> {
+    let claimed: HashSet<&Path> = exposure_brackets
+        .iter()
+        .flat_map(|(_, s)| s.iter().map(|f| f.path.as_path()))
+        .collect();
+    let mut found = Vec::new();
+    let mut start = 0;
+    while len > 1 && start + len <= files.len() {
+        let window = &files[start..start + len];
+        let kind = if window.iter().any(|f| claimed.contains(f.path.as_path())) {
+            None
+        } else {
+            let frames: Vec<Option<&CaptureSettings>> =
+                window.iter().map(|f| f.capture_settings.as_ref()).collect();
+            let biases: Vec<Option<Rational32>> = window.iter().map(|f| f.exposure_bias).collect();
+            classify(&frames, &biases)
+        };
+        match kind {
+            Some(kind) => {
+                found.push((kind, window));
+                start += len;
+            }
+            None => start += 1,
+        }
+    }
+    found
+}
+
+/// Converts a duration entered in seconds, negative values count as zero.
+pub fn seconds_to_time_delta(seconds: f64) -> TimeDelta {
+    TimeDelta::milliseconds((seconds.max(0.0) * 1000.0).round() as i64)
+}
+
+/// Converts an EV tolerance entered as a decimal, negative values count as zero.
+pub fn ev_tolerance(ev: f64) -> Rational32 {
+    Rational32::approximate_float(ev.max(0.0)).unwrap_or_default()
+}
+
+/// Replaces the recorded biases by the exposure computed from shutter speed,
+/// aperture and ISO, see [`relative_exposure`]. Files missing the shutter
+/// speed or aperture are left without a bias.
+fn apply_computed_exposure(files: &mut [FileMetadata]) {
+    for file in files {
+        file.exposure_bias = file.capture_settings.as_ref().and_then(|settings| {
+            // Not every camera records the ISO, a constant one cancels out
+            relative_exposure(
+                settings.exposure_time?,
+                settings.fnumber?,
+                settings.iso.unwrap_or(100),
+            )
+        });
+    }
+}
+
+/// Puts the files of each camera after one another, in the order the cameras
+/// first appear, e.g. for a RAW body and a JPEG-only body shot side by side.
+/// The files of a camera keep their order, whatever their file type.
+fn group_by_camera(files: &mut [FileMetadata]) {
+    let mut cameras: Vec<Option<String>> = Vec::new();
+    for file in files.iter() {
+        if !cameras.contains(&file.camera) {
+            cameras.push(file.camera.clone());
+        }
+    }
+    if cameras.len() > 1 {
+        info!(
+            "Searching the files of {} cameras on their own",
+            cameras.len()
+        );
+    }
+    files.sort_by_key(|f| cameras.iter().position(|c| *c == f.camera));
+}
+
+/// Splits `files` where the camera changes or consecutive capture times are
+/// more than `gap` apart, i.e. between separately shot scenes. Files without
+/// a capture time stay with the burst before them.
+fn split_into_bursts(files: &[FileMetadata], gap: TimeDelta) -> Vec<&[FileMetadata]> {
+    let mut bursts = Vec::new();
+    let mut start = 0;
+    let mut last_time: Option<NaiveDateTime> = None;
+    for (i, file) in files.iter().enumerate() {
+        let paused = matches!(
+            (last_time, file.capture_time),
+            (Some(last), Some(time)) if (time - last).abs() > gap
+        );
+        let other_camera = i > start && file.camera != files[i - 1].camera;
+        if paused || other_camera {
+            bursts.push(&files[start..i]);
+            start = i;
+        }
+        if file.capture_time.is_some() {
+            last_time = file.capture_time;
+        }
+    }
+    if start < files.len() {
+        bursts.push(&files[start..]);
+    }
+    bursts
+}
+
+/// Finds the windows of `files` following any of the entered sequences, see
+/// [`find_bracket_patterns`].
+fn find_matching_sequences<'a>(
+    files: &'a [FileMetadata],
+    settings: &ProcessSettings,
+) -> Vec<&'a [FileMetadata]> {
+    let biases: Vec<Option<Rational32>> = files.iter().map(|f| f.exposure_bias).collect();
+    // Overlapping first, so a window rejected for its capture times doesn't
+    // keep the next one from being taken
+    let overlapping = find_bracket_patterns(
+        &biases,
+        &settings.sequences,
+        &settings.ev_mode,
+        settings.delta_reference,
+        settings.anchor_bias,
+        settings.ev_tolerance,
+        MatchMode::Overlapping,
+    )
+    .into_iter()
+    .filter(|(_, range)| within_span(&files[range.clone()], settings.max_sequence_span))
+    .filter(|(_, range)| follows_bracket_shots(&files[range.clone()]));
+    let found = match settings.match_mode {
+        MatchMode::Overlapping => overlapping.collect(),
+        MatchMode::Greedy => without_overlaps(overlapping, |(_, range)| range),
+    };
+    found
+        .into_iter()
+        .map(|(pattern, range)| {
+            debug!(
+                "{} starts a bracket of sequence {}",
+                files[range.start].path.display(),
+                format_pattern(&settings.sequences[pattern])
+            );
+            &files[range]
+        })
+        .collect()
+}
+
+/// Finds the brackets of `files` by their evenly spaced biases, see
+/// [`detect_brackets`].
+fn find_detected_brackets<'a>(
+    files: &'a [FileMetadata],
+    settings: &ProcessSettings,
+) -> Vec<&'a [FileMetadata]> {
+    let biases: Vec<Option<Rational32>> = files.iter().map(|f| f.exposure_bias).collect();
+    detect_brackets(&biases, settings.ev_tolerance)
+        .into_iter()
+        .map(|bracket| &files[bracket.range])
+        .filter(|sequence| within_span(sequence, settings.max_sequence_span))
+        .filter(|sequence| follows_bracket_shots(sequence))
+        .collect()
+}
+
+/// Whether the capture times of `sequence` lie within `max_span`, frames
+/// without one aren't counted. Always true without a limit.
+fn within_span(sequence: &[FileMetadata], max_span: Option<TimeDelta>) -> bool {
+    let Some(max_span) = max_span else {
+        return true;
+    };
+    let times = sequence.iter().filter_map(|f| f.capture_time);
+    let (Some(first), Some(last)) = (times.clone().min(), times.max()) else {
+        return true;
+    };
+    let within = last - first <= max_span;
+    if !within {
+        debug!(
+            "Skipping the match starting at {}, its frames were shot over {} s",
+            sequence[0].path.display(),
+            (last - first).num_seconds()
+        );
+    }
+    within
+}
+
+/// Whether the frames of `sequence` whose maker notes number them within
+/// their camera's bracket are at that position, so a match can't start in the
+/// middle of one bracket and end in the next.
+fn follows_bracket_shots(sequence: &[FileMetadata]) -> bool {
+    let follows = sequence
+        .iter()
+        .enumerate()
+        .all(|(i, f)| f.bracket_shot.is_none_or(|shot| usize::from(shot) == i + 1));
+    if !follows {
+        debug!(
+            "Skipping the match starting at {}, it isn't one bracket of the camera",
+            sequence[0].path.display()
+        );
+    }
+    follows
+}
+
+/// E.g. "5 frames in 2/3 EV steps: 0, -2/3, 2/3, -4/3, 4/3", the sequence
+/// given the way it would be entered for such brackets.
+fn describe_detected(sequence: &[FileMetadata], settings: &ProcessSettings) -> Option<String> {
+    let biases: Vec<Option<Rational32>> = sequence.iter().map(|f| f.exposure_bias).collect();
+    let bracket = detect_brackets(&biases, settings.ev_tolerance)
+        .into_iter()
+        .find(|bracket| bracket.range == (0..sequence.len()))?;
+    Some(format!(
+        "{} frames in {} EV steps: {}",
+        bracket.frames(),
+        bracket.step,
+        format_sequence(&bracket.sequence(&biases, &settings.ev_mode))
+    ))
+}
+
+/// The entered sequence the exposure bracket `sequence` follows, like
+/// [`find_bracket_patterns`] took it.
+fn matched_sequence<'a>(
+    sequence: &[FileMetadata],
+    settings: &'a ProcessSettings,
+) -> Option<&'a [Option<Rational32>]> {
+    let biases: Vec<Option<Rational32>> = sequence.iter().map(|f| f.exposure_bias).collect();
+    settings
+        .sequences
+        .iter()
+        .filter(|s| s.len() == sequence.len())
+        .find(|s| {
+            biases_match_sequence(
+                &biases,
+                s,
+                &settings.ev_mode,
+                settings.delta_reference,
+                settings.ev_tolerance,
+            )
+        })
+        .map(Vec::as_slice)
+}
+
+/// E.g. "0, -1, 1", the way sequences are entered.
+pub fn format_sequence(sequence: &[Rational32]) -> String {
+    sequence
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Like [`format_sequence`], with a `*` for frames of any bias.
+pub fn format_pattern(pattern: &[Option<Rational32>]) -> String {
+    pattern
+        .iter()
+        .map(|v| v.map_or("*".to_string(), |v| v.to_string()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn diagnose_no_matches(
+    files: &[FileMetadata],
+    order: FileOrder,
+    stats: &ScanStats,
+    settings: &ProcessSettings,
+) -> Vec<String> {
+    let out_of_order = files
+        .windows(2)
+        .filter(|w| matches!((w[0].capture_time, w[1].capture_time), (Some(a), Some(b)) if b < a))
+        .count();
+    // Worth a second search, a scrambled folder order is a common cause
+    let found_in_capture_order = (order != FileOrder::CaptureTime && out_of_order > 0).then(|| {
+        let mut sorted = files.to_vec();
+        sort_by_capture_time(&mut sorted);
+        if settings.group_per_camera {
+            group_by_camera(&mut sorted);
+        }
+        detect_sequences(&sorted, settings).0.len()
+    });
+    let biases: Vec<Option<Rational32>> = files.iter().map(|f| f.exposure_bias).collect();
+
+    explain_no_matches(&NoMatchInput {
+        stats,
+        biases: &biases,
+        sequences: &settings.sequences,
+        ev_mode: settings.ev_mode.clone(),
+        delta_reference: settings.delta_reference,
+        tolerance: settings.ev_tolerance,
+        ev_format: settings.ev_format,
+        found_in_capture_order,
+        out_of_order,
+    })
+}
+
+/// The reference frame of delta mode, else the first frame.
+fn base_frame<'a>(sequence: &'a [FileMetadata], settings: &ProcessSettings) -> &'a FileMetadata {
+    let index = match settings.ev_mode {
+        EvMode::Delta => matched_sequence(sequence, settings)
+            .or(settings.sequences.first().map(Vec::as_slice))
+            .and_then(|pattern| settings.delta_reference.index(pattern)),
+        EvMode::Absolute => None,
+    };
+    index.and_then(|i| sequence.get(i)).unwrap_or(&sequence[0])
+}
+
+/// E.g. "Exposure bracket of 3 frames, −1 to +1 EV, shot 2024-05-01 12:30:00 with SONY ILCE-7RM5",
+/// followed by the note of the sequence
+fn folder_comment(
+    sequence: &[FileMetadata],
+    kind: BracketKind,
+    base_frame: &FileMetadata,
+    ev_format: EvFormat,
+    note: Option<&str>,
+) -> String {
+    let mut comment = format!("{} of {} frames", kind, sequence.len());
+    let biases = sequence.iter().filter_map(|f| f.exposure_bias);
+    if let (BracketKind::Exposure, Some(min), Some(max)) =
+        (kind, biases.clone().min(), biases.max())
+    {
+        comment.push_str(&format!(
+            ", {} to {} EV",
+            ev_format.format(min),
+            ev_format.format(max)
+        ));
+    }
+    if let Some(time) = base_frame.capture_time {
+        comment.push_str(&format!(", shot {}", time));
+    }
+    if let Some(camera) = &base_frame.camera {
+        comment.push_str(&format!(" with {}", camera));
+    }
+    if let Some(note) = note {
+        comment.push_str(&format!(": {}", note));
+    }
+    comment
+}
+
+/// Names sequence folders after the first file of the sequence
+pub const DEFAULT_FOLDER_NAME: &str = "{name}";
+
+/// Fills in the folder name `template`: `{name}` is the name of the first
+/// file without extension, `{date}` the capture date like `2024-05-01` and
+/// `{note}` the note given during review. Characters file systems don't allow
+/// are replaced, and separators left dangling by an empty token are dropped,
+/// so `{name} - {note}` is just `IMG_1234` without a note. Falls back to the
+/// file name if nothing is left.
+fn fill_folder_name(
+    template: &str,
+    name: &str,
+    capture_time: Option<NaiveDateTime>,
+    note: Option<&str>,
+) -> String {
+    let date = capture_time
+        .map(|time| time.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let filled = template
+        .replace("{name}", name)
+        .replace("{date}", &date)
+        .replace("{note}", note.unwrap_or_default().trim());
+    let filled: String = filled
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let filled = filled
+        .trim_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '_' | '.'))
+        .to_string();
+    if filled.is_empty() {
+        name.to_string()
+    } else {
+        filled
+    }
+}
+
+/// Prefix of the hidden folder a sequence is gathered in before it gets its name
+const STAGING_PREFIX: &str = ".ebo-staging-";
+
+/// Start of the names "Rename in Place" gives, followed by the sequence number
+const RENAME_PREFIX: &str = "BRK";
+
+/// Moves the files of a sequence into the new folder `folder_name` in `dir`.
+///
+/// The files are gathered in a hidden staging folder first, which is only
+/// renamed once all of them arrived. If a move fails, the files already moved
+/// are put back, so a failed or interrupted run never leaves a half-filled
+/// sequence folder behind.
+fn move_sequence_to_folder(files: &[PathBuf], dir: &Path, folder_name: &str) -> Result<(), String> {
+    let final_path = dir.join(folder_name);
+    if final_path.exists() {
+        return Err(format!(
+            "Failed to create folder {}: it already exists",
+            folder_name
+        ));
+    }
+    let staging_path = dir.join(format!("{}{}", STAGING_PREFIX, folder_name));
+    fs::create_dir(&staging_path).map_err(|e| {
+        format!(
+            "Failed to create staging folder {}: {}",
+            staging_path.display(),
+            e
+        )
+    })?;
+
+    let mut moved: Vec<(&Path, PathBuf)> = Vec::new();
+    let mut failure = None;
+    for path in files {
+        let staged_path = staging_path.join(path.file_name().unwrap());
+        match move_file(path, &staged_path) {
+            Ok(()) => moved.push((path, staged_path)),
+            Err(e) => {
+                failure = Some(format!(
+                    "Failed to move file {} to {}: {}",
+                    path.display(),
+                    folder_name,
+                    e
+                ));
+                break;
+            }
+        }
+    }
+    if failure.is_none() {
+        match fs::rename(&staging_path, &final_path) {
+            Ok(()) => {
+                journal::record(JournalEntry::CreateFolder {
+                    path: final_path.clone(),
+                });
+                for (original_path, staged_path) in moved {
+                    journal::record(JournalEntry::Move {
+                        from: original_path.to_path_buf(),
+                        to: final_path.join(staged_path.file_name().unwrap()),
+                    });
+                }
+                return Ok(());
+            }
+            Err(e) => {
+                failure = Some(format!(
+                    "Failed to rename staging folder to {}: {}",
+                    folder_name, e
+                ))
+            }
+        }
+    }
+
+    // Roll back, the sequence stays where it was
+    for (original_path, staged_path) in moved {
+        if let Err(e) = move_file(&staged_path, original_path) {
+            warn!(
+                "Failed to move {} back to {}: {}",
+                staged_path.display(),
+                original_path.display(),
+                e
+            );
+        }
+    }
+    if let Err(e) = fs::remove_dir(&staging_path) {
+        warn!(
+            "Failed to remove staging folder {}: {}",
+            staging_path.display(),
+            e
+        );
+    }
+    Err(failure.unwrap_or_default())
+}
+
+/// The sequence number of a file "Rename in Place" renamed, e.g. 1 for
+/// `BRK0001_1of5_IMG_2034.NEF`.
+fn renamed_number(name: &str) -> Option<u32> {
+    let (number, rest) = name.strip_prefix(RENAME_PREFIX)?.split_once('_')?;
+    let (position, _) = rest.split_once('_')?;
+    let (index, count) = position.split_once("of")?;
+    let digits = |text: &str| !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit());
+    (number.len() >= 4 && digits(number) && digits(index) && digits(count))
+        .then(|| number.parse().ok())
+        .flatten()
+}
+
+/// The sequence number following the highest one already given in `dir`, so
+/// running a folder again continues the numbering.
+fn next_rename_number(dir: &Path) -> u32 {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| renamed_number(&entry.file_name().to_string_lossy()))
+                .max()
+                .unwrap_or(0)
+        })
+        .unwrap_or(0)
+        + 1
+}
+
+/// Renames the frames of a sequence in place to `BRK<number>_<i>of<n>_<name>`,
+/// their XMP sidecars along with them. Nothing is renamed if a new name is
+/// taken, and if a rename fails the others are undone. Returns the new paths
+/// of the frames.
+fn rename_sequence_in_place(files: &[PathBuf], number: u32) -> Result<Vec<PathBuf>, String> {
+    let renamed = |path: &Path, prefix: &str| {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        path.with_file_name(format!("{}{}", prefix, name))
+    };
+    let mut renames: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut new_paths = Vec::new();
+    for (index, path) in files.iter().enumerate() {
+        let prefix = format!(
+            "{}{:04}_{}of{}_",
+            RENAME_PREFIX,
+            number,
+            index + 1,
+            files.len()
+        );
+        new_paths.push(renamed(path, &prefix));
+        renames.push((path.clone(), renamed(path, &prefix)));
+        for sidecar in sidecar_files(path) {
+            let target = renamed(&sidecar, &prefix);
+            renames.push((sidecar, target));
+        }
+    }
+    if let Some((_, taken)) = renames.iter().find(|(_, to)| to.exists()) {
+        return Err(format!(
+            "Failed to rename sequence, {} already exists",
+            taken.display()
+        ));
+    }
+
+    for (done, (from, to)) in renames.iter().enumerate() {
+        if let Err(e) = fs::rename(from, to) {
+            // Roll back, the sequence keeps its names
+            for (original, new) in &renames[..done] {
+                if let Err(e) = fs::rename(new, original) {
+                    warn!(
+                        "Failed to rename {} back to {}: {}",
+                        new.display(),
+                        original.display(),
+                        e
+                    );
+                }
+            }
+            return Err(format!("Failed to rename {}: {}", from.display(), e));
+        }
+    }
+    for (from, to) in renames {
+        journal::record(JournalEntry::Move { from, to });
+    }
+    Ok(new_paths)
+}
+
+/// Renames `from` to `to`, or copies and deletes it if `to` is on another
+/// drive, e.g. when moving from a memory card into a destination root.
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(from, to)?;
+            fs::remove_file(from).inspect_err(|_| {
+                // Keep the original rather than two copies
+                let _ = fs::remove_file(to);
+            })
+        }
+        result => result,
+    }
+}
+
+/// Copies the files of a sequence into the new folder `folder_name` in `dir`,
+/// the originals stay where they are.
+///
+/// Like moving, the copies are gathered in a hidden staging folder that only
+/// gets its name once all of them are complete, so an interrupted run never
+/// leaves a sequence folder with truncated copies behind.
+fn copy_sequence_to_folder(files: &[PathBuf], dir: &Path, folder_name: &str) -> Result<(), String> {
+    let final_path = dir.join(folder_name);
+    if final_path.exists() {
+        return Err(format!(
+            "Failed to create folder {}: it already exists",
+            folder_name
+        ));
+    }
+    let staging_path = dir.join(format!("{}{}", STAGING_PREFIX, folder_name));
+    fs::create_dir(&staging_path).map_err(|e| {
+        format!(
+            "Failed to create staging folder {}: {}",
+            staging_path.display(),
+            e
+        )
+    })?;
+
+    let mut failure = None;
+    for path in files {
+        let staged_path = staging_path.join(path.file_name().unwrap());
+        if let Err(e) = fs::copy(path, &staged_path) {
+            failure = Some(format!(
+                "Failed to copy file {} to {}: {}",
+                path.display(),
+                folder_name,
+                e
+            ));
+            break;
+        }
+    }
+    if failure.is_none() {
+        match fs::rename(&staging_path, &final_path) {
+            Ok(()) => {
+                journal::record(JournalEntry::CreateFolder {
+                    path: final_path.clone(),
+                });
+                for path in files {
+                    journal::record(JournalEntry::CreateFile {
+                        path: final_path.join(path.file_name().unwrap()),
+                    });
+                }
+                return Ok(());
+            }
+            Err(e) => {
+                failure = Some(format!(
+                    "Failed to rename staging folder to {}: {}",
+                    folder_name, e
+                ))
+            }
+        }
+    }
+
+    // Only copies in there, the originals are untouched
+    if let Err(e) = fs::remove_dir_all(&staging_path) {
+        warn!(
+            "Failed to remove staging folder {}: {}",
+            staging_path.display(),
+            e
+        );
+    }
+    Err(failure.unwrap_or_default())
+}
+
+/// The files next to those of `sequence` that share their name, e.g.
+/// `IMG_0001.JPG` and `IMG_0001.xmp` for `IMG_0001.CR2`, or `IMG_0001.CR2.xmp`.
+/// Files of the searched types are left out, they are grouped on their own.
+fn companion_files(sequence: &[FileMetadata], extensions: &[String]) -> Vec<PathBuf> {
+    let mut companions = Vec::new();
+    for file_meta in sequence {
+        let (Some(dir), Some(name), Some(stem)) = (
+            file_meta.path.parent(),
+            file_meta.path.file_name(),
+            file_meta.path.file_stem(),
+        ) else {
+            continue;
+        };
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        let (name, stem) = (name.to_string_lossy(), stem.to_string_lossy());
+        for path in entries.flatten().map(|e| e.path()) {
+            let Some(other) = path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            let same_name = path
+                .file_stem()
+                .is_some_and(|s| s.to_string_lossy() == stem)
+                || other.starts_with(&format!("{}.", name));
+            if other != name
+                && same_name
+                && path.is_file()
+                && !has_matching_extension(&path, extensions)
+                && !companions.contains(&path)
+            {
+                debug!("Taking {} along with {}", other, name);
+                companions.push(path);
+            }
+        }
+    }
+    companions
+}
+
+/// The existing XMP sidecars of `path`, `IMG_0001.ARW.xmp` and `IMG_0001.xmp`,
+/// see [`xmp::sidecar_path`].
+fn sidecar_files(path: &Path) -> Vec<PathBuf> {
+    let mut full_name = path.as_os_str().to_owned();
+    full_name.push(".xmp");
+    [PathBuf::from(full_name), path.with_extension("xmp")]
+        .into_iter()
+        .filter(|sidecar| sidecar != path && sidecar.is_file())
+        .collect()
+}
+
+/// Folders created for a sequence, innermost first.
+struct CreatedFolders(Vec<PathBuf>);
+
+impl CreatedFolders {
+    /// Removes the folders made for a sequence that couldn't be moved, if
+    /// they are still empty.
+    fn remove_if_empty(self) {
+        for folder in self.0 {
+            if fs::remove_dir(&folder).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Creates `path` and the folders leading to it below `dir`, e.g. the date
+/// folder of a sequence.
+fn create_folders(dir: &Path, path: &Path) -> Result<CreatedFolders, String> {
+    let created: Vec<PathBuf> = path
+        .ancestors()
+        .take_while(|p| *p != dir && !p.exists())
+        .map(Path::to_path_buf)
+        .collect();
+    fs::create_dir_all(path)
+        .map_err(|e| format!("Failed to create folder {}: {}", path.display(), e))?;
+    for folder in created.iter().rev() {
+        journal::record(JournalEntry::CreateFolder {
+            path: folder.clone(),
+        });
+    }
+    Ok(CreatedFolders(created))
+}
+
+/// Where moving puts a sequence, e.g. `<dir>/2024-05-01/IMG_1234` with date
+/// folders, below the destination root instead of the files' folder if one is
+/// set. Undated sequences go directly into that folder.
+fn sequence_folder(
+    sequence: &[FileMetadata],
+    kind: BracketKind,
+    settings: &ProcessSettings,
+) -> Option<PathBuf> {
+    let first_file = sequence.first()?;
+    let dir = match &settings.destination_root {
+        Some(root) => root.as_path(),
+        None => first_file.path.parent()?,
+    };
+    let capture_time = base_frame(sequence, settings).capture_time;
+    let folder_name = format!(
+        "{}{}",
+        kind.folder_prefix(),
+        fill_folder_name(
+            &settings.folder_name,
+            &first_file.path.file_stem()?.to_string_lossy(),
+            capture_time,
+            sequence_note(sequence, settings),
+        )
+    );
+    let date_folder = capture_time.and_then(|t| settings.date_folders.path(t));
+    Some(match date_folder {
+        Some(date_folder) => dir.join(date_folder).join(folder_name),
+        None => dir.join(folder_name),
+    })
+}
+
+/// The folder or file the first of the selected actions writing anywhere
+/// writes a sequence to, if any.
+fn destination(
+    sequence: &[FileMetadata],
+    kind: BracketKind,
+    settings: &ProcessSettings,
+) -> Option<PathBuf> {
+    execution_order(&settings.selected_actions)
+        .into_iter()
+        .find_map(|action| action_destination(action, sequence, kind, settings))
+}
+
+/// The actions in the order they are executed on a sequence: organizing it
+/// into a folder or renaming its files first, so the others find its files
+/// where they end up.
+fn execution_order(actions: &[Action]) -> Vec<&Action> {
+    let (relocating, others): (Vec<&Action>, Vec<&Action>) =
+        actions.iter().partition(|action| action.relocates());
+    relocating.into_iter().chain(others).collect()
+}
+
+/// The folder or file `action` writes a sequence to, if any.
+fn action_destination(
+    action: &Action,
+    sequence: &[FileMetadata],
+    kind: BracketKind,
+    settings: &ProcessSettings,
+) -> Option<PathBuf> {
+    match action {
+        Action::MoveToFolder | Action::CopyToFolder => {
+            let base_frame = base_frame(sequence, settings);
+            if settings.date_folders != DateFolders::Off && base_frame.capture_time.is_none() {
+                warn!(
+                    "{} has no capture time, its sequence is not put in a date folder",
+                    base_frame.path.display()
+                );
+            }
+            sequence_folder(sequence, kind, settings)
+        }
+        Action::SaveSequencesToTextfile => sequence
+            .first()
+            .and_then(|f| f.path.parent())
+            .map(|dir| dir.join(settings.textfile_format.file_name())),
+        Action::WriteFusionScripts => Some(hdr_tools::fusion_script_path(
+            sequence.first()?.path.parent()?,
+            &group_id(sequence),
+        )),
+        Action::WriteHuginProjects => Some(hdr_tools::hugin_project_path(
+            sequence.first()?.path.parent()?,
+            &group_id(sequence),
+        )),
+        Action::WriteHdrMergeCommands => sequence
+            .first()
+            .and_then(|f| f.path.parent())
+            .map(hdr_tools::hdrmerge_script_path),
+        Action::WriteDarktableGroups => sequence
+            .first()
+            .and_then(|f| f.path.parent())
+            .map(darktable::script_path),
+        Action::WriteGroupTags
+        | Action::WriteLightroomKeywords
+        | Action::WriteDigikamTags
+        | Action::RunCommand
+        | Action::RenameInPlace
+        | Action::AddToCatalog
+        | Action::Script(_) => None,
+    }
+}
+
+/// The note given to `sequence` during review, if any.
+fn sequence_note<'a>(sequence: &[FileMetadata], settings: &'a ProcessSettings) -> Option<&'a str> {
+    let first_file = sequence.first()?;
+    settings
+        .sequence_notes
+        .get(&first_file.path)
+        .map(String::as_str)
+        .filter(|note| !note.trim().is_empty())
+}
+
+/// Reports what would keep `action` from succeeding, without changing anything.
+fn check_dry_run(
+    action: &Action,
+    sequence: &[FileMetadata],
+    kind: BracketKind,
+    settings: &ProcessSettings,
+    errors: &mut Vec<String>,
+) {
+    if let Action::Script(file_name) = action {
+        if script_actions::find(file_name).is_none() {
+            record_error(
+                errors,
+                format!(
+                    "Script action {} is not in the actions directory",
+                    file_name
+                ),
+            );
+        }
+        return;
+    }
+    if !action.organizes() {
+        return;
+    }
+    if let Some(folder) = sequence_folder(sequence, kind, settings) {
+        if folder.exists() {
+            record_error(
+                errors,
+                format!(
+                    "Folder {} already exists, the sequence would not be organized",
+                    folder.display()
+                ),
+            );
+        }
+    }
+}
+
+/// Logs a problem and keeps it for the run summary.
+fn record_error(errors: &mut Vec<String>, message: String) {
+    warn!("{}", message);
+    errors.push(message);
+}
+
+/// Executes the selected actions on `sequence`, in [`execution_order`]. Once
+/// it is moved into a folder or renamed, the other actions get the files
+/// where they are now. If that fails, the other actions are skipped, so they
+/// don't refer to files that may have been moved in part.
+fn execute_actions_on_sequence(
+    sequence: &[FileMetadata],
+    kind: BracketKind,
+    settings: &ProcessSettings,
+    mut catalog: Option<&mut Catalog>,
+    output: &mut RunOutput,
+    errors: &mut Vec<String>,
+) {
+    if settings.dry_run {
+        for action in &settings.selected_actions {
+            check_dry_run(action, sequence, kind, settings, errors);
+        }
+        return;
+    }
+    // Looked up by the original paths, so the note stays with the sequence once it moved
+    let note = sequence_note(sequence, settings);
+    let mut moved: Option<Vec<FileMetadata>> = None;
+    for action in execution_order(&settings.selected_actions) {
+        let current = moved.as_deref().unwrap_or(sequence);
+        let paths = execute_action_on_sequence(
+            action,
+            ActionTarget {
+                sequence: current,
+                kind,
+                note,
+            },
+            settings,
+            catalog.as_deref_mut(),
+            output,
+            errors,
+        );
+        if !action.relocates() {
+            continue;
+        }
+        let Some(paths) = paths else {
+            if settings.selected_actions.len() > 1 {
+                warn!("Skipping the other actions on the sequence, it wasn't organized");
+            }
+            return;
+        };
+        moved = Some(
+            current
+                .iter()
+                .zip(paths)
+                .map(|(f, path)| FileMetadata { path, ..f.clone() })
+                .collect(),
+        );
+    }
+}
+
+/// The sequence an action is executed on.
+struct ActionTarget<'a> {
+    /// Its files where they are now, after being moved or renamed
+    sequence: &'a [FileMetadata],
+    kind: BracketKind,
+    /// The note given during review, looked up before the files moved
+    note: Option<&'a str>,
+}
+
+/// Executes `action` on `target`. For actions that [`Action::relocates`],
+/// returns where the files of the sequence are afterwards, `None` if it failed.
+fn execute_action_on_sequence(
+    action: &Action,
+    target: ActionTarget,
+    settings: &ProcessSettings,
+    catalog: Option<&mut Catalog>,
+    output: &mut RunOutput,
+    errors: &mut Vec<String>,
+) -> Option<Vec<PathBuf>> {
+    let ActionTarget {
+        sequence,
+        kind,
+        note,
+    } = target;
+    // Results are placed next to the files, so explicitly picked files from
+    // different folders still end up beside their originals
+    let dir = sequence.first().and_then(|f| f.path.parent())?;
+    match action {
+        Action::MoveToFolder | Action::CopyToFolder => {
+            let folder = sequence_folder(sequence, kind, settings)?;
+            let (Some(target_dir), Some(folder_name)) = (folder.parent(), folder.file_name())
+            else {
+                return None;
+            };
+            let folder_name = folder_name.to_string_lossy();
+            let created = match create_folders(dir, target_dir) {
+                Ok(created) => created,
+                Err(e) => {
+                    record_error(errors, e);
+                    return None;
+                }
+            };
+            let mut files: Vec<PathBuf> = sequence.iter().map(|f| f.path.clone()).collect();
+            if settings.move_companions {
+                files.extend(companion_files(sequence, &settings.extensions));
+            } else if settings.move_sidecars {
+                for sidecar in sequence.iter().flat_map(|f| sidecar_files(&f.path)) {
+                    if !files.contains(&sidecar) {
+                        files.push(sidecar);
+                    }
+                }
+            }
+            let copy = *action == Action::CopyToFolder;
+            let result = if copy {
+                copy_sequence_to_folder(&files, target_dir, &folder_name)
+            } else {
+                move_sequence_to_folder(&files, target_dir, &folder_name)
+            };
+            match result {
+                Ok(()) => {
+                    info!(
+                        "{} sequence to folder {}",
+                        if copy { "Copied" } else { "Moved" },
+                        folder.display()
+                    );
+                    if let Some(note) = note {
+                        journal::record(JournalEntry::Note {
+                            path: folder.clone(),
+                            note: note.to_string(),
+                        });
+                    }
+                    if settings.folder_metadata {
+                        let base_frame = base_frame(sequence, settings);
+                        journal::record(JournalEntry::CreateFile {
+                            path: folder.join(COMMENT_FILE_NAME),
+                        });
+                        let comment =
+                            folder_comment(sequence, kind, base_frame, settings.ev_format, note);
+                        if let Err(e) = describe_folder(&folder, &comment, base_frame.capture_time)
+                        {
+                            record_error(
+                                errors,
+                                format!("Failed to describe folder {}: {}", folder_name, e),
+                            );
+                        }
+                    }
+                    return Some(if copy {
+                        sequence.iter().map(|f| f.path.clone()).collect()
+                    } else {
+                        sequence
+                            .iter()
+                            .map(|f| folder.join(f.path.file_name().unwrap_or_default()))
+                            .collect()
+                    });
+                }
+                Err(e) => {
+                    created.remove_if_empty();
+                    record_error(errors, e);
+                }
+            }
+        }
+        Action::RenameInPlace => {
+            let files: Vec<PathBuf> = sequence.iter().map(|f| f.path.clone()).collect();
+            let first_name = files[0].file_name().unwrap_or_default().to_string_lossy();
+            if renamed_number(&first_name).is_some() {
+                info!(
+                    "{} is already renamed, leaving its sequence as it is",
+                    first_name
+                );
+                return Some(files);
+            }
+            let number = next_rename_number(dir);
+            match rename_sequence_in_place(&files, number) {
+                Ok(paths) => {
+                    info!("Renamed sequence as {}{:04}", RENAME_PREFIX, number);
+                    if let Some(note) = note {
+                        journal::record(JournalEntry::Note {
+                            path: paths[0].clone(),
+                            note: note.to_string(),
+                        });
+                    }
+                    return Some(paths);
+                }
+                Err(e) => record_error(errors, e),
+            }
+        }
+        Action::SaveSequencesToTextfile => {
+            let entry = SequenceEntry {
+                group: group_id(sequence),
+                files: sequence.iter().map(|f| f.path.as_path()).collect(),
+                biases: sequence.iter().map(|f| f.exposure_bias).collect(),
+                ev_format: settings.ev_format,
+            };
+            match output.append(dir, &entry) {
+                Ok(path) => info!("Appended sequence to {}", path.display()),
+                Err(e) => {
+                    record_error(
+                        errors,
+                        format!(
+                            "Failed to write to {}: {}",
+                            settings.textfile_format.file_name(),
+                            e
+                        ),
+                    );
+                }
+            }
+        }
+        Action::WriteGroupTags => {
+            let group = group_id(sequence);
+            for (index, file_meta) in sequence.iter().enumerate() {
+                let description = format!(
+                    "Exposure bracket {} ({}/{})",
+                    group,
+                    index + 1,
+                    sequence.len()
+                );
+                let embed = settings.embed_xmp && embedded_xmp::supports(&file_meta.path);
+                let document = if embed {
+                    Xmp::open_embedded(&file_meta.path)
+                } else {
+                    Xmp::open_sidecar(&file_meta.path)
+                };
+                let result = document.and_then(|mut document| {
+                    document.add_to_bag(
+                        &xmp::DC,
+                        "subject",
+                        &[BRACKET_KEYWORD.to_string(), group.clone()],
+                    );
+                    document.set_alt_text_if_missing(&xmp::DC, "description", &description);
+                    document.save()
+                });
+                if let Err(e) = result {
+                    record_error(
+                        errors,
+                        format!(
+                            "Failed to write XMP for {}: {}",
+                            file_meta.path.display(),
+                            e
+                        ),
+                    );
+                }
+            }
+            info!("Tagged sequence as {}", group);
+        }
+        Action::AddToCatalog => {
+            if let Some(catalog) = catalog {
+                let files = sequence
+                    .iter()
+                    .map(|f| CatalogFile {
+                        path: f.path.clone(),
+                        exposure_bias: f.exposure_bias,
+                    })
+                    .collect();
+                let first_file = sequence.first();
+                let mut entry = CatalogSequence::new(
+                    files,
+                    first_file.and_then(|f| f.capture_time),
+                    first_file.and_then(|f| f.camera.clone()),
+                );
+                entry.note = note.map(str::to_string);
+                catalog.add(entry);
+                info!("Added sequence to the catalog");
+            }
+        }
+        Action::WriteFusionScripts => {
+            let name = group_id(sequence);
+            let files: Vec<&Path> = sequence.iter().map(|f| f.path.as_path()).collect();
+            let path = hdr_tools::fusion_script_path(dir, &name);
+            let existed = path.exists();
+            match hdr_tools::write_fusion_script(&path, &name, &files, &settings.fusion_command) {
+                Ok(()) => {
+                    if !existed {
+                        journal::record(JournalEntry::CreateFile { path: path.clone() });
+                    }
+                    info!("Wrote fusion script {}", path.display());
+                }
+                Err(e) => record_error(
+                    errors,
+                    format!("Failed to write fusion script {}: {}", path.display(), e),
+                ),
+            }
+        }
+        Action::WriteHuginProjects => {
+            let files: Vec<(&Path, Option<Rational32>)> = sequence
+                .iter()
+                .map(|f| (f.path.as_path(), f.exposure_bias))
+                .collect();
+            let path = hdr_tools::hugin_project_path(dir, &group_id(sequence));
+            let existed = path.exists();
+            match hdr_tools::write_hugin_project(&path, &files) {
+                Ok(()) => {
+                    if !existed {
+                        journal::record(JournalEntry::CreateFile { path: path.clone() });
+                    }
+                    info!("Wrote Hugin project {}", path.display());
+                }
+                Err(e) => record_error(
+                    errors,
+                    format!("Failed to write Hugin project {}: {}", path.display(), e),
+                ),
+            }
+        }
+        Action::WriteHdrMergeCommands => {
+            let files: Vec<&Path> = sequence.iter().map(|f| f.path.as_path()).collect();
+            let command =
+                hdr_tools::hdrmerge_command(&settings.hdrmerge_path, &group_id(sequence), &files);
+            let path = hdr_tools::hdrmerge_script_path(dir);
+            let existed = path.exists();
+            // Replaced by each run, so running a folder again doesn't merge its sequences twice
+            let result = output
+                .append_text(&path, hdr_tools::HDRMERGE_SCRIPT_HEADER, command, true)
+                .and_then(|target| hdr_tools::make_executable(&target).map(|()| target));
+            match result {
+                Ok(target) => {
+                    if !existed {
+                        journal::record(JournalEntry::CreateFile {
+                            path: target.clone(),
+                        });
+                    }
+                    info!("Added HDRMerge command to {}", target.display());
+                }
+                Err(e) => record_error(
+                    errors,
+                    format!(
+                        "Failed to write HDRMerge commands {}: {}",
+                        path.display(),
+                        e
+                    ),
+                ),
+            }
+        }
+        Action::WriteDarktableGroups => {
+            let group = group_id(sequence);
+            for file_meta in sequence {
+                let result = Xmp::open_sidecar_at(darktable::sidecar_path(&file_meta.path))
+                    .and_then(|mut document| {
+                        document.add_to_bag(
+                            &xmp::DC,
+                            "subject",
+                            &[BRACKET_KEYWORD.to_string(), group.clone()],
+                        );
+                        document.save()
+                    });
+                if let Err(e) = result {
+                    record_error(
+                        errors,
+                        format!(
+                            "Failed to write XMP for {}: {}",
+                            file_meta.path.display(),
+                            e
+                        ),
+                    );
+                }
+            }
+            let files: Vec<&Path> = sequence.iter().map(|f| f.path.as_path()).collect();
+            let path = darktable::script_path(dir);
+            let existed = path.exists();
+            // Replaced by each run like the HDRMerge script
+            let result = output.append_text(
+                &path,
+                darktable::SCRIPT_HEADER,
+                darktable::group_command(&files),
+                true,
+            );
+            match result {
+                Ok(target) => {
+                    if !existed {
+                        journal::record(JournalEntry::CreateFile {
+                            path: target.clone(),
+                        });
+                    }
+                    info!("Added darktable group {} to {}", group, target.display());
+                }
+                Err(e) => record_error(
+                    errors,
+                    format!("Failed to write darktable script {}: {}", path.display(), e),
+                ),
+            }
+        }
+        Action::WriteLightroomKeywords => {
+            let name = keyword_name(sequence);
+            let keyword = format!("{}|{}", PARENT_KEYWORD, name);
+            for file_meta in sequence {
+                // Lightroom reads the XMP of DNG and JPEG files from the files themselves
+                let embed = settings.embed_xmp && embedded_xmp::supports(&file_meta.path);
+                let document = if embed {
+                    Xmp::open_embedded(&file_meta.path)
+                } else {
+                    Xmp::open_sidecar_at(xmp::lightroom_sidecar_path(&file_meta.path))
+                };
+                let result = document.and_then(|mut document| {
+                    document.add_to_bag(
+                        &xmp::LIGHTROOM,
+                        "hierarchicalSubject",
+                        std::slice::from_ref(&keyword),
+                    );
+                    // Lightroom also lists every level of a keyword as a flat one
+                    document.add_to_bag(
+                        &xmp::DC,
+                        "subject",
+                        &[PARENT_KEYWORD.to_string(), name.clone()],
+                    );
+                    document.save()
+                });
+                if let Err(e) = result {
+                    record_error(
+                        errors,
+                        format!(
+                            "Failed to write XMP for {}: {}",
+                            file_meta.path.display(),
+                            e
+                        ),
+                    );
+                }
+            }
+            info!("Added keyword {}", keyword);
+        }
+        Action::WriteDigikamTags => {
+            let name = keyword_name(sequence);
+            let tag = format!("{}/{}", PARENT_KEYWORD, name);
+            for file_meta in sequence {
+                let result = Xmp::open_sidecar(&file_meta.path).and_then(|mut document| {
+                    document.add_to_seq(&xmp::DIGIKAM, "TagsList", std::slice::from_ref(&tag));
+                    document.add_to_bag(
+                        &xmp::DC,
+                        "subject",
+                        &[PARENT_KEYWORD.to_string(), name.clone()],
+                    );
+                    document.save()
+                });
+                if let Err(e) = result {
+                    record_error(
+                        errors,
+                        format!(
+                            "Failed to write XMP for {}: {}",
+                            file_meta.path.display(),
+                            e
+                        ),
+                    );
+                }
+            }
+            info!("Added tag {}", tag);
+        }
+        Action::RunCommand => {
+            if settings.command_template.trim().is_empty() {
+                record_error(errors, "No command to run for the sequence".to_string());
+                return None;
+            }
+            let files: Vec<PathBuf> = sequence.iter().map(|f| f.path.clone()).collect();
+            let biases: Vec<Option<Rational32>> =
+                sequence.iter().map(|f| f.exposure_bias).collect();
+            let command =
+                script_actions::fill_command(&settings.command_template, &files, dir, &biases);
+            match script_actions::run_command(&command, dir) {
+                Ok(()) => info!("Ran command on sequence: {}", command),
+                Err(e) => record_error(errors, e),
+            }
+        }
+        Action::Script(file_name) => {
+            let files: Vec<PathBuf> = sequence.iter().map(|f| f.path.clone()).collect();
+            match script_actions::run(file_name, &files, kind, dir) {
+                Ok(()) => info!("Ran script {} on sequence", file_name),
+                Err(e) => record_error(errors, e),
+            }
+        }
+    }
+    None
+}
+
+/// The Lightroom keyword and digiKam tag of a sequence below [`PARENT_KEYWORD`],
+/// e.g. `2024-05-01_IMG_1234`. Named after the first file rather than
+/// numbered, so running a folder again gives its sequences the same keywords.
+fn keyword_name(sequence: &[FileMetadata]) -> String {
+    let Some(first_file) = sequence.first() else {
+        return String::new();
+    };
+    let stem = first_file
+        .path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    match first_file.capture_time {
+        Some(time) => format!("{}_{}", time.format("%Y-%m-%d"), stem),
+        None => stem.to_string(),
+    }
+}
+
+/// Identifier shared by all files of a sequence, e.g. `bracket-20240501-IMG_1234`.
+/// Galleries index it as a keyword, so searching for it brings up the whole
+/// bracket, and the date keeps it unique when camera file numbers wrap around.
+fn group_id(sequence: &[FileMetadata]) -> String {
+    let Some(first_file) = sequence.first() else {
+        return "bracket".to_string();
+    };
+    let stem = first_file
+        .path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    match first_file.capture_time {
+        Some(time) => format!("bracket-{}-{}", time.format("%Y%m%d"), stem),
+        None => format!("bracket-{}", stem),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn natural_cmp_compares_numbers_by_value() {
+        assert_eq!(natural_cmp("IMG_9999", "IMG_10000"), cmp::Ordering::Less);
+        assert_eq!(natural_cmp("IMG_10000", "IMG_9999"), cmp::Ordering::Greater);
+        assert_eq!(natural_cmp("IMG_2.ARW", "IMG_10.ARW"), cmp::Ordering::Less);
+    }
+
+    #[test]
+    fn natural_cmp_ignores_leading_zeros() {
+        assert_eq!(natural_cmp("IMG_0009", "IMG_10"), cmp::Ordering::Less);
+        assert_eq!(natural_cmp("IMG_0010", "IMG_9"), cmp::Ordering::Greater);
+        // Equal by value, the names themselves decide so the order is stable
+        assert_eq!(natural_cmp("IMG_0009", "IMG_9"), cmp::Ordering::Less);
+        assert_eq!(natural_cmp("IMG_9", "IMG_0009"), cmp::Ordering::Greater);
+    }
+
+    #[test]
+    fn natural_cmp_ignores_case() {
+        assert_eq!(
+            natural_cmp("img_0001.arw", "IMG_0002.ARW"),
+            cmp::Ordering::Less
+        );
+        assert_eq!(
+            natural_cmp("IMG_0003.ARW", "img_0002.arw"),
+            cmp::Ordering::Greater
+        );
+        assert_ne!(
+            natural_cmp("IMG_0001.ARW", "img_0001.arw"),
+            cmp::Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn natural_cmp_compares_suffixes_after_numbers() {
+        assert_eq!(
+            natural_cmp("IMG_0001.ARW", "IMG_0001.JPG"),
+            cmp::Ordering::Less
+        );
+        assert_eq!(natural_cmp("IMG_0001", "IMG_0001-2"), cmp::Ordering::Less);
+        assert_eq!(natural_cmp("IMG_0001a", "IMG_0001b"), cmp::Ordering::Less);
+        assert_eq!(natural_cmp("IMG_0001b", "IMG_0002a"), cmp::Ordering::Less);
+        assert_eq!(natural_cmp("IMG_0001", "IMG_0001"), cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn renamed_number_reads_the_sequence_number() {
+        assert_eq!(renamed_number("BRK0001_1of5_IMG_2034.NEF"), Some(1));
+        assert_eq!(renamed_number("BRK0042_3of3_DSC_0001.ARW"), Some(42));
+        assert_eq!(renamed_number("BRK12345_10of12_a.dng"), Some(12345));
+    }
+
+    #[test]
+    fn renamed_number_rejects_other_names() {
+        for name in [
+            "IMG_2034.NEF",
+            "BRK001_1of5_IMG.NEF",
+            "BRKabcd_1of5_IMG.NEF",
+            "BRK0001_1_IMG.NEF",
+            "BRK0001_of5_IMG.NEF",
+            "BRK0001_1of5",
+            "brk0001_1of5_IMG.NEF",
+        ] {
+            assert_eq!(renamed_number(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn renaming_continues_the_numbering_of_the_folder() {
+        let dir = std::env::temp_dir().join(format!("ebo-rename-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(next_rename_number(&dir), 1);
+
+        let files: Vec<PathBuf> = ["IMG_0001.ARW", "IMG_0002.ARW"]
+            .iter()
+            .map(|name| dir.join(name))
+            .collect();
+        for file in &files {
+            fs::write(file, "").unwrap();
+        }
+        fs::write(dir.join("BRK0007_1of3_IMG_0000.ARW"), "").unwrap();
+        let number = next_rename_number(&dir);
+        assert_eq!(number, 8);
+
+        let renamed = rename_sequence_in_place(&files, number).unwrap();
+        assert_eq!(
+            renamed,
+            [
+                dir.join("BRK0008_1of2_IMG_0001.ARW"),
+                dir.join("BRK0008_2of2_IMG_0002.ARW")
+            ]
+        );
+        assert!(renamed.iter().all(|path| path.exists()));
+        assert!(files.iter().all(|path| !path.exists()));
+        assert_eq!(next_rename_number(&dir), 9);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn folder_name_fills_in_the_tokens() {
+        let time = NaiveDate::from_ymd_opt(2024, 5, 1).and_then(|d| d.and_hms_opt(12, 30, 0));
+        assert_eq!(
+            fill_folder_name(DEFAULT_FOLDER_NAME, "IMG_1234", time, None),
+            "IMG_1234"
+        );
+        assert_eq!(
+            fill_folder_name(
+                "{date} {name} - {note}",
+                "IMG_1234",
+                time,
+                Some(" ghosting ")
+            ),
+            "2024-05-01 IMG_1234 - ghosting"
+        );
+    }
+
+    #[test]
+    fn folder_name_drops_separators_of_empty_tokens() {
+        assert_eq!(
+            fill_folder_name("{name} - {note}", "IMG_1234", None, None),
+            "IMG_1234"
+        );
+        assert_eq!(
+            fill_folder_name("{date}_{name}", "IMG_1234", None, None),
+            "IMG_1234"
+        );
+        assert_eq!(
+            fill_folder_name("{note}", "IMG_1234", None, None),
+            "IMG_1234"
+        );
+    }
+
+    #[test]
+    fn folder_name_replaces_characters_file_systems_reject() {
+        assert_eq!(
+            fill_folder_name("{note}", "IMG_1234", None, Some("sunset/dusk: best?")),
+            "sunset_dusk_ best"
+        );
+    }
+
+    #[test]
+    fn sequence_folders_are_named_with_a_file_name() {
+        assert!(contains_word("IMG_1234", "IMG_1234"));
+        assert!(contains_word("2024-05-01 IMG_1234 - ghosting", "IMG_1234"));
+        assert!(!contains_word("IMG_12345", "IMG_1234"));
+        assert!(!contains_word("xIMG_1234", "IMG_1234"));
+        assert!(!contains_word("IMG_1234", ""));
     }
 }
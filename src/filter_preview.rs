@@ -0,0 +1,230 @@
+//! Read-only preview of the files the auto-bracket filter skips, to check it
+//! isn't discarding a whole camera's worth of genuine brackets before a run.
+
+use crate::app::exposure_mode_to_string;
+use crate::file_utils::{has_matching_extension, is_auto_bracket, BracketModeOverride};
+use crate::metadata_cache::MetadataCache;
+use eframe::egui;
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub struct FilterPreviewSettings {
+    pub extensions: Vec<String>,
+    pub bracket_mode_overrides: Vec<BracketModeOverride>,
+    pub use_metadata_cache: bool,
+}
+
+#[derive(Debug)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub camera: Option<String>,
+    pub exposure_mode: Option<u16>,
+}
+
+#[derive(Debug, Default)]
+pub struct FilterPreview {
+    /// Files with readable metadata, per camera
+    pub files_per_camera: BTreeMap<Option<String>, usize>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+impl FilterPreview {
+    /// Skipped files per camera and exposure mode
+    fn skipped_by_camera(&self) -> BTreeMap<(Option<&str>, Option<u16>), usize> {
+        let mut counts = BTreeMap::new();
+        for file in &self.skipped {
+            *counts
+                .entry((file.camera.as_deref(), file.exposure_mode))
+                .or_default() += 1;
+        }
+        counts
+    }
+}
+
+/// Reads the files of `sources`, folders or single files, and lists those the
+/// auto-bracket filter would skip. Nothing is processed.
+pub fn preview_filter(sources: &[PathBuf], settings: &FilterPreviewSettings) -> FilterPreview {
+    let mut cache = if settings.use_metadata_cache {
+        MetadataCache::load()
+    } else {
+        MetadataCache::default()
+    };
+
+    let mut paths = Vec::new();
+    for source in sources {
+        if source.is_dir() {
+            match fs::read_dir(source) {
+                Ok(entries) => paths.extend(entries.flatten().map(|e| e.path())),
+                Err(e) => warn!("Failed to read directory {}: {}", source.display(), e),
+            }
+        } else {
+            paths.push(source.clone());
+        }
+    }
+    paths.retain(|p| p.is_file() && has_matching_extension(p, &settings.extensions));
+    paths.sort();
+
+    let mut preview = FilterPreview::default();
+    for path in paths {
+        let Some(metadata) = cache.read(&path) else {
+            continue;
+        };
+        *preview
+            .files_per_camera
+            .entry(metadata.camera.clone())
+            .or_default() += 1;
+        if !is_auto_bracket(&metadata, &settings.bracket_mode_overrides) {
+            preview.skipped.push(SkippedFile {
+                path,
+                camera: metadata.camera,
+                exposure_mode: metadata.exposure_mode,
+            });
+        }
+    }
+
+    if settings.use_metadata_cache {
+        cache.save();
+    }
+    info!(
+        "The auto-bracket filter skips {} of {} files",
+        preview.skipped.len(),
+        preview.files_per_camera.values().sum::<usize>()
+    );
+    preview
+}
+
+/// Window listing the skipped files of the picked folders, scanned in the
+/// background whenever it is opened.
+#[derive(Default)]
+pub struct FilterPreviewWindow {
+    pub open: bool,
+    sources: Vec<PathBuf>,
+    result: Arc<Mutex<Option<FilterPreview>>>,
+    running: bool,
+    show_files: bool,
+}
+
+impl FilterPreviewWindow {
+    pub fn open(&mut self, sources: Vec<PathBuf>, settings: FilterPreviewSettings) {
+        self.open = true;
+        self.sources = sources;
+        self.start(settings);
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, settings: impl FnOnce() -> FilterPreviewSettings) {
+        if !self.open {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Skipped by the Auto-Bracket Filter")
+            .open(&mut open)
+            .default_width(520.0)
+            .show(ctx, |ui| self.show_contents(ui, settings));
+        self.open = open;
+    }
+
+    fn show_contents(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings: impl FnOnce() -> FilterPreviewSettings,
+    ) {
+        let result = self.result.lock().unwrap().is_some();
+        if self.running && result {
+            self.running = false;
+        }
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.running, egui::Button::new("Scan Again"))
+                .on_hover_text("Scan with the current file types and camera exceptions")
+                .clicked()
+            {
+                self.start(settings());
+            }
+            if self.running {
+                ui.spinner();
+                ui.label("Reading files…");
+            }
+        });
+
+        let result = self.result.lock().unwrap();
+        let Some(preview) = result.as_ref() else {
+            return;
+        };
+        let total: usize = preview.files_per_camera.values().sum();
+        ui.separator();
+        ui.label(format!(
+            "{} of {} files would be skipped",
+            preview.skipped.len(),
+            total
+        ));
+        for ((camera, mode), count) in preview.skipped_by_camera() {
+            let camera_files = preview
+                .files_per_camera
+                .get(&camera.map(str::to_string))
+                .copied()
+                .unwrap_or_default();
+            let mode = mode.map_or("no exposure mode", exposure_mode_to_string);
+            let text = format!(
+                "{}: {} × {}",
+                camera.unwrap_or("Unknown camera"),
+                count,
+                mode
+            );
+            if count == camera_files {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("⚠ {}, all files of this camera", text),
+                );
+            } else {
+                ui.label(text);
+            }
+        }
+
+        if preview.skipped.is_empty() {
+            return;
+        }
+        ui.checkbox(&mut self.show_files, "List files");
+        if !self.show_files {
+            return;
+        }
+        egui::ScrollArea::vertical()
+            .max_height(360.0)
+            .show(ui, |ui| {
+                egui::Grid::new("filter_preview_files")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("File");
+                        ui.strong("Camera");
+                        ui.strong("Exposure Mode");
+                        ui.end_row();
+                        for file in &preview.skipped {
+                            ui.label(file.path.file_name().unwrap_or_default().to_string_lossy())
+                                .on_hover_text(file.path.display().to_string());
+                            ui.label(file.camera.as_deref().unwrap_or("Unknown"));
+                            ui.label(match file.exposure_mode {
+                                Some(mode) => {
+                                    format!("{} ({})", exposure_mode_to_string(mode), mode)
+                                }
+                                None => "None".to_string(),
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    fn start(&mut self, settings: FilterPreviewSettings) {
+        *self.result.lock().unwrap() = None;
+        self.running = true;
+        let sources = self.sources.clone();
+        let result = Arc::clone(&self.result);
+        thread::spawn(move || {
+            let preview = preview_filter(&sources, &settings);
+            *result.lock().unwrap() = Some(preview);
+        });
+    }
+}
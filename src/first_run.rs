@@ -0,0 +1,335 @@
+//! Wizard shown on the first launch: the user picks the frames of one sample
+//! bracket, the settings matching how their camera records exposure bias are
+//! derived from it, and a dry run on their folder shows what would be found.
+
+use crate::app::{exposure_mode_to_string, Action};
+use crate::exposure_repair::AUTO_BRACKET;
+use crate::file_utils::format_sequence;
+use crate::metadata_cache::MetadataCache;
+use ebo_core::{DeltaReference, EvMode};
+use eframe::egui;
+use num_rational::Rational32;
+use std::path::PathBuf;
+
+/// Set once the wizard was finished or skipped, so it isn't shown again.
+pub const FIRST_RUN_DONE_KEY: &str = "first_run_done";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum Step {
+    #[default]
+    Sample,
+    Action,
+    Preview,
+}
+
+struct SampleFrame {
+    path: PathBuf,
+    exposure_bias: Option<Rational32>,
+    exposure_mode: Option<u16>,
+}
+
+/// The settings detected from a sample bracket.
+struct Encoding {
+    sequence: Vec<Rational32>,
+    filter_by_auto_bracket: bool,
+    /// What was detected, in the user's terms
+    notes: Vec<String>,
+}
+
+/// Derives the sequence from the biases of one bracket. Deltas from the first
+/// frame are used if the bracket was shot with exposure compensation, so it
+/// matches at any compensation.
+fn detect_encoding(frames: &[SampleFrame]) -> Result<Encoding, String> {
+    if frames.len() < 2 {
+        return Err("Pick all frames of one bracket, at least two.".to_string());
+    }
+    let mut biases = Vec::new();
+    for frame in frames {
+        match frame.exposure_bias {
+            Some(bias) => biases.push(bias),
+            None => {
+                return Err(format!(
+                    "{} has no exposure bias. The camera may not record it, \"Repair…\" in the exposure bias table can add it.",
+                    frame.path.display()
+                ))
+            }
+        }
+    }
+    if biases.iter().all(|b| *b == biases[0]) {
+        return Err(format!(
+            "All frames have an exposure bias of {} EV. Either they aren't one bracket, or the camera records the exposure compensation only.",
+            biases[0]
+        ));
+    }
+
+    let mut notes = Vec::new();
+    let sequence = if biases.contains(&Rational32::from(0)) {
+        notes.push("The camera records the bias of every frame.".to_string());
+        biases
+    } else {
+        notes.push(format!(
+            "The bracket was shot with exposure compensation, the sequence is taken relative to the first frame ({} EV).",
+            biases[0]
+        ));
+        biases.iter().map(|b| b - biases[0]).collect()
+    };
+    notes.push(
+        "Delta mode compares the differences between frames, so brackets shot with other exposure compensations match too."
+            .to_string(),
+    );
+
+    let filter_by_auto_bracket = frames.iter().all(|f| f.exposure_mode == Some(AUTO_BRACKET));
+    if !filter_by_auto_bracket {
+        let modes: Vec<&str> = frames
+            .iter()
+            .map(|f| {
+                f.exposure_mode
+                    .map_or("no exposure mode", exposure_mode_to_string)
+            })
+            .collect();
+        notes.push(format!(
+            "The frames aren't marked as auto bracketed ({}), so the auto-bracket filter is turned off.",
+            modes.join(", ")
+        ));
+    }
+    Ok(Encoding {
+        sequence,
+        filter_by_auto_bracket,
+        notes,
+    })
+}
+
+/// The settings chosen in the wizard, applied before the dry run is started.
+pub struct WizardOutcome {
+    pub sequence: String,
+    pub ev_mode: EvMode,
+    pub delta_reference: DeltaReference,
+    pub filter_by_auto_bracket: bool,
+    pub action: Action,
+    pub folder: PathBuf,
+}
+
+#[derive(Default)]
+pub struct FirstRunWizard {
+    pub open: bool,
+    step: Step,
+    sample: Vec<SampleFrame>,
+    encoding: Option<Result<Encoding, String>>,
+    action: Option<Action>,
+    folder: Option<PathBuf>,
+}
+
+impl FirstRunWizard {
+    /// Starts over, e.g. when opened again from the toolbar.
+    pub fn restart(&mut self) {
+        *self = Self {
+            open: true,
+            ..Self::default()
+        };
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, extensions: &[String]) -> Option<WizardOutcome> {
+        if !self.open {
+            return None;
+        }
+        let mut open = true;
+        let mut skipped = false;
+        let mut outcome = None;
+        egui::Window::new("Getting Started")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                outcome = self.show_contents(ui, extensions, &mut skipped)
+            });
+        if !open || skipped || outcome.is_some() {
+            self.open = false;
+            ctx.data_mut(|d| d.insert_persisted(egui::Id::new(FIRST_RUN_DONE_KEY), true));
+        }
+        outcome
+    }
+
+    fn show_contents(
+        &mut self,
+        ui: &mut egui::Ui,
+        extensions: &[String],
+        skipped: &mut bool,
+    ) -> Option<WizardOutcome> {
+        let mut outcome = None;
+        match self.step {
+            Step::Sample => self.show_sample(ui, extensions),
+            Step::Action => self.show_action(ui),
+            Step::Preview => outcome = self.show_preview(ui),
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui
+                .button("Skip")
+                .on_hover_text("Set everything up by hand")
+                .clicked()
+            {
+                *skipped = true;
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let can_continue = match self.step {
+                    Step::Sample => matches!(self.encoding, Some(Ok(_))),
+                    Step::Action => self.action.is_some(),
+                    Step::Preview => false,
+                };
+                if self.step != Step::Preview
+                    && ui
+                        .add_enabled(can_continue, egui::Button::new("Next"))
+                        .clicked()
+                {
+                    self.step = match self.step {
+                        Step::Sample => Step::Action,
+                        Step::Action | Step::Preview => Step::Preview,
+                    };
+                }
+                if self.step != Step::Sample && ui.button("Back").clicked() {
+                    self.step = match self.step {
+                        Step::Preview => Step::Action,
+                        Step::Action | Step::Sample => Step::Sample,
+                    };
+                }
+            });
+        });
+        outcome
+    }
+
+    fn show_sample(&mut self, ui: &mut egui::Ui, extensions: &[String]) {
+        ui.strong("1. Pick a sample bracket");
+        ui.label("Select all frames of one bracket you shot, e.g. the three or five files of one scene. Their exposure biases show how your camera records brackets.");
+        if ui.button("Pick Files…").clicked() {
+            if let Some(paths) = rfd::FileDialog::new()
+                .add_filter("Raw Images", extensions)
+                .pick_files()
+            {
+                self.read_sample(paths);
+            }
+        }
+        if self.sample.is_empty() {
+            return;
+        }
+
+        egui::Grid::new("first_run_sample")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("File");
+                ui.strong("Exposure Bias");
+                ui.strong("Exposure Mode");
+                ui.end_row();
+                for frame in &self.sample {
+                    ui.label(frame.path.file_name().unwrap_or_default().to_string_lossy());
+                    ui.label(
+                        frame
+                            .exposure_bias
+                            .map_or("None".to_string(), |b| format!("{} EV", b)),
+                    );
+                    ui.label(frame.exposure_mode.map_or("None", exposure_mode_to_string));
+                    ui.end_row();
+                }
+            });
+        match &self.encoding {
+            Some(Ok(encoding)) => {
+                ui.label(format!("Sequence: {}", format_sequence(&encoding.sequence)));
+                for note in &encoding.notes {
+                    ui.label(note);
+                }
+            }
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", e));
+            }
+            None => {}
+        }
+    }
+
+    fn read_sample(&mut self, mut paths: Vec<PathBuf>) {
+        paths.sort();
+        let mut cache = MetadataCache::default();
+        self.sample = paths
+            .into_iter()
+            .map(|path| {
+                let metadata = cache.read(&path);
+                SampleFrame {
+                    exposure_bias: metadata.as_ref().and_then(|m| m.exposure_bias),
+                    exposure_mode: metadata.as_ref().and_then(|m| m.exposure_mode),
+                    path,
+                }
+            })
+            .collect();
+        self.encoding = Some(detect_encoding(&self.sample));
+        if self.folder.is_none() {
+            self.folder = self
+                .sample
+                .first()
+                .and_then(|f| f.path.parent())
+                .map(PathBuf::from);
+        }
+    }
+
+    fn show_action(&mut self, ui: &mut egui::Ui) {
+        ui.strong("2. Choose what happens with the brackets");
+        let actions = [
+            (
+                Action::MoveToFolder,
+                "Every bracket gets its own folder, named after its first file.",
+            ),
+            (
+                Action::CopyToFolder,
+                "Every bracket is copied into its own folder, the originals stay where they are.",
+            ),
+            (
+                Action::SaveSequencesToTextfile,
+                "The brackets are listed in sequences.txt, the files stay where they are.",
+            ),
+            (
+                Action::WriteGroupTags,
+                "The frames of a bracket are tagged as a group in their XMP sidecars.",
+            ),
+            (
+                Action::AddToCatalog,
+                "The brackets are only added to the catalog to browse them later.",
+            ),
+        ];
+        for (action, description) in actions {
+            ui.radio_value(&mut self.action, Some(action.clone()), action.to_string());
+            ui.indent(description, |ui| ui.label(description));
+        }
+    }
+
+    fn show_preview(&mut self, ui: &mut egui::Ui) -> Option<WizardOutcome> {
+        ui.strong("3. Try it on your photos");
+        ui.label("A dry run finds the brackets in a folder and lists what would be done with them. No file is changed until you confirm.");
+        ui.horizontal(|ui| {
+            let label = ui.label(match &self.folder {
+                Some(folder) => folder.display().to_string(),
+                None => "No folder selected".to_string(),
+            });
+            if ui.button("Browse…").labelled_by(label.id).clicked() {
+                if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                    self.folder = Some(folder);
+                }
+            }
+        });
+        let can_run = self.folder.is_some() && self.action.is_some();
+        if !ui
+            .add_enabled(can_run, egui::Button::new("Preview (Dry Run)"))
+            .clicked()
+        {
+            return None;
+        }
+        let Some(Ok(encoding)) = &self.encoding else {
+            return None;
+        };
+        Some(WizardOutcome {
+            sequence: format_sequence(&encoding.sequence),
+            ev_mode: EvMode::Delta,
+            delta_reference: DeltaReference::Zero,
+            filter_by_auto_bracket: encoding.filter_by_auto_bracket,
+            action: self.action.clone()?,
+            folder: self.folder.clone()?,
+        })
+    }
+}
@@ -0,0 +1,102 @@
+//! Makes created sequence folders describe themselves in file managers: the
+//! folder's modification time becomes the capture time of the sequence, and
+//! a `desktop.ini` (Windows Explorer) or `.directory` (KDE Dolphin) entry
+//! holds a comment shown as tooltip.
+
+use chrono::{Local, NaiveDateTime, TimeZone};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// The file in the folder that holds the comment
+#[cfg(windows)]
+pub const COMMENT_FILE_NAME: &str = "desktop.ini";
+#[cfg(not(windows))]
+pub const COMMENT_FILE_NAME: &str = ".directory";
+
+/// Writes the comment entry, then sets the modification time, which writing
+/// the entry would otherwise change again.
+pub fn describe_folder(
+    folder: &Path,
+    comment: &str,
+    capture_time: Option<NaiveDateTime>,
+) -> io::Result<()> {
+    write_comment(folder, comment)?;
+    if let Some(time) = capture_time.and_then(to_system_time) {
+        set_modified(folder, time)?;
+    }
+    Ok(())
+}
+
+/// EXIF capture times have no time zone, they are taken as local time.
+fn to_system_time(time: NaiveDateTime) -> Option<SystemTime> {
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(SystemTime::from)
+}
+
+#[cfg(windows)]
+fn write_comment(folder: &Path, comment: &str) -> io::Result<()> {
+    use windows::core::HSTRING;
+    use windows::Win32::Storage::FileSystem::{
+        GetFileAttributesW, SetFileAttributesW, FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_READONLY,
+        FILE_ATTRIBUTE_SYSTEM, FILE_FLAGS_AND_ATTRIBUTES, INVALID_FILE_ATTRIBUTES,
+    };
+
+    let path = folder.join(COMMENT_FILE_NAME);
+    // Explorer reads desktop.ini as UTF-16 with BOM
+    let content = format!("[.ShellClassInfo]\r\nInfoTip={}\r\n", single_line(comment));
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(content.encode_utf16().flat_map(u16::to_le_bytes));
+    fs::write(&path, bytes)?;
+
+    // Explorer only honors desktop.ini in read-only or system folders, the
+    // read-only flag of a folder doesn't protect its files
+    // SAFETY: both paths are valid, null-terminated HSTRINGs
+    unsafe {
+        SetFileAttributesW(
+            &HSTRING::from(path.as_path()),
+            FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM,
+        )?;
+        let folder = HSTRING::from(folder);
+        let attributes = GetFileAttributesW(&folder);
+        if attributes == INVALID_FILE_ATTRIBUTES {
+            return Err(io::Error::last_os_error());
+        }
+        SetFileAttributesW(
+            &folder,
+            FILE_FLAGS_AND_ATTRIBUTES(attributes) | FILE_ATTRIBUTE_READONLY,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn write_comment(folder: &Path, comment: &str) -> io::Result<()> {
+    let content = format!("[Desktop Entry]\nComment={}\n", single_line(comment));
+    fs::write(folder.join(COMMENT_FILE_NAME), content)
+}
+
+fn single_line(comment: &str) -> String {
+    comment.replace(['\r', '\n'], " ")
+}
+
+#[cfg(windows)]
+fn set_modified(folder: &Path, time: SystemTime) -> io::Result<()> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use windows::Win32::Storage::FileSystem::{FILE_FLAG_BACKUP_SEMANTICS, FILE_WRITE_ATTRIBUTES};
+
+    // Directories can only be opened with backup semantics
+    let file = fs::OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES.0)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS.0)
+        .open(folder)?;
+    file.set_modified(time)
+}
+
+#[cfg(not(windows))]
+fn set_modified(folder: &Path, time: SystemTime) -> io::Result<()> {
+    fs::File::open(folder)?.set_modified(time)
+}
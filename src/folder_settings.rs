@@ -0,0 +1,272 @@
+//! Per-folder defaults in a `.ebo.toml` file kept with the photos, e.g. for
+//! per-client folder conventions:
+//!
+//! ```toml
+//! sequence = "0, -1, 1"
+//! action = "move_to_folder"
+//! ev_mode = "delta"
+//! delta_reference = "first"
+//! ev_tolerance = 0.05
+//! match_mode = "greedy"
+//! filter_by_auto_bracket = false
+//! extensions = ["arw", "dng"]
+//! anchor_bias = "0"
+//! burst_gap = 3.0
+//! max_sequence_span = 10.0
+//! detect_other_brackets = true
+//! computed_exposure = true
+//! group_per_camera = true
+//! auto_detect = false
+//! subfolder_depth = 3
+//! date_folders = "day"
+//! folder_name = "{date} {name} - {note}"
+//! file_order = "capture_time"
+//! destination_root = "D:/HDR"
+//! write_manifest = true
+//! remove_empty_folders = true
+//! move_sidecars = true
+//! move_companions = true
+//!
+//! [[bracket_mode_overrides]]
+//! camera = "ILCE-7RM5"
+//! exposure_mode = 1
+//!
+//! [[clock_offsets]]
+//! camera = "EOS R5"
+//! seconds = -3600
+//! ```
+//!
+//! Every key is optional, missing ones keep the current settings.
+
+use crate::app::{describe_actions, deserialize_optional_actions, Action, FileOrder};
+use crate::file_utils::{BracketModeOverride, ClockOffset, DateFolders};
+use ebo_core::{DeltaReference, EvMode, MatchMode};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = ".ebo.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FolderSettings {
+    pub sequence: Option<String>,
+    /// One action or a list of them, e.g. `["move_to_folder", "save_sequences_to_textfile"]`
+    #[serde(default, deserialize_with = "deserialize_optional_actions")]
+    pub action: Option<Vec<Action>>,
+    pub ev_mode: Option<EvMode>,
+    /// `zero`, `first`, `middle` or a frame number, see [`DeltaReference`]
+    #[serde(default, deserialize_with = "deserialize_delta_reference")]
+    pub delta_reference: Option<DeltaReference>,
+    /// How far a bias may be off the sequence value and still match, in EV
+    pub ev_tolerance: Option<f64>,
+    /// `overlapping` or `greedy`, see [`MatchMode`]
+    pub match_mode: Option<MatchMode>,
+    pub filter_by_auto_bracket: Option<bool>,
+    pub bracket_mode_overrides: Option<Vec<BracketModeOverride>>,
+    pub clock_offsets: Option<Vec<ClockOffset>>,
+    pub use_metadata_cache: Option<bool>,
+    pub extensions: Option<Vec<String>>,
+    pub anchor_bias: Option<String>,
+    /// Longest pause within a burst in seconds, turns burst splitting on
+    pub burst_gap: Option<f64>,
+    /// Longest time a sequence may be shot over in seconds, turns the limit on
+    pub max_sequence_span: Option<f64>,
+    pub detect_other_brackets: Option<bool>,
+    /// Match on the exposure computed from shutter speed, aperture and ISO
+    pub computed_exposure: Option<bool>,
+    /// Search the files of each camera on their own
+    pub group_per_camera: Option<bool>,
+    /// Find brackets by their evenly spaced biases instead of the sequence
+    pub auto_detect: Option<bool>,
+    pub date_folders: Option<DateFolders>,
+    /// Name of the sequence folders, e.g. `"{date} {name} - {note}"`
+    pub folder_name: Option<String>,
+    /// `filesystem`, `capture_time` or `file_name`
+    pub file_order: Option<FileOrder>,
+    /// Where sequence folders are created, relative to the folder unless absolute
+    pub destination_root: Option<PathBuf>,
+    /// Hash the organized files into an archive manifest after each run
+    pub write_manifest: Option<bool>,
+    /// Remove scanned subfolders a move left empty
+    pub remove_empty_folders: Option<bool>,
+    /// Move the XMP sidecars of a sequence's files along with them
+    pub move_sidecars: Option<bool>,
+    /// Move the files named like a sequence's files along with them
+    pub move_companions: Option<bool>,
+    /// Levels of subfolders scanned below the folder, 0 for none
+    pub subfolder_depth: Option<usize>,
+}
+
+impl FolderSettings {
+    /// Reads the settings file of `folder`, `Ok(None)` if it has none.
+    pub fn load(folder: &Path) -> Result<Option<Self>, String> {
+        let path = folder.join(FILE_NAME);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        toml::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("Invalid {}: {}", path.display(), e))
+    }
+
+    /// A short list of what the file sets, for offering to apply it.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(sequence) = &self.sequence {
+            parts.push(format!("sequence {}", sequence));
+        }
+        if let Some(actions) = &self.action {
+            parts.push(format!("action \"{}\"", describe_actions(actions)));
+        }
+        if let Some(ev_mode) = &self.ev_mode {
+            parts.push(ev_mode.to_string());
+        }
+        if let Some(delta_reference) = &self.delta_reference {
+            parts.push(format!("deltas from the {}", delta_reference));
+        }
+        if let Some(tolerance) = self.ev_tolerance {
+            parts.push(format!("biases matched within ±{} EV", tolerance));
+        }
+        if let Some(match_mode) = self.match_mode {
+            parts.push(format!("{} matches", match_mode.to_string().to_lowercase()));
+        }
+        if let Some(filter) = self.filter_by_auto_bracket {
+            parts.push(format!(
+                "auto-bracket filter {}",
+                if filter { "on" } else { "off" }
+            ));
+        }
+        if let Some(overrides) = &self.bracket_mode_overrides {
+            parts.push(format!("{} auto-bracket exceptions", overrides.len()));
+        }
+        if let Some(offsets) = &self.clock_offsets {
+            parts.push(format!("{} camera clock offsets", offsets.len()));
+        }
+        if let Some(cache) = self.use_metadata_cache {
+            parts.push(format!(
+                "metadata cache {}",
+                if cache { "on" } else { "off" }
+            ));
+        }
+        if let Some(extensions) = &self.extensions {
+            parts.push(format!("extensions {}", extensions.join(", ")));
+        }
+        if let Some(anchor_bias) = &self.anchor_bias {
+            parts.push(format!("anchored on {} EV", anchor_bias));
+        }
+        if let Some(burst_gap) = self.burst_gap {
+            parts.push(format!("bursts split at {} s", burst_gap));
+        }
+        if let Some(max_sequence_span) = self.max_sequence_span {
+            parts.push(format!("sequences within {} s", max_sequence_span));
+        }
+        if let Some(detect) = self.detect_other_brackets {
+            parts.push(format!(
+                "aperture, white balance and flash brackets {}",
+                if detect { "detected" } else { "ignored" }
+            ));
+        }
+        if let Some(computed) = self.computed_exposure {
+            parts.push(if computed {
+                "EV computed from the exposure settings".to_string()
+            } else {
+                "EV from the recorded bias".to_string()
+            });
+        }
+        if let Some(group) = self.group_per_camera {
+            parts.push(if group {
+                "grouped per camera".to_string()
+            } else {
+                "cameras grouped together".to_string()
+            });
+        }
+        if let Some(auto_detect) = self.auto_detect {
+            parts.push(format!(
+                "brackets {}",
+                if auto_detect {
+                    "auto-detected"
+                } else {
+                    "matched to the sequence"
+                }
+            ));
+        }
+        if let Some(date_folders) = self.date_folders {
+            parts.push(format!("date folders \"{}\"", date_folders));
+        }
+        if let Some(folder_name) = &self.folder_name {
+            parts.push(format!("folders named \"{}\"", folder_name));
+        }
+        if let Some(file_order) = &self.file_order {
+            parts.push(
+                match file_order {
+                    FileOrder::Selection => "files in filesystem order",
+                    FileOrder::CaptureTime => "files sorted by capture time",
+                    FileOrder::FileName => "files sorted by name",
+                    FileOrder::NaturalFileName => "files sorted by name and number",
+                }
+                .to_string(),
+            );
+        }
+        if let Some(root) = &self.destination_root {
+            parts.push(format!("sequence folders in {}", root.display()));
+        }
+        if let Some(write_manifest) = self.write_manifest {
+            parts.push(format!(
+                "archive manifest {}",
+                if write_manifest { "on" } else { "off" }
+            ));
+        }
+        if let Some(remove) = self.remove_empty_folders {
+            parts.push(format!(
+                "emptied subfolders {}",
+                if remove { "removed" } else { "kept" }
+            ));
+        }
+        if let Some(move_sidecars) = self.move_sidecars {
+            parts.push(format!(
+                "XMP sidecars {}",
+                if move_sidecars { "taken along" } else { "left" }
+            ));
+        }
+        if let Some(move_companions) = self.move_companions {
+            parts.push(format!(
+                "files of the same name {}",
+                if move_companions {
+                    "taken along"
+                } else {
+                    "left"
+                }
+            ));
+        }
+        if let Some(depth) = self.subfolder_depth {
+            parts.push(format!("subfolders {} levels deep", depth));
+        }
+        if parts.is_empty() {
+            "no settings".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Takes the reference as a string like in the API, numbers are accepted bare too.
+fn deserialize_delta_reference<'de, D>(deserializer: D) -> Result<Option<DeltaReference>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Name(String),
+        Position(usize),
+    }
+    let value = match Value::deserialize(deserializer)? {
+        Value::Name(name) => name,
+        Value::Position(position) => position.to_string(),
+    };
+    value.parse().map(Some).map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,241 @@
+//! Files that hand the sequences to HDR tools: a script per sequence that
+//! aligns and fuses its frames with align_image_stack and enfuse, from a
+//! command template, a Hugin project per sequence stacking its frames, and
+//! a script per folder merging each sequence into a DNG with HDRMerge.
+
+use num_rational::Rational32;
+use num_traits::ToPrimitive;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Aligns the frames, then fuses the aligned ones next to the script
+pub const DEFAULT_FUSION_COMMAND: &str =
+    "align_image_stack -a \"{name}_aligned_\" {files}\nenfuse -o \"{name}_fused.tif\" {aligned}";
+
+/// HDRMerge as found on the `PATH`
+pub const DEFAULT_HDRMERGE_PATH: &str = "hdrmerge";
+
+#[cfg(windows)]
+const SCRIPT_EXTENSION: &str = "bat";
+#[cfg(not(windows))]
+const SCRIPT_EXTENSION: &str = "sh";
+
+/// Starts the HDRMerge script, run from its folder so the DNGs go there
+#[cfg(windows)]
+pub const HDRMERGE_SCRIPT_HEADER: &str =
+    "@echo off\r\nrem HDRMerge commands\r\ncd /d \"%~dp0\"\r\n";
+#[cfg(not(windows))]
+pub const HDRMERGE_SCRIPT_HEADER: &str =
+    "#!/bin/sh\n# HDRMerge commands\ncd \"$(dirname \"$0\")\"\n";
+
+/// Where the fusion script of the sequence `name` goes.
+pub fn fusion_script_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}_fuse.{}", name, SCRIPT_EXTENSION))
+}
+
+/// Fills in `template`: `{files}` becomes the quoted paths of the frames,
+/// `{name}` the name of the sequence and `{aligned}` the quoted files
+/// `align_image_stack -a "{name}_aligned_"` writes for them. The name comes
+/// from a file name and the template may use it within quotes, so anything
+/// but letters, digits, `-`, `_` and `.` is replaced by `_`.
+fn fusion_commands(template: &str, name: &str, files: &[&Path]) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let quoted: Vec<String> = files
+        .iter()
+        .map(|file| quote(&file.to_string_lossy()))
+        .collect();
+    let aligned: Vec<String> = (0..files.len())
+        .map(|i| quote(&format!("{}_aligned_{:04}.tif", name, i)))
+        .collect();
+    fill_template(template, |token| match token {
+        "files" => Some(quoted.join(" ")),
+        "aligned" => Some(aligned.join(" ")),
+        "name" => Some(name.clone()),
+        _ => None,
+    })
+}
+
+/// Writes the script running the filled in `template` at `path`, from the
+/// folder of the script, so the tools write their results next to it.
+pub fn write_fusion_script(
+    path: &Path,
+    name: &str,
+    files: &[&Path],
+    template: &str,
+) -> io::Result<()> {
+    let commands = fusion_commands(template, name, files);
+    #[cfg(windows)]
+    let content = format!(
+        "@echo off\r\nrem Exposure fusion of {}\r\ncd /d \"%~dp0\"\r\n{}\r\n",
+        name,
+        commands.lines().collect::<Vec<_>>().join("\r\n")
+    );
+    #[cfg(not(windows))]
+    let content = format!(
+        "#!/bin/sh\n# Exposure fusion of {}\nset -e\ncd \"$(dirname \"$0\")\"\n{}\n",
+        name, commands
+    );
+    fs::write(path, content)?;
+    make_executable(path)
+}
+
+/// Where the HDRMerge script of the sequences in `dir` goes.
+pub fn hdrmerge_script_path(dir: &Path) -> PathBuf {
+    dir.join(format!("hdrmerge.{}", SCRIPT_EXTENSION))
+}
+
+/// The line of the HDRMerge script merging `files` into `{name}.dng`.
+pub fn hdrmerge_command(executable: &str, name: &str, files: &[&Path]) -> String {
+    let files: Vec<String> = files
+        .iter()
+        .map(|file| quote(&file.to_string_lossy()))
+        .collect();
+    #[cfg(windows)]
+    let line_end = "\r\n";
+    #[cfg(not(windows))]
+    let line_end = "\n";
+    format!(
+        "{} -o {} {}{}",
+        quote(executable),
+        quote(&format!("{}.dng", name)),
+        files.join(" "),
+        line_end
+    )
+}
+
+/// Lets the script be started directly, where the system asks for that.
+pub fn make_executable(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Where the Hugin project of the sequence `name` goes.
+pub fn hugin_project_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.pto", name))
+}
+
+/// Writes a Hugin project at `path` with the `files` of a sequence as one
+/// stack, set up for an exposure fused output. Each frame's exposure is
+/// taken from its exposure bias, `None` where unknown. The image sizes are
+/// left for Hugin to read from the files.
+pub fn write_hugin_project(path: &Path, files: &[(&Path, Option<Rational32>)]) -> io::Result<()> {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut content = String::from(
+        "# hugin project file\n#hugin_ptoversion 2\np f0 w0 h0 v50 E0 R0 n\"TIFF_m c:LZW r:CROP\"\nm i0\n\n# image lines\n",
+    );
+    for (file, bias) in files {
+        // Files next to the project are referenced by name, so the folder can be moved
+        let name = match file.strip_prefix(dir) {
+            Ok(relative) => relative.to_string_lossy(),
+            Err(_) => file.to_string_lossy(),
+        };
+        // A brighter frame has a lower exposure value
+        let ev = bias.and_then(|b| b.to_f64()).map_or(0.0, |b| 0.0 - b);
+        content.push_str(&format!(
+            "i w0 h0 f0 v50 Ra0 Rb0 Rc0 Rd0 Re0 Eev{} Er1 Eb1 r0 p0 y0 TrX0 TrY0 TrZ0 Tpy0 Tpp0 j0 a0 b0 c0 d0 e0 g0 t0 Va1 Vb0 Vc0 Vd0 Vx0 Vy0 Vm5 n\"{}\"\n",
+            ev, name
+        ));
+    }
+    content.push_str(
+        "\n# specify variables that should be optimized\nv\n\n# control points\n\n#hugin_optimizeReferenceImage 0\n#hugin_blender enblend\n#hugin_remapper nona\n#hugin_outputLDRBlended false\n#hugin_outputLDRExposureBlended true\n",
+    );
+    fs::write(path, content)
+}
+
+/// Double quotes for batch files, with `%` doubled so cmd expands nothing
+/// within. Windows paths can't hold `"`, other text gets it doubled.
+#[cfg(windows)]
+pub fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\"").replace('%', "%%"))
+}
+
+/// Single quotes, so the shell expands nothing within
+#[cfg(not(windows))]
+pub fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Replaces each `{token}` of `template` by `value(token)` in a single pass,
+/// so text filled in is never searched for tokens again. Tokens `value`
+/// doesn't know are kept as they are.
+pub fn fill_template(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let token = rest
+            .find('}')
+            .and_then(|end| Some((end, value(&rest[1..end])?)));
+        match token {
+            Some((end, text)) => {
+                filled.push_str(&text);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(windows))]
+    fn fusion_script_quotes_files_and_cleans_the_name() {
+        let dir = std::env::temp_dir().join(format!("ebo-fusion-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = fusion_script_path(&dir, "bracket");
+        let files = [
+            Path::new("/photos/{name} $HOME.ARW"),
+            Path::new("/photos/b.ARW"),
+        ];
+        write_fusion_script(
+            &path,
+            "bracket-20240501-$(id)`x`\"",
+            &files,
+            DEFAULT_FUSION_COMMAND,
+        )
+        .unwrap();
+
+        let script = fs::read_to_string(&path).unwrap();
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains(
+            "align_image_stack -a \"bracket-20240501-__id__x___aligned_\" '/photos/{name} $HOME.ARW' '/photos/b.ARW'\n"
+        ));
+        assert!(script.contains(
+            "enfuse -o \"bracket-20240501-__id__x___fused.tif\" 'bracket-20240501-__id__x___aligned_0000.tif' 'bracket-20240501-__id__x___aligned_0001.tif'\n"
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fill_template_keeps_unknown_and_unclosed_tokens() {
+        let filled = fill_template("{a} {b} {a", |token| {
+            (token == "a").then(|| "{b}".to_string())
+        });
+        assert_eq!(filled, "{b} {b} {a");
+    }
+}
@@ -0,0 +1,173 @@
+//! Local socket used to keep a single running instance and to let scripts
+//! trigger runs in the already open window.
+//!
+//! The protocol is one line per command: `focus`, `open <folder>` or
+//! `run <folder>`, answered with `ok` or `error <reason>`. Each line starts
+//! with the token the running instance wrote to `instance_token` in the
+//! user's storage directory, so only the same user can send commands.
+
+use eframe::egui;
+use log::{debug, info, warn};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+pub const IPC_ADDRESS: &str = "127.0.0.1:7879";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+/// Longest command line read, far more than a folder path needs
+const MAX_LINE_LENGTH: u64 = 4096;
+const TOKEN_FILE_NAME: &str = "instance_token";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IpcCommand {
+    /// Bring the running window to the front
+    Focus,
+    /// Select a folder without starting
+    Open(PathBuf),
+    /// Select a folder and start processing it with the current settings
+    Run(PathBuf),
+}
+
+impl IpcCommand {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        match command {
+            "focus" => Some(IpcCommand::Focus),
+            "open" if !argument.is_empty() => Some(IpcCommand::Open(PathBuf::from(argument))),
+            "run" if !argument.is_empty() => Some(IpcCommand::Run(PathBuf::from(argument))),
+            _ => None,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        match self {
+            IpcCommand::Focus => "focus\n".to_string(),
+            IpcCommand::Open(path) => format!("open {}\n", path.display()),
+            IpcCommand::Run(path) => format!("run {}\n", path.display()),
+        }
+    }
+}
+
+/// Sends a command to an already running instance. Returns `false` if no
+/// instance accepted it, in which case the caller should open its own window.
+pub fn forward_to_running_instance(command: &IpcCommand) -> bool {
+    // Without a token of our own there is no instance of this user to reach
+    let Some(token) = token_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return false;
+    };
+    let Ok(address) = IPC_ADDRESS.parse::<SocketAddr>() else {
+        return false;
+    };
+    let Ok(mut stream) = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) else {
+        return false;
+    };
+    if stream.set_read_timeout(Some(REPLY_TIMEOUT)).is_err()
+        || stream
+            .write_all(format!("{} {}", token.trim(), command.to_line()).as_bytes())
+            .is_err()
+    {
+        return false;
+    }
+
+    let mut reply = String::new();
+    if BufReader::new(stream.take(MAX_LINE_LENGTH))
+        .read_line(&mut reply)
+        .is_err()
+    {
+        return false;
+    }
+    // Anything else listening on the port, or an instance of another user,
+    // is not one of our instances
+    let forwarded = reply.trim() == "ok";
+    if forwarded {
+        info!("Forwarded {:?} to the running instance", command);
+    }
+    forwarded
+}
+
+/// Listens for commands of other instances and scripts. Returns `None` if
+/// the socket is unavailable, e.g. because another program already uses it.
+pub fn listen(ctx: egui::Context) -> Option<Receiver<IpcCommand>> {
+    let listener = TcpListener::bind(IPC_ADDRESS)
+        .map_err(|e| warn!("Failed to listen on {}: {}", IPC_ADDRESS, e))
+        .ok()?;
+    let token = match write_token() {
+        Ok(token) => token,
+        Err(e) => {
+            warn!("Failed to write the instance token: {}", e);
+            return None;
+        }
+    };
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = handle_connection(stream, &token, &sender, &ctx) {
+                debug!("Failed to handle IPC connection: {}", e);
+            }
+        }
+    });
+    Some(receiver)
+}
+
+fn token_path() -> Option<PathBuf> {
+    eframe::storage_dir(crate::APP_NAME).map(|dir| dir.join(TOKEN_FILE_NAME))
+}
+
+/// Writes a new token only this user can read and returns it.
+fn write_token() -> io::Result<String> {
+    let path = token_path().ok_or_else(|| io::Error::other("no storage directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let token = crate::server::generate_token()?;
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to newly created files
+        if path.exists() {
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(&path)?.write_all(token.as_bytes())?;
+    Ok(token)
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    token: &str,
+    sender: &Sender<IpcCommand>,
+    ctx: &egui::Context,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(stream.try_clone()?.take(MAX_LINE_LENGTH)).read_line(&mut line)?;
+
+    let Some(command) = line
+        .trim_start()
+        .split_once(' ')
+        .filter(|(sent, _)| *sent == token)
+        .map(|(_, command)| command)
+    else {
+        return stream.write_all(b"error unauthorized\n");
+    };
+    match IpcCommand::parse(command) {
+        Some(command) => {
+            let _ = sender.send(command);
+            ctx.request_repaint();
+            stream.write_all(b"ok\n")
+        }
+        None => stream.write_all(b"error unknown command\n"),
+    }
+}
@@ -0,0 +1,361 @@
+//! Journal of the files a run moved, so the last run can be undone.
+//!
+//! While a [`RunJournal`] is alive, every folder created and every file moved
+//! by its thread is appended to `last_run_journal.jsonl` in the app's storage,
+//! one JSON object per line:
+//!
+//! ```text
+//! {"op":"create_folder","path":"/photos/IMG_0012"}
+//! {"op":"move","from":"/photos/IMG_0012.ARW","to":"/photos/IMG_0012/IMG_0012.ARW"}
+//! {"op":"create_file","path":"/photos/IMG_0012/.directory"}
+//! {"op":"note","path":"/photos/IMG_0012","note":"best of sunset"}
+//! ```
+//!
+//! The file is only replaced once a run actually moves something, so a later
+//! run that writes text files, scripts or tags doesn't take the undo away.
+//! Until then the entries are held back, and dropped if nothing is moved. Undoing
+//! replays the journal in reverse: folders removed for being left empty are
+//! created again, files are moved back, then the folders made for them are
+//! removed if they are empty.
+
+use crate::file_utils::move_file;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const JOURNAL_FILE_NAME: &str = "last_run_journal.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JournalEntry {
+    CreateFolder {
+        path: PathBuf,
+    },
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// A file the run added, e.g. the comment of a sequence folder
+    CreateFile {
+        path: PathBuf,
+    },
+    /// A scanned folder the run left empty and removed
+    RemoveFolder {
+        path: PathBuf,
+    },
+    /// The review note of a sequence moved into the folder or renamed to
+    /// the file at `path`, nothing to undo
+    Note {
+        path: PathBuf,
+        note: String,
+    },
+}
+
+impl JournalEntry {
+    /// The file or folder the run left behind.
+    fn path(&self) -> &Path {
+        match self {
+            JournalEntry::Move { to, .. } => to,
+            JournalEntry::CreateFolder { path }
+            | JournalEntry::CreateFile { path }
+            | JournalEntry::RemoveFolder { path }
+            | JournalEntry::Note { path, .. } => path,
+        }
+    }
+
+    /// Whether the entry moves or creates folders, which makes the run the
+    /// one to undo.
+    fn replaces_journal(&self) -> bool {
+        matches!(
+            self,
+            JournalEntry::CreateFolder { .. }
+                | JournalEntry::Move { .. }
+                | JournalEntry::RemoveFolder { .. }
+        )
+    }
+}
+
+/// The journal of a running run.
+struct Recording {
+    path: Option<PathBuf>,
+    /// Entries held back until the run replaces the journal
+    pending: Vec<JournalEntry>,
+    /// Opened on the first entry that replaces the journal
+    file: Option<File>,
+}
+
+thread_local! {
+    /// `Some` while a journal is recorded
+    static CURRENT: RefCell<Option<Recording>> = const { RefCell::new(None) };
+}
+
+fn journal_path() -> Option<PathBuf> {
+    eframe::storage_dir(crate::APP_NAME).map(|dir| dir.join(JOURNAL_FILE_NAME))
+}
+
+fn create_journal(path: Option<&Path>) -> io::Result<File> {
+    let path = path.ok_or_else(|| io::Error::other("no storage directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    File::create(path)
+}
+
+/// Records the changes of the current thread's run until dropped.
+pub struct RunJournal;
+
+impl RunJournal {
+    pub fn start() -> Self {
+        Self::start_at(journal_path())
+    }
+
+    fn start_at(path: Option<PathBuf>) -> Self {
+        CURRENT.set(Some(Recording {
+            path,
+            pending: Vec::new(),
+            file: None,
+        }));
+        Self
+    }
+}
+
+impl Drop for RunJournal {
+    fn drop(&mut self) {
+        CURRENT.set(None);
+    }
+}
+
+/// Appends `entry` to the journal of the current run, if one is recorded.
+pub fn record(entry: JournalEntry) {
+    CURRENT.with_borrow_mut(|current| {
+        let Some(recording) = current else {
+            return;
+        };
+        if recording.file.is_none() {
+            if !entry.replaces_journal() {
+                recording.pending.push(entry);
+                return;
+            }
+            match create_journal(recording.path.as_deref()) {
+                Ok(created) => recording.file = Some(created),
+                Err(e) => {
+                    warn!("Failed to create the undo journal: {}", e);
+                    *current = None;
+                    return;
+                }
+            }
+        }
+        let Some(file) = &mut recording.file else {
+            return;
+        };
+        for entry in recording.pending.drain(..).chain([entry]) {
+            let written = serde_json::to_string(&entry)
+                .map_err(io::Error::other)
+                .and_then(|line| writeln!(file, "{}", line))
+                .and_then(|()| file.flush());
+            if let Err(e) = written {
+                warn!("Failed to write the undo journal: {}", e);
+            }
+        }
+    });
+}
+
+/// The entries of the last run that moved files, `Ok(None)` if there is nothing to undo.
+pub fn load_last_run() -> Result<Option<Vec<JournalEntry>>, String> {
+    let Some(path) = journal_path() else {
+        return Ok(None);
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let entries = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<JournalEntry>, _>>()
+        .map_err(|e| format!("Invalid undo journal {}: {}", path.display(), e))?;
+    Ok((!entries.is_empty()).then_some(entries))
+}
+
+/// Whether there is a run to undo, without reading the journal.
+pub fn has_last_run() -> bool {
+    journal_path().is_some_and(|path| path.is_file())
+}
+
+/// Moves the files of the last run back and removes the folders created for
+/// them. Entries that couldn't be undone stay in the journal for another try.
+pub fn undo_last_run(entries: Vec<JournalEntry>) -> Vec<String> {
+    undo_entries(entries, |_| true)
+}
+
+/// Undoes what the last run did within `folder`, e.g. a single sequence
+/// folder, the rest stays in the journal for "Undo Last Run".
+pub fn undo_folder(folder: &Path) -> Vec<String> {
+    let entries = match load_last_run() {
+        Ok(Some(entries)) => entries,
+        Ok(None) => return vec!["There is no run to undo".to_string()],
+        Err(e) => return vec![e],
+    };
+    if !entries.iter().any(|entry| entry.path().starts_with(folder)) {
+        return vec![format!(
+            "{} is not part of the last run that moved files",
+            folder.display()
+        )];
+    }
+    info!("Undoing {}", folder.display());
+    undo_entries(entries, |entry| entry.path().starts_with(folder))
+}
+
+/// Undoes the `selected` entries in reverse and keeps the others, and those
+/// that failed, in the journal.
+fn undo_entries(
+    entries: Vec<JournalEntry>,
+    selected: impl Fn(&JournalEntry) -> bool,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut remaining = Vec::new();
+    for entry in entries.into_iter().rev() {
+        if !selected(&entry) {
+            remaining.push(entry);
+            continue;
+        }
+        match undo_entry(&entry) {
+            Ok(()) => {}
+            Err(e) => {
+                warn!("{}", e);
+                errors.push(e);
+                remaining.push(entry);
+            }
+        }
+    }
+    remaining.reverse();
+
+    let Some(path) = journal_path() else {
+        return errors;
+    };
+    let result = if remaining.is_empty() {
+        info!("Undid the last run");
+        fs::remove_file(&path)
+    } else {
+        write_entries(&path, &remaining)
+    };
+    if let Err(e) = result {
+        errors.push(format!(
+            "Failed to update the undo journal {}: {}",
+            path.display(),
+            e
+        ));
+    }
+    errors
+}
+
+fn undo_entry(entry: &JournalEntry) -> Result<(), String> {
+    match entry {
+        JournalEntry::Move { from, to } => {
+            if !to.exists() {
+                return Err(format!("{} is no longer there", to.display()));
+            }
+            if from.exists() {
+                return Err(format!(
+                    "Can't move {} back, {} already exists",
+                    to.display(),
+                    from.display()
+                ));
+            }
+            move_file(to, from).map_err(|e| {
+                format!(
+                    "Failed to move {} back to {}: {}",
+                    to.display(),
+                    from.display(),
+                    e
+                )
+            })
+        }
+        // Already gone, e.g. removed when its sequence couldn't be moved
+        JournalEntry::CreateFolder { path } | JournalEntry::CreateFile { path }
+            if !path.exists() =>
+        {
+            Ok(())
+        }
+        JournalEntry::CreateFile { path } => {
+            fs::remove_file(path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+        }
+        JournalEntry::CreateFolder { path } => fs::remove_dir(path)
+            .map_err(|e| format!("Failed to remove folder {}: {}", path.display(), e)),
+        JournalEntry::RemoveFolder { path } => fs::create_dir_all(path)
+            .map_err(|e| format!("Failed to create folder {}: {}", path.display(), e)),
+        JournalEntry::Note { .. } => Ok(()),
+    }
+}
+
+fn write_entries(path: &Path, entries: &[JournalEntry]) -> io::Result<()> {
+    let mut content = String::new();
+    for entry in entries {
+        content.push_str(&serde_json::to_string(entry).map_err(io::Error::other)?);
+        content.push('\n');
+    }
+    fs::write(path, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ebo-journal-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read_journal(path: &Path) -> String {
+        fs::read_to_string(path).unwrap_or_default()
+    }
+
+    #[test]
+    fn run_that_moves_nothing_keeps_previous_journal() {
+        let dir = temp_dir("script-only");
+        let path = dir.join(JOURNAL_FILE_NAME);
+        let previous = "{\"op\":\"create_folder\",\"path\":\"/photos/IMG_0012\"}\n";
+        fs::write(&path, previous).unwrap();
+
+        let journal = RunJournal::start_at(Some(path.clone()));
+        record(JournalEntry::CreateFile {
+            path: dir.join("IMG_0012_fuse.sh"),
+        });
+        drop(journal);
+
+        assert_eq!(read_journal(&path), previous);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn held_back_entries_are_written_with_the_first_move() {
+        let dir = temp_dir("move");
+        let path = dir.join(JOURNAL_FILE_NAME);
+        fs::write(&path, "{\"op\":\"create_folder\",\"path\":\"/old\"}\n").unwrap();
+
+        let journal = RunJournal::start_at(Some(path.clone()));
+        record(JournalEntry::CreateFile {
+            path: PathBuf::from("/photos/IMG_0012_fuse.sh"),
+        });
+        record(JournalEntry::Move {
+            from: PathBuf::from("/photos/IMG_0012.ARW"),
+            to: PathBuf::from("/photos/IMG_0012/IMG_0012.ARW"),
+        });
+        drop(journal);
+
+        let content = read_journal(&path);
+        let ops: Vec<&str> = content
+            .lines()
+            .map(|line| line.split('"').nth(3).unwrap())
+            .collect();
+        assert_eq!(ops, ["create_file", "move"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
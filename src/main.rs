@@ -1,20 +1,126 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
 mod app;
+mod archive_manifest;
+mod audit;
+mod background_mode;
+mod bracket_kind;
+mod bracket_simulation;
+mod camera_profile;
+mod catalog;
+mod catalog_browser;
+mod catalog_query;
+mod catalog_sqlite;
+mod cli;
+mod darktable;
+mod detachable;
+mod diagnostics;
+mod dry_run;
+mod embedded_xmp;
+mod exif_metadata;
+mod exiftool;
+mod exposure_repair;
 mod file_utils;
+mod filter_preview;
+mod first_run;
+mod folder_metadata;
+mod folder_settings;
+mod hdr_tools;
+mod instance;
+mod journal;
+mod maker_notes;
+mod metadata_cache;
+mod metadata_export;
+mod output_writer;
+mod result_review;
+mod run_log;
+mod script_actions;
+mod sequence_check;
+mod sequence_list;
+mod server;
+mod sidecar_metadata;
+mod sleep_inhibitor;
+mod taskbar;
+mod theme;
+mod webhook;
+mod xmp;
 
+use clap::Parser;
 use eframe::egui;
+use instance::IpcCommand;
+
+pub const APP_NAME: &str = "Exposure Bracketing Organizer";
 
 fn main() -> eframe::Result {
-    env_logger::init();
+    run_log::init();
+
+    if std::env::args_os().len() > 1 {
+        cli::attach_parent_console();
+    }
+    let args = cli::Args::parse();
+    if let Some(command) = &args.subcommand {
+        if let Err(e) = command.run() {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(exiftool) = &args.exiftool {
+        exiftool::configure(Some(exiftool.clone()));
+    }
+    if let Some(address) = &args.serve {
+        if let Err(e) = server::serve(address) {
+            log::error!("Failed to serve on {}: {}", address, e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(root) = &args.verify_archive {
+        std::process::exit(if cli::verify_archive(root) { 0 } else { 1 });
+    }
+
+    let folder = args
+        .path
+        .map(|path| std::fs::canonicalize(&path).unwrap_or(path));
+    if let Some(job_folder) = args.job.folder.clone() {
+        let format = args.progress.unwrap_or(cli::ProgressFormat::Plain);
+        let request = args.job.into_request(job_folder);
+        std::process::exit(if cli::run(request, format) { 0 } else { 1 });
+    }
+    if let Some(format) = args.progress {
+        let Some(folder) = folder.filter(|_| args.run) else {
+            eprintln!("--progress needs --run and a folder");
+            std::process::exit(2);
+        };
+        let request = args.job.into_request(folder);
+        std::process::exit(if cli::run(request, format) { 0 } else { 1 });
+    }
+    let startup_command = match folder {
+        Some(folder) if args.run => IpcCommand::Run(folder),
+        Some(folder) => IpcCommand::Open(folder),
+        None => IpcCommand::Focus,
+    };
+    if instance::forward_to_running_instance(&startup_command) {
+        return Ok(());
+    }
 
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([450.0, 450.0]),
+        // Only used on the first start, afterwards the last size and position are restored
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([640.0, 560.0])
+            .with_min_inner_size([450.0, 400.0]),
+        persist_window: true,
         ..Default::default()
     };
     eframe::run_native(
-        "Exposure Bracketing Organizer",
+        APP_NAME,
         options,
-        Box::new(|_cc| Ok(Box::<app::ExposureBracketingOrganizerApp>::default())),
+        Box::new(|cc| {
+            Ok(Box::new(app::ExposureBracketingOrganizerApp::new(
+                cc,
+                startup_command,
+            )))
+        }),
     )
 }
@@ -0,0 +1,163 @@
+//! Bracketing recorded in the maker notes of TIFF based raws, e.g. CR2, NEF
+//! and ARW, for cameras whose EXIF doesn't tell the frames of an auto bracket
+//! apart from other shots: Canon bodies only record it there and Sony bodies
+//! often report auto exposure during bracketing, whatever `ExposureMode` says,
+//! and Nikon bodies keep the dialed compensation as the exposure bias and
+//! record each frame's offset within the bracket there.
+//! CR3 raws aren't TIFF based, their maker notes aren't read.
+
+use log::debug;
+use num_rational::Rational32;
+use rawler::formats::tiff::ifd::OffsetMode;
+use rawler::formats::tiff::reader::TiffReader;
+use rawler::formats::tiff::{GenericTiffReader, Value, IFD};
+use rawler::tags::ExifTag;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::panic;
+use std::path::Path;
+
+/// Canon's shot info, index 16 holds the AEB state and shot number
+const CANON_SHOT_INFO: u16 = 0x0004;
+/// Canon's file info, index 3 holds the bracket mode and 5 the shot number
+const CANON_FILE_INFO: u16 = 0x0093;
+/// Canon's bracket mode of an auto exposure bracket, flash, ISO and white
+/// balance brackets have others
+const CANON_AEB: i16 = 1;
+/// Nikon's offset of the frame within an exposure bracket
+const NIKON_EXPOSURE_BRACKET_VALUE: u16 = 0x0019;
+/// Nikon's drive and bracketing flags
+const NIKON_SHOOTING_MODE: u16 = 0x0089;
+/// The flag of Nikon's shooting mode set during exposure bracketing
+const NIKON_EXPOSURE_BRACKETING: u32 = 1 << 4;
+/// Sony's drive mode, continuous, bracketing or single frames
+const SONY_RELEASE_MODE: u16 = 0xb049;
+/// Sony's release mode of an exposure bracket, white balance and DRO
+/// brackets have others
+pub const SONY_EXPOSURE_BRACKETING: u16 = 5;
+
+/// A frame the maker notes record as part of an auto exposure bracket.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MakerNoteBracket {
+    /// Position of the frame within the bracket, from 1, where recorded
+    pub shot: Option<u16>,
+    /// Exposure of the frame relative to the bracket's, where the camera
+    /// records it apart from the exposure bias
+    #[serde(default)]
+    pub bracket_value: Option<Rational32>,
+}
+
+/// The makes whose maker notes are read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Make {
+    Canon,
+    Nikon,
+    Sony,
+}
+
+/// Reads the bracketing of `path` from the maker notes of the camera `make`,
+/// `None` if they don't record the frame as part of a bracket, for other
+/// makes or files without them.
+pub fn read_maker_note_bracket(path: &Path, make: &str) -> Option<MakerNoteBracket> {
+    let make = match make.trim().to_ascii_lowercase() {
+        m if m.starts_with("canon") => Make::Canon,
+        m if m.starts_with("nikon") => Make::Nikon,
+        m if m.starts_with("sony") => Make::Sony,
+        _ => return None,
+    };
+    // rawler panics on some malformed maker notes, e.g. entries pointing
+    // past the end of the file
+    let maker_notes = match panic::catch_unwind(|| read_maker_notes(path)) {
+        Ok(maker_notes) => maker_notes?,
+        Err(_) => {
+            debug!("rawler failed on the maker notes of {}", path.display());
+            return None;
+        }
+    };
+    let bracket = match make {
+        Make::Canon => canon_bracket(&maker_notes),
+        Make::Nikon => nikon_bracket(&maker_notes),
+        Make::Sony => sony_bracket(&maker_notes),
+    };
+    if let Some(bracket) = &bracket {
+        debug!("Maker notes of {}: {:?}", path.display(), bracket);
+    }
+    bracket
+}
+
+fn read_maker_notes(path: &Path) -> Option<IFD> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let tiff = GenericTiffReader::new(&mut reader, 0, 0, None, &[]).ok()?;
+    match tiff
+        .find_first_ifd_with_tag(ExifTag::MakerNotes)?
+        .parse_makernote(&mut reader, OffsetMode::Absolute, &[])
+    {
+        Ok(maker_notes) => maker_notes,
+        Err(e) => {
+            debug!("Can't read the maker notes of {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn canon_bracket(maker_notes: &IFD) -> Option<MakerNoteBracket> {
+    // Signed shorts, though the IFD declares them unsigned
+    let short = |tag: u16, index: usize| {
+        maker_notes
+            .get_entry(tag)
+            .and_then(|entry| entry.value.get_u16(index).ok().flatten())
+            .map(|value| value as i16)
+    };
+    let shot = |value: Option<i16>| value.and_then(|v| u16::try_from(v).ok()).filter(|v| *v > 0);
+    if short(CANON_FILE_INFO, 3) == Some(CANON_AEB) {
+        return Some(MakerNoteBracket {
+            shot: shot(short(CANON_FILE_INFO, 5)),
+            bracket_value: None,
+        });
+    }
+    // Older bodies only have the shot info: -1 for AEB, else the shot number
+    match short(CANON_SHOT_INFO, 16)? {
+        0 => None,
+        state => Some(MakerNoteBracket {
+            shot: shot(Some(state)),
+            bracket_value: None,
+        }),
+    }
+}
+
+fn nikon_bracket(maker_notes: &IFD) -> Option<MakerNoteBracket> {
+    let shooting_mode = maker_notes
+        .get_entry(NIKON_SHOOTING_MODE)
+        .and_then(|entry| entry.value.get_u32(0).ok().flatten());
+    let bracket_value = match &maker_notes.get_entry(NIKON_EXPOSURE_BRACKET_VALUE)?.value {
+        Value::SRational(values) => values
+            .first()
+            .filter(|value| value.d != 0)
+            .map(|value| Rational32::new(value.n, value.d)),
+        _ => None,
+    };
+    nikon_bracket_from(shooting_mode, bracket_value)
+}
+
+fn sony_bracket(maker_notes: &IFD) -> Option<MakerNoteBracket> {
+    let release_mode = maker_notes
+        .get_entry(SONY_RELEASE_MODE)
+        .and_then(|entry| entry.value.get_u16(0).ok().flatten());
+    (release_mode == Some(SONY_EXPOSURE_BRACKETING)).then(MakerNoteBracket::default)
+}
+
+/// The bracket of a Nikon frame from its `ShootingMode` and
+/// `ExposureBracketValue`, which exiftool reads under the same names.
+pub fn nikon_bracket_from(
+    shooting_mode: Option<u32>,
+    bracket_value: Option<Rational32>,
+) -> Option<MakerNoteBracket> {
+    let bracketing = shooting_mode.is_some_and(|mode| mode & NIKON_EXPOSURE_BRACKETING != 0);
+    // The bracket value stays 0 outside of bracketing
+    let offset = bracket_value.is_some_and(|value| value != Rational32::from(0));
+    (bracketing || offset).then_some(MakerNoteBracket {
+        shot: None,
+        bracket_value,
+    })
+}
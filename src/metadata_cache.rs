@@ -0,0 +1,200 @@
+use crate::bracket_kind::CaptureSettings;
+use crate::exif_metadata::read_exif_metadata;
+use crate::exiftool::read_exiftool_metadata;
+use crate::exposure_repair::read_override;
+use crate::file_utils::{capture_time, extract_raw_metadata};
+use crate::maker_notes::{read_maker_note_bracket, MakerNoteBracket};
+use crate::sidecar_metadata::read_sidecar_metadata;
+use chrono::NaiveDateTime;
+use log::{debug, warn};
+use num_rational::Rational32;
+use rawler::decoders::RawMetadata;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const CACHE_FILE_NAME: &str = "metadata_cache.json";
+
+/// The exposure related metadata of a file, as far as detection needs it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedMetadata {
+    pub capture_time: Option<NaiveDateTime>,
+    pub exposure_bias: Option<Rational32>,
+    pub exposure_mode: Option<u16>,
+    /// Make and model, missing in caches written by older versions
+    #[serde(default)]
+    pub camera: Option<String>,
+    /// Settings other bracketing modes vary, missing in caches written by older versions
+    #[serde(default)]
+    pub capture_settings: Option<CaptureSettings>,
+    /// The camera's shutter count, orders frames shot within the same second
+    #[serde(default)]
+    pub image_number: Option<u32>,
+    /// Set if the maker notes record the frame as part of an auto exposure
+    /// bracket, see [`read_maker_note_bracket`]
+    #[serde(default)]
+    pub maker_note_bracket: Option<MakerNoteBracket>,
+}
+
+impl From<&RawMetadata> for CachedMetadata {
+    fn from(raw_metadata: &RawMetadata) -> Self {
+        Self {
+            capture_time: capture_time(raw_metadata),
+            exposure_bias: raw_metadata
+                .exif
+                .exposure_bias
+                .map(|eb| Rational32::new(eb.n, eb.d)),
+            exposure_mode: raw_metadata.exif.exposure_mode,
+            camera: camera_name(raw_metadata),
+            capture_settings: Some(CaptureSettings::from(&raw_metadata.exif)),
+            image_number: raw_metadata.exif.image_number,
+            maker_note_bracket: None,
+        }
+    }
+}
+
+impl CachedMetadata {
+    /// Sets the bracket recorded in the maker notes. A bracket value is added
+    /// to the exposure bias, which such cameras keep at the dialed compensation.
+    pub fn with_maker_note_bracket(mut self, bracket: Option<MakerNoteBracket>) -> Self {
+        if let Some(value) = bracket.as_ref().and_then(|b| b.bracket_value) {
+            self.exposure_bias = self.exposure_bias.map(|bias| bias + value);
+        }
+        self.maker_note_bracket = bracket;
+        self
+    }
+
+    /// Replaces the exposure values by those repaired in the sidecar of `path`.
+    pub fn with_sidecar_overrides(mut self, path: &Path) -> Self {
+        if let Some(values) = read_override(path) {
+            self.exposure_bias = values.exposure_bias.or(self.exposure_bias);
+            self.exposure_mode = values.exposure_mode.or(self.exposure_mode);
+        }
+        self
+    }
+}
+
+/// Reads the metadata of a file from its editor's sidecar if that has all of
+/// it, otherwise from the file itself.
+pub fn read_metadata(path: &Path) -> Option<CachedMetadata> {
+    read_sidecar_metadata(path).or_else(|| read_file_metadata(path))
+}
+
+/// Reads the metadata recorded in a file, through rawler for raws along with
+/// their maker notes and from the plain EXIF of JPEG, HEIF and TIFF files, else with exiftool if one is
+/// configured.
+pub fn read_file_metadata(path: &Path) -> Option<CachedMetadata> {
+    extract_raw_metadata(path)
+        .map(|m| {
+            CachedMetadata::from(&m).with_maker_note_bracket(read_maker_note_bracket(path, &m.make))
+        })
+        .or_else(|| read_exif_metadata(path))
+        .or_else(|| read_exiftool_metadata(path))
+}
+
+fn camera_name(raw_metadata: &RawMetadata) -> Option<String> {
+    let name = format!("{} {}", raw_metadata.make.trim(), raw_metadata.model.trim());
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    size: u64,
+    modified_nanos: u128,
+    metadata: CachedMetadata,
+}
+
+/// Metadata of already scanned files, so re-runs only read new or modified files.
+#[derive(Default, Serialize, Deserialize)]
+pub struct MetadataCache {
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+impl MetadataCache {
+    fn cache_path() -> Option<PathBuf> {
+        eframe::storage_dir(crate::APP_NAME).map(|dir| dir.join(CACHE_FILE_NAME))
+    }
+
+    pub fn load() -> Self {
+        let Some(path) = Self::cache_path() else {
+            return Self::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring invalid metadata cache {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&mut self) {
+        // Forget files that were moved or deleted, unless their whole folder is
+        // unavailable (e.g. an unmounted drive)
+        self.entries
+            .retain(|path, _| path.exists() || !path.parent().is_some_and(|p| p.exists()));
+
+        let Some(path) = Self::cache_path() else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            if let Err(e) = fs::create_dir_all(dir) {
+                warn!("Failed to create {}: {}", dir.display(), e);
+                return;
+            }
+        }
+        match serde_json::to_string(self) {
+            Ok(content) => {
+                if let Err(e) = fs::write(&path, content) {
+                    warn!("Failed to write metadata cache {}: {}", path.display(), e);
+                }
+            }
+            Err(e) => warn!("Failed to serialize metadata cache: {}", e),
+        }
+    }
+
+    /// Returns the metadata of a file, with the exposure values of its
+    /// sidecar taking precedence.
+    pub fn read(&mut self, path: &Path) -> Option<CachedMetadata> {
+        // Sidecars change independently of the file, so they aren't cached
+        self.read_recorded(path)
+            .map(|metadata| metadata.with_sidecar_overrides(path))
+    }
+
+    /// Returns the metadata recorded in a file, reading it only if the file is
+    /// new or its size or modification time changed since it was cached.
+    fn read_recorded(&mut self, path: &Path) -> Option<CachedMetadata> {
+        let fingerprint = fs::metadata(path).ok().and_then(|m| {
+            let modified = m.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+            Some((m.len(), modified.as_nanos()))
+        });
+
+        if let (Some((size, modified_nanos)), Some(entry)) = (fingerprint, self.entries.get(path)) {
+            // Entries of older versions lack the camera or capture settings,
+            // read those files again
+            let complete =
+                entry.metadata.camera.is_some() && entry.metadata.capture_settings.is_some();
+            if entry.size == size && entry.modified_nanos == modified_nanos && complete {
+                debug!("Using cached metadata for {}", path.display());
+                return Some(entry.metadata.clone());
+            }
+        }
+
+        let metadata = read_metadata(path)?;
+
+        if let Some((size, modified_nanos)) = fingerprint {
+            self.entries.insert(
+                path.to_path_buf(),
+                CacheEntry {
+                    size,
+                    modified_nanos,
+                    metadata: metadata.clone(),
+                },
+            );
+        }
+        Some(metadata)
+    }
+}
@@ -0,0 +1,184 @@
+//! Exports the metadata of the files listed in the exposure bias window, to
+//! analyze the bracketing in a spreadsheet or in scripts.
+
+use crate::app::{exposure_mode_to_string, ExposureInfo};
+use crate::file_utils::format_pattern;
+use chrono::NaiveDateTime;
+use ebo_core::{find_bracket_patterns, DeltaReference, EvMode, MatchMode};
+use num_rational::Rational32;
+use num_traits::ToPrimitive;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+const CSV_HEADER: [&str; 7] = [
+    "filename",
+    "exposure_bias",
+    "exposure_mode",
+    "iso",
+    "shutter",
+    "aperture",
+    "capture_time",
+];
+
+/// How the JSON export finds the sequences among the listed files.
+pub struct SequenceMatching {
+    pub sequences: Vec<Vec<Option<Rational32>>>,
+    pub ev_mode: EvMode,
+    pub delta_reference: DeltaReference,
+    pub ev_tolerance: Rational32,
+}
+
+/// The metadata of one file, shutter in seconds.
+struct FileRecord<'a> {
+    filename: &'a str,
+    path: &'a Path,
+    exposure_bias: Option<Rational32>,
+    exposure_mode: Option<&'static str>,
+    iso: Option<u32>,
+    shutter: Option<Rational32>,
+    aperture: Option<Rational32>,
+    capture_time: Option<NaiveDateTime>,
+}
+
+impl<'a> From<&'a ExposureInfo> for FileRecord<'a> {
+    fn from(info: &'a ExposureInfo) -> Self {
+        let settings = info.capture_settings.as_ref();
+        Self {
+            filename: &info.filename,
+            path: &info.path,
+            exposure_bias: match (info.exposure_bias_n, info.exposure_bias_d) {
+                (Some(n), Some(d @ 1..)) => Some(Rational32::new(n, d)),
+                _ => None,
+            },
+            exposure_mode: info.exposure_mode.map(exposure_mode_to_string),
+            iso: settings.and_then(|s| s.iso),
+            shutter: settings.and_then(|s| s.exposure_time),
+            aperture: settings.and_then(|s| s.fnumber),
+            capture_time: info.capture_time,
+        }
+    }
+}
+
+/// A file as written to JSON: numbers as decimals, the exposure bias also
+/// as the exact fraction the camera recorded.
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    filename: &'a str,
+    path: &'a Path,
+    exposure_bias: Option<f64>,
+    /// E.g. `-2/3`
+    exposure_bias_fraction: Option<String>,
+    exposure_mode: Option<&'static str>,
+    iso: Option<u32>,
+    shutter: Option<f64>,
+    aperture: Option<f64>,
+    capture_time: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize)]
+struct JsonSequence<'a> {
+    /// The sequence the frames follow, e.g. `0, -1, 1`
+    pattern: String,
+    files: Vec<&'a Path>,
+}
+
+/// The layout of the sequence lists "Load list…" reads, with the files.
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    files: Vec<JsonFile<'a>>,
+    sequences: Vec<JsonSequence<'a>>,
+}
+
+/// Writes one row per file. Exposure bias, shutter in seconds and aperture
+/// are decimals and empty where unknown, the capture time is as the camera
+/// recorded it, e.g. `2024-05-01 12:30:00.050`.
+pub fn write_csv(infos: &[&ExposureInfo], path: &Path) -> Result<(), String> {
+    let mut content = CSV_HEADER.join(",");
+    content.push('\n');
+    for info in infos {
+        let record = FileRecord::from(*info);
+        let row = [
+            record.filename.to_string(),
+            record.exposure_bias.map_or_else(String::new, decimal),
+            record.exposure_mode.unwrap_or_default().to_string(),
+            record.iso.map_or_else(String::new, |iso| iso.to_string()),
+            record.shutter.map_or_else(String::new, decimal),
+            record.aperture.map_or_else(String::new, decimal),
+            record.capture_time.map_or_else(String::new, |t| {
+                t.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+            }),
+        ];
+        let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        content.push_str(&row.join(","));
+        content.push('\n');
+    }
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Writes the files and the sequences `matching` finds among them, in the
+/// order they are listed, each file claimed by one sequence at most.
+pub fn write_json(
+    infos: &[&ExposureInfo],
+    matching: &SequenceMatching,
+    path: &Path,
+) -> Result<(), String> {
+    let records: Vec<FileRecord> = infos.iter().map(|info| FileRecord::from(*info)).collect();
+    let biases: Vec<Option<Rational32>> = records.iter().map(|r| r.exposure_bias).collect();
+    let sequences = find_bracket_patterns(
+        &biases,
+        &matching.sequences,
+        &matching.ev_mode,
+        matching.delta_reference,
+        None,
+        matching.ev_tolerance,
+        MatchMode::Greedy,
+    )
+    .into_iter()
+    .map(|(pattern, range)| JsonSequence {
+        pattern: format_pattern(&matching.sequences[pattern]),
+        files: records[range].iter().map(|r| r.path).collect(),
+    })
+    .collect();
+    let export = JsonExport {
+        files: records
+            .iter()
+            .map(|r| JsonFile {
+                filename: r.filename,
+                path: r.path,
+                exposure_bias: r.exposure_bias.and_then(|b| b.to_f64()),
+                exposure_bias_fraction: r.exposure_bias.map(|b| b.to_string()),
+                exposure_mode: r.exposure_mode,
+                iso: r.iso,
+                shutter: r.shutter.and_then(|s| s.to_f64()),
+                aperture: r.aperture.and_then(|a| a.to_f64()),
+                capture_time: r.capture_time,
+            })
+            .collect(),
+        sequences,
+    };
+    let content = serde_json::to_string_pretty(&export)
+        .map_err(|e| format!("Failed to export metadata: {}", e))?;
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// E.g. `-0.666667` or `0.004`, without trailing zeros.
+pub fn decimal(value: Rational32) -> String {
+    let value = value.to_f64().unwrap_or_default();
+    let text = format!("{:.6}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Quotes fields containing separators, quotes or line breaks.
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
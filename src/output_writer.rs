@@ -0,0 +1,378 @@
+//! Writes of runs to shared text files such as `sequences.txt`, in one of
+//! the [`TextfileFormat`]s.
+//!
+//! All appends go through a single writer thread in the order they were
+//! submitted, and each one holds an exclusive lock on the file while it
+//! writes, so the blocks of concurrent jobs (or another instance) never
+//! interleave within a sequence. What happens when two runs write the same
+//! file at the same time is chosen with [`TextfileConflict`].
+
+use crate::metadata_export::{csv_field, decimal};
+use ebo_core::EvFormat;
+use log::info;
+use num_rational::Rational32;
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextfileConflict {
+    /// Both runs append to the file, each sequence as a whole
+    #[default]
+    Append,
+    /// A run keeps the file to itself until it is done, the other one waits
+    Wait,
+    /// The later run writes to a numbered file instead, e.g. `sequences-2.txt`
+    SeparateFile,
+}
+
+impl std::fmt::Display for TextfileConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextfileConflict::Append => write!(f, "Append"),
+            TextfileConflict::Wait => write!(f, "Wait for the Other Run"),
+            TextfileConflict::SeparateFile => write!(f, "Write a Separate File"),
+        }
+    }
+}
+
+/// How "Save Sequences to Textfile" writes the sequences.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextfileFormat {
+    /// One path per line and a blank line between sequences
+    #[default]
+    Paths,
+    /// Like the paths, with each file's exposure bias after a tab, as
+    /// biases are shown in the app
+    TabSeparated,
+    /// One row per file with its sequence, position and exposure bias as a
+    /// decimal
+    Csv,
+    /// The layout of the JSON sequence lists "Load list…" reads
+    Json,
+}
+
+impl TextfileFormat {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            TextfileFormat::Paths => "sequences.txt",
+            TextfileFormat::TabSeparated => "sequences.tsv",
+            TextfileFormat::Csv => "sequences.csv",
+            TextfileFormat::Json => "sequences.json",
+        }
+    }
+}
+
+impl std::fmt::Display for TextfileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextfileFormat::Paths => write!(f, "Paths"),
+            TextfileFormat::TabSeparated => write!(f, "Paths and EV, Tab Separated"),
+            TextfileFormat::Csv => write!(f, "CSV"),
+            TextfileFormat::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+const CSV_HEADER: &str = "sequence,frame,path,exposure_bias\n";
+
+/// A sequence as written to the text file.
+pub struct SequenceEntry<'a> {
+    /// Names the sequence in CSV and JSON, e.g. `bracket-20240501-DSC01234`
+    pub group: String,
+    pub files: Vec<&'a Path>,
+    pub biases: Vec<Option<Rational32>>,
+    /// How the tab separated format writes the biases
+    pub ev_format: EvFormat,
+}
+
+enum Block {
+    /// Appended as is, after `header` if the file is empty
+    Text {
+        header: Option<&'static str>,
+        content: String,
+    },
+    /// Added to the `sequences` of the JSON document in the file
+    Json(Value),
+}
+
+impl Block {
+    fn new(format: TextfileFormat, entry: &SequenceEntry) -> Self {
+        let biases = entry.files.iter().zip(&entry.biases);
+        let mut content = String::new();
+        match format {
+            TextfileFormat::Paths => {
+                for file in &entry.files {
+                    content.push_str(&format!("{}\n", file.display()));
+                }
+            }
+            TextfileFormat::TabSeparated => {
+                for (file, bias) in biases {
+                    let bias = bias.map_or_else(String::new, |b| entry.ev_format.format(b));
+                    content.push_str(&format!("{}\t{}\n", file.display(), bias));
+                }
+            }
+            TextfileFormat::Csv => {
+                for (frame, (file, bias)) in biases.enumerate() {
+                    let row = [
+                        entry.group.clone(),
+                        (frame + 1).to_string(),
+                        file.display().to_string(),
+                        bias.map_or_else(String::new, decimal),
+                    ];
+                    let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+                    content.push_str(&row.join(","));
+                    content.push('\n');
+                }
+                return Block::Text {
+                    header: Some(CSV_HEADER),
+                    content,
+                };
+            }
+            TextfileFormat::Json => {
+                return Block::Json(json!({
+                    "group": entry.group,
+                    "files": entry.files,
+                    "biases": entry
+                        .biases
+                        .iter()
+                        .map(|b| b.and_then(|b| b.to_f64()))
+                        .collect::<Vec<_>>(),
+                }));
+            }
+        }
+        // Add a blank line between sequences
+        content.push('\n');
+        Block::Text {
+            header: None,
+            content,
+        }
+    }
+}
+
+struct AppendRequest {
+    path: PathBuf,
+    block: Block,
+    /// Empties the file first
+    truncate: bool,
+    done: Sender<io::Result<()>>,
+}
+
+fn writer() -> &'static Mutex<Sender<AppendRequest>> {
+    static WRITER: OnceLock<Mutex<Sender<AppendRequest>>> = OnceLock::new();
+    WRITER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<AppendRequest>();
+        thread::spawn(move || {
+            for request in receiver {
+                let result = append_locked(&request.path, request.block, request.truncate);
+                // The run may have given up waiting, nothing to report to then
+                let _ = request.done.send(result);
+            }
+        });
+        Mutex::new(sender)
+    })
+}
+
+fn append_locked(path: &Path, block: Block, truncate: bool) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(false)
+        .open(path)?;
+    // Also keeps other processes, e.g. a second instance, out while writing
+    file.lock()?;
+    if truncate {
+        file.set_len(0)?;
+    }
+    match block {
+        Block::Text { header, content } => {
+            if file.seek(SeekFrom::End(0))? == 0 {
+                if let Some(header) = header {
+                    file.write_all(header.as_bytes())?;
+                }
+            }
+            file.write_all(content.as_bytes())?;
+        }
+        Block::Json(sequence) => {
+            let mut existing = String::new();
+            file.read_to_string(&mut existing)?;
+            let mut document = if existing.trim().is_empty() {
+                json!({ "sequences": [] })
+            } else {
+                serde_json::from_str(&existing)?
+            };
+            let Some(sequences) = document.get_mut("sequences").and_then(Value::as_array_mut)
+            else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the file is no sequence list",
+                ));
+            };
+            sequences.push(sequence);
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(serde_json::to_string_pretty(&document)?.as_bytes())?;
+        }
+    }
+    file.flush()
+}
+
+/// Appends `block` after all earlier submitted blocks.
+fn append(path: &Path, block: Block, truncate: bool) -> io::Result<()> {
+    let (done, result) = mpsc::channel();
+    let request = AppendRequest {
+        path: path.to_path_buf(),
+        block,
+        truncate,
+        done,
+    };
+    writer()
+        .lock()
+        .unwrap()
+        .send(request)
+        .map_err(|_| io::Error::other("the writer thread stopped"))?;
+    result
+        .recv()
+        .unwrap_or_else(|_| Err(io::Error::other("the writer thread stopped")))
+}
+
+/// Files currently written by a run, see [`TextfileConflict::Wait`] and
+/// [`TextfileConflict::SeparateFile`].
+fn claims() -> &'static (Mutex<HashSet<PathBuf>>, Condvar) {
+    static CLAIMS: OnceLock<(Mutex<HashSet<PathBuf>>, Condvar)> = OnceLock::new();
+    CLAIMS.get_or_init(Default::default)
+}
+
+/// The text files of one run. Files claimed by it are released when dropped.
+pub struct RunOutput {
+    conflict: TextfileConflict,
+    format: TextfileFormat,
+    /// Whether a file is emptied before the run's first sequence is written
+    overwrite: bool,
+    /// The file actually written for each requested one
+    files: HashMap<PathBuf, PathBuf>,
+}
+
+impl RunOutput {
+    pub fn new(conflict: TextfileConflict, format: TextfileFormat, overwrite: bool) -> Self {
+        Self {
+            conflict,
+            format,
+            overwrite,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Appends `entry` to the sequences file in `dir`, or to the file this
+    /// run uses instead.
+    pub fn append(&mut self, dir: &Path, entry: &SequenceEntry) -> io::Result<PathBuf> {
+        let (target, first) = self.target(&dir.join(self.format.file_name()));
+        append(
+            &target,
+            Block::new(self.format, entry),
+            first && self.overwrite,
+        )?;
+        Ok(target)
+    }
+
+    /// Appends `content` to `path`, or to the file this run uses instead,
+    /// after `header` if the file is empty. With `replace`, the run's first
+    /// block replaces what the file held.
+    pub fn append_text(
+        &mut self,
+        path: &Path,
+        header: &'static str,
+        content: String,
+        replace: bool,
+    ) -> io::Result<PathBuf> {
+        let (target, first) = self.target(path);
+        let block = Block::Text {
+            header: Some(header),
+            content,
+        };
+        append(&target, block, first && replace)?;
+        Ok(target)
+    }
+
+    /// The file written for `path`, and whether this run writes it first.
+    fn target(&mut self, path: &Path) -> (PathBuf, bool) {
+        match self.files.get(path) {
+            Some(target) => (target.clone(), false),
+            None => {
+                let target = self.claim(path);
+                self.files.insert(path.to_path_buf(), target.clone());
+                (target, true)
+            }
+        }
+    }
+
+    fn claim(&self, path: &Path) -> PathBuf {
+        let (claimed, released) = claims();
+        let mut claimed = claimed.lock().unwrap();
+        match self.conflict {
+            TextfileConflict::Append => path.to_path_buf(),
+            TextfileConflict::Wait => {
+                if claimed.contains(path) {
+                    info!("Waiting for another run writing {}", path.display());
+                }
+                while claimed.contains(path) {
+                    claimed = released.wait(claimed).unwrap();
+                }
+                claimed.insert(path.to_path_buf());
+                path.to_path_buf()
+            }
+            TextfileConflict::SeparateFile => {
+                let target = (1..)
+                    .map(|n| numbered(path, n))
+                    .find(|candidate| !claimed.contains(candidate))
+                    .unwrap();
+                if target != path {
+                    info!(
+                        "{} is written by another run, using {}",
+                        path.display(),
+                        target.display()
+                    );
+                }
+                claimed.insert(target.clone());
+                target
+            }
+        }
+    }
+}
+
+impl Drop for RunOutput {
+    fn drop(&mut self) {
+        if self.conflict == TextfileConflict::Append {
+            return;
+        }
+        let (claimed, released) = claims();
+        let mut claimed = claimed.lock().unwrap();
+        for target in self.files.values() {
+            claimed.remove(target);
+        }
+        released.notify_all();
+    }
+}
+
+/// `sequences.txt`, then `sequences-2.txt`, `sequences-3.txt`, …
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    if n == 1 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, n, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, n),
+    };
+    path.with_file_name(name)
+}
@@ -0,0 +1,312 @@
+//! Review of the folders a run created, one at a time: the frames as a
+//! filmstrip next to a quick fusion of them, so a misgrouped sequence stands
+//! out without going through the folders in a file manager. The arrow keys
+//! step through the folders, and a wrong one can be undone on its own.
+//!
+//! The fusion only weighs the embedded previews by how well exposed each
+//! pixel is, it hints at what a merge would look like, not at its quality.
+
+use crate::app::accessible_name;
+use crate::file_utils::{load_thumbnail, open_in_file_manager};
+use crate::journal;
+use eframe::egui;
+use image::{Rgb, RgbImage};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Size the previews are loaded at, the filmstrip shows them smaller
+const PREVIEW_SIZE: u32 = 480;
+const FILMSTRIP_HEIGHT: f32 = 96.0;
+/// Spread of the weight around mid grey, smaller favours well exposed pixels more
+const FUSION_SIGMA: f32 = 0.2;
+
+struct FolderPreview {
+    folder: PathBuf,
+    frames: Vec<(PathBuf, Option<egui::ColorImage>)>,
+    fused: Option<egui::ColorImage>,
+}
+
+struct LoadedFolder {
+    frames: Vec<(PathBuf, Option<egui::TextureHandle>)>,
+    fused: Option<egui::TextureHandle>,
+}
+
+#[derive(Default)]
+pub struct ResultReview {
+    pub open: bool,
+    folders: Vec<PathBuf>,
+    index: usize,
+    undone: HashSet<PathBuf>,
+    /// Previews loaded in the background, those of folders left meanwhile are dropped
+    results: Arc<Mutex<Vec<FolderPreview>>>,
+    loaded: Option<LoadedFolder>,
+    loading: bool,
+    errors: Vec<String>,
+}
+
+impl ResultReview {
+    /// Opens the review of `folders`, starting with the first one.
+    pub fn open(&mut self, ctx: &egui::Context, folders: Vec<PathBuf>) {
+        self.folders = folders;
+        self.index = 0;
+        self.undone.clear();
+        self.open = true;
+        self.load(ctx);
+    }
+
+    /// `can_undo` is false while a run is going on.
+    pub fn show(&mut self, ctx: &egui::Context, can_undo: bool) {
+        if !self.open {
+            return;
+        }
+        if !ctx.wants_keyboard_input() {
+            let (previous, next) = ctx.input(|i| {
+                (
+                    i.key_pressed(egui::Key::ArrowLeft) || i.key_pressed(egui::Key::ArrowUp),
+                    i.key_pressed(egui::Key::ArrowRight) || i.key_pressed(egui::Key::ArrowDown),
+                )
+            });
+            if previous {
+                self.step(ctx, -1);
+            } else if next {
+                self.step(ctx, 1);
+            }
+        }
+        let mut open = true;
+        egui::Window::new("Review Results")
+            .open(&mut open)
+            .default_width(PREVIEW_SIZE as f32 * 1.5)
+            .show(ctx, |ui| self.show_contents(ui, can_undo));
+        self.open = open;
+    }
+
+    fn show_contents(&mut self, ui: &mut egui::Ui, can_undo: bool) {
+        let Some(folder) = self.folders.get(self.index).cloned() else {
+            ui.label("The last run created no folders.");
+            return;
+        };
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.index > 0, egui::Button::new("◀ Previous"))
+                .on_hover_text("Left arrow")
+                .clicked()
+            {
+                self.step(ui.ctx(), -1);
+            }
+            ui.label(format!("{} of {}", self.index + 1, self.folders.len()));
+            if ui
+                .add_enabled(
+                    self.index + 1 < self.folders.len(),
+                    egui::Button::new("Next ▶"),
+                )
+                .on_hover_text("Right arrow")
+                .clicked()
+            {
+                self.step(ui.ctx(), 1);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.strong(folder.display().to_string());
+            if ui.small_button("Open Folder").clicked() {
+                open_in_file_manager(&folder);
+            }
+            let undone = self.undone.contains(&folder);
+            if ui
+                .add_enabled(can_undo && !undone, egui::Button::new("Undo This One"))
+                .on_hover_text("Move the files of this folder back and remove it, the other folders of the run stay")
+                .clicked()
+            {
+                self.errors = journal::undo_folder(&folder);
+                if self.errors.is_empty() {
+                    self.undone.insert(folder.clone());
+                    self.loaded = None;
+                }
+            }
+        });
+        for error in &self.errors {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", error));
+        }
+        if self.undone.contains(&folder) {
+            ui.label("Undone, the files are back where they were.");
+            return;
+        }
+
+        self.take_result(ui.ctx(), &folder);
+        if self.loading {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Loading previews…");
+            });
+            return;
+        }
+        let Some(loaded) = &self.loaded else {
+            return;
+        };
+        match &loaded.fused {
+            Some(fused) => {
+                let image = ui.add(egui::Image::new(fused).max_width(PREVIEW_SIZE as f32));
+                accessible_name(image, "Fused preview of the folder");
+            }
+            None => {
+                ui.label("No fused preview, the frames have no previews of the same size.");
+            }
+        }
+        ui.separator();
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                for (path, texture) in &loaded.frames {
+                    let name = file_name(path);
+                    let response = match texture {
+                        Some(texture) => {
+                            let image =
+                                ui.add(egui::Image::new(texture).max_height(FILMSTRIP_HEIGHT));
+                            accessible_name(image, &name)
+                        }
+                        None => ui.label(format!("{}\n(no preview)", name)),
+                    };
+                    response.on_hover_text(name);
+                }
+            });
+        });
+    }
+
+    fn step(&mut self, ctx: &egui::Context, offset: isize) {
+        let Some(index) = self.index.checked_add_signed(offset) else {
+            return;
+        };
+        if index < self.folders.len() && index != self.index {
+            self.index = index;
+            self.errors.clear();
+            self.load(ctx);
+        }
+    }
+
+    fn take_result(&mut self, ctx: &egui::Context, folder: &Path) {
+        let mut results = self.results.lock().unwrap();
+        let Some(position) = results.iter().position(|r| r.folder == folder) else {
+            return;
+        };
+        let preview = results.remove(position);
+        results.clear();
+        drop(results);
+
+        let texture = |name: String, image: egui::ColorImage| {
+            ctx.load_texture(name, image, Default::default())
+        };
+        self.loaded = Some(LoadedFolder {
+            frames: preview
+                .frames
+                .into_iter()
+                .map(|(path, image)| {
+                    let handle = image.map(|image| texture(path.display().to_string(), image));
+                    (path, handle)
+                })
+                .collect(),
+            fused: preview
+                .fused
+                .map(|image| texture(format!("fused {}", folder.display()), image)),
+        });
+        self.loading = false;
+    }
+
+    fn load(&mut self, ctx: &egui::Context) {
+        let Some(folder) = self.folders.get(self.index).cloned() else {
+            return;
+        };
+        self.loaded = None;
+        if self.undone.contains(&folder) {
+            self.loading = false;
+            return;
+        }
+        self.loading = true;
+        let results = Arc::clone(&self.results);
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let preview = load_folder(folder);
+            results.lock().unwrap().push(preview);
+            ctx.request_repaint();
+        });
+    }
+}
+
+fn load_folder(folder: PathBuf) -> FolderPreview {
+    let mut paths: Vec<PathBuf> = fs::read_dir(&folder)
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    // Not the comments written for file managers
+    paths.retain(|path| {
+        path.is_file()
+            && path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| !n.starts_with('.') && n != "desktop.ini")
+    });
+    paths.sort();
+
+    let images: Vec<(PathBuf, Option<RgbImage>)> = paths
+        .into_iter()
+        .map(|path| {
+            let image = load_thumbnail(&path, PREVIEW_SIZE).map(|i| i.to_rgb8());
+            (path, image)
+        })
+        .collect();
+    let loaded: Vec<&RgbImage> = images.iter().filter_map(|(_, i)| i.as_ref()).collect();
+    let fused = fuse(&loaded).map(|image| color_image(&image));
+    FolderPreview {
+        folder,
+        frames: images
+            .iter()
+            .map(|(path, image)| (path.clone(), image.as_ref().map(color_image)))
+            .collect(),
+        fused,
+    }
+}
+
+/// Blends `frames` pixel by pixel, weighted by how close to mid grey each
+/// one is. Frames of another size than the first are left out.
+pub fn fuse(frames: &[&RgbImage]) -> Option<RgbImage> {
+    let dimensions = frames.first()?.dimensions();
+    let frames: Vec<&RgbImage> = frames
+        .iter()
+        .copied()
+        .filter(|f| f.dimensions() == dimensions)
+        .collect();
+    if frames.len() < 2 {
+        return None;
+    }
+    let mut fused = RgbImage::new(dimensions.0, dimensions.1);
+    for (x, y, pixel) in fused.enumerate_pixels_mut() {
+        let mut sum = [0.0f32; 3];
+        let mut total = 0.0;
+        for frame in &frames {
+            let values = frame.get_pixel(x, y).0.map(|v| v as f32 / 255.0);
+            let weight = values
+                .iter()
+                .map(|v| (-(v - 0.5).powi(2) / (2.0 * FUSION_SIGMA * FUSION_SIGMA)).exp())
+                .product::<f32>()
+                + f32::EPSILON;
+            for (sum, value) in sum.iter_mut().zip(values) {
+                *sum += weight * value;
+            }
+            total += weight;
+        }
+        *pixel = Rgb(sum.map(|v| (v / total * 255.0).round() as u8));
+    }
+    Some(fused)
+}
+
+pub fn color_image(image: &RgbImage) -> egui::ColorImage {
+    egui::ColorImage::from_rgb(
+        [image.width() as usize, image.height() as usize],
+        image.as_raw(),
+    )
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,113 @@
+//! Log files of single runs, so problems found days later can still be
+//! diagnosed without reproducing the run.
+//!
+//! All records still go to env_logger as configured by `RUST_LOG`. While a
+//! [`RunLog`] is alive, the info, warning and error records of its thread are
+//! also appended to its file. Jobs run on a thread of their own, so
+//! concurrent jobs of the headless mode each get only their own records.
+
+use crate::app::Action;
+use chrono::Local;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+thread_local! {
+    static CURRENT: RefCell<Option<File>> = const { RefCell::new(None) };
+}
+
+struct Logger {
+    inner: env_logger::Logger,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata) || (metadata.level() <= Level::Info && has_run_log())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.inner.matches(record) {
+            self.inner.log(record);
+        }
+        if record.level() > Level::Info {
+            return;
+        }
+        CURRENT.with_borrow_mut(|file| {
+            if let Some(file) = file {
+                // Nowhere left to report a failing log file
+                let _ = writeln!(
+                    file,
+                    "{} {:<5} {}: {}",
+                    Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                    record.level(),
+                    record.target(),
+                    record.args()
+                );
+            }
+        });
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+        CURRENT.with_borrow_mut(|file| {
+            if let Some(file) = file {
+                let _ = file.flush();
+            }
+        });
+    }
+}
+
+fn has_run_log() -> bool {
+    CURRENT.with_borrow(Option::is_some)
+}
+
+/// Installs the logger, replaces `env_logger::init()`.
+pub fn init() {
+    let inner = env_logger::Builder::from_default_env().build();
+    // Info records have to reach the logger even if RUST_LOG hides them
+    let max_level = inner.filter().max(LevelFilter::Info);
+    if log::set_boxed_logger(Box::new(Logger { inner })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// The directory for the log of a run: the configured one, else the processed
+/// folder. Catalog only runs must not add files to the folder, their logs go
+/// to the app's storage instead.
+pub fn log_dir(
+    configured: Option<&Path>,
+    folder: Option<&Path>,
+    actions: &[Action],
+) -> Option<PathBuf> {
+    if let Some(dir) = configured {
+        return Some(dir.to_path_buf());
+    }
+    if actions.iter().all(|action| *action == Action::AddToCatalog) {
+        return eframe::storage_dir(crate::APP_NAME).map(|dir| dir.join("logs"));
+    }
+    folder.map(Path::to_path_buf)
+}
+
+/// Writes the records of the current thread to a file until dropped.
+pub struct RunLog;
+
+impl RunLog {
+    /// Creates `ebo-run-<date>-<time>.log` in `dir`.
+    pub fn start(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let name = format!("ebo-run-{}.log", Local::now().format("%Y%m%d-%H%M%S"));
+        let path = dir.join(name);
+        // Appends, two jobs starting in the same second must not clobber each other
+        let file = File::options().create(true).append(true).open(&path)?;
+        CURRENT.set(Some(file));
+        Ok(Self)
+    }
+}
+
+impl Drop for RunLog {
+    fn drop(&mut self) {
+        CURRENT.set(None);
+    }
+}
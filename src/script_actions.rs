@@ -0,0 +1,339 @@
+//! Actions contributed by scripts, a small plug-in system without embedding a
+//! language. Every executable in the actions directory
+//! (`~/.config/ebo/actions/`, `%APPDATA%\ebo\actions\` on Windows) that names
+//! itself in a comment within its first lines is listed as an action:
+//!
+//! ```sh
+//! #!/bin/sh
+//! # ebo-name: Merge with enfuse
+//! # ebo-description: Fuses every bracket into a JPEG next to its frames
+//! enfuse -o "$(dirname "$1")/$(basename "$1" .ARW)-fused.jpg" "$@"
+//! ```
+//!
+//! A script runs once per sequence, in the folder of its first file, with the
+//! paths of the frames as arguments and as lines on stdin. `EBO_BRACKET_KIND`
+//! (`exposure`, `aperture`, `white_balance` or `flash`) and `EBO_FRAME_COUNT`
+//! are set as well. A non-zero exit status is reported as an error of the run.
+//! What a script changes is not recorded in the journal, so it can't be undone.
+//!
+//! The "Run Command" action runs a command template from the settings the
+//! same way, filled in by [`fill_command`] and run with the system's shell.
+
+use crate::bracket_kind::BracketKind;
+use crate::hdr_tools::{fill_template, quote};
+use crate::metadata_export::decimal;
+use log::info;
+use num_rational::Rational32;
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+#[cfg(windows)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// The header is only looked for in the first lines, it belongs at the top
+const HEADER_LINES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptAction {
+    /// File name within the actions directory, what settings refer to it by
+    pub file_name: String,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Where scripts are discovered, `None` if the home folder is unknown.
+pub fn actions_dir() -> Option<PathBuf> {
+    let config = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }?;
+    Some(config.join("ebo").join("actions"))
+}
+
+/// The scripts in the actions directory, sorted by name.
+pub fn discover() -> Vec<ScriptAction> {
+    let Some(dir) = actions_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut actions: Vec<ScriptAction> = entries
+        .flatten()
+        .filter_map(|entry| load(&entry.path()))
+        .collect();
+    actions.sort_by(|a, b| a.name.cmp(&b.name));
+    if !actions.is_empty() {
+        info!(
+            "Found {} script actions in {}",
+            actions.len(),
+            dir.display()
+        );
+    }
+    actions
+}
+
+/// The script `file_name` in the actions directory, if it is still there and
+/// has a header. Names with path separators are refused, so settings and API
+/// requests can only run scripts placed there.
+pub fn find(file_name: &str) -> Option<ScriptAction> {
+    if Path::new(file_name).file_name()? != file_name {
+        return None;
+    }
+    load(&actions_dir()?.join(file_name))
+}
+
+fn load(path: &Path) -> Option<ScriptAction> {
+    if !is_executable(path) {
+        return None;
+    }
+    let file_name = path.file_name()?.to_str()?.to_string();
+    let mut head = Vec::new();
+    fs::File::open(path)
+        .ok()?
+        .take(4096)
+        .read_to_end(&mut head)
+        .ok()?;
+    let head = String::from_utf8_lossy(&head);
+
+    let mut name = None;
+    let mut description = None;
+    for line in head.lines().take(HEADER_LINES) {
+        let Some((key, value)) = header_field(line) else {
+            continue;
+        };
+        match key {
+            "ebo-name" => name = Some(value.to_string()),
+            "ebo-description" => description = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    Some(ScriptAction {
+        file_name,
+        name: name.filter(|n| !n.is_empty())?,
+        description: description.filter(|d| !d.is_empty()),
+    })
+}
+
+/// `# ebo-name: Merge` as key and value, behind the comment marker of any
+/// common script language.
+fn header_field(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    let comment = ["#", "//", "--", "::", ";", "REM ", "rem "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))?;
+    let (key, value) = comment.split_once(':')?;
+    let key = key.trim();
+    key.starts_with("ebo-").then(|| (key, value.trim()))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    let runnable = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| ["bat", "cmd", "exe", "com"].contains(&e.to_ascii_lowercase().as_str()));
+    runnable && path.is_file()
+}
+
+/// Runs the script `file_name` for the frames `files` of one sequence in `dir`.
+pub fn run(
+    file_name: &str,
+    files: &[PathBuf],
+    kind: BracketKind,
+    dir: &Path,
+) -> Result<(), String> {
+    let Some(script) = find(file_name) else {
+        return Err(format!(
+            "Script action {} is no longer in the actions directory",
+            file_name
+        ));
+    };
+    let path = actions_dir()
+        .map(|dir| dir.join(&script.file_name))
+        .ok_or_else(|| "The actions directory is unknown".to_string())?;
+    let kind = serde_json::to_value(kind)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+
+    let mut child = Command::new(&path)
+        .args(files)
+        .current_dir(dir)
+        .env("EBO_BRACKET_KIND", kind)
+        .env("EBO_FRAME_COUNT", files.len().to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start script {}: {}", script.name, e))?;
+
+    // Written aside, a script that doesn't read stdin must not block the run
+    let list: String = files.iter().map(|f| format!("{}\n", f.display())).collect();
+    if let Some(mut stdin) = child.stdin.take() {
+        thread::spawn(move || {
+            let _ = stdin.write_all(list.as_bytes());
+        });
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Script {} failed: {}", script.name, e))?;
+    log_output(&format!("Script {}", script.name), &script.name, &output)
+}
+
+/// Fills in a command template for the frames `files` of one sequence in
+/// `dir`: `{files}` becomes their quoted paths, `{folder}` the quoted folder,
+/// `{count}` the number of frames and `{ev_range}` the EV between the
+/// darkest and the brightest frame, e.g. `4` or `1.333333`.
+pub fn fill_command(
+    template: &str,
+    files: &[PathBuf],
+    dir: &Path,
+    biases: &[Option<Rational32>],
+) -> String {
+    let quoted: Vec<String> = files
+        .iter()
+        .map(|file| quote(&file.to_string_lossy()))
+        .collect();
+    let biases: Vec<Rational32> = biases.iter().flatten().copied().collect();
+    let ev_range = match (biases.iter().min(), biases.iter().max()) {
+        (Some(min), Some(max)) => decimal(max - min),
+        _ => String::new(),
+    };
+    fill_template(template, |token| match token {
+        "files" => Some(quoted.join(" ")),
+        "folder" => Some(quote(&dir.to_string_lossy())),
+        "count" => Some(files.len().to_string()),
+        "ev_range" => Some(ev_range.clone()),
+        _ => None,
+    })
+}
+
+/// Runs a filled in command template with the system's shell in `dir`. On
+/// Windows it runs as a batch file, which is what [`quote`] escapes for.
+pub fn run_command(command: &str, dir: &Path) -> Result<(), String> {
+    #[cfg(windows)]
+    let batch = {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "ebo-command-{}-{}.bat",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let content = format!(
+            "@echo off\r\n{}\r\n",
+            command.lines().collect::<Vec<_>>().join("\r\n")
+        );
+        fs::write(&path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        path
+    };
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(&batch);
+        shell
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    };
+    let output = shell.current_dir(dir).stdin(Stdio::null()).output();
+    #[cfg(windows)]
+    let _ = fs::remove_file(&batch);
+    let output =
+        output.map_err(|e| format!("Failed to run command in {}: {}", dir.display(), e))?;
+    log_output(&format!("Command in {}", dir.display()), "command", &output)
+}
+
+/// Logs what a script or command printed, an error with its last line of
+/// stderr if it failed.
+fn log_output(description: &str, name: &str, output: &Output) -> Result<(), String> {
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        info!("{}: {}", name, line);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        for line in stderr.lines() {
+            info!("{}: {}", name, line);
+        }
+        Ok(())
+    } else {
+        Err(format!(
+            "{} exited with {}{}",
+            description,
+            output.status,
+            stderr
+                .lines()
+                .last()
+                .map(|line| format!(": {}", line))
+                .unwrap_or_default()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(windows))]
+    fn fill_command_quotes_paths() {
+        let files = [
+            PathBuf::from("/photos/my trip/IMG 1.ARW"),
+            PathBuf::from("/photos/my trip/it's.ARW"),
+        ];
+        let biases = [Some(Rational32::new(-2, 1)), Some(Rational32::new(2, 1))];
+        let command = fill_command(
+            "enfuse -o HDR_{count}_{ev_range}EV.tif {files} # {folder}",
+            &files,
+            Path::new("/photos/my trip"),
+            &biases,
+        );
+        assert_eq!(
+            command,
+            "enfuse -o HDR_2_4EV.tif '/photos/my trip/IMG 1.ARW' '/photos/my trip/it'\\''s.ARW' # '/photos/my trip'"
+        );
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn fill_command_keeps_tokens_within_paths() {
+        let files = [PathBuf::from("/photos/{folder} $(reboot) {count}.ARW")];
+        let command = fill_command("ls {files} {unknown}", &files, Path::new("/photos"), &[]);
+        assert_eq!(
+            command,
+            "ls '/photos/{folder} $(reboot) {count}.ARW' {unknown}"
+        );
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn fill_command_escapes_percent_signs() {
+        let files = [PathBuf::from(r"C:\photos\100%PATH%\IMG 1.ARW")];
+        let command = fill_command("echo {files}", &files, Path::new(r"C:\photos"), &[]);
+        assert_eq!(command, r#"echo "C:\photos\100%%PATH%%\IMG 1.ARW""#);
+    }
+
+    #[test]
+    fn fill_command_without_biases_leaves_ev_range_empty() {
+        let command = fill_command("{count}:{ev_range}", &[], Path::new("/photos"), &[None]);
+        assert_eq!(command, "0:");
+    }
+}
@@ -0,0 +1,64 @@
+//! Plausibility checks of an entered sequence against what camera auto
+//! bracketing can shoot, so a typo is caught before a run finds nothing.
+//!
+//! The limits are those of current bodies in general: up to 9 frames, steps
+//! between 1/3 and 3 EV. A warning doesn't stop a run, manual brackets or an
+//! unusual camera may well need such a sequence.
+
+use num_rational::Rational32;
+
+/// The most frames any camera's auto bracketing takes
+const MAX_BRACKET_FRAMES: usize = 9;
+
+/// Why `sequence` is unlikely to match brackets of a camera, empty if it looks
+/// fine. Its `*` frames only count towards the number of frames.
+pub fn sequence_warnings(sequence: &[Option<Rational32>]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if sequence.len() > MAX_BRACKET_FRAMES {
+        warnings.push(format!(
+            "{} frames entered, camera auto bracketing takes at most {}.",
+            sequence.len(),
+            MAX_BRACKET_FRAMES
+        ));
+    }
+
+    let mut values: Vec<Rational32> = sequence.iter().flatten().copied().collect();
+    values.sort();
+    for pair in values.windows(2).filter(|w| w[0] == w[1]) {
+        warnings.push(format!(
+            "{} EV is entered more than once, the frames of a bracket differ in exposure.",
+            pair[0]
+        ));
+    }
+    values.dedup();
+
+    let steps: Vec<Rational32> = values.windows(2).map(|w| w[1] - w[0]).collect();
+    if let Some(step) = steps.iter().min().filter(|s| **s < Rational32::new(1, 3)) {
+        warnings.push(format!(
+            "Steps of {} EV are finer than the 1/3 EV cameras bracket in.",
+            step
+        ));
+    }
+    if let Some(step) = steps.iter().max().filter(|s| **s > Rational32::from(3)) {
+        warnings.push(format!(
+            "Steps of {} EV are wider than the 3 EV cameras bracket in.",
+            step
+        ));
+    }
+
+    let tenths: Vec<String> = values
+        .iter()
+        .filter(|v| {
+            let tenths = *v * 10;
+            tenths.is_integer() && matches!(tenths.to_integer().abs() % 10, 3 | 7)
+        })
+        .map(|v| v.to_string())
+        .collect();
+    if !tenths.is_empty() {
+        warnings.push(format!(
+            "{} look like third stops written as tenths. Most cameras record thirds as 1/3 and 2/3, check with \"Get Exposure Bias\" how yours does.",
+            tenths.join(", ")
+        ));
+    }
+    warnings
+}
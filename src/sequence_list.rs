@@ -0,0 +1,163 @@
+use serde::Deserialize;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A JSON sequence list, either `{"sequences": [{"files": [...]}, ...]}`
+/// or a plain array of file arrays.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonSequenceList {
+    Document { sequences: Vec<JsonSequence> },
+    Groups(Vec<Vec<PathBuf>>),
+}
+
+#[derive(Deserialize)]
+struct JsonSequence {
+    files: Vec<PathBuf>,
+}
+
+/// Loads the groups of a previously exported sequence list.
+///
+/// Text lists use the `sequences.txt` layout: one path per line and a blank
+/// line between sequences, anything after a tab on a line is left out, e.g.
+/// the exposure biases of `sequences.tsv`. Relative paths are resolved
+/// against the folder of the list itself.
+pub fn load_sequence_list(path: &Path) -> Result<Vec<Vec<PathBuf>>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let groups = if has_json_extension(path) {
+        parse_json(&content)
+            .map_err(|e| format!("Invalid sequence list {}: {}", path.display(), e))?
+    } else {
+        parse_text(&content)
+    };
+
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let groups: Vec<Vec<PathBuf>> = groups
+        .into_iter()
+        .map(|group| group.into_iter().map(|p| base.join(p)).collect())
+        .filter(|group: &Vec<PathBuf>| !group.is_empty())
+        .collect();
+
+    if groups.is_empty() {
+        return Err(format!("No sequences found in {}", path.display()));
+    }
+    Ok(groups)
+}
+
+fn has_json_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+}
+
+fn parse_json(content: &str) -> Result<Vec<Vec<PathBuf>>, serde_json::Error> {
+    Ok(match serde_json::from_str(content)? {
+        JsonSequenceList::Document { sequences } => {
+            sequences.into_iter().map(|s| s.files).collect()
+        }
+        JsonSequenceList::Groups(groups) => groups,
+    })
+}
+
+fn parse_text(content: &str) -> Vec<Vec<PathBuf>> {
+    let mut groups = Vec::new();
+    let mut current = Vec::new();
+    for line in content.lines() {
+        let line = line.split('\t').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            if !current.is_empty() {
+                groups.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(PathBuf::from(line));
+        }
+    }
+    if !current.is_empty() {
+        groups.push(current);
+    }
+    groups
+}
+
+/// Writes groups in the layout [`load_sequence_list`] reads, as JSON if the
+/// path ends in `.json` and as a text list otherwise.
+pub fn save_sequence_list(path: &Path, groups: &[Vec<PathBuf>]) -> Result<(), String> {
+    let content = if has_json_extension(path) {
+        let sequences: Vec<_> = groups
+            .iter()
+            .map(|files| json!({ "files": files }))
+            .collect();
+        serde_json::to_string_pretty(&json!({ "sequences": sequences }))
+            .map_err(|e| format!("Failed to serialize sequence list: {}", e))?
+    } else {
+        groups
+            .iter()
+            .map(|files| {
+                let mut group: String =
+                    files.iter().map(|f| format!("{}\n", f.display())).collect();
+                group.push('\n');
+                group
+            })
+            .collect()
+    };
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn text_lists_split_at_blank_lines() {
+        let groups = parse_text("a.ARW\nb.ARW\n\n\n c.ARW \r\nd.ARW\n");
+        assert_eq!(
+            groups,
+            [paths(&["a.ARW", "b.ARW"]), paths(&["c.ARW", "d.ARW"])]
+        );
+    }
+
+    #[test]
+    fn text_lists_drop_columns_after_a_tab() {
+        let groups = parse_text("a.ARW\t0\nb.ARW\t-1\n\t\nc.ARW\t1");
+        assert_eq!(groups, [paths(&["a.ARW", "b.ARW"]), paths(&["c.ARW"])]);
+    }
+
+    #[test]
+    fn json_lists_are_documents_or_arrays() {
+        let document = r#"{"sequences": [{"files": ["a.ARW", "b.ARW"]}, {"files": ["c.ARW"]}]}"#;
+        let array = r#"[["a.ARW", "b.ARW"], ["c.ARW"]]"#;
+        let expected = [paths(&["a.ARW", "b.ARW"]), paths(&["c.ARW"])];
+        assert_eq!(parse_json(document).unwrap(), expected);
+        assert_eq!(parse_json(array).unwrap(), expected);
+        assert!(parse_json(r#"{"files": []}"#).is_err());
+    }
+
+    #[test]
+    fn saved_lists_load_relative_to_their_folder() {
+        let dir = std::env::temp_dir().join(format!("ebo-sequence-list-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let groups = [paths(&["a.ARW", "b.ARW"]), paths(&["sub/c.ARW"])];
+        let resolved: Vec<Vec<PathBuf>> = groups
+            .iter()
+            .map(|group| group.iter().map(|p| dir.join(p)).collect())
+            .collect();
+
+        for name in ["sequences.txt", "sequences.JSON"] {
+            let list = dir.join(name);
+            save_sequence_list(&list, &groups).unwrap();
+            assert_eq!(load_sequence_list(&list).unwrap(), resolved);
+        }
+
+        let empty = dir.join("empty.txt");
+        fs::write(&empty, "\n\n").unwrap();
+        assert!(load_sequence_list(&empty).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
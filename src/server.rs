@@ -0,0 +1,820 @@
+//! Headless mode: a small HTTP API on localhost to submit, follow and cancel jobs.
+
+use crate::app::{
+    check_actions, deserialize_optional_actions, Action, FileOrder, DEFAULT_EV_TOLERANCE,
+    DEFAULT_EXTENSIONS,
+};
+use crate::archive_manifest::{manifest_roots, write_manifests};
+use crate::file_utils::{
+    collect_directories, count_files_in_directory, ev_tolerance, process_directories,
+    seconds_to_time_delta, BracketModeOverride, ClockOffset, DateFolders, JobProgress,
+    ProcessSettings, ProgressEvent, RunReport, DEFAULT_FOLDER_NAME,
+};
+use crate::folder_settings::FolderSettings;
+use crate::hdr_tools::{DEFAULT_FUSION_COMMAND, DEFAULT_HDRMERGE_PATH};
+use crate::output_writer::{TextfileConflict, TextfileFormat};
+use crate::run_log::{self, RunLog};
+use crate::script_actions;
+use crate::sleep_inhibitor::SleepInhibitor;
+use crate::webhook::{self, RunSummary};
+use ebo_core::{
+    parse_exposure_sequence, parse_exposure_sequences, DeltaReference, EvFormat, EvMode, MatchMode,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+/// Requests larger than this are rejected, job submissions are tiny
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Request line and headers together are cut off beyond this
+const MAX_HEADER_SIZE: u64 = 16 * 1024;
+const MAX_HEADERS: usize = 100;
+
+/// Finished jobs kept for their results, the oldest are dropped beyond this
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// Clients that stop sending in the middle of a request are dropped after this
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings missing in a request are taken from the folder's `.ebo.toml`,
+/// then from the defaults.
+#[derive(Default, Deserialize)]
+pub struct JobRequest {
+    pub folder: PathBuf,
+    pub sequence: Option<String>,
+    /// One action or a list of them, executed on each sequence
+    #[serde(default, deserialize_with = "deserialize_optional_actions")]
+    pub action: Option<Vec<Action>>,
+    pub ev_mode: Option<EvMode>,
+    /// `zero`, `first`, `middle` or a frame number
+    pub delta_reference: Option<String>,
+    pub filter_by_auto_bracket: Option<bool>,
+    /// Exposure modes the auto-bracket filter also accepts from some cameras
+    pub bracket_mode_overrides: Option<Vec<BracketModeOverride>>,
+    /// Corrections of camera clocks, a list of `camera` and `seconds`
+    pub clock_offsets: Option<Vec<ClockOffset>>,
+    pub use_metadata_cache: Option<bool>,
+    pub extensions: Option<Vec<String>>,
+    pub webhook_url: Option<String>,
+    /// Modifies DNG and JPEG originals, so it has to be asked for explicitly
+    #[serde(default)]
+    pub embed_xmp: bool,
+    /// Template of the fusion scripts, the default aligns and fuses all frames
+    pub fusion_command: Option<String>,
+    /// The HDRMerge executable, `hdrmerge` from the `PATH` if missing
+    pub hdrmerge_path: Option<String>,
+    /// The template of `run_command`, only given on the command line. Jobs
+    /// from the API can't run arbitrary commands, only scripts from the
+    /// actions directory.
+    #[serde(skip_deserializing)]
+    pub command: Option<String>,
+    /// `append`, `wait` or `separate_file` when another job writes the same sequences.txt
+    #[serde(default)]
+    pub textfile_conflict: TextfileConflict,
+    /// `paths`, `tab_separated`, `csv` or `json`
+    #[serde(default)]
+    pub textfile_format: TextfileFormat,
+    /// Replace the sequences file instead of appending to it
+    #[serde(default)]
+    pub overwrite_textfile: bool,
+    /// Date moved sequence folders by their capture time and add a comment
+    #[serde(default)]
+    pub folder_metadata: bool,
+    /// `off`, `day`, `hour` or `year_month_day` to gather moved sequences in date folders
+    pub date_folders: Option<DateFolders>,
+    /// Name of the sequence folders with `{name}`, `{date}` and `{note}` filled in
+    pub folder_name: Option<String>,
+    /// Order the files are searched in, as the filesystem lists them by default
+    pub file_order: Option<FileOrder>,
+    /// Where sequence folders are created instead of next to the files
+    pub destination_root: Option<PathBuf>,
+    /// Update the archive manifest of the organized folder afterwards
+    pub write_manifest: Option<bool>,
+    /// Remove scanned subfolders left empty by moving their sequences out
+    pub remove_empty_folders: Option<bool>,
+    /// Move or copy the XMP sidecars of a sequence's files along, on by default
+    pub move_sidecars: Option<bool>,
+    /// Move or copy the JPEGs and sidecars named like a sequence's files along
+    pub move_companions: Option<bool>,
+    /// Only windows whose reference frame has this bias are tried, e.g. "0"
+    pub anchor_bias: Option<String>,
+    /// How far a bias may be off the sequence value and still match, in EV
+    pub ev_tolerance: Option<f64>,
+    /// `overlapping` or `greedy`, whether a file can be part of two sequences
+    pub match_mode: Option<MatchMode>,
+    /// Also look for aperture, white balance and flash brackets
+    pub detect_other_brackets: Option<bool>,
+    /// Match on the exposure computed from shutter speed, aperture and ISO
+    pub computed_exposure: Option<bool>,
+    /// Search the files of each camera on their own
+    pub group_per_camera: Option<bool>,
+    /// Find brackets by their evenly spaced biases, `sequence` isn't needed then
+    pub auto_detect: Option<bool>,
+    /// Only match sequences within bursts split at pauses longer than this, in seconds
+    pub burst_gap: Option<f64>,
+    /// Reject sequences whose first and last frame are further apart, in seconds
+    pub max_sequence_span: Option<f64>,
+    /// Levels of subfolders processed below the folder, each one on its own
+    pub subfolder_depth: Option<usize>,
+    /// Only report the sequences and where their files would go, nothing is changed
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub write_log: bool,
+    /// Where the run log goes, defaults to the processed folder
+    pub log_directory: Option<PathBuf>,
+}
+
+struct Job {
+    folder: PathBuf,
+    progress: JobProgress,
+    report: Arc<Mutex<Option<RunReport>>>,
+}
+
+#[derive(Serialize)]
+struct JobStatus<'a> {
+    id: u64,
+    folder: &'a PathBuf,
+    state: &'static str,
+    total_files: usize,
+    processed_files: usize,
+    sequences_found: usize,
+}
+
+impl Job {
+    fn status(&self, id: u64) -> JobStatus<'_> {
+        let running = self.progress.running.load(Ordering::Relaxed);
+        let state = if running && self.progress.is_paused() {
+            "paused"
+        } else if running {
+            "running"
+        } else if self.progress.is_cancelled() {
+            "cancelled"
+        } else {
+            "finished"
+        };
+        JobStatus {
+            id,
+            folder: &self.folder,
+            state,
+            total_files: self.progress.total_files.load(Ordering::Relaxed),
+            processed_files: self.progress.processed_files.load(Ordering::Relaxed),
+            sequences_found: self
+                .progress
+                .exposure_bracketings_found
+                .load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Jobs {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+}
+
+impl Jobs {
+    /// Drops the oldest finished jobs beyond [`MAX_FINISHED_JOBS`].
+    fn prune(&mut self) {
+        let finished: Vec<u64> = self
+            .jobs
+            .iter()
+            .filter(|(_, job)| !job.progress.running.load(Ordering::Relaxed))
+            .map(|(id, _)| *id)
+            .collect();
+        let excess = finished.len().saturating_sub(MAX_FINISHED_JOBS);
+        for id in &finished[..excess] {
+            self.jobs.remove(id);
+        }
+    }
+}
+
+/// Serves the job API until the process is terminated.
+///
+/// - `POST /jobs` submits a job, e.g. `{"folder": "/photos", "sequence": "0/10, -10/10, 10/10"}`
+/// - `GET /jobs` and `GET /jobs/{id}` report progress
+/// - `GET /jobs/{id}/events` streams the job's progress events as JSON lines until it finishes
+/// - `GET /jobs/{id}/results` returns the found sequences once the job is done
+/// - `POST /jobs/{id}/cancel` stops a job between two sequences
+/// - `POST /jobs/{id}/pause` and `POST /jobs/{id}/resume` hold and continue a job
+///
+/// Every request has to carry the token printed at launch as
+/// `Authorization: Bearer <token>`. Requests from web pages, with an
+/// `Origin`, for another host or posting something else than JSON are
+/// refused, so no site opened in a browser can submit jobs. Only the last
+/// 100 finished jobs are kept.
+pub fn serve(address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    let local_address = listener.local_addr()?;
+    let token = Arc::new(generate_token()?);
+    info!("Serving job API on http://{}", local_address);
+    println!("API token: {}", token);
+
+    let jobs = Arc::new(Mutex::new(Jobs::default()));
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let jobs = Arc::clone(&jobs);
+                let token = Arc::clone(&token);
+                thread::spawn(move || {
+                    let access = Access {
+                        token: &token,
+                        local_address,
+                    };
+                    if let Err(e) = handle_connection(stream, &access, &jobs) {
+                        warn!("Failed to handle request: {}", e);
+                    }
+                });
+            }
+            Err(e) => warn!("Failed to accept connection: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// A random token, as hex, new for each start.
+pub fn generate_token() -> io::Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(io::Error::other)?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// What a request needs to be let in.
+struct Access<'a> {
+    token: &'a str,
+    local_address: SocketAddr,
+}
+
+/// The headers of a request the server looks at.
+#[derive(Default)]
+struct RequestHeaders {
+    content_length: usize,
+    content_type: Option<String>,
+    origin: Option<String>,
+    host: Option<String>,
+    authorization: Option<String>,
+}
+
+impl Access<'_> {
+    /// The status and error refusing the request, if it isn't let in.
+    fn check(&self, method: &str, headers: &RequestHeaders) -> Option<(u16, &'static str)> {
+        if headers.origin.is_some() {
+            return Some((403, "Requests from web pages are not allowed"));
+        }
+        if !headers
+            .host
+            .as_deref()
+            .is_some_and(|host| self.is_own_host(host))
+        {
+            return Some((403, "Unknown host"));
+        }
+        let expected = format!("Bearer {}", self.token);
+        if headers.authorization.as_deref().map(str::trim) != Some(expected.as_str()) {
+            return Some((401, "Missing or wrong API token"));
+        }
+        let is_json = headers.content_type.as_deref().is_some_and(|content_type| {
+            let media_type = content_type.split(';').next().unwrap_or_default();
+            media_type.trim().eq_ignore_ascii_case("application/json")
+        });
+        if method == "POST" && !is_json {
+            return Some((415, "Content-Type has to be application/json"));
+        }
+        None
+    }
+
+    /// Whether a `Host` header names this server, `localhost` or its own
+    /// address. Other names are refused against DNS rebinding.
+    fn is_own_host(&self, host: &str) -> bool {
+        let name = match host.rsplit_once(':') {
+            Some((name, port)) if !name.is_empty() && port.parse::<u16>().is_ok() => name,
+            _ => host,
+        };
+        let name = name.trim_start_matches('[').trim_end_matches(']');
+        name.eq_ignore_ascii_case("localhost")
+            || name
+                .parse::<IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback() || ip == self.local_address.ip())
+    }
+}
+
+fn handle_connection(stream: TcpStream, access: &Access, jobs: &Mutex<Jobs>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_HEADER_SIZE));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut too_large = !request_line.ends_with('\n');
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = RequestHeaders::default();
+    let mut header_count = 0;
+    while !too_large {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        header_count += 1;
+        // Cut off by the size limit, or more headers than any client sends
+        if !line.ends_with('\n') || header_count > MAX_HEADERS {
+            too_large = true;
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim().to_string();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => headers.content_length = value.parse().unwrap_or(0),
+                "content-type" => headers.content_type = Some(value),
+                "origin" => headers.origin = Some(value),
+                "host" => headers.host = Some(value),
+                "authorization" => headers.authorization = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    if too_large {
+        return respond(
+            stream,
+            431,
+            &json!({ "error": "Request headers too large" }),
+        );
+    }
+
+    let refused = access.check(&method, &headers);
+    if refused.is_none() && method == "GET" {
+        if let Some(id) = events_job_id(&path) {
+            return stream_events(stream, id, jobs);
+        }
+    }
+
+    let (status, body) = if let Some((status, error)) = refused {
+        warn!("Refused {} {}: {}", method, path, error);
+        (status, json!({ "error": error }))
+    } else if headers.content_length > MAX_BODY_SIZE {
+        (413, json!({ "error": "Request body too large" }))
+    } else {
+        let mut body = vec![0; headers.content_length];
+        reader.get_mut().set_limit(headers.content_length as u64);
+        reader.read_exact(&mut body)?;
+        route(&method, &path, &body, jobs)
+    };
+    respond(stream, status, &body)
+}
+
+fn respond(mut stream: TcpStream, status: u16, body: &Value) -> io::Result<()> {
+    let body = body.to_string();
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// The job id of a `/jobs/{id}/events` path.
+fn events_job_id(path: &str) -> Option<u64> {
+    let path = path.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        ["jobs", id, "events"] => id.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Writes the events of a job as JSON lines while they happen. The response
+/// ends with the `finished` event, right away for jobs that are already done.
+fn stream_events(mut stream: TcpStream, id: u64, jobs: &Mutex<Jobs>) -> io::Result<()> {
+    let events = {
+        let jobs = jobs.lock().unwrap();
+        let Some(job) = jobs.jobs.get(&id) else {
+            let body = json!({ "error": "Unknown job" }).to_string();
+            return write!(
+                stream,
+                "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        };
+        // Subscribed holding the report, the job can't finish unnoticed in between
+        let report = job.report.lock().unwrap();
+        match &*report {
+            Some(report) => Err(ProgressEvent::Finished {
+                sequences_found: report.sequences.len(),
+                errors: report.errors.len(),
+                cancelled: job.progress.is_cancelled(),
+            }),
+            None => Ok(job.progress.subscribe()),
+        }
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n"
+    )?;
+    let events = match events {
+        Ok(events) => events,
+        Err(finished) => return writeln!(stream, "{}", json!(finished)),
+    };
+    for event in events {
+        writeln!(stream, "{}", json!(event))?;
+        stream.flush()?;
+        if matches!(event, ProgressEvent::Finished { .. }) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn route(method: &str, path: &str, body: &[u8], jobs: &Mutex<Jobs>) -> (u16, Value) {
+    let segments: Vec<&str> = path
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    match (method, segments.as_slice()) {
+        ("POST", ["jobs"]) => submit_job(body, jobs),
+        ("GET", ["jobs"]) => {
+            let jobs = jobs.lock().unwrap();
+            let statuses: Vec<JobStatus> =
+                jobs.jobs.iter().map(|(id, job)| job.status(*id)).collect();
+            (200, json!(statuses))
+        }
+        (method, ["jobs", id, rest @ ..]) => {
+            let Ok(id) = id.parse::<u64>() else {
+                return (404, json!({ "error": "Unknown job" }));
+            };
+            let jobs = jobs.lock().unwrap();
+            let Some(job) = jobs.jobs.get(&id) else {
+                return (404, json!({ "error": "Unknown job" }));
+            };
+            match (method, rest) {
+                ("GET", []) => (200, json!(job.status(id))),
+                ("GET", ["results"]) => match &*job.report.lock().unwrap() {
+                    Some(report) => (200, json!(report)),
+                    None => (409, json!({ "error": "Job is still running" })),
+                },
+                ("POST", ["cancel"]) | ("DELETE", []) => {
+                    job.progress.cancel();
+                    (200, json!(job.status(id)))
+                }
+                ("POST", ["pause"]) => {
+                    job.progress.set_paused(true);
+                    (200, json!(job.status(id)))
+                }
+                ("POST", ["resume"]) => {
+                    job.progress.set_paused(false);
+                    (200, json!(job.status(id)))
+                }
+                _ => (405, json!({ "error": "Unsupported request" })),
+            }
+        }
+        _ => (404, json!({ "error": "Not found" })),
+    }
+}
+
+fn submit_job(body: &[u8], jobs: &Mutex<Jobs>) -> (u16, Value) {
+    let request: JobRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return (400, json!({ "error": format!("Invalid job: {}", e) })),
+    };
+    let job = match plan_job(request) {
+        Ok(job) => job,
+        Err(e) => return (400, json!({ "error": e })),
+    };
+
+    let progress = JobProgress::default();
+    progress.start();
+    let report = Arc::new(Mutex::new(None));
+
+    let mut jobs = jobs.lock().unwrap();
+    jobs.prune();
+    jobs.next_id += 1;
+    let id = jobs.next_id;
+    jobs.jobs.insert(
+        id,
+        Job {
+            folder: job.folder.clone(),
+            progress: progress.clone(),
+            report: Arc::clone(&report),
+        },
+    );
+
+    info!("Starting job {} for {}", id, job.folder.display());
+    thread::spawn(move || {
+        let result = job.run(&progress);
+        // Finished while holding the report, see stream_events
+        let mut report = report.lock().unwrap();
+        progress.finish(report.insert(result));
+        info!("Finished job {}", id);
+    });
+
+    (201, json!(jobs.jobs[&id].status(id)))
+}
+
+/// A job with all settings resolved, ready to run.
+pub struct PlannedJob {
+    pub folder: PathBuf,
+    settings: ProcessSettings,
+    subfolder_depth: usize,
+    webhook_url: Option<String>,
+    log_dir: Option<PathBuf>,
+}
+
+/// Checks a request and fills in what it leaves out from the folder's
+/// `.ebo.toml` and the defaults.
+pub fn plan_job(request: JobRequest) -> Result<PlannedJob, String> {
+    if !request.folder.is_dir() {
+        return Err("Folder does not exist".to_string());
+    }
+    let folder_settings = FolderSettings::load(&request.folder)?.unwrap_or_default();
+
+    let auto_detect = request
+        .auto_detect
+        .or(folder_settings.auto_detect)
+        .unwrap_or(false);
+    let sequence = request.sequence.or(folder_settings.sequence);
+    let sequences = match sequence {
+        None if auto_detect => Vec::new(),
+        None => return Err("No exposure bias sequence given".to_string()),
+        Some(sequence) => parse_exposure_sequences(&sequence),
+    };
+    if !auto_detect
+        && (sequences.is_empty()
+            || sequences
+                .iter()
+                .any(|s| s.len() < 2 || s.iter().all(Option::is_none)))
+    {
+        return Err("Invalid or single-value exposure bias sequence.".to_string());
+    }
+
+    let anchor_bias = request.anchor_bias.or(folder_settings.anchor_bias);
+    let anchor_bias = match anchor_bias.as_deref().map(parse_exposure_sequence) {
+        None => None,
+        Some(bias) if bias.len() == 1 => Some(bias[0]),
+        Some(_) => return Err("Invalid anchor bias".to_string()),
+    };
+
+    let delta_reference = match request
+        .delta_reference
+        .as_deref()
+        .map(str::parse::<DeltaReference>)
+    {
+        None => folder_settings.delta_reference.unwrap_or_default(),
+        Some(Ok(delta_reference)) => delta_reference,
+        Some(Err(e)) => return Err(e),
+    };
+
+    let actions = request
+        .action
+        .or(folder_settings.action)
+        .unwrap_or_else(|| vec![Action::MoveToFolder]);
+    check_actions(&actions)?;
+    for action in &actions {
+        if let Action::Script(file_name) = action {
+            if script_actions::find(file_name).is_none() {
+                return Err(format!(
+                    "No script action {} in the actions directory",
+                    file_name
+                ));
+            }
+        }
+    }
+    if actions.contains(&Action::RunCommand) && request.command.is_none() {
+        return Err("Commands can only be run from the app or with --command".to_string());
+    }
+
+    let settings = ProcessSettings {
+        extensions: request
+            .extensions
+            .or(folder_settings.extensions)
+            .unwrap_or_else(|| DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect()),
+        sequences,
+        selected_actions: actions,
+        ev_format: EvFormat::default(),
+        ev_mode: request
+            .ev_mode
+            .or(folder_settings.ev_mode)
+            .unwrap_or(EvMode::Delta),
+        delta_reference,
+        filter_by_auto_bracket: request
+            .filter_by_auto_bracket
+            .or(folder_settings.filter_by_auto_bracket)
+            .unwrap_or(true),
+        bracket_mode_overrides: request
+            .bracket_mode_overrides
+            .or(folder_settings.bracket_mode_overrides)
+            .unwrap_or_default(),
+        clock_offsets: request
+            .clock_offsets
+            .or(folder_settings.clock_offsets)
+            .unwrap_or_default(),
+        use_metadata_cache: request
+            .use_metadata_cache
+            .or(folder_settings.use_metadata_cache)
+            .unwrap_or(true),
+        embed_xmp: request.embed_xmp,
+        fusion_command: request
+            .fusion_command
+            .unwrap_or_else(|| DEFAULT_FUSION_COMMAND.to_string()),
+        hdrmerge_path: request
+            .hdrmerge_path
+            .unwrap_or_else(|| DEFAULT_HDRMERGE_PATH.to_string()),
+        command_template: request.command.unwrap_or_default(),
+        folder_metadata: request.folder_metadata,
+        date_folders: request
+            .date_folders
+            .or(folder_settings.date_folders)
+            .unwrap_or_default(),
+        folder_name: request
+            .folder_name
+            .or(folder_settings.folder_name)
+            .unwrap_or_else(|| DEFAULT_FOLDER_NAME.to_string()),
+        folder_order: request
+            .file_order
+            .or(folder_settings.file_order)
+            .unwrap_or_default(),
+        textfile_conflict: request.textfile_conflict,
+        textfile_format: request.textfile_format,
+        overwrite_textfile: request.overwrite_textfile,
+        anchor_bias,
+        ev_tolerance: ev_tolerance(
+            request
+                .ev_tolerance
+                .or(folder_settings.ev_tolerance)
+                .unwrap_or(DEFAULT_EV_TOLERANCE),
+        ),
+        match_mode: request
+            .match_mode
+            .or(folder_settings.match_mode)
+            .unwrap_or_default(),
+        detect_other_brackets: request
+            .detect_other_brackets
+            .or(folder_settings.detect_other_brackets)
+            .unwrap_or(false),
+        computed_exposure: request
+            .computed_exposure
+            .or(folder_settings.computed_exposure)
+            .unwrap_or(false),
+        group_per_camera: request
+            .group_per_camera
+            .or(folder_settings.group_per_camera)
+            .unwrap_or(false),
+        auto_detect,
+        burst_gap: request
+            .burst_gap
+            .or(folder_settings.burst_gap)
+            .map(seconds_to_time_delta),
+        max_sequence_span: request
+            .max_sequence_span
+            .or(folder_settings.max_sequence_span)
+            .map(seconds_to_time_delta),
+        excluded_files: HashSet::new(),
+        dry_run: request.dry_run,
+        sequence_notes: HashMap::new(),
+        rejected_sequences: HashSet::new(),
+        destination_root: request
+            .destination_root
+            .or(folder_settings.destination_root)
+            .map(|root| request.folder.join(root)),
+        write_manifest: request
+            .write_manifest
+            .or(folder_settings.write_manifest)
+            .unwrap_or(false),
+        remove_empty_folders: request
+            .remove_empty_folders
+            .or(folder_settings.remove_empty_folders)
+            .unwrap_or(false),
+        move_sidecars: request
+            .move_sidecars
+            .or(folder_settings.move_sidecars)
+            .unwrap_or(true),
+        move_companions: request
+            .move_companions
+            .or(folder_settings.move_companions)
+            .unwrap_or(false),
+    };
+
+    let subfolder_depth = request
+        .subfolder_depth
+        .or(folder_settings.subfolder_depth)
+        .unwrap_or(0);
+
+    let webhook_url = request
+        .webhook_url
+        .filter(|url| !url.trim().is_empty() && !request.dry_run);
+    let log_dir = (request.write_log && !request.dry_run)
+        .then(|| {
+            run_log::log_dir(
+                request.log_directory.as_deref(),
+                Some(&request.folder),
+                &settings.selected_actions,
+            )
+        })
+        .flatten();
+    Ok(PlannedJob {
+        folder: request.folder,
+        settings,
+        subfolder_depth,
+        webhook_url,
+        log_dir,
+    })
+}
+
+impl PlannedJob {
+    /// Runs the job on the current thread. `progress` has to be started, it
+    /// isn't finished here.
+    pub fn run(self, progress: &JobProgress) -> RunReport {
+        let _sleep_inhibitor = SleepInhibitor::acquire();
+        let _run_log = self.log_dir.and_then(|dir| {
+            RunLog::start(&dir)
+                .map_err(|e| warn!("Failed to create a run log in {}: {}", dir.display(), e))
+                .ok()
+        });
+        info!("Processing {}", self.folder.display());
+
+        let dirs = collect_directories(&self.folder, self.subfolder_depth);
+        let total = dirs
+            .iter()
+            .map(|dir| count_files_in_directory(dir, &self.settings.extensions))
+            .sum();
+        progress.set_total_files(total);
+
+        let manifest_roots = manifest_roots(std::slice::from_ref(&self.folder), &self.settings);
+        let mut result = process_directories(&dirs, progress, self.settings);
+        result.summarize();
+        write_manifests(&manifest_roots, &mut result);
+        if let Some(url) = self.webhook_url {
+            webhook::notify(
+                &url,
+                &RunSummary::new(Some(&self.folder), progress, &result),
+            );
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_keeps_running_and_the_latest_finished_jobs() {
+        let mut jobs = Jobs::default();
+        for id in 1..=MAX_FINISHED_JOBS as u64 + 5 {
+            let progress = JobProgress::default();
+            if id <= 2 {
+                progress.start();
+            }
+            jobs.jobs.insert(
+                id,
+                Job {
+                    folder: PathBuf::from("/photos"),
+                    progress,
+                    report: Arc::new(Mutex::new(None)),
+                },
+            );
+        }
+        jobs.prune();
+
+        let ids: Vec<u64> = jobs.jobs.keys().copied().collect();
+        assert_eq!(ids.len(), MAX_FINISHED_JOBS + 2);
+        assert_eq!(ids[..3], [1, 2, 6]);
+    }
+}
@@ -0,0 +1,92 @@
+//! Capture metadata from the XMP sidecars raw editors leave next to the
+//! files. Lightroom and Camera Raw copy the EXIF of the raw file into them,
+//! and a sidecar of a few kilobytes is much faster to parse than the raw file,
+//! so folders that already went through such an editor are scanned quickly.
+//!
+//! A sidecar is only used if it has everything detection needs: the capture
+//! time, the exposure bias and the exposure mode. Sidecars written by this app
+//! hold repaired values only and are applied on top of the raw file's instead,
+//! see [`crate::exposure_repair`].
+
+use crate::bracket_kind::CaptureSettings;
+use crate::metadata_cache::CachedMetadata;
+use crate::xmp::{Xmp, AUX, EXIF, TIFF};
+use chrono::NaiveDateTime;
+use ebo_core::parse_exposure_sequence;
+use log::debug;
+use num_rational::Rational32;
+use std::path::Path;
+
+/// Reads the metadata of `path` from its XMP sidecar, `None` if it has no
+/// sidecar or the sidecar lacks any of the values detection needs.
+pub fn read_sidecar_metadata(path: &Path) -> Option<CachedMetadata> {
+    let xmp = Xmp::read_sidecar(path).ok()??;
+    let capture_time = xmp
+        .get_text(&EXIF, "DateTimeOriginal")
+        .and_then(|v| parse_xmp_date(&v))?;
+    let exposure_bias = xmp
+        .get_text(&EXIF, "ExposureBiasValue")
+        .and_then(|v| parse_rational(&v))?;
+    let exposure_mode = xmp
+        .get_text(&EXIF, "ExposureMode")
+        .and_then(|v| v.trim().parse().ok())?;
+
+    let make = xmp.get_text(&TIFF, "Make").unwrap_or_default();
+    let model = xmp.get_text(&TIFF, "Model").unwrap_or_default();
+    let camera = format!("{} {}", make.trim(), model.trim());
+    let camera = camera.trim();
+
+    let number = |property: &str| {
+        xmp.get_text(&EXIF, property)
+            .and_then(|v| v.trim().parse().ok())
+    };
+    let rational = |property: &str| {
+        xmp.get_text(&EXIF, property)
+            .and_then(|v| parse_rational(&v))
+    };
+    let capture_settings = CaptureSettings {
+        fnumber: rational("FNumber"),
+        exposure_time: rational("ExposureTime"),
+        iso: xmp
+            .get_first_item(&EXIF, "ISOSpeedRatings")
+            .and_then(|v| v.trim().parse().ok()),
+        white_balance: number("WhiteBalance"),
+        light_source: number("LightSource"),
+        // Written as a structure of its bits, which isn't read
+        flash: number("Flash"),
+        flash_energy: rational("FlashEnergy"),
+    };
+
+    debug!("Using the metadata of the sidecar of {}", path.display());
+    Some(CachedMetadata {
+        capture_time: Some(capture_time),
+        exposure_bias: Some(exposure_bias),
+        exposure_mode: Some(exposure_mode),
+        camera: (!camera.is_empty()).then(|| camera.to_string()),
+        capture_settings: Some(capture_settings),
+        image_number: xmp
+            .get_text(&AUX, "ImageNumber")
+            .and_then(|v| v.trim().parse().ok()),
+        maker_note_bracket: None,
+    })
+}
+
+/// Parses an XMP date like `2024-05-01T12:30:00.05+02:00`, dropping the time zone
+/// like the local time EXIF records.
+fn parse_xmp_date(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    let end = value
+        .char_indices()
+        .skip("2024-05-01T12:30:00".len())
+        .find(|(_, c)| matches!(c, 'Z' | '+' | '-'))
+        .map_or(value.len(), |(i, _)| i);
+    NaiveDateTime::parse_from_str(&value[..end], "%Y-%m-%dT%H:%M:%S%.f").ok()
+}
+
+/// Parses a single rational like `-2/3` or `28/10`.
+fn parse_rational(value: &str) -> Option<Rational32> {
+    match parse_exposure_sequence(value).as_slice() {
+        [value] => Some(*value),
+        _ => None,
+    }
+}
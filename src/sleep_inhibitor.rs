@@ -0,0 +1,77 @@
+//! Keeps the system from suspending while a job runs.
+
+use log::debug;
+#[cfg(not(windows))]
+use std::process::{Child, Command, Stdio};
+
+const REASON: &str = "Organizing exposure bracketing sequences";
+
+/// Prevents system sleep until dropped.
+///
+/// On Windows the execution state is tied to the calling thread, so the
+/// inhibitor has to be acquired and dropped on the worker thread itself.
+pub struct SleepInhibitor {
+    #[cfg(not(windows))]
+    child: Option<Child>,
+}
+
+impl SleepInhibitor {
+    #[cfg(windows)]
+    pub fn acquire() -> Self {
+        use windows::Win32::System::Power::{
+            SetThreadExecutionState, ES_CONTINUOUS, ES_SYSTEM_REQUIRED,
+        };
+        // SAFETY: only changes the execution state of the current thread
+        if unsafe { SetThreadExecutionState(ES_CONTINUOUS | ES_SYSTEM_REQUIRED) }.0 == 0 {
+            debug!("Failed to prevent system sleep ({})", REASON);
+        }
+        Self {}
+    }
+
+    #[cfg(not(windows))]
+    pub fn acquire() -> Self {
+        #[cfg(target_os = "macos")]
+        let command = Command::new("caffeinate")
+            .args(["-i", "-w"])
+            .arg(std::process::id().to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        #[cfg(not(target_os = "macos"))]
+        let command = Command::new("systemd-inhibit")
+            .arg("--what=idle:sleep")
+            .arg(format!("--who={}", crate::APP_NAME))
+            .arg(format!("--why={}", REASON))
+            .args(["--mode=block", "sleep", "infinity"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        let child = command
+            .map_err(|e| debug!("Failed to prevent system sleep ({}): {}", REASON, e))
+            .ok();
+        Self { child }
+    }
+}
+
+impl Drop for SleepInhibitor {
+    #[cfg(windows)]
+    fn drop(&mut self) {
+        use windows::Win32::System::Power::{SetThreadExecutionState, ES_CONTINUOUS};
+        // SAFETY: resets the execution state of the current thread
+        unsafe {
+            SetThreadExecutionState(ES_CONTINUOUS);
+        }
+    }
+
+    #[cfg(not(windows))]
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Progress indicator on the Windows taskbar button. A no-op on other platforms.
+
+#[cfg(windows)]
+use windows::Win32::{
+    Foundation::HWND,
+    System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+    },
+    UI::Shell::{ITaskbarList3, TaskbarList, TBPF_NOPROGRESS, TBPF_NORMAL},
+};
+
+#[derive(Default)]
+pub struct TaskbarProgress {
+    #[cfg(windows)]
+    taskbar: Option<ITaskbarList3>,
+    #[cfg(windows)]
+    initialized: bool,
+}
+
+impl TaskbarProgress {
+    /// Shows `progress` (0.0 to 1.0) on the taskbar button, or clears it on `None`.
+    #[cfg(windows)]
+    pub fn set(&mut self, frame: &eframe::Frame, progress: Option<f32>) {
+        use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+        let hwnd = match frame.window_handle().map(|h| h.as_raw()) {
+            Ok(RawWindowHandle::Win32(handle)) => HWND(handle.hwnd.get() as *mut _),
+            _ => return,
+        };
+
+        if !self.initialized {
+            self.initialized = true;
+            // SAFETY: plain COM calls on the UI thread; a failing initialization
+            // (e.g. COM already set up by the windowing library) is harmless
+            unsafe {
+                let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+                self.taskbar =
+                    CoCreateInstance::<_, ITaskbarList3>(&TaskbarList, None, CLSCTX_INPROC_SERVER)
+                        .ok()
+                        .filter(|taskbar| taskbar.HrInit().is_ok());
+            }
+        }
+
+        let Some(taskbar) = &self.taskbar else {
+            return;
+        };
+        // SAFETY: `hwnd` belongs to the live main window of this process
+        unsafe {
+            let _ = match progress {
+                Some(fraction) => taskbar
+                    .SetProgressState(hwnd, TBPF_NORMAL)
+                    .and_then(|_| taskbar.SetProgressValue(hwnd, (fraction * 1000.0) as u64, 1000)),
+                None => taskbar.SetProgressState(hwnd, TBPF_NOPROGRESS),
+            };
+        }
+    }
+
+    #[cfg(not(windows))]
+    pub fn set(&mut self, _frame: &eframe::Frame, _progress: Option<f32>) {}
+}
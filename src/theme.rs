@@ -0,0 +1,127 @@
+//! Standard and high-contrast look of the app.
+//!
+//! The high-contrast variant uses white on black with outlined widgets, a
+//! clearly visible row striping and larger hit targets, for low-vision users
+//! reviewing long tables.
+
+use eframe::egui::{self, Color32, Stroke};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Theme {
+    /// High contrast while the operating system's high-contrast mode is on
+    #[default]
+    System,
+    Standard,
+    HighContrast,
+}
+
+impl std::fmt::Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Theme::System => write!(f, "System"),
+            Theme::Standard => write!(f, "Standard"),
+            Theme::HighContrast => write!(f, "High Contrast"),
+        }
+    }
+}
+
+impl Theme {
+    pub fn is_high_contrast(self) -> bool {
+        match self {
+            Theme::System => os_high_contrast(),
+            Theme::Standard => false,
+            Theme::HighContrast => true,
+        }
+    }
+}
+
+/// Replaces the dark and light styles of `ctx`, so the choice applies whatever
+/// dark or light preference egui follows.
+pub fn apply(ctx: &egui::Context, high_contrast: bool) {
+    for theme in [egui::Theme::Dark, egui::Theme::Light] {
+        let style = if high_contrast {
+            high_contrast_style()
+        } else {
+            egui::Style {
+                visuals: theme.default_visuals(),
+                ..Default::default()
+            }
+        };
+        ctx.set_style_of(theme, style);
+    }
+}
+
+fn high_contrast_style() -> egui::Style {
+    let mut style = egui::Style {
+        visuals: egui::Visuals::dark(),
+        ..Default::default()
+    };
+
+    let visuals = &mut style.visuals;
+    visuals.panel_fill = Color32::BLACK;
+    visuals.window_fill = Color32::BLACK;
+    visuals.window_stroke = Stroke::new(2.0, Color32::WHITE);
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.faint_bg_color = Color32::from_gray(45);
+    visuals.weak_text_color = Some(Color32::from_gray(220));
+    visuals.hyperlink_color = Color32::from_rgb(0x80, 0xd0, 0xff);
+    visuals.warn_fg_color = Color32::from_rgb(0xff, 0xd0, 0x00);
+    visuals.error_fg_color = Color32::from_rgb(0xff, 0x70, 0x70);
+    visuals.selection.bg_fill = Color32::from_rgb(0x00, 0x50, 0xc0);
+    visuals.selection.stroke = Stroke::new(2.0, Color32::WHITE);
+
+    let widgets = &mut visuals.widgets;
+    widgets.noninteractive.bg_stroke = Stroke::new(1.0, Color32::from_gray(200));
+    widgets.noninteractive.fg_stroke = Stroke::new(1.0, Color32::WHITE);
+    for (widget, fill) in [
+        (&mut widgets.inactive, Color32::from_gray(20)),
+        (&mut widgets.hovered, Color32::from_gray(60)),
+        (&mut widgets.active, Color32::from_gray(90)),
+        (&mut widgets.open, Color32::from_gray(60)),
+    ] {
+        widget.bg_fill = fill;
+        widget.weak_bg_fill = fill;
+        widget.bg_stroke = Stroke::new(1.5, Color32::WHITE);
+        widget.fg_stroke = Stroke::new(1.5, Color32::WHITE);
+    }
+    widgets.hovered.bg_stroke = Stroke::new(2.0, Color32::YELLOW);
+    widgets.active.bg_stroke = Stroke::new(2.0, Color32::YELLOW);
+
+    let spacing = &mut style.spacing;
+    spacing.interact_size = egui::vec2(48.0, 28.0);
+    spacing.button_padding = egui::vec2(8.0, 5.0);
+    spacing.item_spacing = egui::vec2(10.0, 6.0);
+    spacing.icon_width = 20.0;
+    spacing.icon_width_inner = 12.0;
+
+    style
+}
+
+#[cfg(windows)]
+fn os_high_contrast() -> bool {
+    use windows::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETHIGHCONTRAST, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    };
+
+    let mut high_contrast = HIGHCONTRASTW {
+        cbSize: std::mem::size_of::<HIGHCONTRASTW>() as u32,
+        ..Default::default()
+    };
+    // SAFETY: the buffer is a HIGHCONTRASTW with cbSize set, as SPI_GETHIGHCONTRAST expects
+    let result = unsafe {
+        SystemParametersInfoW(
+            SPI_GETHIGHCONTRAST,
+            high_contrast.cbSize,
+            Some(&mut high_contrast as *mut HIGHCONTRASTW as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    };
+    result.is_ok() && high_contrast.dwFlags.contains(HCF_HIGHCONTRASTON)
+}
+
+#[cfg(not(windows))]
+fn os_high_contrast() -> bool {
+    false
+}
@@ -0,0 +1,64 @@
+//! Notifies an external service, e.g. a Home Assistant or Slack webhook, when a job is done.
+
+use crate::file_utils::{JobProgress, RunReport};
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+pub struct RunSummary<'a> {
+    pub folder: Option<PathBuf>,
+    pub sequences_found: usize,
+    pub files_processed: usize,
+    pub cancelled: bool,
+    pub errors: &'a [String],
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub diagnostics: &'a [String],
+    /// Number of sequences by their number of frames
+    pub frame_counts: &'a BTreeMap<usize, usize>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub anomalies: &'a [String],
+}
+
+impl<'a> RunSummary<'a> {
+    pub fn new(folder: Option<&Path>, progress: &JobProgress, report: &'a RunReport) -> Self {
+        Self {
+            folder: folder.map(Path::to_path_buf),
+            sequences_found: report.sequences.len(),
+            files_processed: progress.processed_files.load(Ordering::Relaxed),
+            cancelled: progress.is_cancelled(),
+            errors: &report.errors,
+            diagnostics: &report.diagnostics,
+            frame_counts: &report.frame_counts,
+            anomalies: &report.anomalies,
+        }
+    }
+}
+
+/// POSTs the summary as JSON to `url`. Failures are only logged, a
+/// notification must never fail the run itself.
+pub fn notify(url: &str, summary: &RunSummary) {
+    let body = match serde_json::to_string(summary) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Failed to serialize run summary: {}", e);
+            return;
+        }
+    };
+
+    let result = ureq::post(url)
+        .config()
+        .timeout_global(Some(TIMEOUT))
+        .build()
+        .header("Content-Type", "application/json")
+        .send(body);
+    match result {
+        Ok(_) => info!("Sent run summary to {}", url),
+        Err(e) => warn!("Failed to send run summary to {}: {}", url, e),
+    }
+}
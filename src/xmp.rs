@@ -0,0 +1,507 @@
+//! Minimal reading and writing of XMP metadata, in sidecar files or embedded.
+//!
+//! Packets written by other applications are edited in place as text, so
+//! everything this module does not touch is preserved byte for byte.
+
+use crate::embedded_xmp;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub struct Namespace {
+    pub prefix: &'static str,
+    pub uri: &'static str,
+}
+
+pub const AUX: Namespace = Namespace {
+    prefix: "aux",
+    uri: "http://ns.adobe.com/exif/1.0/aux/",
+};
+
+pub const DC: Namespace = Namespace {
+    prefix: "dc",
+    uri: "http://purl.org/dc/elements/1.1/",
+};
+
+pub const DIGIKAM: Namespace = Namespace {
+    prefix: "digiKam",
+    uri: "http://www.digikam.org/ns/1.0/",
+};
+
+pub const EXIF: Namespace = Namespace {
+    prefix: "exif",
+    uri: "http://ns.adobe.com/exif/1.0/",
+};
+
+pub const LIGHTROOM: Namespace = Namespace {
+    prefix: "lr",
+    uri: "http://ns.adobe.com/lightroom/1.0/",
+};
+
+pub const TIFF: Namespace = Namespace {
+    prefix: "tiff",
+    uri: "http://ns.adobe.com/tiff/1.0/",
+};
+
+const RDF_URI: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
+const TEMPLATE: &str = r#"<?xpacket begin="﻿" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="">
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>
+"#;
+
+/// Returns the sidecar of a file: an existing `IMG_0001.ARW.xmp` or
+/// `IMG_0001.xmp`, otherwise the path for a new `IMG_0001.ARW.xmp`.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut full_name = path.as_os_str().to_owned();
+    full_name.push(".xmp");
+    let full_name = PathBuf::from(full_name);
+    if full_name.exists() {
+        return full_name;
+    }
+    let stem_name = path.with_extension("xmp");
+    if stem_name.exists() {
+        return stem_name;
+    }
+    full_name
+}
+
+/// Returns the sidecar Lightroom reads: an existing one, otherwise the path
+/// for a new `IMG_0001.xmp`.
+pub fn lightroom_sidecar_path(path: &Path) -> PathBuf {
+    let existing = sidecar_path(path);
+    if existing.exists() {
+        return existing;
+    }
+    path.with_extension("xmp")
+}
+
+enum Storage {
+    Sidecar(PathBuf),
+    /// The packet inside the file itself, see [`embedded_xmp`]
+    Embedded(PathBuf),
+}
+
+/// An XMP packet that is edited and written back to where it was read from.
+pub struct Xmp {
+    storage: Storage,
+    content: String,
+}
+
+impl Xmp {
+    /// Loads the sidecar of `file`, or starts an empty one if there is none yet.
+    pub fn open_sidecar(file: &Path) -> io::Result<Self> {
+        Self::open_sidecar_at(sidecar_path(file))
+    }
+
+    /// Loads the sidecar at `path`, or starts an empty one there.
+    pub fn open_sidecar_at(path: PathBuf) -> io::Result<Self> {
+        let content = if path.exists() {
+            fs::read_to_string(&path)?
+        } else {
+            TEMPLATE.to_string()
+        };
+        Self::new(Storage::Sidecar(path), content)
+    }
+
+    /// Loads the sidecar of `file` if it has one.
+    pub fn read_sidecar(file: &Path) -> io::Result<Option<Self>> {
+        let path = sidecar_path(file);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        Self::new(Storage::Sidecar(path), content).map(Some)
+    }
+
+    /// Loads the packet embedded in a DNG or JPEG file, or starts an empty one.
+    pub fn open_embedded(file: &Path) -> io::Result<Self> {
+        let content = embedded_xmp::read(file)?.unwrap_or_else(|| TEMPLATE.to_string());
+        Self::new(Storage::Embedded(file.to_path_buf()), content)
+    }
+
+    fn new(storage: Storage, content: String) -> io::Result<Self> {
+        if !content.contains("<rdf:Description") {
+            let path = match &storage {
+                Storage::Sidecar(path) | Storage::Embedded(path) => path,
+            };
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("XMP of {} has no rdf:Description", path.display()),
+            ));
+        }
+        Ok(Self { storage, content })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        match &self.storage {
+            Storage::Sidecar(path) => fs::write(path, &self.content),
+            Storage::Embedded(path) => embedded_xmp::write(path, &self.content),
+        }
+    }
+
+    /// Adds `values` to an unordered list property (`rdf:Bag`), skipping values
+    /// that are already present.
+    pub fn add_to_bag(&mut self, ns: &Namespace, property: &str, values: &[String]) {
+        self.add_to_container(ns, property, "Bag", values);
+    }
+
+    /// Adds `values` to an ordered list property (`rdf:Seq`), skipping values
+    /// that are already present.
+    pub fn add_to_seq(&mut self, ns: &Namespace, property: &str, values: &[String]) {
+        self.add_to_container(ns, property, "Seq", values);
+    }
+
+    /// Sets a language alternative text like `dc:description`, unless the
+    /// sidecar already has a value for it.
+    pub fn set_alt_text_if_missing(&mut self, ns: &Namespace, property: &str, value: &str) {
+        if self.has_property(ns, property) {
+            return;
+        }
+        let Some(description) = self.ensure_namespace(ns) else {
+            return;
+        };
+        let element = format!(
+            "   <{p}:{n}>\n    <rdf:Alt>\n     <rdf:li xml:lang=\"x-default\">{v}</rdf:li>\n    </rdf:Alt>\n   </{p}:{n}>\n",
+            p = ns.prefix,
+            n = property,
+            v = escape(value)
+        );
+        self.insert_into_description(description, &element);
+    }
+
+    /// Returns a simple property, written either as an attribute of
+    /// `rdf:Description` or as an element.
+    pub fn get_text(&self, ns: &Namespace, property: &str) -> Option<String> {
+        let name = format!("{}:{}", ns.prefix, property);
+        if let Some(i) = find_attribute(&self.content, &format!("{}=\"", name)) {
+            let start = i + name.len() + 2;
+            let end = start + self.content[start..].find('"')?;
+            return Some(unescape(&self.content[start..end]));
+        }
+        let open = format!("<{}>", name);
+        let start = self.content.find(&open)? + open.len();
+        let end = start + self.content[start..].find(&format!("</{}>", name))?;
+        Some(unescape(self.content[start..end].trim()))
+    }
+
+    /// Returns the first item of an array property like `exif:ISOSpeedRatings`,
+    /// or the value of a simple one.
+    pub fn get_first_item(&self, ns: &Namespace, property: &str) -> Option<String> {
+        let text = self.get_text(ns, property)?;
+        let Some(start) = text.find("<rdf:li>").map(|i| i + "<rdf:li>".len()) else {
+            return Some(text);
+        };
+        let end = start + text[start..].find("</rdf:li>")?;
+        Some(text[start..end].trim().to_string())
+    }
+
+    /// Sets a simple property, replacing its value if it already exists.
+    pub fn set_text(&mut self, ns: &Namespace, property: &str, value: &str) {
+        let name = format!("{}:{}", ns.prefix, property);
+        if let Some(i) = find_attribute(&self.content, &format!("{}=\"", name)) {
+            let start = i + name.len() + 2;
+            if let Some(end) = self.content[start..].find('"').map(|e| start + e) {
+                self.content.replace_range(start..end, &escape(value));
+                return;
+            }
+        }
+        let open = format!("<{}>", name);
+        if let Some(start) = self.content.find(&open).map(|i| i + open.len()) {
+            if let Some(end) = self.content[start..]
+                .find(&format!("</{}>", name))
+                .map(|e| start + e)
+            {
+                self.content.replace_range(start..end, &escape(value));
+                return;
+            }
+        }
+
+        let Some(description) = self.ensure_namespace(ns) else {
+            return;
+        };
+        let element = format!("   {}{}</{}>\n", open, escape(value), name);
+        self.insert_into_description(description, &element);
+    }
+
+    fn has_property(&self, ns: &Namespace, property: &str) -> bool {
+        let name = format!("{}:{}", ns.prefix, property);
+        self.content.contains(&format!("<{}>", name))
+            || self.content.contains(&format!("<{} ", name))
+            || find_attribute(&self.content, &format!("{}=\"", name)).is_some()
+    }
+
+    fn add_to_container(
+        &mut self,
+        ns: &Namespace,
+        property: &str,
+        container: &str,
+        values: &[String],
+    ) {
+        let name = format!("{}:{}", ns.prefix, property);
+        let open = format!("<{}>", name);
+        let close = format!("</{}>", name);
+
+        if let Some(start) = self.content.find(&open) {
+            let Some(end) = self.content[start..].find(&close).map(|i| start + i) else {
+                return;
+            };
+            let existing = &self.content[start..end];
+            let new_items: String = values
+                .iter()
+                .filter(|v| !existing.contains(&format!(">{}</rdf:li>", escape(v))))
+                .map(|v| format!("     <rdf:li>{}</rdf:li>\n", escape(v)))
+                .collect();
+            if new_items.is_empty() {
+                return;
+            }
+            let container_close = format!("</rdf:{}>", container);
+            match existing.rfind(&container_close) {
+                Some(i) => {
+                    let line_start = existing[..i].rfind('\n').map_or(i, |n| n + 1);
+                    let at = if existing[line_start..i].trim().is_empty() {
+                        line_start
+                    } else {
+                        i
+                    };
+                    self.content.insert_str(start + at, &new_items);
+                }
+                None => {
+                    // Empty `<rdf:Bag/>` or an unexpected layout, rewrite the property
+                    let element = format!(
+                        "{}\n    <rdf:{c}>\n{}    </rdf:{c}>\n   {}",
+                        open,
+                        new_items,
+                        close,
+                        c = container
+                    );
+                    self.content
+                        .replace_range(start..end + close.len(), &element);
+                }
+            }
+            return;
+        }
+
+        let Some(description) = self.ensure_namespace(ns) else {
+            return;
+        };
+        let items: String = values
+            .iter()
+            .map(|v| format!("     <rdf:li>{}</rdf:li>\n", escape(v)))
+            .collect();
+        let element = format!(
+            "   {}\n    <rdf:{c}>\n{}    </rdf:{c}>\n   {}\n",
+            open,
+            items,
+            close,
+            c = container
+        );
+        self.insert_into_description(description, &element);
+    }
+
+    /// Returns the start of the `rdf:Description` to add properties of `ns`
+    /// to. Sidecars written by exiftool declare every namespace on a
+    /// description of its own, so that is the one declaring `ns`, otherwise
+    /// the first, where `ns` is declared if no parent element does.
+    fn ensure_namespace(&mut self, ns: &Namespace) -> Option<usize> {
+        let declaration = format!("xmlns:{}=", ns.prefix);
+        let descriptions: Vec<usize> = self
+            .content
+            .match_indices("<rdf:Description")
+            .map(|(i, _)| i)
+            .collect();
+        let first = *descriptions.first()?;
+        let declaring = descriptions.iter().copied().find(|&start| {
+            self.content[start..]
+                .split_once('>')
+                .is_some_and(|(tag, _)| tag.contains(&declaration))
+        });
+        if let Some(start) = declaring {
+            return Some(start);
+        }
+        if self.content[..first].contains(&declaration) {
+            return Some(first);
+        }
+        if !self.content.contains("xmlns:rdf=") {
+            // Broken sidecar without RDF namespace, declare it as well
+            self.insert_description_attribute(first, &format!("xmlns:rdf=\"{}\"", RDF_URI));
+        }
+        self.insert_description_attribute(first, &format!("xmlns:{}=\"{}\"", ns.prefix, ns.uri));
+        Some(first)
+    }
+
+    fn insert_description_attribute(&mut self, start: usize, attribute: &str) {
+        let at = start + "<rdf:Description".len();
+        self.content.insert_str(at, &format!("\n    {}", attribute));
+    }
+
+    /// Inserts `element` as the last child of the `rdf:Description` at `start`.
+    fn insert_into_description(&mut self, start: usize, element: &str) {
+        let Some(tag_end) = self.content[start..].find('>').map(|i| start + i) else {
+            return;
+        };
+
+        if self.content[..tag_end].ends_with('/') {
+            // Self-closing description, give it children
+            let replacement = format!(">\n{}  </rdf:Description>", element);
+            self.content
+                .replace_range(tag_end - 1..tag_end + 1, &replacement);
+            return;
+        }
+
+        let Some(close) = self.content[tag_end..]
+            .find("</rdf:Description>")
+            .map(|i| tag_end + i)
+        else {
+            return;
+        };
+        let line_start = self.content[..close].rfind('\n').map_or(close, |i| i + 1);
+        let at = if self.content[line_start..close].trim().is_empty() {
+            line_start
+        } else {
+            close
+        };
+        self.content.insert_str(at, element);
+    }
+}
+
+/// Finds `name="` as an attribute, i.e. preceded by whitespace.
+fn find_attribute(content: &str, attribute: &str) -> Option<usize> {
+    content.match_indices(attribute).find_map(|(i, _)| {
+        content[..i]
+            .chars()
+            .next_back()
+            .filter(|c| c.is_whitespace())
+            .map(|_| i)
+    })
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xmp(content: &str) -> Xmp {
+        Xmp::new(
+            Storage::Sidecar(PathBuf::from("IMG.xmp")),
+            content.to_string(),
+        )
+        .unwrap()
+    }
+
+    /// The descriptions of the packet, each from its start tag to its end.
+    fn descriptions(xmp: &Xmp) -> Vec<&str> {
+        xmp.content.split("<rdf:Description").skip(1).collect()
+    }
+
+    const EXIFTOOL_SIDECAR: &str = "<x:xmpmeta xmlns:x='adobe:ns:meta/'>
+<rdf:RDF xmlns:rdf='http://www.w3.org/1999/02/22-rdf-syntax-ns#'>
+ <rdf:Description rdf:about=''
+  xmlns:exif='http://ns.adobe.com/exif/1.0/'>
+  <exif:ExposureBiasValue>-2</exif:ExposureBiasValue>
+ </rdf:Description>
+
+ <rdf:Description rdf:about=''
+  xmlns:dc='http://purl.org/dc/elements/1.1/'>
+  <dc:subject>
+   <rdf:Bag>
+    <rdf:li>sunset</rdf:li>
+   </rdf:Bag>
+  </dc:subject>
+ </rdf:Description>
+</rdf:RDF>
+</x:xmpmeta>
+";
+
+    #[test]
+    fn properties_go_to_the_description_declaring_their_namespace() {
+        let mut xmp = xmp(EXIFTOOL_SIDECAR);
+        xmp.set_alt_text_if_missing(&DC, "description", "bracket");
+        xmp.add_to_bag(
+            &DC,
+            "subject",
+            &["sunset".to_string(), "bracket".to_string()],
+        );
+
+        let descriptions = descriptions(&xmp);
+        assert!(!descriptions[0].contains("dc:"));
+        assert!(!descriptions[0].contains("xmlns:dc="));
+        assert!(descriptions[1].contains("<rdf:li xml:lang=\"x-default\">bracket</rdf:li>"));
+        assert_eq!(
+            descriptions[1].matches("<rdf:li>sunset</rdf:li>").count(),
+            1
+        );
+        assert!(descriptions[1].contains("<rdf:li>bracket</rdf:li>"));
+    }
+
+    #[test]
+    fn undeclared_namespaces_are_declared_where_the_property_goes() {
+        let mut xmp = xmp(EXIFTOOL_SIDECAR);
+        xmp.add_to_bag(
+            &LIGHTROOM,
+            "hierarchicalSubject",
+            &["HDR|bracket".to_string()],
+        );
+
+        let descriptions = descriptions(&xmp);
+        let (start_tag, _) = descriptions[0].split_once('>').unwrap();
+        assert!(start_tag.contains("xmlns:lr=\"http://ns.adobe.com/lightroom/1.0/\""));
+        assert!(descriptions[0].contains("<lr:hierarchicalSubject>"));
+        assert!(!descriptions[1].contains("lr:"));
+    }
+
+    #[test]
+    fn namespaces_declared_on_a_parent_are_not_declared_again() {
+        let mut xmp = xmp(&EXIFTOOL_SIDECAR.replace(
+            "<rdf:RDF ",
+            "<rdf:RDF xmlns:digiKam='http://www.digikam.org/ns/1.0/' ",
+        ));
+        xmp.add_to_bag(&DIGIKAM, "TagsList", &["HDR/bracket".to_string()]);
+
+        assert_eq!(xmp.content.matches("xmlns:digiKam=").count(), 1);
+        assert!(descriptions(&xmp)[0].contains("<digiKam:TagsList>"));
+    }
+
+    #[test]
+    fn self_closing_description_gets_children() {
+        let mut xmp = xmp("<x:xmpmeta xmlns:x='adobe:ns:meta/'>
+ <rdf:RDF xmlns:rdf='http://www.w3.org/1999/02/22-rdf-syntax-ns#'>
+  <rdf:Description rdf:about='' xmlns:xmp='http://ns.adobe.com/xap/1.0/' xmp:Rating='3'/>
+ </rdf:RDF>
+</x:xmpmeta>
+");
+        xmp.add_to_bag(&DC, "subject", &["bracket".to_string()]);
+        xmp.set_alt_text_if_missing(&DC, "description", "first");
+        xmp.set_alt_text_if_missing(&DC, "description", "second");
+
+        let descriptions = descriptions(&xmp);
+        assert_eq!(descriptions.len(), 1);
+        let (start_tag, children) = descriptions[0].split_once('>').unwrap();
+        assert!(start_tag.contains("xmlns:dc=") && start_tag.contains("xmp:Rating='3'"));
+        assert!(!start_tag.ends_with('/'));
+        assert!(children.contains("<rdf:li>bracket</rdf:li>"));
+        assert!(children.contains(">first</rdf:li>") && !children.contains(">second</rdf:li>"));
+        assert!(children.contains("</rdf:Description>"));
+    }
+}